@@ -0,0 +1,41 @@
+//! Build script: records the git commit the server is built from, served by
+//! `GET /version`
+//!
+//! Docker builds don't copy `.git`, so a `GIT_COMMIT` (or Render's
+//! `RENDER_GIT_COMMIT`) build variable takes precedence over asking git.
+
+use std::path::Path;
+use std::process::Command;
+
+const COMMIT_VARS: [&str; 2] = ["GIT_COMMIT", "RENDER_GIT_COMMIT"];
+
+fn main() {
+    let commit = COMMIT_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()))
+        .or_else(git_head)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_COMMIT={}", commit.trim());
+
+    for var in COMMIT_VARS {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    // Rebuild when HEAD moves, whether detached or along a branch
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            if Path::new(".git").join(branch).exists() {
+                println!("cargo:rerun-if-changed=.git/{branch}");
+            }
+        }
+    }
+}
+
+/// Commit hash of the checked out tree, if built from a git checkout
+fn git_head() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
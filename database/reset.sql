@@ -20,6 +20,7 @@ DROP FUNCTION IF EXISTS grant_item_on_purchase();
 DROP VIEW IF EXISTS user_inventory_details;
 DROP VIEW IF EXISTS leaderboard_wins;
 DROP VIEW IF EXISTS leaderboard_kd;
+DROP VIEW IF EXISTS player_career_stats;
 
 -- Drop tables (in dependency order)
 DROP TABLE IF EXISTS player_match_stats CASCADE;
@@ -14,12 +14,18 @@ CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 CREATE TABLE IF NOT EXISTS profiles (
     id UUID PRIMARY KEY REFERENCES auth.users(id) ON DELETE CASCADE,
     display_name TEXT,
+    display_name_changed_at TIMESTAMPTZ,  -- Last player-initiated rename (rate limiting)
+    abandon_count INTEGER NOT NULL DEFAULT 0,  -- Matches left while still alive
+    region TEXT,  -- Default matchmaking region, set by the last join that named one
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
 -- Create index for faster lookups
 CREATE INDEX IF NOT EXISTS idx_profiles_created_at ON profiles(created_at);
 
+-- Display names are unique regardless of case
+CREATE UNIQUE INDEX IF NOT EXISTS idx_profiles_display_name_lower ON profiles(lower(display_name));
+
 -- Enable RLS
 ALTER TABLE profiles ENABLE ROW LEVEL SECURITY;
 
@@ -50,13 +56,15 @@ CREATE POLICY "Users can update own profile"
 
 CREATE TABLE IF NOT EXISTS items (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
-    type TEXT NOT NULL,  -- e.g., 'flag_skin', 'ship_skin', 'trail_effect'
+    type TEXT NOT NULL,  -- e.g., 'flag_skin', 'ship_skin', 'trail_effect', 'kill_effect', 'weapon_loadout', 'battle_pass'
     name TEXT NOT NULL,
     description TEXT,
     price_usd INTEGER NOT NULL,  -- Price in cents (e.g., 499 = $4.99)
+    price_coins INTEGER,  -- Optional: price in soft currency (NULL = not buyable with coins)
     stripe_price_id TEXT,  -- Optional: pre-created Stripe price ID
     preview_url TEXT,  -- URL to preview image
     rarity TEXT DEFAULT 'common',  -- common, rare, epic, legendary
+    requires_pass BOOLEAN NOT NULL DEFAULT FALSE,  -- Only granted/equipped with an active battle pass
     active BOOLEAN NOT NULL DEFAULT TRUE,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
@@ -119,6 +127,34 @@ CREATE POLICY "Users can update own inventory"
 
 -- Only service role can insert inventory entries (via webhook after purchase)
 
+-- =============================================================================
+-- INVENTORY_GRANTS TABLE
+-- =============================================================================
+-- Audit trail of item grants, written by inventory_grant(); the reference
+-- makes a replayed grant a no-op
+
+CREATE TABLE IF NOT EXISTS inventory_grants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL REFERENCES items(id),  -- Deactivate items instead of deleting them
+    source TEXT NOT NULL CHECK (source IN ('purchase', 'reward', 'admin')),
+    reference_id TEXT NOT NULL,  -- e.g., Stripe session ID, coin purchase idempotency key
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (user_id, item_id, reference_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_grants_user ON inventory_grants(user_id, granted_at DESC);
+
+-- Enable RLS
+ALTER TABLE inventory_grants ENABLE ROW LEVEL SECURITY;
+
+-- Users can view their own grants
+CREATE POLICY "Users can view own inventory grants"
+    ON inventory_grants
+    FOR SELECT
+    USING (auth.uid() = user_id);
+
 -- =============================================================================
 -- PURCHASES TABLE
 -- =============================================================================
@@ -130,6 +166,7 @@ CREATE TABLE IF NOT EXISTS purchases (
     item_id UUID NOT NULL REFERENCES items(id) ON DELETE RESTRICT,
     stripe_session_id TEXT,
     stripe_payment_intent TEXT,
+    quantity INTEGER NOT NULL DEFAULT 1,  -- One row per cart line
     status TEXT NOT NULL DEFAULT 'pending',  -- pending, paid, failed, refunded
     amount_usd INTEGER,  -- Amount in cents at time of purchase
     currency TEXT DEFAULT 'usd',
@@ -158,9 +195,10 @@ CREATE POLICY "Users can view own purchases"
 -- Only service role can insert/update purchases (via server-side operations)
 
 -- =============================================================================
--- MATCH_HISTORY TABLE (OPTIONAL - for stats tracking)
+-- MATCH_HISTORY TABLE
 -- =============================================================================
--- Records completed matches for stats and leaderboards
+-- Records completed matches for stats and leaderboards (written by the server
+-- when a match ends)
 
 CREATE TABLE IF NOT EXISTS match_history (
     id UUID PRIMARY KEY,  -- Match ID from server
@@ -188,9 +226,9 @@ CREATE POLICY "Anyone can view match history"
     USING (TRUE);
 
 -- =============================================================================
--- PLAYER_MATCH_STATS TABLE (OPTIONAL - for detailed stats)
+-- PLAYER_MATCH_STATS TABLE
 -- =============================================================================
--- Per-player statistics for each match
+-- Per-player statistics for each match (human players only)
 
 CREATE TABLE IF NOT EXISTS player_match_stats (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -198,6 +236,7 @@ CREATE TABLE IF NOT EXISTS player_match_stats (
     user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
     ship_type TEXT NOT NULL,
     kills INTEGER NOT NULL DEFAULT 0,
+    best_streak INTEGER NOT NULL DEFAULT 0,  -- Most kills without dying
     damage_dealt REAL NOT NULL DEFAULT 0,
     damage_taken REAL NOT NULL DEFAULT 0,
     shots_fired INTEGER NOT NULL DEFAULT 0,
@@ -280,10 +319,369 @@ CREATE POLICY "Users can view own detailed stats"
     FOR SELECT
     USING (auth.uid() = user_id);
 
+-- =============================================================================
+-- WALLETS TABLE
+-- =============================================================================
+-- Soft currency balances, earned from logins and matches
+
+CREATE TABLE IF NOT EXISTS wallets (
+    user_id UUID PRIMARY KEY REFERENCES auth.users(id) ON DELETE CASCADE,
+    balance BIGINT NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Enable RLS
+ALTER TABLE wallets ENABLE ROW LEVEL SECURITY;
+
+-- Users can view their own wallet
+CREATE POLICY "Users can view own wallet"
+    ON wallets
+    FOR SELECT
+    USING (auth.uid() = user_id);
+
+-- =============================================================================
+-- WALLET_TRANSACTIONS TABLE
+-- =============================================================================
+-- Ledger of balance changes; the idempotency key makes replays no-ops
+
+CREATE TABLE IF NOT EXISTS wallet_transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL,  -- Positive = credit, negative = debit
+    reason TEXT NOT NULL,  -- e.g., 'daily_login', 'match_reward', 'purchase'
+    idempotency_key TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_transactions_user ON wallet_transactions(user_id, created_at DESC);
+
+-- Enable RLS
+ALTER TABLE wallet_transactions ENABLE ROW LEVEL SECURITY;
+
+-- Users can view their own transactions
+CREATE POLICY "Users can view own wallet transactions"
+    ON wallet_transactions
+    FOR SELECT
+    USING (auth.uid() = user_id);
+
+-- =============================================================================
+-- SUBSCRIPTIONS TABLE
+-- =============================================================================
+-- Battle pass subscriptions, mirrored from Stripe webhooks via subscription_apply()
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+    stripe_subscription_id TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    status TEXT NOT NULL,  -- Stripe status: active, trialing, past_due, canceled, ...
+    last_event_at BIGINT NOT NULL,  -- Creation time (unix seconds) of the last applied event
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_subscriptions_user ON subscriptions(user_id, status);
+
+-- Enable RLS
+ALTER TABLE subscriptions ENABLE ROW LEVEL SECURITY;
+
+-- Users can view their own subscriptions
+CREATE POLICY "Users can view own subscriptions"
+    ON subscriptions
+    FOR SELECT
+    USING (auth.uid() = user_id);
+
+-- =============================================================================
+-- STRIPE EVENTS TABLE
+-- =============================================================================
+-- Stripe webhook events already handled, so redeliveries are skipped
+
+CREATE TABLE IF NOT EXISTS stripe_events (
+    id TEXT PRIMARY KEY,  -- Stripe event ID (evt_...)
+    event_type TEXT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Enable RLS; no policies, so only the server (service role) can see it
+ALTER TABLE stripe_events ENABLE ROW LEVEL SECURITY;
+
+-- =============================================================================
+-- PROGRESSION TABLE
+-- =============================================================================
+-- Lifetime XP from match results, awarded via match_end_apply(); levels are
+-- derived from it by the server
+
+CREATE TABLE IF NOT EXISTS progression (
+    user_id UUID PRIMARY KEY REFERENCES auth.users(id) ON DELETE CASCADE,
+    xp BIGINT NOT NULL DEFAULT 0 CHECK (xp >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Enable RLS
+ALTER TABLE progression ENABLE ROW LEVEL SECURITY;
+
+-- Anyone can view XP (levels are shown on profiles)
+CREATE POLICY "Anyone can view progression"
+    ON progression
+    FOR SELECT
+    USING (true);
+
+-- =============================================================================
+-- XP_AWARDS TABLE
+-- =============================================================================
+-- One row per awarded match, so each match adds XP at most once
+
+CREATE TABLE IF NOT EXISTS xp_awards (
+    match_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES auth.users(id) ON DELETE CASCADE,
+    xp INTEGER NOT NULL CHECK (xp >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (match_id, user_id)
+);
+
+-- Enable RLS
+ALTER TABLE xp_awards ENABLE ROW LEVEL SECURITY;
+
+-- Users can view their own awards
+CREATE POLICY "Users can view own XP awards"
+    ON xp_awards
+    FOR SELECT
+    USING (auth.uid() = user_id);
+
 -- =============================================================================
 -- FUNCTIONS & TRIGGERS
 -- =============================================================================
 
+-- Atomically apply a wallet transaction and return the new balance.
+-- Replaying an idempotency key returns the current balance without changes;
+-- a debit that would overdraw raises 'insufficient_funds' and changes nothing.
+CREATE OR REPLACE FUNCTION wallet_apply(
+    p_user_id UUID,
+    p_amount BIGINT,
+    p_reason TEXT,
+    p_idempotency_key TEXT
+)
+RETURNS BIGINT AS $$
+DECLARE
+    v_balance BIGINT;
+BEGIN
+    INSERT INTO wallet_transactions (user_id, amount, reason, idempotency_key)
+    VALUES (p_user_id, p_amount, p_reason, p_idempotency_key)
+    ON CONFLICT (idempotency_key) DO NOTHING;
+
+    IF NOT FOUND THEN
+        SELECT balance INTO v_balance FROM wallets WHERE user_id = p_user_id;
+        RETURN COALESCE(v_balance, 0);
+    END IF;
+
+    INSERT INTO wallets (user_id) VALUES (p_user_id)
+    ON CONFLICT (user_id) DO NOTHING;
+
+    UPDATE wallets
+    SET balance = balance + p_amount, updated_at = NOW()
+    WHERE user_id = p_user_id AND balance + p_amount >= 0
+    RETURNING balance INTO v_balance;
+
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'insufficient_funds';
+    END IF;
+
+    RETURN v_balance;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+-- Only the server (service role) may move currency
+REVOKE EXECUTE ON FUNCTION wallet_apply(UUID, BIGINT, TEXT, TEXT) FROM PUBLIC, anon, authenticated;
+
+-- Record a finished match in one call: its match_history row, each human
+-- player's player_match_stats row, and their XP. Results and progression
+-- each commit or roll back on their own; the returned object says which were
+-- applied and, for progression, every player's new XP total. Safe to retry:
+-- rows already written and XP already awarded for the match are left alone.
+CREATE OR REPLACE FUNCTION match_end_apply(
+    p_match_id UUID,
+    p_match JSONB,
+    p_players JSONB
+)
+RETURNS JSONB AS $$
+DECLARE
+    v_results BOOLEAN := TRUE;
+    v_progression BOOLEAN := TRUE;
+    v_xp_totals JSONB := '{}'::JSONB;
+BEGIN
+    BEGIN
+        INSERT INTO match_history (id, seed, started_at, ended_at, duration_secs, total_players, winner_user_id)
+        SELECT p_match_id, m.seed, m.started_at, m.ended_at, m.duration_secs, m.total_players, m.winner_user_id
+        FROM jsonb_populate_record(NULL::match_history, p_match) AS m
+        ON CONFLICT (id) DO NOTHING;
+
+        INSERT INTO player_match_stats (
+            match_id, user_id, ship_type, kills, best_streak, damage_dealt, damage_taken,
+            shots_fired, shots_hit, placement, alive_time_secs
+        )
+        SELECT p_match_id, p.user_id, p.ship_type, p.kills, p.best_streak, p.damage_dealt, p.damage_taken,
+            p.shots_fired, p.shots_hit, p.placement, p.alive_time_secs
+        FROM jsonb_populate_recordset(NULL::player_match_stats, p_players) AS p
+        ON CONFLICT (match_id, user_id) DO NOTHING;
+    EXCEPTION WHEN OTHERS THEN
+        RAISE WARNING 'match_end_apply results for %: %', p_match_id, SQLERRM;
+        v_results := FALSE;
+    END;
+
+    BEGIN
+        WITH awarded AS (
+            INSERT INTO xp_awards (match_id, user_id, xp)
+            SELECT p_match_id, p.user_id, p.xp_gained
+            FROM jsonb_to_recordset(p_players) AS p(user_id UUID, xp_gained INTEGER)
+            WHERE p.xp_gained > 0
+            ON CONFLICT (match_id, user_id) DO NOTHING
+            RETURNING user_id, xp
+        )
+        INSERT INTO progression (user_id, xp)
+        SELECT user_id, xp FROM awarded
+        ON CONFLICT (user_id) DO UPDATE
+        SET xp = progression.xp + EXCLUDED.xp, updated_at = NOW();
+
+        SELECT COALESCE(jsonb_object_agg(pr.user_id, pr.xp), '{}'::JSONB) INTO v_xp_totals
+        FROM progression pr
+        JOIN jsonb_to_recordset(p_players) AS p(user_id UUID) ON pr.user_id = p.user_id;
+    EXCEPTION WHEN OTHERS THEN
+        RAISE WARNING 'match_end_apply progression for %: %', p_match_id, SQLERRM;
+        v_progression := FALSE;
+    END;
+
+    RETURN jsonb_build_object(
+        'results', v_results,
+        'progression', v_progression,
+        'xp_totals', v_xp_totals
+    );
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+-- Only the server (service role) may record matches and award XP
+REVOKE EXECUTE ON FUNCTION match_end_apply(UUID, JSONB, JSONB) FROM PUBLIC, anon, authenticated;
+
+-- Record a subscription status from a Stripe event, unless a newer event
+-- was already applied (Stripe retries and may deliver events out of order).
+-- Returns whether the row changed.
+CREATE OR REPLACE FUNCTION subscription_apply(
+    p_subscription_id TEXT,
+    p_user_id UUID,
+    p_status TEXT,
+    p_event_at BIGINT
+)
+RETURNS BOOLEAN AS $$
+BEGIN
+    INSERT INTO subscriptions (stripe_subscription_id, user_id, status, last_event_at)
+    VALUES (p_subscription_id, p_user_id, p_status, p_event_at)
+    ON CONFLICT (stripe_subscription_id) DO UPDATE
+    SET status = EXCLUDED.status,
+        last_event_at = EXCLUDED.last_event_at,
+        updated_at = NOW()
+    WHERE subscriptions.last_event_at < EXCLUDED.last_event_at;
+
+    RETURN FOUND;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+-- Only the server (service role) may change subscriptions
+REVOKE EXECUTE ON FUNCTION subscription_apply(TEXT, UUID, TEXT, BIGINT) FROM PUBLIC, anon, authenticated;
+
+-- Count an abandoned match against a profile in a single statement, so
+-- concurrent abandons never overwrite each other. Returns the new count
+-- (NULL if the user has no profile).
+CREATE OR REPLACE FUNCTION abandon_record(p_user_id UUID)
+RETURNS INTEGER AS $$
+    UPDATE profiles
+    SET abandon_count = abandon_count + 1
+    WHERE id = p_user_id
+    RETURNING abandon_count;
+$$ LANGUAGE sql SECURITY DEFINER;
+
+-- Only the server (service role) may count abandons
+REVOKE EXECUTE ON FUNCTION abandon_record(UUID) FROM PUBLIC, anon, authenticated;
+
+-- Grant an item and record why, in one transaction. Granting the same item
+-- to the same user under the same reference again changes nothing. Returns
+-- whether the grant was new.
+CREATE OR REPLACE FUNCTION inventory_grant(
+    p_user_id UUID,
+    p_item_id UUID,
+    p_source TEXT,
+    p_reference_id TEXT
+)
+RETURNS BOOLEAN AS $$
+BEGIN
+    INSERT INTO inventory_grants (user_id, item_id, source, reference_id)
+    VALUES (p_user_id, p_item_id, p_source, p_reference_id)
+    ON CONFLICT (user_id, item_id, reference_id) DO NOTHING;
+
+    IF NOT FOUND THEN
+        RETURN FALSE;
+    END IF;
+
+    INSERT INTO user_inventory (user_id, item_id, owned, equipped, acquired_at)
+    VALUES (p_user_id, p_item_id, TRUE, FALSE, NOW())
+    ON CONFLICT (user_id, item_id)
+    DO UPDATE SET owned = TRUE, acquired_at = COALESCE(user_inventory.acquired_at, NOW());
+
+    RETURN TRUE;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+-- Only the server (service role) may grant items
+REVOKE EXECUTE ON FUNCTION inventory_grant(UUID, UUID, TEXT, TEXT) FROM PUBLIC, anon, authenticated;
+
+-- Grant an item to many users at once (every profile if p_user_ids is
+-- NULL), in one transaction. Users who already own the item, or got it under
+-- this reference before, are counted as already owned and get no grant row
+CREATE OR REPLACE FUNCTION inventory_grant_bulk(
+    p_user_ids UUID[],
+    p_item_id UUID,
+    p_source TEXT,
+    p_reference_id TEXT
+)
+RETURNS JSONB AS $$
+DECLARE
+    v_targets BIGINT;
+    v_granted BIGINT;
+BEGIN
+    WITH targets AS (
+        SELECT id AS user_id FROM profiles
+        WHERE p_user_ids IS NULL OR id = ANY(p_user_ids)
+    ),
+    fresh AS (
+        SELECT t.user_id FROM targets t
+        WHERE NOT EXISTS (
+            SELECT 1 FROM user_inventory ui
+            WHERE ui.user_id = t.user_id AND ui.item_id = p_item_id AND ui.owned
+        )
+    ),
+    logged AS (
+        INSERT INTO inventory_grants (user_id, item_id, source, reference_id)
+        SELECT user_id, p_item_id, p_source, p_reference_id FROM fresh
+        ON CONFLICT (user_id, item_id, reference_id) DO NOTHING
+        RETURNING user_id
+    ),
+    owned AS (
+        INSERT INTO user_inventory (user_id, item_id, owned, equipped, acquired_at)
+        SELECT user_id, p_item_id, TRUE, FALSE, NOW() FROM logged
+        ON CONFLICT (user_id, item_id)
+        DO UPDATE SET owned = TRUE, acquired_at = COALESCE(user_inventory.acquired_at, NOW())
+        RETURNING user_id
+    )
+    SELECT (SELECT COUNT(*) FROM targets), (SELECT COUNT(*) FROM owned)
+    INTO v_targets, v_granted;
+
+    RETURN jsonb_build_object(
+        'granted', v_granted,
+        'already_owned', v_targets - v_granted,
+        'not_found', CASE WHEN p_user_ids IS NULL THEN 0
+            ELSE (SELECT COUNT(DISTINCT u) FROM unnest(p_user_ids) u) - v_targets END
+    );
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+REVOKE EXECUTE ON FUNCTION inventory_grant_bulk(UUID[], UUID, TEXT, TEXT) FROM PUBLIC, anon, authenticated;
+
 -- Function to automatically create a profile when a new user signs up
 CREATE OR REPLACE FUNCTION handle_new_user()
 RETURNS TRIGGER AS $$
@@ -339,11 +737,11 @@ CREATE OR REPLACE FUNCTION grant_item_on_purchase()
 RETURNS TRIGGER AS $$
 BEGIN
     -- Only process when status changes to 'paid'
+    -- Same reference as the webhook's grant, so the purchase is logged once
     IF NEW.status = 'paid' AND (OLD.status IS NULL OR OLD.status != 'paid') THEN
-        INSERT INTO user_inventory (user_id, item_id, owned, equipped, acquired_at)
-        VALUES (NEW.user_id, NEW.item_id, TRUE, FALSE, NOW())
-        ON CONFLICT (user_id, item_id) 
-        DO UPDATE SET owned = TRUE, acquired_at = COALESCE(user_inventory.acquired_at, NOW());
+        PERFORM inventory_grant(
+            NEW.user_id, NEW.item_id, 'purchase', COALESCE(NEW.stripe_session_id, NEW.id::TEXT)
+        );
     END IF;
     
     RETURN NEW;
@@ -371,6 +769,13 @@ INSERT INTO items (id, type, name, description, price_usd, rarity, active) VALUE
     (uuid_generate_v4(), 'trail_effect', 'Bioluminescence', 'Glowing trail of ocean life', 699, 'rare', TRUE)
 ON CONFLICT DO NOTHING;
 
+-- Monthly battle pass (bought with "mode": "subscription") and a cosmetic pass
+-- holders can claim for free with a coin checkout
+INSERT INTO items (id, type, name, description, price_usd, price_coins, rarity, requires_pass, active) VALUES
+    (uuid_generate_v4(), 'battle_pass', 'Battle Pass', 'Monthly pass unlocking exclusive cosmetics', 999, NULL, 'epic', FALSE, TRUE),
+    (uuid_generate_v4(), 'flag_skin', 'Admiral''s Standard', 'Battle pass exclusive flag', 0, 0, 'legendary', TRUE, TRUE)
+ON CONFLICT DO NOTHING;
+
 -- =============================================================================
 -- VIEWS (for convenience)
 -- =============================================================================
@@ -423,3 +828,17 @@ LEFT JOIN player_stats_aggregate s ON p.id = s.user_id
 WHERE COALESCE(s.total_matches, 0) >= 10  -- Higher threshold for K/D
 ORDER BY kd_ratio DESC, total_kills DESC
 LIMIT 100;
+
+-- View for career stats, aggregated from per-match results
+-- (a player who didn't win a match died in it)
+CREATE OR REPLACE VIEW player_career_stats AS
+SELECT 
+    user_id,
+    COUNT(*)::INTEGER AS total_matches,
+    COUNT(*) FILTER (WHERE placement = 1)::INTEGER AS wins,
+    COALESCE(SUM(kills), 0)::INTEGER AS kills,
+    COUNT(*) FILTER (WHERE placement <> 1)::INTEGER AS deaths,
+    MIN(placement) AS best_placement,
+    COALESCE(SUM(damage_dealt), 0)::REAL AS total_damage
+FROM player_match_stats
+GROUP BY user_id;
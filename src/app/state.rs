@@ -1,12 +1,27 @@
 //! Application state shared across routes
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::config::Config;
-use crate::game::MatchRegistry;
+use crate::game::arena::{ArenaCatalog, ArenaError};
+use crate::game::balance::{BalanceError, BalanceTable};
+use crate::game::combat::MAX_AIM_ASSIST_CONE;
+use crate::game::{MatchRegistry, MatchSettings, ZoneConfig, ZoneConfigError};
+use crate::matchmaking::abandon::AbandonPolicy;
+use crate::matchmaking::queue::MatchmakingQueue;
 use crate::matchmaking::MatchmakingService;
 use crate::payments::StripeService;
-use crate::store::{InventoryStore, ProfileStore, SupabaseClient};
+use crate::store::{
+    InventoryStore, ProfileStore, StatsStore, StripeEventStore, SubscriptionStore,
+    SupabaseClient, WalletStore,
+};
+use crate::util::counters::ServerCounters;
+use crate::util::ip_limit::IpLimitPolicy;
+use crate::util::rate_limit::ApiRateLimiters;
+use crate::ws::lag::LagMetrics;
+use crate::ws::ticket::TicketStore;
 
 /// Shared application state
 #[derive(Clone)]
@@ -15,13 +30,40 @@ pub struct AppState {
     pub supabase: SupabaseClient,
     pub profile_store: ProfileStore,
     pub inventory_store: InventoryStore,
+    pub wallet_store: WalletStore,
+    pub stats_store: StatsStore,
+    pub subscription_store: SubscriptionStore,
+    /// Stripe webhook events already handled
+    pub stripe_events: StripeEventStore,
     pub stripe: StripeService,
     pub matchmaking: Arc<MatchmakingService>,
     pub match_registry: Arc<MatchRegistry>,
+    pub ws_tickets: Arc<TicketStore>,
+    /// Per-user HTTP rate limiters
+    pub rate_limiters: ApiRateLimiters,
+    /// Slow WebSocket client counters
+    pub lag_metrics: Arc<LagMetrics>,
+    /// Connection, match, queue and webhook counters
+    pub counters: Arc<ServerCounters>,
+    /// Ship and weapon stats every match is created with
+    pub balance: Arc<BalanceTable>,
+}
+
+/// Errors loading the game config files named in `Config`
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+    #[error(transparent)]
+    Zone(#[from] ZoneConfigError),
+
+    #[error(transparent)]
+    Balance(#[from] BalanceError),
+
+    #[error(transparent)]
+    Arena(#[from] ArenaError),
 }
 
 impl AppState {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config) -> Result<Self, StateError> {
         let config = Arc::new(config);
 
         // Initialize Supabase client
@@ -30,24 +72,132 @@ impl AppState {
         // Initialize stores
         let profile_store = ProfileStore::new(supabase.clone());
         let inventory_store = InventoryStore::new(supabase.clone());
+        let wallet_store = WalletStore::new(supabase.clone());
+        let stats_store = StatsStore::new(supabase.clone());
+        let subscription_store = SubscriptionStore::new(supabase.clone());
+        let stripe_events = StripeEventStore::new(supabase.clone());
 
         // Initialize Stripe
         let stripe = StripeService::new(&config, supabase.clone());
 
         // Initialize match registry
-        let match_registry = Arc::new(MatchRegistry::new());
+        let match_registry = Arc::new(
+            MatchRegistry::new()
+                .with_event_retention(Duration::from_secs(config.event_retention_secs)),
+        );
+
+        // Load zone pacing once; a bad file fails startup rather than every match
+        let zone = match &config.zone_config_path {
+            Some(path) => ZoneConfig::from_file(path)?,
+            None => ZoneConfig::default(),
+        };
+        let balance = Arc::new(match &config.balance_config_path {
+            Some(path) => BalanceTable::from_file(path)?,
+            None => BalanceTable::default(),
+        });
+        let arenas = match &config.arena_config_path {
+            Some(path) => ArenaCatalog::from_file(path, &zone)?,
+            None => ArenaCatalog::built_in(&zone),
+        };
+        let arena_rotation = arenas.rotation(&config.match_arenas)?;
 
         // Initialize matchmaking service (Arc for sharing across cloned AppState)
-        let matchmaking = Arc::new(MatchmakingService::new(match_registry.clone()));
+        let match_settings = MatchSettings {
+            min_players: config.match_min_players,
+            max_players: config.match_max_players,
+            warmup: config.match_warmup_secs,
+            countdown: config.match_countdown_secs,
+            bot_fill_delay: config.bot_fill_delay_secs,
+            empty_timeout: config.match_empty_timeout_secs,
+            snapshot_precision: config.snapshot_precision,
+            snapshot_mode: config.snapshot_mode,
+            snapshot_projectiles: config.snapshot_projectiles,
+            tick_rate: config.simulation_tps,
+            snapshot_rate: config.snapshot_tps,
+            max_pause: config.match_max_pause_secs,
+            arena: arena_rotation[0].clone(),
+            broadcast_per_player: config.snapshot_buffer_per_player.max(1),
+            replay_dir: config.replay_dir.as_ref().map(PathBuf::from),
+            zone_bypasses_shield: config.zone_bypasses_shield,
+            self_damage: config.self_damage,
+            projectile_clash: config.projectile_clash,
+            late_join: config.late_join,
+            spawn_protection: config.spawn_protection_secs.max(0.0),
+            spawn_separation: config.spawn_separation.max(0.0),
+            max_projectiles: config.max_projectiles.max(1),
+            max_projectiles_per_player: config.max_projectiles_per_player.max(1),
+            max_events_per_tick: config.max_events_per_tick.max(1),
+            event_log_capacity: config.event_log_capacity,
+            balance: balance.clone(),
+            zone_warning_lead: config.zone_warning_secs.max(0.0),
+            prediction_tolerance: config.prediction_tolerance.max(0.0),
+            input_timeout: config.input_timeout_secs.max(0.0),
+            max_duration: config.match_max_duration_secs,
+            sudden_death: config.sudden_death_secs.max(0.0),
+            practice: false,
+            aim_assist_cone: config
+                .aim_assist_cone_deg
+                .to_radians()
+                .clamp(0.0, MAX_AIM_ASSIST_CONE),
+            aim_assist_strength: config.aim_assist_strength.clamp(0.0, 1.0),
+            private: false,
+        };
+        let abandon_policy = AbandonPolicy {
+            threshold: config.abandon_threshold,
+            window: Duration::from_secs(config.abandon_window_secs),
+            cooldown: Duration::from_secs(config.abandon_cooldown_secs),
+        };
+        let queue = MatchmakingQueue::new(
+            config.match_min_players,
+            config.match_max_players,
+            config.matchmaking_max_wait_secs,
+        )
+        .with_cross_region_wait(Duration::from_secs(config.cross_region_wait_secs))
+        .with_smallest_match(config.match_size_min);
+        let counters = Arc::new(ServerCounters::default());
+        let matchmaking = Arc::new(
+            MatchmakingService::new(
+                queue,
+                match_registry.clone(),
+                match_settings,
+                profile_store.clone(),
+                inventory_store.clone(),
+                wallet_store.clone(),
+                stats_store.clone(),
+                abandon_policy,
+            )
+            .with_arenas(arena_rotation)
+            .with_counters(counters.clone())
+            .with_duplicate_policy(config.ws_duplicate_connections)
+            .with_practice_bots(config.practice_bots),
+        );
+
+        let rate_limiters = ApiRateLimiters::new().with_ip_policy(IpLimitPolicy {
+            requests_per_second: config.ip_rate_limit_per_sec,
+            max_connections: config.ip_max_connections,
+            trusted_proxy_hops: config.trusted_proxy_hops,
+        });
+
+        // Initialize WebSocket ticket store
+        let ws_tickets = Arc::new(TicketStore::new());
 
-        Self {
+        Ok(Self {
             config,
             supabase,
             profile_store,
             inventory_store,
+            wallet_store,
+            stats_store,
+            subscription_store,
+            stripe_events,
             stripe,
             matchmaking,
             match_registry,
-        }
+            ws_tickets,
+            rate_limiters,
+            lag_metrics: Arc::new(LagMetrics::default()),
+            counters,
+            balance,
+        })
     }
 }
@@ -1,8 +1,15 @@
 //! Configuration module - environment variable parsing
 
+use axum::http::HeaderValue;
 use std::env;
 use std::net::SocketAddr;
 
+use crate::game::arena::{CLUTTERED_ARENA, OPEN_ARENA};
+use crate::game::snapshot::SnapshotMode;
+use crate::matchmaking::service::DuplicateConnectionPolicy;
+use crate::util::time::{SIMULATION_TPS, SNAPSHOT_TPS};
+use crate::ws::protocol::SnapshotPrecision;
+
 /// Application configuration loaded from environment variables
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -10,6 +17,12 @@ pub struct Config {
     pub server_addr: SocketAddr,
     /// Log level (trace, debug, info, warn, error)
     pub log_level: String,
+    /// Human-readable or JSON log lines
+    pub log_format: LogFormat,
+    /// PEM certificate chain for serving HTTPS/WSS directly (plaintext if unset)
+    pub tls_cert_path: Option<String>,
+    /// PEM private key for `tls_cert_path`
+    pub tls_key_path: Option<String>,
 
     /// Supabase project URL
     pub supabase_url: String,
@@ -19,6 +32,23 @@ pub struct Config {
     pub supabase_service_role_key: String,
     /// Supabase JWT secret for token verification
     pub supabase_jwt_secret: String,
+    /// Expected `aud` claim on Supabase JWTs
+    pub jwt_audience: String,
+    /// Expected `iss` claim on Supabase JWTs (unchecked if unset)
+    pub jwt_issuer: Option<String>,
+    /// Clock skew tolerated when checking JWT expiry (seconds)
+    pub jwt_leeway_secs: u64,
+    /// Allow `POST /guest` tokens for play without an account (off by default)
+    pub guest_play: bool,
+    /// Lifetime of guest tokens (seconds)
+    pub guest_token_ttl_secs: u64,
+    /// Static bearer token for admin routes (admin routes only take JWTs
+    /// with the admin role if unset)
+    pub admin_token: Option<String>,
+    /// Retries for transient Supabase failures (after the initial attempt)
+    pub supabase_max_retries: u32,
+    /// Base delay for Supabase retry backoff, in milliseconds
+    pub supabase_retry_base_delay_ms: u64,
 
     /// Stripe secret API key
     pub stripe_secret_key: String,
@@ -27,11 +57,148 @@ pub struct Config {
 
     /// Public base URL for callbacks
     pub public_base_url: String,
-    /// Allowed client origin for CORS
+    /// Allowed client origins for CORS (comma-separated)
     pub client_origin: String,
+    /// How long browsers may cache a CORS preflight (seconds)
+    pub cors_max_age_secs: u64,
+    /// Add security headers to every response
+    pub security_headers: bool,
+    /// Referrer-Policy sent with security headers (omitted when empty)
+    pub referrer_policy: String,
+    /// Content-Security-Policy for HTML responses (omitted when empty)
+    pub content_security_policy: String,
+    /// Fill waiting matches with bots after this many seconds (disabled when unset or 0)
+    pub bot_fill_delay_secs: Option<f32>,
+    /// Bots flying against the player in a practice match
+    pub practice_bots: usize,
+    /// Encoding for player positions in snapshots
+    pub snapshot_precision: SnapshotPrecision,
+    /// Simulation ticks per second for new matches
+    pub simulation_tps: u32,
+    /// Snapshots per second for new matches (at most `simulation_tps`)
+    pub snapshot_tps: u32,
+    /// Shared broadcast snapshots, or snapshots culled to each player's view
+    pub snapshot_mode: SnapshotMode,
+    /// Include live projectiles in snapshots
+    pub snapshot_projectiles: bool,
+    /// Drift allowed between a client's own prediction and the server before
+    /// `input_ack` corrects it (world units, 0 = always correct)
+    pub prediction_tolerance: f32,
+    /// Time without an input before a ship's controls are let go (seconds,
+    /// 0 = hold the last input)
+    pub input_timeout_secs: f32,
+    /// Interval between WebSocket ping frames (seconds)
+    pub ws_ping_interval_secs: u64,
+    /// Close WebSocket connections silent for this long (seconds)
+    pub ws_idle_timeout_secs: u64,
+    /// Accept `?token=` on WebSocket upgrades (deprecated in favor of tickets)
+    pub ws_allow_query_token: bool,
+    /// Let clients opt into deflated WebSocket messages
+    pub ws_compression: bool,
+    /// Lag events within the window that close a WebSocket (0 = never)
+    pub ws_lag_disconnect_threshold: usize,
+    /// How far back lag events count towards the threshold (seconds)
+    pub ws_lag_window_secs: u64,
+    /// Largest WebSocket frame or message accepted before the connection is dropped
+    pub ws_max_frame_bytes: usize,
+    /// Largest client message that gets parsed; bigger ones are dropped
+    pub ws_max_message_bytes: usize,
+    /// Oversized or malformed client messages that close a WebSocket (0 = never)
+    pub ws_max_bad_messages: usize,
+    /// How long before its token expires a WebSocket is asked to refresh it
+    pub ws_token_refresh_secs: u64,
+    /// What happens when a user opens a second WebSocket
+    pub ws_duplicate_connections: DuplicateConnectionPolicy,
+    /// Requests per second one client IP may make to public routes
+    pub ip_rate_limit_per_sec: u32,
+    /// WebSocket connections one client IP may hold at once (0 = unlimited)
+    pub ip_max_connections: usize,
+    /// Reverse proxies in front of the server whose `X-Forwarded-For`
+    /// entries are trusted (0 = use the socket address)
+    pub trusted_proxy_hops: usize,
+    /// Match broadcast buffer slots per player slot
+    pub snapshot_buffer_per_player: usize,
+    /// Minimum time between display name changes per user (seconds)
+    pub display_name_cooldown_secs: u64,
+    /// Abandons within the window that trigger a matchmaking cooldown
+    pub abandon_threshold: usize,
+    /// How far back abandons count towards the threshold (seconds)
+    pub abandon_window_secs: u64,
+    /// Matchmaking cooldown after the latest abandon (seconds)
+    pub abandon_cooldown_secs: u64,
+    /// Total time a host may keep a match paused (seconds)
+    pub match_max_pause_secs: f32,
+    /// Close a waiting match nobody is in after this long (seconds, never if unset or 0)
+    pub match_empty_timeout_secs: Option<f32>,
+    /// Time a match may run before sudden death (seconds, unlimited if unset)
+    pub match_max_duration_secs: Option<f32>,
+    /// How long sudden death closes the zone before the survivors are ranked (seconds)
+    pub sudden_death_secs: f32,
+    /// JSON file with the zone config for new matches (built-in default if unset)
+    pub zone_config_path: Option<String>,
+    /// JSON file overriding ship and weapon stats (built-in stats if unset)
+    pub balance_config_path: Option<String>,
+    /// JSON file adding or replacing arenas (built-in arenas only if unset)
+    pub arena_config_path: Option<String>,
+    /// Arenas new matches are played on, picked per match by its seed
+    pub match_arenas: Vec<String>,
+    /// Directory to write match replays to (recording disabled if unset)
+    pub replay_dir: Option<String>,
+    /// Zone damage ignores shields and goes straight to health
+    pub zone_bypasses_shield: bool,
+    /// Splash damage (mine blasts) hits its owner too
+    pub self_damage: bool,
+    /// Shots from different players cancel each other out when they meet
+    pub projectile_clash: bool,
+    /// Cone either side of the aim aim assist looks for targets in (degrees, 0 disables)
+    pub aim_assist_cone_deg: f32,
+    /// Share of the way aim assist turns a shot toward its target (0-1)
+    pub aim_assist_strength: f32,
+    /// Notice given by `zone_warning` before each shrink (seconds, 0 disables)
+    pub zone_warning_secs: f32,
+    /// Let players join matches that have already started
+    pub late_join: bool,
+    /// Damage immunity for ships at the start of a match or joining late (seconds)
+    pub spawn_protection_secs: f32,
+    /// Distance kept between new spawns and other ships, when there's room
+    pub spawn_separation: f32,
+    /// Live projectiles per match before the oldest are dropped
+    pub max_projectiles: usize,
+    /// In-flight projectiles per player before firing is held back
+    pub max_projectiles_per_player: usize,
+    /// Events broadcast per match tick before some are dropped
+    pub max_events_per_tick: usize,
+    /// Events kept per match for `/matches/{id}/events`
+    pub event_log_capacity: usize,
+    /// How long a finished match's events stay available (seconds)
+    pub event_retention_secs: u64,
+    /// Matchmaking regions players may queue in; the first is the default
+    pub matchmaking_regions: Vec<String>,
+    /// Wait before a region short of players fills from other regions (seconds)
+    pub cross_region_wait_secs: u64,
+    /// Time each `/ready` dependency check may take before it counts as failed (seconds)
+    pub ready_check_timeout_secs: u64,
+    /// Players a match needs before its countdown starts (also what the queue waits for)
+    pub match_min_players: usize,
+    /// Players per match, the most matchmaking gives a new match
+    pub match_max_players: usize,
+    /// Fewest players matchmaking gives a new match; sizes ramp up from here
+    /// to `match_max_players` as the queue grows (kept within the min/max)
+    pub match_size_min: usize,
+    /// Warmup between enough players joining and the countdown (seconds, 0 = none)
+    pub match_warmup_secs: f32,
+    /// Countdown before the match starts (seconds)
+    pub match_countdown_secs: f32,
+    /// Wait after which the queue starts a match with fewer than the minimum (seconds)
+    pub matchmaking_max_wait_secs: u64,
 }
 
 impl Config {
+    /// Region for players who don't pick one
+    pub fn default_region(&self) -> &str {
+        &self.matchmaking_regions[0]
+    }
+
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self, ConfigError> {
         // Render provides PORT env var, fall back to SERVER_ADDR or default
@@ -41,12 +208,41 @@ impl Config {
             env::var("SERVER_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string())
         };
 
-        Ok(Self {
+        let simulation_tps = env::var("SIMULATION_TPS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(SIMULATION_TPS)
+            .clamp(1, 120);
+
+        // Per-player snapshots need a view radius; setting one alone keeps
+        // selecting them, as before SNAPSHOT_MODE existed
+        let view_radius = env::var("SNAPSHOT_VIEW_RADIUS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|radius: &f32| *radius > 0.0);
+        let snapshot_mode = match (env::var("SNAPSHOT_MODE").as_deref(), view_radius) {
+            (Ok("broadcast"), _) => SnapshotMode::Broadcast,
+            (Ok("per_player"), None) => {
+                return Err(ConfigError::Invalid(
+                    "SNAPSHOT_MODE=per_player needs a positive SNAPSHOT_VIEW_RADIUS".to_string(),
+                ))
+            }
+            (_, Some(view_radius)) => SnapshotMode::PerPlayer { view_radius },
+            (_, None) => SnapshotMode::Broadcast,
+        };
+
+        let config = Self {
             server_addr: server_addr
                 .parse()
                 .map_err(|_| ConfigError::InvalidAddress)?,
 
             log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            log_format: match env::var("LOG_FORMAT").as_deref() {
+                Ok("json") => LogFormat::Json,
+                _ => LogFormat::Pretty,
+            },
+            tls_cert_path: env::var("TLS_CERT_PATH").ok().filter(|p| !p.is_empty()),
+            tls_key_path: env::var("TLS_KEY_PATH").ok().filter(|p| !p.is_empty()),
 
             supabase_url: env::var("SUPABASE_URL")
                 .map_err(|_| ConfigError::Missing("SUPABASE_URL"))?,
@@ -56,6 +252,28 @@ impl Config {
                 .map_err(|_| ConfigError::Missing("SUPABASE_SERVICE_ROLE_KEY"))?,
             supabase_jwt_secret: env::var("SUPABASE_JWT_SECRET")
                 .map_err(|_| ConfigError::Missing("SUPABASE_JWT_SECRET"))?,
+            jwt_audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "authenticated".to_string()),
+            jwt_issuer: env::var("JWT_ISSUER").ok(),
+            admin_token: env::var("ADMIN_TOKEN").ok().filter(|v| !v.is_empty()),
+            jwt_leeway_secs: env::var("JWT_LEEWAY_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            guest_play: env::var("GUEST_PLAY")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            guest_token_ttl_secs: env::var("GUEST_TOKEN_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600),
+            supabase_max_retries: env::var("SUPABASE_MAX_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            supabase_retry_base_delay_ms: env::var("SUPABASE_RETRY_BASE_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
 
             stripe_secret_key: env::var("STRIPE_SECRET_KEY")
                 .map_err(|_| ConfigError::Missing("STRIPE_SECRET_KEY"))?,
@@ -66,8 +284,322 @@ impl Config {
                 .map_err(|_| ConfigError::Missing("PUBLIC_BASE_URL"))?,
             client_origin: env::var("CLIENT_ORIGIN")
                 .map_err(|_| ConfigError::Missing("CLIENT_ORIGIN"))?,
-        })
+            cors_max_age_secs: env::var("CORS_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            security_headers: env::var("SECURITY_HEADERS")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            referrer_policy: env::var("REFERRER_POLICY")
+                .unwrap_or_else(|_| "no-referrer".to_string()),
+            content_security_policy: env::var("CONTENT_SECURITY_POLICY")
+                .unwrap_or_else(|_| "default-src 'none'; frame-ancestors 'none'".to_string()),
+            bot_fill_delay_secs: env::var("BOT_FILL_DELAY_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .or(Some(10.0))
+                .filter(|secs| *secs > 0.0),
+            practice_bots: env::var("PRACTICE_BOTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            snapshot_precision: match env::var("SNAPSHOT_PRECISION").as_deref() {
+                Ok("quantized") => SnapshotPrecision::Quantized {
+                    position_scale: env::var("SNAPSHOT_POSITION_SCALE")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(100.0),
+                },
+                _ => SnapshotPrecision::Full,
+            },
+            simulation_tps,
+            snapshot_tps: env::var("SNAPSHOT_TPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(SNAPSHOT_TPS)
+                .clamp(1, simulation_tps),
+            snapshot_mode,
+            snapshot_projectiles: env::var("SNAPSHOT_PROJECTILES")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            prediction_tolerance: env::var("PREDICTION_TOLERANCE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0),
+            input_timeout_secs: env::var("INPUT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.5),
+            ws_ping_interval_secs: env::var("WS_PING_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            ws_idle_timeout_secs: env::var("WS_IDLE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            ws_allow_query_token: env::var("WS_ALLOW_QUERY_TOKEN")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            ws_compression: env::var("WS_COMPRESSION")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            ws_lag_disconnect_threshold: env::var("WS_LAG_DISCONNECT_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            ws_lag_window_secs: env::var("WS_LAG_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            ws_max_frame_bytes: env::var("WS_MAX_FRAME_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(64 * 1024),
+            ws_max_message_bytes: env::var("WS_MAX_MESSAGE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(4 * 1024),
+            ws_max_bad_messages: env::var("WS_MAX_BAD_MESSAGES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            ws_token_refresh_secs: env::var("WS_TOKEN_REFRESH_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            ws_duplicate_connections: match env::var("WS_DUPLICATE_CONNECTIONS").as_deref() {
+                Ok("reject_new") => DuplicateConnectionPolicy::RejectNew,
+                _ => DuplicateConnectionPolicy::EvictOld,
+            },
+            ip_rate_limit_per_sec: env::var("IP_RATE_LIMIT_PER_SEC")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            ip_max_connections: env::var("IP_MAX_CONNECTIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            trusted_proxy_hops: env::var("TRUSTED_PROXY_HOPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            snapshot_buffer_per_player: env::var("SNAPSHOT_BUFFER_PER_PLAYER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(8),
+            display_name_cooldown_secs: env::var("DISPLAY_NAME_COOLDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600),
+            abandon_threshold: env::var("ABANDON_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            abandon_window_secs: env::var("ABANDON_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1800),
+            abandon_cooldown_secs: env::var("ABANDON_COOLDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(120),
+            match_max_pause_secs: env::var("MATCH_MAX_PAUSE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(120.0),
+            match_empty_timeout_secs: env::var("MATCH_EMPTY_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .or(Some(60.0))
+                .filter(|secs: &f32| *secs > 0.0),
+            match_max_duration_secs: env::var("MATCH_MAX_DURATION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|secs: &f32| *secs > 0.0),
+            sudden_death_secs: env::var("SUDDEN_DEATH_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30.0),
+            zone_config_path: env::var("ZONE_CONFIG_PATH").ok().filter(|p| !p.is_empty()),
+            balance_config_path: env::var("BALANCE_CONFIG_PATH").ok().filter(|p| !p.is_empty()),
+            arena_config_path: env::var("ARENA_CONFIG_PATH").ok().filter(|p| !p.is_empty()),
+            match_arenas: env::var("MATCH_ARENAS")
+                .ok()
+                .map(|v| {
+                    v.split(',')
+                        .map(|id| id.trim().to_string())
+                        .filter(|id| !id.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|ids| !ids.is_empty())
+                .unwrap_or_else(|| vec![OPEN_ARENA.to_string(), CLUTTERED_ARENA.to_string()]),
+            replay_dir: env::var("REPLAY_DIR").ok().filter(|d| !d.is_empty()),
+            zone_bypasses_shield: env::var("ZONE_DAMAGE_BYPASSES_SHIELD")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            self_damage: env::var("SELF_DAMAGE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            projectile_clash: env::var("PROJECTILE_CLASH")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            aim_assist_cone_deg: env::var("AIM_ASSIST_CONE_DEG")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(6.0),
+            aim_assist_strength: env::var("AIM_ASSIST_STRENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.5),
+            zone_warning_secs: env::var("ZONE_WARNING_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10.0),
+            late_join: env::var("LATE_JOIN")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            spawn_protection_secs: env::var("SPAWN_PROTECTION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3.0),
+            spawn_separation: env::var("SPAWN_SEPARATION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300.0),
+            max_projectiles: env::var("MAX_PROJECTILES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            max_projectiles_per_player: env::var("MAX_PROJECTILES_PER_PLAYER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(64),
+            max_events_per_tick: env::var("MAX_EVENTS_PER_TICK")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(256),
+            event_log_capacity: env::var("MATCH_EVENT_LOG_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10_000),
+            event_retention_secs: env::var("MATCH_EVENT_RETENTION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            matchmaking_regions: env::var("MATCHMAKING_REGIONS")
+                .ok()
+                .map(|v| {
+                    v.split(',')
+                        .map(|region| region.trim().to_lowercase())
+                        .filter(|region| !region.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|regions| !regions.is_empty())
+                .unwrap_or_else(|| vec!["global".to_string()]),
+            cross_region_wait_secs: env::var("CROSS_REGION_WAIT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            ready_check_timeout_secs: env::var("READY_CHECK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            match_min_players: env::var("MATCH_MIN_PLAYERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            match_max_players: env::var("MATCH_MAX_PLAYERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            match_size_min: env::var("MATCH_SIZE_MIN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(8),
+            match_warmup_secs: env::var("MATCH_WARMUP_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0),
+            match_countdown_secs: env::var("MATCH_COUNTDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5.0),
+            matchmaking_max_wait_secs: env::var("MATCHMAKING_MAX_WAIT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+        };
+        config.validate()?;
+        Ok(config)
     }
+
+    /// Reject settings no match could run with
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.match_min_players == 0 {
+            return Err(ConfigError::Invalid(
+                "MATCH_MIN_PLAYERS must be at least 1".to_string(),
+            ));
+        }
+        if self.match_min_players > self.match_max_players {
+            return Err(ConfigError::Invalid(format!(
+                "MATCH_MIN_PLAYERS ({}) can't exceed MATCH_MAX_PLAYERS ({})",
+                self.match_min_players, self.match_max_players
+            )));
+        }
+        if self.match_warmup_secs < 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "MATCH_WARMUP_SECS can't be negative, got {}",
+                self.match_warmup_secs
+            )));
+        }
+        if self.match_countdown_secs <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "MATCH_COUNTDOWN_SECS must be positive, got {}",
+                self.match_countdown_secs
+            )));
+        }
+        if self.ws_max_message_bytes == 0 || self.ws_max_message_bytes > self.ws_max_frame_bytes {
+            return Err(ConfigError::Invalid(format!(
+                "WS_MAX_MESSAGE_BYTES must be between 1 and WS_MAX_FRAME_BYTES ({}), got {}",
+                self.ws_max_frame_bytes, self.ws_max_message_bytes
+            )));
+        }
+        for (name, value) in [
+            ("REFERRER_POLICY", &self.referrer_policy),
+            ("CONTENT_SECURITY_POLICY", &self.content_security_policy),
+        ] {
+            if HeaderValue::from_str(value).is_err() {
+                return Err(ConfigError::Invalid(format!(
+                    "{name} is not a valid header value"
+                )));
+            }
+        }
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            return Err(ConfigError::Invalid(
+                "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+            ));
+        }
+        if self.admin_token.as_ref().is_some_and(|t| t.len() < MIN_ADMIN_TOKEN_LEN) {
+            return Err(ConfigError::Invalid(format!(
+                "ADMIN_TOKEN must be at least {MIN_ADMIN_TOKEN_LEN} characters"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Shortest `ADMIN_TOKEN` accepted, so it can't be guessed
+const MIN_ADMIN_TOKEN_LEN: usize = 32;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines for local development
+    Pretty,
+    /// One JSON object per line, with event fields as top-level keys
+    Json,
 }
 
 /// Configuration errors
@@ -78,4 +610,7 @@ pub enum ConfigError {
 
     #[error("Invalid server address format")]
     InvalidAddress,
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
 }
@@ -0,0 +1,192 @@
+//! Input sanity checks - flags clients sending inputs no honest client could
+//!
+//! The server owns physics, so a tampered client can only lie through the
+//! fields of `InputTick`. Each implausible input is rejected and raises the
+//! player's suspicion score, which slowly decays; past `KICK_THRESHOLD` the
+//! player is removed from the match.
+
+use std::f32::consts::TAU;
+
+use crate::util::rate_limit::INPUT_RATE_LIMIT;
+
+use super::TickInput;
+
+/// Suspicion at which a player is kicked
+pub const KICK_THRESHOLD: f32 = 100.0;
+
+/// Suspicion forgiven per second of match time
+const SUSPICION_DECAY_PER_SEC: f32 = 5.0;
+
+/// Most distinct input seqs accepted per second. The WebSocket limiter
+/// already caps honest clients at `INPUT_RATE_LIMIT`; twice that leaves room
+/// for inputs that queued up behind a slow tick
+const MAX_SEQS_PER_SEC: u32 = INPUT_RATE_LIMIT * 2;
+
+/// Largest forward jump in seq between consecutive accepted inputs
+const MAX_SEQ_JUMP: u32 = 1_000;
+
+/// An input that no honest client would send
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// NaN or infinite throttle, steer or aim
+    NonFiniteInput,
+    /// Seq skipped far ahead of the last accepted one
+    SeqJump(u32),
+    /// More distinct seqs in a second than the input rate limit allows
+    SeqFlood(u32),
+}
+
+impl Violation {
+    /// Suspicion added for one occurrence
+    fn weight(&self) -> f32 {
+        match self {
+            Violation::NonFiniteInput => 50.0,
+            Violation::SeqJump(_) => 25.0,
+            Violation::SeqFlood(_) => 10.0,
+        }
+    }
+}
+
+/// Per-player suspicion tracking, driven by the match clock so replays
+/// reach the same verdicts
+#[derive(Debug, Clone, Default)]
+pub struct InputGuard {
+    suspicion: f32,
+    /// Match clock of the last decay step
+    last_decay_ms: u64,
+    /// Start of the current one-second seq window
+    window_start_ms: u64,
+    seqs_in_window: u32,
+}
+
+impl InputGuard {
+    /// Check an input newer than `last_seq`. Returns the violation, if any,
+    /// in which case the input should be dropped
+    pub fn inspect(&mut self, input: &TickInput, last_seq: u32, now_ms: u64) -> Option<Violation> {
+        self.decay(now_ms);
+
+        if now_ms.saturating_sub(self.window_start_ms) >= 1000 {
+            self.window_start_ms = now_ms;
+            self.seqs_in_window = 0;
+        }
+        self.seqs_in_window += 1;
+
+        let violation = if ![input.throttle, input.steer, input.strafe, input.aim_yaw]
+            .iter()
+            .all(|v| v.is_finite())
+        {
+            Some(Violation::NonFiniteInput)
+        } else if last_seq > 0 && input.seq - last_seq > MAX_SEQ_JUMP {
+            Some(Violation::SeqJump(input.seq - last_seq))
+        } else if self.seqs_in_window > MAX_SEQS_PER_SEC {
+            Some(Violation::SeqFlood(self.seqs_in_window))
+        } else {
+            None
+        };
+
+        if let Some(violation) = violation {
+            self.suspicion += violation.weight();
+        }
+        violation
+    }
+
+    /// Current suspicion score
+    pub fn suspicion(&self) -> f32 {
+        self.suspicion
+    }
+
+    /// Whether the player has crossed the kick threshold
+    pub fn should_kick(&self) -> bool {
+        self.suspicion >= KICK_THRESHOLD
+    }
+
+    fn decay(&mut self, now_ms: u64) {
+        let elapsed_secs = now_ms.saturating_sub(self.last_decay_ms) as f32 / 1000.0;
+        self.suspicion = (self.suspicion - elapsed_secs * SUSPICION_DECAY_PER_SEC).max(0.0);
+        self.last_decay_ms = now_ms;
+    }
+}
+
+/// Wrap an aim angle into `[0, TAU)`
+pub fn normalize_yaw(yaw: f32) -> f32 {
+    let wrapped = yaw.rem_euclid(TAU);
+    // rem_euclid rounds tiny negative angles up to exactly TAU
+    if wrapped >= TAU {
+        0.0
+    } else {
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(seq: u32) -> TickInput {
+        TickInput {
+            seq,
+            throttle: 1.0,
+            aim_yaw: 1.0,
+            ..TickInput::default()
+        }
+    }
+
+    #[test]
+    fn honest_inputs_are_never_flagged() {
+        let mut guard = InputGuard::default();
+        for seq in 1..=3000 {
+            let now_ms = seq as u64 * 1000 / INPUT_RATE_LIMIT as u64;
+            assert_eq!(guard.inspect(&input(seq), seq - 1, now_ms), None);
+        }
+        assert_eq!(guard.suspicion(), 0.0);
+    }
+
+    #[test]
+    fn non_finite_aim_is_rejected() {
+        let mut guard = InputGuard::default();
+        let bad = TickInput {
+            aim_yaw: f32::NAN,
+            ..input(1)
+        };
+        assert_eq!(guard.inspect(&bad, 0, 0), Some(Violation::NonFiniteInput));
+        assert!(guard.suspicion() > 0.0);
+    }
+
+    #[test]
+    fn seq_jumps_are_flagged() {
+        let mut guard = InputGuard::default();
+        assert_eq!(
+            guard.inspect(&input(5000), 10, 0),
+            Some(Violation::SeqJump(4990))
+        );
+    }
+
+    #[test]
+    fn sustained_flooding_gets_kicked() {
+        let mut guard = InputGuard::default();
+        let mut seq = 0;
+        while !guard.should_kick() {
+            seq += 1;
+            guard.inspect(&input(seq), seq - 1, 0);
+            assert!(seq < 1000, "flooding never crossed the kick threshold");
+        }
+        assert!(seq > MAX_SEQS_PER_SEC);
+    }
+
+    #[test]
+    fn suspicion_decays_over_time() {
+        let mut guard = InputGuard::default();
+        guard.inspect(&input(5000), 10, 0);
+        let flagged = guard.suspicion();
+        guard.inspect(&input(5001), 5000, 2000);
+        assert!(guard.suspicion() < flagged);
+    }
+
+    #[test]
+    fn yaw_wraps_into_one_turn() {
+        assert_eq!(normalize_yaw(1.0), 1.0);
+        assert!((normalize_yaw(-1.0) - (TAU - 1.0)).abs() < 1e-5);
+        assert!((normalize_yaw(TAU + 0.5) - 0.5).abs() < 1e-5);
+        assert_eq!(normalize_yaw(-1e-9), 0.0);
+    }
+}
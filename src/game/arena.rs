@@ -0,0 +1,355 @@
+//! Arenas matches are played in
+//!
+//! An arena sets the hard world boundary, the obstacles and repair pads
+//! inside it, the zone's size and pacing, and how far from the zone center
+//! players spawn. `open` and `cluttered` are built in, laid out around the
+//! configured zone.
+//! `ARENA_CONFIG_PATH` can point at a JSON file adding arenas or replacing
+//! built-in ones by id; it is read and validated once at startup. Each new
+//! match takes an arena from the `MATCH_ARENAS` rotation.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use crate::ws::protocol::{RepairPad, WorldBounds};
+
+use super::r#match::ZoneConfig;
+
+/// Id of the arena without obstacles
+pub const OPEN_ARENA: &str = "open";
+
+/// Id of the arena with asteroid belts around the center
+pub const CLUTTERED_ARENA: &str = "cluttered";
+
+/// Spawn radius of arenas that don't set one, as a fraction of the initial
+/// zone radius
+const DEFAULT_SPAWN_FRACTION: f32 = 0.8;
+
+/// A solid circle ships bump into and shots stop at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Obstacle {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+impl Obstacle {
+    /// Distance from a point to the obstacle's edge (negative inside)
+    pub fn distance(&self, x: f32, y: f32) -> f32 {
+        (x - self.x).hypot(y - self.y) - self.radius
+    }
+}
+
+/// Map a match is played on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Arena {
+    pub id: String,
+    /// Hard world boundary ships can't leave
+    pub bounds: WorldBounds,
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub repair_pads: Vec<RepairPad>,
+    /// Zone size and shrink timings
+    pub zone: ZoneConfig,
+    /// Spawns land within this distance of the zone center while the zone is
+    /// at full size, and proportionally closer once it shrinks
+    pub spawn_radius: f32,
+}
+
+impl Arena {
+    /// No obstacles, bounded just past the zone
+    pub fn open(zone: &ZoneConfig) -> Self {
+        Self {
+            id: OPEN_ARENA.to_string(),
+            bounds: zone.world_bounds(),
+            obstacles: Vec::new(),
+            repair_pads: Vec::new(),
+            zone: zone.clone(),
+            spawn_radius: zone.initial_radius * DEFAULT_SPAWN_FRACTION,
+        }
+    }
+
+    /// The open arena with two belts of asteroids around the center, which
+    /// is left clear for the final zones
+    pub fn cluttered(zone: &ZoneConfig) -> Self {
+        let r = zone.initial_radius;
+        let aspect = zone.shape.aspect();
+        let belt = |count: usize, distance: f32, radius: f32, offset: f32| {
+            (0..count).map(move |i| {
+                let angle = offset + i as f32 * TAU / count as f32;
+                Obstacle {
+                    x: angle.cos() * distance,
+                    y: angle.sin() * distance * aspect,
+                    radius,
+                }
+            })
+        };
+
+        Self {
+            id: CLUTTERED_ARENA.to_string(),
+            obstacles: belt(6, 0.35 * r, 0.04 * r, 0.0)
+                .chain(belt(10, 0.65 * r, 0.05 * r, TAU / 20.0))
+                .collect(),
+            ..Self::open(zone)
+        }
+    }
+
+    /// Check the arena is playable
+    pub fn validate(&self) -> Result<(), ArenaError> {
+        let invalid = |msg: String| Err(ArenaError::Invalid(format!("arena {}: {}", self.id, msg)));
+
+        if self.id.is_empty() {
+            return Err(ArenaError::Invalid("arena id can't be empty".to_string()));
+        }
+        if let Err(e) = self.zone.validate() {
+            return invalid(e.to_string());
+        }
+        if !(self.bounds.half_width > 0.0 && self.bounds.half_height > 0.0) {
+            return invalid(format!(
+                "bounds must be positive, got {} x {}",
+                self.bounds.half_width, self.bounds.half_height
+            ));
+        }
+        if !(self.spawn_radius > 0.0 && self.spawn_radius <= self.zone.initial_radius) {
+            return invalid(format!(
+                "spawn_radius must be positive and at most the initial zone radius {}, got {}",
+                self.zone.initial_radius, self.spawn_radius
+            ));
+        }
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            if !(obstacle.radius > 0.0 && obstacle.radius.is_finite()) {
+                return invalid(format!(
+                    "obstacle {}: radius must be positive, got {}",
+                    i, obstacle.radius
+                ));
+            }
+            if !(obstacle.x.abs() <= self.bounds.half_width
+                && obstacle.y.abs() <= self.bounds.half_height)
+            {
+                return invalid(format!(
+                    "obstacle {}: center ({}, {}) is outside the bounds",
+                    i, obstacle.x, obstacle.y
+                ));
+            }
+            if obstacle.distance(0.0, 0.0) <= 0.0 {
+                return invalid(format!("obstacle {}: covers the zone center", i));
+            }
+        }
+        for (i, pad) in self.repair_pads.iter().enumerate() {
+            if !(pad.radius > 0.0 && pad.radius.is_finite()) {
+                return invalid(format!(
+                    "repair pad {}: radius must be positive, got {}",
+                    i, pad.radius
+                ));
+            }
+            if !(pad.heal_per_sec > 0.0 && pad.heal_per_sec.is_finite()) {
+                return invalid(format!(
+                    "repair pad {}: heal_per_sec must be positive, got {}",
+                    i, pad.heal_per_sec
+                ));
+            }
+            if !(pad.cooldown >= 0.0 && pad.cooldown.is_finite()) {
+                return invalid(format!(
+                    "repair pad {}: cooldown can't be negative, got {}",
+                    i, pad.cooldown
+                ));
+            }
+            if !(pad.x.abs() <= self.bounds.half_width && pad.y.abs() <= self.bounds.half_height) {
+                return invalid(format!(
+                    "repair pad {}: center ({}, {}) is outside the bounds",
+                    i, pad.x, pad.y
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An arena as written in a config file; anything left out is derived from
+/// the configured zone like the built-in arenas
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArenaEntry {
+    id: String,
+    #[serde(default)]
+    obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    repair_pads: Vec<RepairPad>,
+    zone: Option<ZoneConfig>,
+    bounds: Option<WorldBounds>,
+    spawn_radius: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArenaFile {
+    arenas: Vec<ArenaEntry>,
+}
+
+/// Every arena matches can be played in, by id
+#[derive(Debug, Clone)]
+pub struct ArenaCatalog {
+    arenas: BTreeMap<String, Arc<Arena>>,
+}
+
+impl ArenaCatalog {
+    /// The built-in arenas, laid out around `zone`
+    pub fn built_in(zone: &ZoneConfig) -> Self {
+        let arenas = [Arena::open(zone), Arena::cluttered(zone)]
+            .into_iter()
+            .map(|arena| (arena.id.clone(), Arc::new(arena)))
+            .collect();
+        Self { arenas }
+    }
+
+    /// Load arenas from a JSON file on top of the built-in ones and validate
+    /// them
+    pub fn from_file(path: &str, zone: &ZoneConfig) -> Result<Self, ArenaError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| ArenaError::Read(path.to_string(), e))?;
+        Self::from_json(&contents, zone).map_err(|e| match e {
+            ArenaError::Parse(_, e) => ArenaError::Parse(path.to_string(), e),
+            e => e,
+        })
+    }
+
+    /// Parse arenas from JSON on top of the built-in ones and validate them.
+    /// An arena with a built-in id replaces that arena entirely
+    pub fn from_json(json: &str, zone: &ZoneConfig) -> Result<Self, ArenaError> {
+        let file: ArenaFile =
+            serde_json::from_str(json).map_err(|e| ArenaError::Parse(String::new(), e))?;
+
+        let mut catalog = Self::built_in(zone);
+        for entry in file.arenas {
+            let zone = entry.zone.unwrap_or_else(|| zone.clone());
+            let arena = Arena {
+                bounds: entry.bounds.unwrap_or_else(|| zone.world_bounds()),
+                spawn_radius: entry
+                    .spawn_radius
+                    .unwrap_or(zone.initial_radius * DEFAULT_SPAWN_FRACTION),
+                id: entry.id,
+                obstacles: entry.obstacles,
+                repair_pads: entry.repair_pads,
+                zone,
+            };
+            arena.validate()?;
+            catalog.arenas.insert(arena.id.clone(), Arc::new(arena));
+        }
+        Ok(catalog)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Arena>> {
+        self.arenas.get(id).cloned()
+    }
+
+    /// Look up every arena of a rotation, failing on unknown ids
+    pub fn rotation(&self, ids: &[String]) -> Result<Vec<Arc<Arena>>, ArenaError> {
+        if ids.is_empty() {
+            return Err(ArenaError::Invalid(
+                "the arena rotation is empty".to_string(),
+            ));
+        }
+        ids.iter()
+            .map(|id| {
+                self.get(id)
+                    .ok_or_else(|| ArenaError::Invalid(format!("unknown arena {id}")))
+            })
+            .collect()
+    }
+}
+
+/// Errors loading arenas
+#[derive(Debug, thiserror::Error)]
+pub enum ArenaError {
+    #[error("Failed to read arena config {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse arena config {0}: {1}")]
+    Parse(String, serde_json::Error),
+
+    #[error("Invalid arena config: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_arenas_are_valid() {
+        let zone = ZoneConfig::default();
+        let catalog = ArenaCatalog::built_in(&zone);
+        for id in [OPEN_ARENA, CLUTTERED_ARENA] {
+            catalog.get(id).unwrap().validate().unwrap();
+        }
+        assert!(catalog.get(OPEN_ARENA).unwrap().obstacles.is_empty());
+        assert!(!catalog.get(CLUTTERED_ARENA).unwrap().obstacles.is_empty());
+    }
+
+    #[test]
+    fn file_arenas_fill_in_defaults_and_replace_by_id() {
+        let json = r#"{ "arenas": [
+            { "id": "pillars", "obstacles": [{ "x": 300, "y": 0, "radius": 50 }] },
+            { "id": "open", "spawn_radius": 400 }
+        ] }"#;
+        let zone = ZoneConfig::default();
+        let catalog = ArenaCatalog::from_json(json, &zone).unwrap();
+
+        let pillars = catalog.get("pillars").unwrap();
+        assert_eq!(pillars.bounds, zone.world_bounds());
+        assert_eq!(pillars.obstacles.len(), 1);
+        assert_eq!(catalog.get(OPEN_ARENA).unwrap().spawn_radius, 400.0);
+        assert!(catalog.get(CLUTTERED_ARENA).is_some());
+    }
+
+    #[test]
+    fn rejects_unplayable_arenas() {
+        let zone = ZoneConfig::default();
+        for (json, field) in [
+            (
+                r#"{ "arenas": [{ "id": "a", "obstacles": [{ "x": 0, "y": 0, "radius": 10 }] }] }"#,
+                "zone center",
+            ),
+            (
+                r#"{ "arenas": [{ "id": "a", "obstacles": [{ "x": 9000, "y": 0, "radius": 10 }] }] }"#,
+                "outside the bounds",
+            ),
+            (
+                r#"{ "arenas": [{ "id": "a", "spawn_radius": 0 }] }"#,
+                "spawn_radius",
+            ),
+            (
+                r#"{ "arenas": [{ "id": "a", "repair_pads": [
+                    { "x": 0, "y": 0, "radius": 50, "heal_per_sec": 0 }
+                ] }] }"#,
+                "heal_per_sec",
+            ),
+            (r#"{ "arenas": [{ "id": "" }] }"#, "id"),
+        ] {
+            let err = ArenaCatalog::from_json(json, &zone).unwrap_err();
+            assert!(err.to_string().contains(field), "{err}");
+        }
+    }
+
+    #[test]
+    fn example_file_loads() {
+        let catalog =
+            ArenaCatalog::from_file("arena.example.json", &ZoneConfig::default()).unwrap();
+        let pillars = catalog.get("pillars").unwrap();
+        assert_eq!(pillars.obstacles.len(), 4);
+        assert_eq!(pillars.repair_pads.len(), 1);
+    }
+
+    #[test]
+    fn rotation_rejects_unknown_ids() {
+        let catalog = ArenaCatalog::built_in(&ZoneConfig::default());
+        let rotation = catalog.rotation(&[CLUTTERED_ARENA.to_string()]).unwrap();
+        assert_eq!(rotation[0].id, CLUTTERED_ARENA);
+        assert!(catalog.rotation(&["moon".to_string()]).is_err());
+        assert!(catalog.rotation(&[]).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! Ship and weapon balance, loadable from a file
+//!
+//! `ShipStats::for_type` and `WeaponStats::for_loadout` hold the built-in
+//! tuning. `BALANCE_CONFIG_PATH` can point at a JSON file overriding any of
+//! it; the file is read and validated once at startup and the table is shared
+//! by every match created afterwards.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use crate::ws::protocol::{ShipType, WeaponLoadout};
+
+use super::combat::WeaponStats;
+use super::physics::ShipStats;
+
+/// Stats of every hull and weapon loadout
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceTable {
+    ships: HashMap<ShipType, ShipStats>,
+    weapons: HashMap<WeaponLoadout, WeaponStats>,
+}
+
+/// Entries of a balance file; anything left out keeps its built-in stats
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BalanceOverrides {
+    #[serde(default)]
+    ships: HashMap<ShipType, ShipStats>,
+    #[serde(default)]
+    weapons: HashMap<WeaponLoadout, WeaponStats>,
+}
+
+impl Default for BalanceTable {
+    /// The built-in stats
+    fn default() -> Self {
+        Self {
+            ships: ShipType::ALL
+                .into_iter()
+                .map(|t| (t, ShipStats::for_type(t)))
+                .collect(),
+            weapons: WeaponLoadout::ALL
+                .into_iter()
+                .map(|l| (l, WeaponStats::for_loadout(l)))
+                .collect(),
+        }
+    }
+}
+
+impl BalanceTable {
+    /// Load overrides from a JSON file on top of the built-in stats and
+    /// validate the result
+    pub fn from_file(path: &str) -> Result<Self, BalanceError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| BalanceError::Read(path.to_string(), e))?;
+        Self::from_json(&contents).map_err(|e| match e {
+            BalanceError::Parse(_, e) => BalanceError::Parse(path.to_string(), e),
+            e => e,
+        })
+    }
+
+    /// Parse overrides from JSON on top of the built-in stats and validate
+    /// the result
+    pub fn from_json(json: &str) -> Result<Self, BalanceError> {
+        let overrides: BalanceOverrides =
+            serde_json::from_str(json).map_err(|e| BalanceError::Parse(String::new(), e))?;
+
+        let mut table = Self::default();
+        table.ships.extend(overrides.ships);
+        table.weapons.extend(overrides.weapons);
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Stats of a hull
+    pub fn ship(&self, ship_type: ShipType) -> ShipStats {
+        self.ships
+            .get(&ship_type)
+            .copied()
+            .unwrap_or_else(|| ShipStats::for_type(ship_type))
+    }
+
+    /// Stats of a weapon loadout
+    pub fn weapon(&self, loadout: WeaponLoadout) -> WeaponStats {
+        self.weapons
+            .get(&loadout)
+            .copied()
+            .unwrap_or_else(|| WeaponStats::for_loadout(loadout))
+    }
+
+    /// Check every stat is in a range the simulation can run with
+    pub fn validate(&self) -> Result<(), BalanceError> {
+        for ship_type in ShipType::ALL {
+            let s = self.ship(ship_type);
+            let name = |field: &str| format!("ships.{}.{}", ship_type.key(), field);
+
+            positive(&name("max_speed"), s.max_speed)?;
+            positive(&name("acceleration"), s.acceleration)?;
+            in_range(&name("drag"), s.drag, f32::MIN_POSITIVE, 1.0)?;
+            positive(&name("turn_rate"), s.turn_rate)?;
+            positive(&name("max_health"), s.max_health)?;
+            non_negative(&name("max_shield"), s.max_shield)?;
+            non_negative(&name("shield_regen"), s.shield_regen)?;
+            positive(&name("hitbox_radius"), s.hitbox_radius)?;
+            in_range(&name("boost_speed_multiplier"), s.boost_speed_multiplier, 1.0, f32::MAX)?;
+            in_range(&name("boost_accel_multiplier"), s.boost_accel_multiplier, 1.0, f32::MAX)?;
+            in_range(&name("reverse_multiplier"), s.reverse_multiplier, 0.0, 1.0)?;
+        }
+
+        for loadout in WeaponLoadout::ALL {
+            let w = self.weapon(loadout);
+            let name = |field: &str| format!("weapons.{}.{}", loadout.key(), field);
+
+            non_negative(&name("damage"), w.damage)?;
+            positive(&name("projectile_speed"), w.projectile_speed)?;
+            non_negative(&name("cooldown"), w.cooldown)?;
+            positive(&name("projectile_lifetime"), w.projectile_lifetime)?;
+            positive(&name("projectile_radius"), w.projectile_radius)?;
+            in_range(&name("crit_chance"), w.crit_chance, 0.0, 1.0)?;
+            in_range(&name("crit_multiplier"), w.crit_multiplier, 1.0, f32::MAX)?;
+            in_range(&name("spread_radians"), w.spread_radians, 0.0, TAU)?;
+            in_range(&name("velocity_inheritance"), w.velocity_inheritance, 0.0, 1.0)?;
+            if w.pellet_count == 0 {
+                return Err(BalanceError::Invalid(format!(
+                    "{} must be at least 1",
+                    name("pellet_count")
+                )));
+            }
+            if let Some(effect) = w.on_hit_effect {
+                positive(&name("on_hit_effect.duration"), effect.duration)?;
+                non_negative(&name("on_hit_effect.damage_per_second"), effect.damage_per_second)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn in_range(name: &str, value: f32, min: f32, max: f32) -> Result<(), BalanceError> {
+    // Written so NaN fails too
+    if value >= min && value <= max {
+        Ok(())
+    } else {
+        Err(BalanceError::Invalid(format!(
+            "{name} must be between {min} and {max}, got {value}"
+        )))
+    }
+}
+
+fn positive(name: &str, value: f32) -> Result<(), BalanceError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(BalanceError::Invalid(format!(
+            "{name} must be positive, got {value}"
+        )))
+    }
+}
+
+fn non_negative(name: &str, value: f32) -> Result<(), BalanceError> {
+    if value >= 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(BalanceError::Invalid(format!(
+            "{name} can't be negative, got {value}"
+        )))
+    }
+}
+
+/// Errors loading a balance file
+#[derive(Debug, thiserror::Error)]
+pub enum BalanceError {
+    #[error("Failed to read balance config {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse balance config {0}: {1}")]
+    Parse(String, serde_json::Error),
+
+    #[error("Invalid balance config: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_file_matches_built_in_stats() {
+        let table = BalanceTable::from_file("balance.example.json").unwrap();
+        assert_eq!(table, BalanceTable::default());
+    }
+
+    #[test]
+    fn overrides_replace_only_their_entries() {
+        let mut scout = ShipStats::for_type(ShipType::Scout);
+        scout.max_speed = 450.0;
+        let json = serde_json::json!({ "ships": { "scout": scout } }).to_string();
+
+        let table = BalanceTable::from_json(&json).unwrap();
+        assert_eq!(table.ship(ShipType::Scout).max_speed, 450.0);
+        assert_eq!(
+            table.ship(ShipType::Fighter).max_speed,
+            ShipStats::for_type(ShipType::Fighter).max_speed
+        );
+        assert_eq!(
+            table.weapon(WeaponLoadout::Railgun).damage,
+            WeaponStats::for_loadout(WeaponLoadout::Railgun).damage
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_stats() {
+        let mut cannon = WeaponStats::for_loadout(WeaponLoadout::Cannon);
+        cannon.cooldown = -0.1;
+        let json = serde_json::json!({ "weapons": { "cannon": cannon } }).to_string();
+        let err = BalanceTable::from_json(&json).unwrap_err();
+        assert!(err.to_string().contains("weapons.cannon.cooldown"), "{err}");
+
+        let mut cruiser = ShipStats::for_type(ShipType::Cruiser);
+        cruiser.max_speed = 0.0;
+        let json = serde_json::json!({ "ships": { "cruiser": cruiser } }).to_string();
+        let err = BalanceTable::from_json(&json).unwrap_err();
+        assert!(err.to_string().contains("ships.cruiser.max_speed"), "{err}");
+    }
+
+    #[test]
+    fn rejects_unknown_and_incomplete_entries() {
+        let unknown = r#"{ "ships": { "battleship": {} } }"#;
+        assert!(matches!(
+            BalanceTable::from_json(unknown),
+            Err(BalanceError::Parse(..))
+        ));
+
+        let incomplete = r#"{ "weapons": { "pulse": { "damage": 6.0 } } }"#;
+        assert!(matches!(
+            BalanceTable::from_json(incomplete),
+            Err(BalanceError::Parse(..))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+//! Simple AI for bot-controlled ships
+
+use std::collections::BTreeMap;
+use std::f32::consts::{PI, TAU};
+use uuid::Uuid;
+
+use crate::ws::protocol::{ShipType, ZoneState};
+
+use super::physics::PhysicsSystem;
+use super::{PlayerState, TickInput};
+
+/// Ship types bots pick from
+pub const BOT_SHIP_TYPES: [ShipType; 4] = [
+    ShipType::Scout,
+    ShipType::Fighter,
+    ShipType::Cruiser,
+    ShipType::Destroyer,
+];
+
+/// Fraction of the zone size bots try to stay within
+const ZONE_COMFORT: f32 = 0.85;
+
+/// Bots ease off the throttle inside this distance to their target
+const KEEP_DISTANCE: f32 = 150.0;
+
+/// Fraction of max projectile travel bots will fire at
+const FIRE_RANGE_FACTOR: f32 = 0.8;
+
+/// Produces inputs for bot-controlled ships
+pub struct BotController;
+
+impl BotController {
+    /// Decide a bot's input for this tick: seek the nearest enemy,
+    /// fire when in range, and head back inside the zone when drifting out
+    pub fn think(
+        bot: &PlayerState,
+        players: &BTreeMap<Uuid, PlayerState>,
+        zone: &ZoneState,
+    ) -> TickInput {
+        let weapon = bot.weapon;
+
+        let target = players
+            .values()
+            .filter(|p| p.alive && p.user_id != bot.user_id)
+            .min_by(|a, b| {
+                distance_sq(bot, a.x, a.y).total_cmp(&distance_sq(bot, b.x, b.y))
+            });
+
+        let to_center_x = zone.center_x - bot.x;
+        let to_center_y = zone.center_y - bot.y;
+        let outside_comfort =
+            PhysicsSystem::zone_distance(bot.x, bot.y, zone) > -zone.radius * (1.0 - ZONE_COMFORT);
+
+        let mut input = TickInput {
+            seq: bot.current_input.seq.wrapping_add(1),
+            throttle: 1.0,
+            aim_yaw: bot.rotation,
+            ..TickInput::default()
+        };
+
+        if let Some(target) = target {
+            let dist = distance_sq(bot, target.x, target.y).sqrt();
+
+            // Lead the target by its travel time
+            let lead = dist / weapon.projectile_speed;
+            let aim_x = target.x + target.vel_x * lead;
+            let aim_y = target.y + target.vel_y * lead;
+            input.aim_yaw = (aim_y - bot.y).atan2(aim_x - bot.x);
+
+            let range = weapon.projectile_speed * weapon.projectile_lifetime * FIRE_RANGE_FACTOR;
+            input.shoot = dist <= range;
+
+            if !outside_comfort {
+                input.steer = steer_towards(bot.rotation, input.aim_yaw);
+                if dist < KEEP_DISTANCE {
+                    input.throttle = 0.3;
+                }
+            }
+        }
+
+        if outside_comfort {
+            input.steer = steer_towards(bot.rotation, to_center_y.atan2(to_center_x));
+        }
+
+        input
+    }
+}
+
+fn distance_sq(bot: &PlayerState, x: f32, y: f32) -> f32 {
+    let dx = x - bot.x;
+    let dy = y - bot.y;
+    dx * dx + dy * dy
+}
+
+/// Steering input that turns `rotation` towards `heading`
+fn steer_towards(rotation: f32, heading: f32) -> f32 {
+    let diff = (heading - rotation + PI).rem_euclid(TAU) - PI;
+    (diff * 2.0).clamp(-1.0, 1.0)
+}
@@ -1,12 +1,55 @@
 //! Combat system - weapons, damage, hit detection
 
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
 use uuid::Uuid;
 
-use crate::util::time::tick_delta;
-use crate::ws::protocol::ShipType;
+use crate::ws::protocol::{StatusEffectKind, WeaponLoadout};
+
+use super::anticheat::normalize_yaw;
+use super::effects::OnHitEffect;
+use super::spatial::SpatialGrid;
+
+/// Time without taking damage before shields start recharging (seconds)
+pub const SHIELD_REGEN_DELAY: f32 = 3.0;
+
+/// Mines a player can have deployed at once
+pub const MAX_MINES_PER_PLAYER: usize = 3;
+
+/// Cooldown between mine deployments (seconds)
+pub const MINE_DEPLOY_COOLDOWN: f32 = 1.0;
+
+/// Damage at the center of a mine's blast
+const MINE_DAMAGE: f32 = 35.0;
+
+/// Distance at which an enemy ship sets a mine off
+const MINE_TRIGGER_RADIUS: f32 = 40.0;
+
+/// Reach of a mine's blast; damage falls off linearly to zero at the edge
+const MINE_BLAST_RADIUS: f32 = 90.0;
+
+/// Mine lifetime before it detonates on its own (seconds)
+const MINE_LIFETIME: f32 = 30.0;
+
+/// Projectiles checked pairwise for clashes; past this they're bucketed in a
+/// spatial grid first
+const CLASH_BUCKET_THRESHOLD: usize = 64;
+
+/// Delay before a fresh mine can be triggered (seconds)
+const MINE_ARM_TIME: f32 = 1.0;
+
+/// Widest cone, either side of the aim, aim assist looks for a target in
+/// (radians, about 10 degrees), whatever it's configured to
+pub const MAX_AIM_ASSIST_CONE: f32 = 0.175;
+
+/// Furthest aim assist turns a shot (radians, about 4 degrees), so it nudges
+/// the aim rather than locking on
+pub const MAX_AIM_ASSIST_SNAP: f32 = 0.07;
 
 /// Weapon stats per ship type
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WeaponStats {
     /// Damage per hit
     pub damage: f32,
@@ -18,38 +61,159 @@ pub struct WeaponStats {
     pub projectile_lifetime: f32,
     /// Projectile hitbox radius
     pub projectile_radius: f32,
+    /// Probability of a hit being critical (0-1)
+    pub crit_chance: f32,
+    /// Damage multiplier on a critical hit
+    pub crit_multiplier: f32,
+    /// Projectiles fired per shot (`damage` is per pellet)
+    pub pellet_count: u32,
+    /// Total angle the pellets are fanned across (radians)
+    pub spread_radians: f32,
+    /// Fraction of the shooter's velocity added to its projectiles (0-1), so
+    /// shots fired ahead of a moving ship fly faster and those fired behind
+    /// it slower
+    #[serde(default)]
+    pub velocity_inheritance: f32,
+    /// Ticks after firing before a projectile can hit ships, so it can't
+    /// catch a ship overlapping the muzzle
+    #[serde(default)]
+    pub arming_ticks: u32,
+    /// Extra ships each projectile passes through before it's spent
+    /// (0 = stops at the first ship it hits)
+    #[serde(default)]
+    pub pierce: u32,
+    /// Status effect left on ships this weapon hits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_hit_effect: Option<OnHitEffect>,
 }
 
 impl WeaponStats {
-    pub fn for_type(ship_type: ShipType) -> Self {
-        match ship_type {
-            ShipType::Scout => Self {
+    /// Stats for a weapon loadout
+    pub fn for_loadout(loadout: WeaponLoadout) -> Self {
+        match loadout {
+            WeaponLoadout::Autocannon => Self {
                 damage: 8.0,
                 projectile_speed: 600.0,
                 cooldown: 0.15,
                 projectile_lifetime: 1.5,
                 projectile_radius: 3.0,
+                crit_chance: 0.15,
+                crit_multiplier: 1.5,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
             },
-            ShipType::Fighter => Self {
+            WeaponLoadout::Blaster => Self {
                 damage: 12.0,
                 projectile_speed: 500.0,
                 cooldown: 0.25,
                 projectile_lifetime: 2.0,
                 projectile_radius: 4.0,
+                crit_chance: 0.10,
+                crit_multiplier: 1.75,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
             },
-            ShipType::Cruiser => Self {
+            WeaponLoadout::Cannon => Self {
                 damage: 15.0,
                 projectile_speed: 400.0,
                 cooldown: 0.4,
                 projectile_lifetime: 2.5,
                 projectile_radius: 5.0,
+                crit_chance: 0.08,
+                crit_multiplier: 1.75,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
             },
-            ShipType::Destroyer => Self {
+            WeaponLoadout::Artillery => Self {
                 damage: 25.0,
                 projectile_speed: 350.0,
                 cooldown: 0.6,
                 projectile_lifetime: 3.0,
                 projectile_radius: 8.0,
+                crit_chance: 0.05,
+                crit_multiplier: 2.0,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
+            },
+            WeaponLoadout::Railgun => Self {
+                damage: 40.0,
+                projectile_speed: 900.0,
+                cooldown: 1.2,
+                projectile_lifetime: 1.5,
+                projectile_radius: 3.0,
+                crit_chance: 0.10,
+                crit_multiplier: 2.0,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
+            },
+            WeaponLoadout::Pulse => Self {
+                damage: 5.0,
+                projectile_speed: 700.0,
+                cooldown: 0.08,
+                projectile_lifetime: 1.0,
+                projectile_radius: 2.5,
+                crit_chance: 0.20,
+                crit_multiplier: 1.5,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
+            },
+            WeaponLoadout::Scattergun => Self {
+                damage: 6.0,
+                projectile_speed: 550.0,
+                cooldown: 0.9,
+                projectile_lifetime: 0.8,
+                projectile_radius: 3.0,
+                crit_chance: 0.05,
+                crit_multiplier: 1.5,
+                pellet_count: 6,
+                spread_radians: 0.5,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: None,
+            },
+            WeaponLoadout::Flamer => Self {
+                damage: 3.0,
+                projectile_speed: 450.0,
+                cooldown: 0.1,
+                projectile_lifetime: 0.6,
+                projectile_radius: 6.0,
+                crit_chance: 0.05,
+                crit_multiplier: 1.5,
+                pellet_count: 1,
+                spread_radians: 0.0,
+                velocity_inheritance: 0.0,
+                arming_ticks: 0,
+                pierce: 0,
+                on_hit_effect: Some(OnHitEffect {
+                    kind: StatusEffectKind::Burning,
+                    duration: 3.0,
+                    damage_per_second: 6.0,
+                }),
             },
         }
     }
@@ -65,37 +229,69 @@ pub struct Projectile {
     pub vel_x: f32,
     pub vel_y: f32,
     pub damage: f32,
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
     pub radius: f32,
     pub lifetime_remaining: f32,
+    /// Updates left before it can hit ships
+    pub arming_ticks: u32,
+    /// Ships it can still hit before it's spent
+    pub hits_remaining: u32,
+    /// Ships it has already hit, which it passes through from then on
+    pub hit_targets: Vec<Uuid>,
+    pub on_hit_effect: Option<OnHitEffect>,
 }
 
 impl Projectile {
-    /// Create a new projectile
+    /// Create a new projectile fired by a ship moving at `shooter_velocity`
     pub fn new(
         owner_id: Uuid,
         x: f32,
         y: f32,
         direction: f32,
+        shooter_velocity: (f32, f32),
         stats: &WeaponStats,
     ) -> Self {
+        let inherited = stats.velocity_inheritance;
         Self {
             id: Uuid::new_v4(),
             owner_id,
             x,
             y,
-            vel_x: direction.cos() * stats.projectile_speed,
-            vel_y: direction.sin() * stats.projectile_speed,
+            vel_x: direction.cos() * stats.projectile_speed + shooter_velocity.0 * inherited,
+            vel_y: direction.sin() * stats.projectile_speed + shooter_velocity.1 * inherited,
             damage: stats.damage,
+            crit_chance: stats.crit_chance,
+            crit_multiplier: stats.crit_multiplier,
             radius: stats.projectile_radius,
             lifetime_remaining: stats.projectile_lifetime,
+            arming_ticks: stats.arming_ticks,
+            hits_remaining: stats.pierce.saturating_add(1),
+            hit_targets: Vec::new(),
+            on_hit_effect: stats.on_hit_effect,
         }
     }
 
+    /// Direction of travel (radians)
+    pub fn direction(&self) -> f32 {
+        self.vel_y.atan2(self.vel_x)
+    }
+
+    /// Speed of travel
+    pub fn speed(&self) -> f32 {
+        self.vel_x.hypot(self.vel_y)
+    }
+
+    /// Whether the projectile can hit ships yet
+    pub fn is_armed(&self) -> bool {
+        self.arming_ticks == 0
+    }
+
     /// Update projectile position, returns false if expired
-    pub fn update(&mut self) -> bool {
-        let dt = tick_delta();
+    pub fn update(&mut self, dt: f32) -> bool {
         self.x += self.vel_x * dt;
         self.y += self.vel_y * dt;
+        self.arming_ticks = self.arming_ticks.saturating_sub(1);
         self.lifetime_remaining -= dt;
         self.lifetime_remaining > 0.0
     }
@@ -108,6 +304,82 @@ impl Projectile {
         let combined_radius = self.radius + target_radius;
         dist_sq <= combined_radius * combined_radius
     }
+
+    /// Whether this and another projectile touched at any point over the
+    /// last `dt` of flight, so fast shots can't skip past each other
+    pub fn touched_during(&self, other: &Projectile, dt: f32) -> bool {
+        let (dx, dy) = (self.x - other.x, self.y - other.y);
+        let (dvx, dvy) = (self.vel_x - other.vel_x, self.vel_y - other.vel_y);
+        // Closest approach, looking back up to `dt`
+        let closing_sq = dvx * dvx + dvy * dvy;
+        let t = if closing_sq > 0.0 {
+            (-(dx * dvx + dy * dvy) / closing_sq).clamp(-dt, 0.0)
+        } else {
+            0.0
+        };
+        let (cx, cy) = (dx + dvx * t, dy + dvy * t);
+        let reach = self.radius + other.radius;
+        cx * cx + cy * cy <= reach * reach
+    }
+}
+
+/// Stationary mine waiting for an enemy ship
+#[derive(Debug, Clone)]
+pub struct Mine {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub x: f32,
+    pub y: f32,
+    pub damage: f32,
+    pub trigger_radius: f32,
+    pub blast_radius: f32,
+    pub lifetime_remaining: f32,
+    /// Seconds until the mine can be triggered
+    pub arm_remaining: f32,
+}
+
+impl Mine {
+    /// Drop a new mine at a position
+    pub fn new(owner_id: Uuid, x: f32, y: f32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            owner_id,
+            x,
+            y,
+            damage: MINE_DAMAGE,
+            trigger_radius: MINE_TRIGGER_RADIUS,
+            blast_radius: MINE_BLAST_RADIUS,
+            lifetime_remaining: MINE_LIFETIME,
+            arm_remaining: MINE_ARM_TIME,
+        }
+    }
+
+    /// Advance the mine's timers, returns false once it has expired
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.arm_remaining = (self.arm_remaining - dt).max(0.0);
+        self.lifetime_remaining -= dt;
+        self.lifetime_remaining > 0.0
+    }
+
+    /// Check whether a ship is close enough to set the mine off
+    pub fn is_triggered_by(&self, target_x: f32, target_y: f32, target_radius: f32) -> bool {
+        if self.arm_remaining > 0.0 {
+            return false;
+        }
+        let dx = self.x - target_x;
+        let dy = self.y - target_y;
+        let reach = self.trigger_radius + target_radius;
+        dx * dx + dy * dy <= reach * reach
+    }
+
+    /// Blast damage dealt to a ship, if it's within reach
+    pub fn blast_damage(&self, target_x: f32, target_y: f32, target_radius: f32) -> Option<f32> {
+        let dx = self.x - target_x;
+        let dy = self.y - target_y;
+        let distance = ((dx * dx + dy * dy).sqrt() - target_radius).max(0.0);
+        (distance < self.blast_radius)
+            .then(|| CombatSystem::splash_damage(self.damage, self.blast_radius, distance))
+    }
 }
 
 /// Combat system for managing weapons and damage
@@ -120,8 +392,7 @@ impl CombatSystem {
     }
 
     /// Update weapon cooldown
-    pub fn update_cooldown(cooldown: f32) -> f32 {
-        let dt = tick_delta();
+    pub fn update_cooldown(cooldown: f32, dt: f32) -> f32 {
         (cooldown - dt).max(0.0)
     }
 
@@ -130,22 +401,156 @@ impl CombatSystem {
         stats.cooldown
     }
 
-    /// Calculate damage with potential modifiers
-    pub fn calculate_damage(base_damage: f32, _modifier: f32) -> f32 {
-        // For MVP, just return base damage
-        // Could add armor, critical hits, etc. later
-        base_damage
+    /// Direction of each pellet in a shot, evenly spaced across the spread
+    /// and centered on the aim
+    pub fn pellet_directions(aim_yaw: f32, stats: &WeaponStats) -> impl Iterator<Item = f32> {
+        let count = stats.pellet_count.max(1);
+        let step = if count > 1 {
+            stats.spread_radians / (count - 1) as f32
+        } else {
+            0.0
+        };
+        let start = aim_yaw - step * (count - 1) as f32 / 2.0;
+        (0..count).map(move |i| start + step * i as f32)
     }
 
-    /// Apply damage to health, returns (new_health, is_dead)
-    pub fn apply_damage(current_health: f32, damage: f32) -> (f32, bool) {
-        let new_health = (current_health - damage).max(0.0);
-        (new_health, new_health <= 0.0)
+    /// Aim for a shot from `origin` with aim assist: turned `strength` (0-1)
+    /// of the way toward the nearest target within `range` and within
+    /// `cone` either side of `aim_yaw`, by no more than `MAX_AIM_ASSIST_SNAP`.
+    /// `aim_yaw` is returned unchanged when no target is in the cone
+    pub fn assisted_aim(
+        origin: (f32, f32),
+        aim_yaw: f32,
+        targets: impl IntoIterator<Item = (f32, f32)>,
+        cone: f32,
+        strength: f32,
+        range: f32,
+    ) -> f32 {
+        let cone = cone.clamp(0.0, MAX_AIM_ASSIST_CONE);
+        let nearest = targets
+            .into_iter()
+            .filter_map(|(x, y)| {
+                let (dx, dy) = (x - origin.0, y - origin.1);
+                let distance = dx.hypot(dy);
+                if distance == 0.0 || distance > range {
+                    return None;
+                }
+                let offset = (dy.atan2(dx) - aim_yaw + PI).rem_euclid(TAU) - PI;
+                (offset.abs() <= cone).then_some((distance, offset))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        match nearest {
+            Some((_, offset)) => {
+                let turn = (offset * strength.clamp(0.0, 1.0))
+                    .clamp(-MAX_AIM_ASSIST_SNAP, MAX_AIM_ASSIST_SNAP);
+                normalize_yaw(aim_yaw + turn)
+            }
+            None => aim_yaw,
+        }
+    }
+
+    /// Calculate damage with a multiplicative modifier (1.0 = unmodified)
+    pub fn calculate_damage(base_damage: f32, modifier: f32) -> f32 {
+        base_damage * modifier
+    }
+
+    /// Roll for a critical hit. Always draws exactly one value from `rng`
+    /// so the match RNG stays in step regardless of the outcome
+    pub fn roll_crit(crit_chance: f32, rng: &mut impl Rng) -> bool {
+        rng.gen::<f32>() < crit_chance
+    }
+
+    /// Splash damage at a distance from the blast center, falling off
+    /// linearly to zero at the blast radius
+    pub fn splash_damage(base_damage: f32, blast_radius: f32, distance: f32) -> f32 {
+        base_damage * (1.0 - distance / blast_radius).clamp(0.0, 1.0)
+    }
+
+    /// Apply damage to the shield first, overflowing into health
+    /// Returns (new_shield, new_health, is_dead)
+    pub fn apply_damage(current_shield: f32, current_health: f32, damage: f32) -> (f32, f32, bool) {
+        let absorbed = damage.min(current_shield);
+        let new_health = (current_health - (damage - absorbed)).max(0.0);
+        (current_shield - absorbed, new_health, new_health <= 0.0)
+    }
+
+    /// Advance shield recharge by one tick; nothing recharges until the
+    /// delay since the last damage has run out
+    /// Returns (new_shield, new_regen_delay)
+    pub fn update_shield(
+        shield: f32,
+        max_shield: f32,
+        regen_per_sec: f32,
+        regen_delay: f32,
+        dt: f32,
+    ) -> (f32, f32) {
+        if regen_delay > 0.0 {
+            return (shield, (regen_delay - dt).max(0.0));
+        }
+        ((shield + regen_per_sec * dt).min(max_shield), 0.0)
     }
 
     /// Calculate zone damage per tick
-    pub fn zone_damage(damage_per_second: f32) -> f32 {
-        damage_per_second * tick_delta()
+    pub fn zone_damage(damage_per_second: f32, dt: f32) -> f32 {
+        damage_per_second * dt
+    }
+
+    /// Pairs of projectiles from different owners among `candidates`
+    /// (indices into `projectiles`) that touched during the last `dt`, lower
+    /// index first. Each projectile clashes at most once, with pairs taken
+    /// in index order, so the result doesn't depend on whether the
+    /// projectiles were bucketed
+    pub fn find_clashes(
+        projectiles: &[Projectile],
+        candidates: &[usize],
+        dt: f32,
+    ) -> Vec<(usize, usize)> {
+        let touching = |a: usize, b: usize| {
+            let (a, b) = (&projectiles[a], &projectiles[b]);
+            a.owner_id != b.owner_id && a.touched_during(b, dt)
+        };
+        let mut pairs = Vec::new();
+
+        if candidates.len() <= CLASH_BUCKET_THRESHOLD {
+            for (n, &a) in candidates.iter().enumerate() {
+                for &b in &candidates[n + 1..] {
+                    if touching(a, b) {
+                        pairs.push((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        } else {
+            // Projectiles that touched are now at most two of the largest
+            // radii plus two ticks of the fastest flight apart
+            let largest = |f: fn(&Projectile) -> f32| {
+                candidates.iter().map(|&i| f(&projectiles[i])).fold(0.0, f32::max)
+            };
+            let reach = (largest(|p| p.radius) + largest(Projectile::speed) * dt) * 2.0;
+            let mut grid = SpatialGrid::default();
+            grid.rebuild(reach, candidates.iter().map(|&i| (projectiles[i].x, projectiles[i].y)));
+            let mut nearby = Vec::new();
+            for (n, &a) in candidates.iter().enumerate() {
+                grid.query_into(projectiles[a].x, projectiles[a].y, reach, &mut nearby);
+                for &b in nearby.iter().filter(|&&m| m > n).map(|m| &candidates[*m]) {
+                    if touching(a, b) {
+                        pairs.push((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+
+        pairs.sort_unstable();
+        let mut spent = vec![false; projectiles.len()];
+        pairs.retain(|&(a, b)| {
+            if spent[a] || spent[b] {
+                return false;
+            }
+            spent[a] = true;
+            spent[b] = true;
+            true
+        });
+        pairs
     }
 }
 
@@ -156,7 +561,195 @@ pub struct HitResult {
     pub shooter_id: Uuid,
     pub target_id: Uuid,
     pub damage: f32,
+    pub crit: bool,
     pub x: f32,
     pub y: f32,
     pub target_killed: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn single_projectile_weapons_fire_along_the_aim() {
+        let stats = WeaponStats::for_loadout(WeaponLoadout::Blaster);
+        let directions: Vec<f32> = CombatSystem::pellet_directions(1.25, &stats).collect();
+        assert_eq!(directions, vec![1.25]);
+    }
+
+    #[test]
+    fn projectiles_inherit_a_fraction_of_the_shooter_velocity() {
+        let owner = Uuid::nil();
+        let still = WeaponStats::for_loadout(WeaponLoadout::Blaster);
+        let inheriting = WeaponStats {
+            velocity_inheritance: 0.5,
+            ..still
+        };
+        let moving = (200.0, 0.0);
+
+        // Without inheritance the ship's speed makes no difference
+        let plain = Projectile::new(owner, 0.0, 0.0, 0.0, moving, &still);
+        assert_eq!(plain.speed(), still.projectile_speed);
+
+        let forward = Projectile::new(owner, 0.0, 0.0, 0.0, moving, &inheriting);
+        let backward = Projectile::new(owner, 0.0, 0.0, std::f32::consts::PI, moving, &inheriting);
+        assert!((forward.speed() - (still.projectile_speed + 100.0)).abs() < 1e-3);
+        assert!((backward.speed() - (still.projectile_speed - 100.0)).abs() < 1e-3);
+        assert!(backward.vel_x < 0.0);
+    }
+
+    #[test]
+    fn pellets_fan_evenly_across_the_spread() {
+        let stats = WeaponStats::for_loadout(WeaponLoadout::Scattergun);
+        let directions: Vec<f32> = CombatSystem::pellet_directions(1.0, &stats).collect();
+
+        assert_eq!(directions.len(), stats.pellet_count as usize);
+        assert!((directions[0] - (1.0 - stats.spread_radians / 2.0)).abs() < 1e-5);
+        assert!((directions[directions.len() - 1] - (1.0 + stats.spread_radians / 2.0)).abs() < 1e-5);
+        let mean = directions.iter().sum::<f32>() / directions.len() as f32;
+        assert!((mean - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn shield_absorbs_damage_before_health() {
+        assert_eq!(
+            CombatSystem::apply_damage(40.0, 100.0, 25.0),
+            (15.0, 100.0, false)
+        );
+        assert_eq!(
+            CombatSystem::apply_damage(40.0, 100.0, 40.0),
+            (0.0, 100.0, false)
+        );
+    }
+
+    #[test]
+    fn damage_beyond_shield_overflows_into_health() {
+        assert_eq!(
+            CombatSystem::apply_damage(10.0, 100.0, 35.0),
+            (0.0, 75.0, false)
+        );
+        assert_eq!(
+            CombatSystem::apply_damage(0.0, 20.0, 15.0),
+            (0.0, 5.0, false)
+        );
+    }
+
+    #[test]
+    fn overflow_past_health_kills() {
+        assert_eq!(
+            CombatSystem::apply_damage(10.0, 20.0, 30.0),
+            (0.0, 0.0, true)
+        );
+        assert_eq!(
+            CombatSystem::apply_damage(10.0, 20.0, 500.0),
+            (0.0, 0.0, true)
+        );
+    }
+
+    #[test]
+    fn shield_waits_out_delay_then_recharges_to_max() {
+        let (mut shield, mut delay) = (0.0, SHIELD_REGEN_DELAY);
+        let mut ticks = 0u32;
+        while delay > 0.0 {
+            (shield, delay) = CombatSystem::update_shield(shield, 40.0, 8.0, delay, DT);
+            assert_eq!(shield, 0.0);
+            ticks += 1;
+        }
+        // Give or take a tick of float rounding
+        let expected = (SHIELD_REGEN_DELAY / DT).round() as u32;
+        assert!(ticks.abs_diff(expected) <= 1, "delay lasted {ticks} ticks");
+
+        (shield, delay) = CombatSystem::update_shield(shield, 40.0, 8.0, delay, DT);
+        assert!((shield - 8.0 * DT).abs() < 1e-6);
+        for _ in 0..10 * 60 {
+            (shield, delay) = CombatSystem::update_shield(shield, 40.0, 8.0, delay, DT);
+        }
+        assert_eq!((shield, delay), (40.0, 0.0));
+    }
+
+    #[test]
+    fn damage_interrupts_recharge() {
+        // Recharging from empty for a second
+        let (mut shield, mut delay) = (0.0, 0.0);
+        for _ in 0..60 {
+            (shield, delay) = CombatSystem::update_shield(shield, 40.0, 8.0, delay, DT);
+        }
+        assert!((shield - 8.0).abs() < 1e-3);
+
+        // A hit restarts the delay, as the match does on any damage
+        let (after_hit, _, _) = CombatSystem::apply_damage(shield, 100.0, 5.0);
+        (shield, delay) = (after_hit, SHIELD_REGEN_DELAY);
+        for _ in 0..(SHIELD_REGEN_DELAY / DT) as u32 - 1 {
+            (shield, delay) = CombatSystem::update_shield(shield, 40.0, 8.0, delay, DT);
+            assert_eq!(shield, after_hit);
+        }
+    }
+
+    #[test]
+    fn aim_assist_turns_part_way_toward_a_target_in_the_cone() {
+        // Target 5 degrees above the aim, well within range
+        let target = (500.0 * 5f32.to_radians().cos(), 500.0 * 5f32.to_radians().sin());
+        let aim = CombatSystem::assisted_aim((0.0, 0.0), 0.0, [target], 0.1, 0.5, 900.0);
+        assert!((aim - 2.5f32.to_radians()).abs() < 1e-4);
+
+        // Full strength still stops at the snap limit
+        let aim = CombatSystem::assisted_aim((0.0, 0.0), 0.0, [target], 0.1, 1.0, 900.0);
+        assert!((aim - MAX_AIM_ASSIST_SNAP).abs() < 1e-4);
+
+        // Below the aim, across the wrap at 0
+        let below = (target.0, -target.1);
+        let aim = CombatSystem::assisted_aim((0.0, 0.0), 0.0, [below], 0.1, 0.5, 900.0);
+        assert!((aim - (TAU - 2.5f32.to_radians())).abs() < 1e-4);
+    }
+
+    #[test]
+    fn aim_assist_ignores_targets_outside_the_cone_or_range() {
+        let wide = (0.0, 500.0);
+        let far = (2000.0, 10.0);
+        let behind = (-500.0, 0.0);
+        for target in [wide, far, behind] {
+            let aim = CombatSystem::assisted_aim((0.0, 0.0), 0.0, [target], 0.1, 1.0, 900.0);
+            assert_eq!(aim, 0.0, "{target:?}");
+        }
+
+        // A cone wider than the cap is held to it
+        let off = (500.0 * 0.3f32.cos(), 500.0 * 0.3f32.sin());
+        let aim = CombatSystem::assisted_aim((0.0, 0.0), 0.0, [off], 1.0, 1.0, 900.0);
+        assert_eq!(aim, 0.0);
+    }
+
+    #[test]
+    fn bucketed_clashes_match_the_pairwise_check() {
+        let stats = WeaponStats::for_loadout(WeaponLoadout::Blaster);
+        // A crowd of shots from three players, each touching a few others
+        let projectiles: Vec<Projectile> = (0..200u32)
+            .map(|n| {
+                let owner = Uuid::from_u128((n % 3) as u128);
+                let (x, y) = ((n % 20) as f32 * 7.0, (n / 20) as f32 * 7.0);
+                Projectile::new(owner, x, y, 0.0, (0.0, 0.0), &stats)
+            })
+            .collect();
+        let candidates: Vec<usize> = (0..projectiles.len()).collect();
+
+        let bucketed = CombatSystem::find_clashes(&projectiles, &candidates, DT);
+
+        // Every pair in index order, skipping projectiles already spent
+        let mut spent = vec![false; projectiles.len()];
+        let mut pairwise = Vec::new();
+        for a in 0..projectiles.len() {
+            for b in a + 1..projectiles.len() {
+                let (p, q) = (&projectiles[a], &projectiles[b]);
+                let touching = p.owner_id != q.owner_id && p.touched_during(q, DT);
+                if touching && !spent[a] && !spent[b] {
+                    (spent[a], spent[b]) = (true, true);
+                    pairwise.push((a, b));
+                }
+            }
+        }
+        assert!(!pairwise.is_empty());
+        assert_eq!(bucketed, pairwise);
+    }
+}
@@ -0,0 +1,148 @@
+//! Status effects on ships
+//!
+//! A weapon with an `on_hit_effect` leaves the ships it hits under that
+//! effect for a while. Every effect belongs to the player whose hit applied
+//! it, who is credited with its damage, kills included.
+//!
+//! Stacking: a ship carries at most one effect of a kind per source. Another
+//! hit from the same source refreshes it, keeping the longer remaining
+//! duration and the higher damage. Effects from different sources stack, up
+//! to `MAX_STACKS` of a kind; past that the new effect replaces the one
+//! closest to running out.
+//!
+//! Durations are counted in ticks so effects end on the same tick on every
+//! run of a match.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ws::protocol::StatusEffectKind;
+
+/// Most effects of one kind a ship can be under at once
+pub const MAX_STACKS: usize = 3;
+
+/// Effect a weapon leaves on the ships it hits
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnHitEffect {
+    pub kind: StatusEffectKind,
+    /// Seconds the effect lasts
+    pub duration: f32,
+    /// Damage dealt per second while it lasts
+    pub damage_per_second: f32,
+}
+
+/// An effect a ship is under
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// Player whose hit applied the effect
+    pub source_id: Uuid,
+    pub remaining_ticks: u32,
+    /// Damage dealt each tick
+    pub damage_per_tick: f32,
+}
+
+impl StatusEffect {
+    /// The effect a hit by `source_id` applies, in a match ticking every `dt`
+    /// seconds
+    pub fn from_hit(effect: &OnHitEffect, source_id: Uuid, dt: f32) -> Self {
+        Self {
+            kind: effect.kind,
+            source_id,
+            remaining_ticks: (effect.duration / dt).round().max(1.0) as u32,
+            damage_per_tick: effect.damage_per_second * dt,
+        }
+    }
+}
+
+/// Add an effect to a ship's effects following the stacking rule
+pub fn apply(effects: &mut Vec<StatusEffect>, effect: StatusEffect) {
+    if let Some(existing) = effects
+        .iter_mut()
+        .find(|e| e.kind == effect.kind && e.source_id == effect.source_id)
+    {
+        existing.remaining_ticks = existing.remaining_ticks.max(effect.remaining_ticks);
+        existing.damage_per_tick = existing.damage_per_tick.max(effect.damage_per_tick);
+        return;
+    }
+
+    let stacks = effects.iter().filter(|e| e.kind == effect.kind).count();
+    if stacks < MAX_STACKS {
+        effects.push(effect);
+        return;
+    }
+
+    // Ties replace the first of the shortest, so every run picks the same one
+    if let Some(shortest) = effects
+        .iter_mut()
+        .filter(|e| e.kind == effect.kind)
+        .min_by_key(|e| e.remaining_ticks)
+    {
+        *shortest = effect;
+    }
+}
+
+/// Advance a ship's effects by one tick, returning those that act this tick
+/// and dropping the ones that run out
+pub fn tick(effects: &mut Vec<StatusEffect>) -> Vec<StatusEffect> {
+    let active = effects.clone();
+    for effect in effects.iter_mut() {
+        effect.remaining_ticks -= 1;
+    }
+    effects.retain(|e| e.remaining_ticks > 0);
+    active
+}
+
+/// `PlayerSnapshot::effects` bitmask of a ship's effects
+pub fn mask(effects: &[StatusEffect]) -> u8 {
+    effects.iter().fold(0, |mask, e| mask | e.kind.bit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 30.0;
+
+    fn burn(source: u128, duration: f32, damage_per_second: f32) -> StatusEffect {
+        let effect = OnHitEffect {
+            kind: StatusEffectKind::Burning,
+            duration,
+            damage_per_second,
+        };
+        StatusEffect::from_hit(&effect, Uuid::from_u128(source), DT)
+    }
+
+    #[test]
+    fn same_source_refreshes_and_other_sources_stack() {
+        let mut effects = Vec::new();
+        apply(&mut effects, burn(1, 1.0, 6.0));
+        apply(&mut effects, burn(1, 3.0, 3.0));
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].remaining_ticks, 90);
+        assert_eq!(effects[0].damage_per_tick, 6.0 * DT);
+
+        apply(&mut effects, burn(2, 1.0, 6.0));
+        apply(&mut effects, burn(3, 2.0, 6.0));
+        assert_eq!(effects.len(), MAX_STACKS);
+
+        // Full: the fourth source replaces the one closest to running out
+        apply(&mut effects, burn(4, 2.0, 6.0));
+        let sources: Vec<u128> = effects.iter().map(|e| e.source_id.as_u128()).collect();
+        assert_eq!(sources, vec![1, 4, 3]);
+        assert_eq!(mask(&effects), StatusEffectKind::Burning.bit());
+    }
+
+    #[test]
+    fn effects_act_for_their_whole_duration() {
+        let mut effects = vec![burn(1, 1.0, 6.0)];
+        let mut ticks = 0;
+        while !effects.is_empty() {
+            assert_eq!(tick(&mut effects).len(), 1);
+            ticks += 1;
+        }
+        assert_eq!(ticks, 30);
+        assert_eq!(mask(&effects), 0);
+    }
+}
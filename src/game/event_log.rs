@@ -0,0 +1,187 @@
+//! Per-match event history for post-match review
+//!
+//! Every match keeps its recent `GameEvent`s, stamped with the tick they
+//! happened on, in a bounded ring buffer shared with its `MatchHandle`. The
+//! registry holds on to the log for a while after the match ends so it can
+//! be fetched over HTTP. Shots are left out: they are by far the most common
+//! event and would push everything else out of the buffer.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+use crate::ws::protocol::GameEvent;
+
+/// An event and the tick it happened on
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedEvent {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Which logged events to return
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// `event_type` as sent to clients, e.g. "kill" or "zone_shrink"
+    pub event_type: Option<String>,
+    /// Only events this player took part in
+    pub player: Option<Uuid>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &GameEvent) -> bool {
+        self.event_type.as_deref().is_none_or(|t| event.kind() == t)
+            && self.player.is_none_or(|id| event.involves(id))
+    }
+}
+
+#[derive(Debug, Default)]
+struct LogBuffer {
+    events: VecDeque<LoggedEvent>,
+    /// Events pushed out by newer ones
+    dropped: u64,
+}
+
+/// Bounded log of a match's events, oldest dropped first
+#[derive(Debug)]
+pub struct MatchEventLog {
+    capacity: usize,
+    buffer: Mutex<LogBuffer>,
+}
+
+impl MatchEventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            buffer: Mutex::new(LogBuffer::default()),
+        }
+    }
+
+    /// Append a tick's events, skipping shots
+    pub fn record(&self, tick: u64, events: &[GameEvent]) {
+        let mut logged = events
+            .iter()
+            .filter(|e| !matches!(e, GameEvent::Shot { .. }))
+            .peekable();
+        if logged.peek().is_none() {
+            return;
+        }
+
+        let mut buffer = self.buffer.lock();
+        for event in logged {
+            if buffer.events.len() == self.capacity {
+                buffer.events.pop_front();
+                buffer.dropped += 1;
+            }
+            buffer.events.push_back(LoggedEvent {
+                tick,
+                event: event.clone(),
+            });
+        }
+    }
+
+    /// Logged events passing `filter`, oldest first, and how many events the
+    /// log has dropped to stay within its capacity
+    pub fn query(&self, filter: &EventFilter) -> (Vec<LoggedEvent>, u64) {
+        let buffer = self.buffer.lock();
+        let events = buffer
+            .events
+            .iter()
+            .filter(|e| filter.matches(&e.event))
+            .cloned()
+            .collect();
+        (events, buffer.dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kill(killer: u128, victim: u128) -> GameEvent {
+        GameEvent::Kill {
+            killer_id: Some(Uuid::from_u128(killer)),
+            victim_id: Uuid::from_u128(victim),
+            cause: "shot".to_string(),
+        }
+    }
+
+    fn hit(shooter: u128, target: u128) -> GameEvent {
+        GameEvent::Hit {
+            shooter_id: Uuid::from_u128(shooter),
+            target_id: Uuid::from_u128(target),
+            damage: 10.0,
+            crit: false,
+            x: 0.0,
+            y: 0.0,
+            target_remaining_health: 90.0,
+            target_remaining_shield: 0.0,
+        }
+    }
+
+    fn shot(shooter: u128) -> GameEvent {
+        GameEvent::Shot {
+            shooter_id: Uuid::from_u128(shooter),
+            projectile_id: Uuid::from_u128(99),
+            x: 0.0,
+            y: 0.0,
+            direction: 0.0,
+            speed: 100.0,
+            assisted_yaw: None,
+        }
+    }
+
+    #[test]
+    fn oldest_events_are_dropped_past_capacity() {
+        let log = MatchEventLog::new(3);
+        for tick in 1..=5 {
+            log.record(tick, &[hit(1, 2), shot(1)]);
+        }
+
+        let (events, dropped) = log.query(&EventFilter::default());
+        let ticks: Vec<u64> = events.iter().map(|e| e.tick).collect();
+        assert_eq!(ticks, [3, 4, 5]);
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn filters_by_type_and_player() {
+        let log = MatchEventLog::new(100);
+        log.record(1, &[hit(1, 2), hit(3, 4)]);
+        log.record(2, &[kill(1, 2)]);
+
+        let kills = EventFilter {
+            event_type: Some("kill".to_string()),
+            ..EventFilter::default()
+        };
+        assert_eq!(log.query(&kills).0.len(), 1);
+
+        let victim = EventFilter {
+            player: Some(Uuid::from_u128(2)),
+            ..EventFilter::default()
+        };
+        assert_eq!(log.query(&victim).0.len(), 2);
+
+        let both = EventFilter {
+            event_type: Some("hit".to_string()),
+            player: Some(Uuid::from_u128(4)),
+        };
+        let (events, _) = log.query(&both);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tick, 1);
+    }
+
+    #[test]
+    fn logged_events_serialize_flat() {
+        let event = LoggedEvent {
+            tick: 7,
+            event: kill(1, 2),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["tick"], 7);
+        assert_eq!(json["event_type"], "kill");
+        assert_eq!(json["cause"], "shot");
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-player input buffering
+//!
+//! Clients send one input per frame, but the network delivers them in
+//! bursts: two can land between one pair of ticks and none between the next.
+//! Applying inputs as they arrive would lose the first of each burst and
+//! repeat a stale one through each gap, so accepted inputs wait in a short
+//! queue and every tick plays out one of them:
+//!
+//! - The oldest waiting input is applied, spreading a burst back over the
+//!   ticks it was sent for.
+//! - If more than `MAX_BACKLOG` inputs would still be waiting, the client is
+//!   running ahead of the server and the oldest are skipped to keep latency
+//!   down. A `shoot` or `deploy_mine` in a skipped input carries over to the
+//!   next one, so presses aren't lost.
+//! - With nothing waiting the last input is held. After `HOLD_TICKS` ticks
+//!   without a new input its steer decays by `STALE_STEER_DECAY` each tick,
+//!   so a client that stops sending mid-turn doesn't leave its ship spinning.
+//!   Throttle, aim and buttons stay held, until the match's input timeout
+//!   lets go of the controls altogether.
+//!
+//! Only tick counts matter, never arrival times, so a replay, which feeds
+//! inputs in between the same ticks, plays out identically.
+
+use std::collections::VecDeque;
+
+use super::TickInput;
+
+/// Most inputs kept waiting; pushing past this skips the oldest
+const CAPACITY: usize = 8;
+
+/// Most inputs left waiting after a tick takes one
+const MAX_BACKLOG: usize = 1;
+
+/// Ticks a missing input is covered by repeating the last one unchanged
+const HOLD_TICKS: u32 = 3;
+
+/// Factor applied to a held steer each tick once it goes stale
+const STALE_STEER_DECAY: f32 = 0.7;
+
+/// Decayed steer below this snaps to zero
+const MIN_STEER: f32 = 0.01;
+
+/// Accepted inputs of one player waiting for their tick
+#[derive(Debug, Clone, Default)]
+pub struct InputBuffer {
+    pending: VecDeque<TickInput>,
+    /// Ticks since a buffered input was last applied
+    idle_ticks: u32,
+}
+
+impl InputBuffer {
+    /// Queue an accepted input. Inputs must be pushed in seq order
+    pub fn push(&mut self, input: TickInput) {
+        if self.pending.len() == CAPACITY {
+            self.skip_oldest();
+        }
+        self.pending.push_back(input);
+    }
+
+    /// The input to simulate this tick, given the one simulated last tick
+    pub fn next(&mut self, current: &TickInput) -> TickInput {
+        while self.pending.len() > MAX_BACKLOG + 1 {
+            self.skip_oldest();
+        }
+        if let Some(input) = self.pending.pop_front() {
+            self.idle_ticks = 0;
+            return input;
+        }
+
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+        let mut held = current.clone();
+        if self.idle_ticks > HOLD_TICKS {
+            held.steer *= STALE_STEER_DECAY;
+            if held.steer.abs() < MIN_STEER {
+                held.steer = 0.0;
+            }
+        }
+        held
+    }
+
+    /// Drop the oldest waiting input, carrying its presses over to the next
+    fn skip_oldest(&mut self) {
+        let Some(skipped) = self.pending.pop_front() else {
+            return;
+        };
+        if let Some(next) = self.pending.front_mut() {
+            next.shoot |= skipped.shoot;
+            next.deploy_mine |= skipped.deploy_mine;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(seq: u32) -> TickInput {
+        TickInput {
+            seq,
+            throttle: 1.0,
+            steer: 1.0,
+            ..TickInput::default()
+        }
+    }
+
+    #[test]
+    fn a_burst_is_played_out_one_per_tick() {
+        let mut buffer = InputBuffer::default();
+        buffer.push(input(1));
+        buffer.push(input(2));
+
+        let first = buffer.next(&TickInput::default());
+        let second = buffer.next(&first);
+        let third = buffer.next(&second);
+        assert_eq!((first.seq, second.seq, third.seq), (1, 2, 2));
+    }
+
+    #[test]
+    fn a_client_running_ahead_is_caught_up_without_losing_presses() {
+        let mut buffer = InputBuffer::default();
+        buffer.push(TickInput {
+            shoot: true,
+            ..input(1)
+        });
+        for seq in 2..=4 {
+            buffer.push(input(seq));
+        }
+
+        let applied = buffer.next(&TickInput::default());
+        assert_eq!(applied.seq, 3);
+        assert!(applied.shoot);
+        assert_eq!(buffer.next(&applied).seq, 4);
+    }
+
+    #[test]
+    fn stale_steer_decays_to_zero() {
+        let mut buffer = InputBuffer::default();
+        buffer.push(input(1));
+        let mut current = buffer.next(&TickInput::default());
+
+        for _ in 0..HOLD_TICKS {
+            current = buffer.next(&current);
+            assert_eq!(current.steer, 1.0);
+        }
+        for _ in 0..30 {
+            current = buffer.next(&current);
+        }
+        assert_eq!(current.steer, 0.0);
+        assert_eq!(current.throttle, 1.0);
+        assert_eq!(current.seq, 1);
+    }
+}
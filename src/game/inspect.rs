@@ -0,0 +1,151 @@
+//! Live match state for the admin inspection endpoint
+//!
+//! Match state lives inside the match task, so the tick loop publishes a
+//! copy of it once per second to a `MatchInspector` shared with its
+//! `MatchHandle`. If the loop gets stuck the copy stops updating, and its
+//! age shows for how long.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::util::time::unix_millis;
+use crate::ws::protocol::{ShipType, WeaponLoadout, ZoneState};
+
+use super::r#match::{MatchPhase, MatchState};
+
+/// A player's state as of the last publish
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerInspection {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub ship_type: ShipType,
+    pub weapon: WeaponLoadout,
+    pub is_bot: bool,
+    pub is_guest: bool,
+    pub alive: bool,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+    pub health: f32,
+    pub shield: f32,
+    pub boost_energy: f32,
+    pub kills: u32,
+    pub last_input_seq: u32,
+    /// Anti-cheat suspicion score
+    pub suspicion: f32,
+    pub spawn_protected_until: Option<u64>,
+}
+
+/// A match's state as of the last publish
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchInspection {
+    /// Unix millis when the match loop published this
+    pub published_at: u64,
+    pub phase: MatchPhase,
+    pub tick: u64,
+    pub paused: bool,
+    pub zone: ZoneState,
+    pub zone_phase: usize,
+    pub projectile_count: usize,
+    pub mine_count: usize,
+    pub players: Vec<PlayerInspection>,
+}
+
+impl MatchInspection {
+    pub fn capture(state: &MatchState) -> Self {
+        let players = state
+            .players
+            .values()
+            .map(|p| PlayerInspection {
+                user_id: p.user_id,
+                display_name: p.display_name.clone(),
+                ship_type: p.ship_type,
+                weapon: p.weapon_loadout,
+                is_bot: p.is_bot,
+                is_guest: p.is_guest,
+                alive: p.alive,
+                x: p.x,
+                y: p.y,
+                rotation: p.rotation,
+                vel_x: p.vel_x,
+                vel_y: p.vel_y,
+                health: p.health,
+                shield: p.shield,
+                boost_energy: p.boost_energy,
+                kills: p.kills,
+                last_input_seq: p.last_input_seq,
+                suspicion: p.input_guard.suspicion(),
+                spawn_protected_until: p.spawn_protected_until,
+            })
+            .collect();
+
+        Self {
+            published_at: unix_millis(),
+            phase: state.phase,
+            tick: state.tick,
+            paused: state.paused,
+            zone: state.zone.clone(),
+            zone_phase: state.current_zone_phase,
+            projectile_count: state.projectiles.len(),
+            mine_count: state.mines.len(),
+            players,
+        }
+    }
+}
+
+/// Latest published state of a match
+#[derive(Debug, Default)]
+pub struct MatchInspector {
+    latest: Mutex<Option<Arc<MatchInspection>>>,
+}
+
+impl MatchInspector {
+    pub fn publish(&self, inspection: MatchInspection) {
+        *self.latest.lock() = Some(Arc::new(inspection));
+    }
+
+    /// Latest published state, if the match has published any yet
+    pub fn latest(&self) -> Option<Arc<MatchInspection>> {
+        self.latest.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::balance::BalanceTable;
+    use crate::game::{Cosmetics, MatchSettings, PlayerState};
+
+    #[test]
+    fn publishes_latest_capture() {
+        let mut state = MatchState::new(Uuid::from_u128(1), 7, MatchSettings::default());
+        let player = PlayerState::new(
+            Uuid::from_u128(2),
+            "Pilot".to_string(),
+            ShipType::Fighter,
+            Cosmetics::default(),
+            120.0,
+            -40.0,
+            0.0,
+            &BalanceTable::default(),
+        );
+        state.players.insert(player.user_id, player);
+
+        let inspector = MatchInspector::default();
+        assert!(inspector.latest().is_none());
+
+        inspector.publish(MatchInspection::capture(&state));
+        state.tick = 30;
+        inspector.publish(MatchInspection::capture(&state));
+
+        let latest = inspector.latest().unwrap();
+        assert_eq!(latest.tick, 30);
+        assert_eq!(latest.players.len(), 1);
+        assert_eq!((latest.players[0].x, latest.players[0].y), (120.0, -40.0));
+        assert_eq!(latest.projectile_count, 0);
+    }
+}
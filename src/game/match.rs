@@ -3,29 +3,59 @@
 use dashmap::DashMap;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, mpsc};
 use tokio::time::interval;
-use tracing::{info, warn};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::util::time::{tick_delta, unix_millis, SIMULATION_TPS, SNAPSHOT_TPS};
+use crate::store::stats::{MatchSummary, PlayerEndState};
+use crate::store::StatsStore;
+use crate::util::latency::LatencyTracker;
+use crate::util::time::{
+    tick_delta, unix_millis, FixedTimestep, Timer, SIMULATION_TPS, SNAPSHOT_TPS,
+};
 use crate::ws::protocol::{
-    ClientMsg, GameEvent, MatchStats, PlayerInfo, PlayerMatchStats, ServerMsg, ShipType, ZoneState,
+    ClientMsg, ErrorCode, GameEvent, MatchStats, PlayerInfo, PlayerLatency, PlayerMatchStats, ServerMsg,
+    RepairPad, RepairPadStatus, ShipMotion, ShipType, SnapshotPrecision, WeaponLoadout, WorldBounds,
+    ZoneShape, ZoneState,
 };
 
-use super::combat::{CombatSystem, HitResult, Projectile, WeaponStats};
-use super::physics::{PhysicsSystem, ShipStats};
-use super::snapshot::SnapshotBuilder;
-use super::{PlayerInput, TickInput};
+use super::anticheat::{normalize_yaw, InputGuard};
+use super::arena::Arena;
+use super::balance::BalanceTable;
+use super::bot::{BotController, BOT_SHIP_TYPES};
+use super::combat::{
+    CombatSystem, HitResult, Mine, Projectile, WeaponStats, MAX_MINES_PER_PLAYER,
+    MINE_DEPLOY_COOLDOWN, SHIELD_REGEN_DELAY,
+};
+use super::effects::{self, StatusEffect};
+use super::event_log::MatchEventLog;
+use super::input_buffer::InputBuffer;
+use super::inspect::{MatchInspection, MatchInspector};
+use super::metrics::{MatchMetrics, TickStatsWindow};
+use super::physics::{PhysicsSystem, ShipStats, BOOST_MAX_ENERGY};
+use super::progression;
+use super::replay::{Replay, ReplayRecorder};
+use super::snapshot::{SnapshotBuilder, SnapshotMode};
+use super::spatial::SpatialGrid;
+use super::{Cosmetics, PlayerInput, TickInput};
 
 /// Match phase
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
 pub enum MatchPhase {
     /// Waiting for players
     Waiting,
+    /// Ships can fly around but combat and the zone are off, giving players
+    /// still loading time to catch up
+    Warmup,
     /// Countdown before start
     Countdown,
     /// Match in progress
@@ -34,13 +64,31 @@ pub enum MatchPhase {
     Ended,
 }
 
+impl MatchPhase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Waiting,
+            1 => Self::Warmup,
+            2 => Self::Countdown,
+            3 => Self::InProgress,
+            _ => Self::Ended,
+        }
+    }
+}
+
 /// Player state in a match (authoritative)
 #[derive(Debug, Clone)]
 pub struct PlayerState {
     pub user_id: Uuid,
     pub display_name: String,
     pub ship_type: ShipType,
-    pub flag_skin_id: Option<Uuid>,
+    /// Hull stats from the match's balance table
+    pub ship: ShipStats,
+    pub cosmetics: Cosmetics,
+    /// Driven by `BotController` instead of client input
+    pub is_bot: bool,
+    /// Playing on a guest token; nothing about them is persisted
+    pub is_guest: bool,
 
     // Position and movement
     pub x: f32,
@@ -51,12 +99,44 @@ pub struct PlayerState {
 
     // Combat
     pub health: f32,
+    /// Absorbs damage before health
+    pub shield: f32,
+    pub max_shield: f32,
+    /// Shield recharged per second
+    pub shield_regen: f32,
+    /// Seconds until the shield starts recharging
+    pub shield_regen_delay: f32,
     pub alive: bool,
+    pub weapon_loadout: WeaponLoadout,
+    pub weapon: WeaponStats,
     pub weapon_cooldown: f32,
+    /// Shots are nudged toward nearly aligned targets, see
+    /// `CombatSystem::assisted_aim`
+    pub aim_assist: bool,
+    /// Seconds until another mine can be deployed
+    pub mine_cooldown: f32,
+    /// Burning and other effects the ship is under, see `effects`
+    pub status_effects: Vec<StatusEffect>,
+
+    // Boost
+    pub boost_energy: f32,
+    pub boost_cooldown: f32,
+    pub boosting: bool,
 
     // Input tracking
     pub last_input_seq: u32,
+    /// Match clock (ms) the last input was accepted at (none yet if unset)
+    pub last_input_at: Option<u64>,
+    /// Highest input seq reported back in an `InputAck`
+    pub last_acked_seq: u32,
+    /// Where the client's prediction puts the ship after `last_acked_seq`,
+    /// replayed from the last correction it was sent (none sent yet if unset)
+    pub predicted: Option<ShipMotion>,
     pub current_input: TickInput,
+    /// Accepted inputs not yet simulated
+    pub input_buffer: InputBuffer,
+    /// Suspicion from implausible inputs
+    pub input_guard: InputGuard,
 
     // Stats
     pub kills: u32,
@@ -64,59 +144,316 @@ pub struct PlayerState {
     pub damage_taken: f32,
     pub shots_fired: u32,
     pub shots_hit: u32,
+    /// Match clock (`MatchState::clock_ms`) when the player joined
     pub spawn_time: u64,
+    /// Match clock when the ship was destroyed
     pub death_time: Option<u64>,
+    /// Left the match while still alive
+    pub abandoned: bool,
+    /// Match clock until which the ship takes no damage
+    pub spawn_protected_until: Option<u64>,
+
+    // Streaks
+    /// Kills since last death
+    pub current_streak: u32,
+    pub best_streak: u32,
+    /// Kills in the current multi-kill chain
+    pub multi_kill_count: u32,
+    pub last_kill_time: Option<u64>,
+
+    /// Connection RTT (humans only)
+    pub latency: Option<Arc<LatencyTracker>>,
 }
 
 impl PlayerState {
+    /// A fresh ship with the hull's default weapon, stats taken from `balance`
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_id: Uuid,
         display_name: String,
         ship_type: ShipType,
-        flag_skin_id: Option<Uuid>,
+        cosmetics: Cosmetics,
         spawn_x: f32,
         spawn_y: f32,
         spawn_rotation: f32,
+        balance: &BalanceTable,
     ) -> Self {
-        let stats = ShipStats::for_type(ship_type);
+        let stats = balance.ship(ship_type);
+        let weapon_loadout = WeaponLoadout::default_for(ship_type);
         Self {
             user_id,
             display_name,
             ship_type,
-            flag_skin_id,
+            ship: stats,
+            cosmetics,
+            is_bot: false,
+            is_guest: false,
             x: spawn_x,
             y: spawn_y,
             rotation: spawn_rotation,
             vel_x: 0.0,
             vel_y: 0.0,
             health: stats.max_health,
+            shield: stats.max_shield,
+            max_shield: stats.max_shield,
+            shield_regen: stats.shield_regen,
+            shield_regen_delay: 0.0,
             alive: true,
+            weapon_loadout,
+            weapon: balance.weapon(weapon_loadout),
             weapon_cooldown: 0.0,
+            aim_assist: false,
+            mine_cooldown: 0.0,
+            status_effects: Vec::new(),
+            boost_energy: BOOST_MAX_ENERGY,
+            boost_cooldown: 0.0,
+            boosting: false,
             last_input_seq: 0,
+            last_input_at: None,
+            last_acked_seq: 0,
+            predicted: None,
             current_input: TickInput::default(),
+            input_buffer: InputBuffer::default(),
+            input_guard: InputGuard::default(),
             kills: 0,
             damage_dealt: 0.0,
             damage_taken: 0.0,
             shots_fired: 0,
             shots_hit: 0,
-            spawn_time: unix_millis(),
+            spawn_time: 0,
             death_time: None,
+            abandoned: false,
+            spawn_protected_until: None,
+            current_streak: 0,
+            best_streak: 0,
+            multi_kill_count: 0,
+            last_kill_time: None,
+            latency: None,
+        }
+    }
+
+    /// Lobby info for this player
+    pub fn info(&self) -> PlayerInfo {
+        PlayerInfo {
+            user_id: self.user_id,
+            display_name: self.display_name.clone(),
+            ship_type: self.ship_type,
+            flag_skin_id: self.cosmetics.flag_skin_id,
+            cosmetics: self.cosmetics.equipped(),
+            is_bot: self.is_bot,
+            weapon: self.weapon_loadout,
         }
     }
+
+    /// The ship's authoritative position and velocity
+    pub fn motion(&self) -> ShipMotion {
+        ShipMotion {
+            x: self.x,
+            y: self.y,
+            rotation: self.rotation,
+            vel_x: self.vel_x,
+            vel_y: self.vel_y,
+        }
+    }
+
+    /// Switch to a different weapon loadout
+    pub fn equip_weapon(&mut self, loadout: WeaponLoadout, balance: &BalanceTable) {
+        self.weapon_loadout = loadout;
+        self.weapon = balance.weapon(loadout);
+    }
+
+    /// Credit a kill, returning the multi-kill count if it chains onto the
+    /// previous kill
+    pub fn record_kill(&mut self, now: u64) -> Option<u32> {
+        self.kills += 1;
+        self.current_streak += 1;
+        self.best_streak = self.best_streak.max(self.current_streak);
+
+        let chained = self
+            .last_kill_time
+            .is_some_and(|last| now.saturating_sub(last) <= MULTI_KILL_WINDOW_MS);
+        self.multi_kill_count = if chained { self.multi_kill_count + 1 } else { 1 };
+        self.last_kill_time = Some(now);
+
+        chained.then_some(self.multi_kill_count)
+    }
+
+    /// Smoothed connection RTT in milliseconds, once measured
+    pub fn rtt_ms(&self) -> Option<u32> {
+        self.latency.as_ref().and_then(|l| l.rtt_ms())
+    }
+
+    /// Whether spawn protection still shields the ship from damage
+    pub fn is_spawn_protected(&self, now: u64) -> bool {
+        self.spawn_protected_until.is_some_and(|until| now < until)
+    }
+
+    /// Mark the ship destroyed, ending any streak and status effects
+    pub fn die(&mut self, now: u64) {
+        self.alive = false;
+        self.death_time = Some(now);
+        self.status_effects.clear();
+        self.current_streak = 0;
+        self.multi_kill_count = 0;
+        self.last_kill_time = None;
+    }
 }
 
-/// Zone configuration for battle royale shrinking
+/// Spawn points tried before settling for the one with the most room
+const SPAWN_ATTEMPTS: usize = 16;
+
+/// Seconds between `LatencyReport` broadcasts
+const LATENCY_REPORT_INTERVAL_SECS: u32 = 2;
+
+/// Seconds between `TimeSync` broadcasts
+const TIME_SYNC_INTERVAL_SECS: u32 = 5;
+
+/// Kills closer together than this chain into a multi-kill (milliseconds)
+const MULTI_KILL_WINDOW_MS: u64 = 4000;
+
+/// Most ticks one pass of the match loop runs to catch up after a stall
+const MAX_CATCH_UP_STEPS: u32 = 5;
+
+/// Smallest broadcast buffer, however few players a match allows
+const MIN_BROADCAST_CAPACITY: usize = 64;
+
+/// Result of a match that got past the waiting phase, for persistence
 #[derive(Debug, Clone)]
+pub struct MatchOutcome {
+    pub match_id: Uuid,
+    pub seed: u64,
+    /// Unix millis
+    pub started_at: u64,
+    /// Unix millis
+    pub ended_at: u64,
+    /// Human winner, if any
+    pub winner_user_id: Option<Uuid>,
+    pub stats: MatchStats,
+}
+
+/// Zone configuration for battle royale shrinking
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoneConfig {
+    /// Zone shape (radii below are half-widths for rectangles)
+    #[serde(default)]
+    pub shape: ZoneShape,
     /// Initial zone radius
     pub initial_radius: f32,
     /// Time before first shrink (seconds)
     pub initial_delay: f32,
     /// Shrink phases configuration
     pub phases: Vec<ZonePhase>,
+    /// How far each new zone center is pulled from a random spot toward
+    /// the surviving ships (0 = random, 1 = as close to them as fits)
+    #[serde(default = "default_center_bias")]
+    pub center_bias: f32,
 }
 
-#[derive(Debug, Clone)]
+fn default_center_bias() -> f32 {
+    0.25
+}
+
+/// Extra room past the initial zone before the hard world boundary
+const WORLD_BOUNDS_MARGIN: f32 = 0.25;
+
+impl ZoneConfig {
+    /// Hard world boundary: the initial zone's extent plus a margin
+    pub fn world_bounds(&self) -> WorldBounds {
+        let half_width = self.initial_radius * (1.0 + WORLD_BOUNDS_MARGIN);
+        WorldBounds {
+            half_width,
+            half_height: half_width * self.shape.aspect(),
+        }
+    }
+
+    /// Load a zone config from a JSON file and validate it
+    pub fn from_file(path: &str) -> Result<Self, ZoneConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ZoneConfigError::Read(path.to_string(), e))?;
+        let config: Self = serde_json::from_str(&contents)
+            .map_err(|e| ZoneConfigError::Parse(path.to_string(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the zone only ever shrinks and every phase takes time
+    pub fn validate(&self) -> Result<(), ZoneConfigError> {
+        let invalid = |msg: String| Err(ZoneConfigError::Invalid(msg));
+
+        if let ZoneShape::Rect { aspect } = self.shape {
+            if aspect <= 0.0 {
+                return invalid(format!("rect aspect must be positive, got {}", aspect));
+            }
+        }
+        if self.initial_radius <= 0.0 {
+            return invalid(format!(
+                "initial_radius must be positive, got {}",
+                self.initial_radius
+            ));
+        }
+        if self.initial_delay < 0.0 {
+            return invalid(format!(
+                "initial_delay can't be negative, got {}",
+                self.initial_delay
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.center_bias) {
+            return invalid(format!(
+                "center_bias must be between 0 and 1, got {}",
+                self.center_bias
+            ));
+        }
+        if self.phases.is_empty() {
+            return invalid("at least one phase is required".to_string());
+        }
+
+        let mut radius = self.initial_radius;
+        for (i, phase) in self.phases.iter().enumerate() {
+            if phase.target_radius < 0.0 || phase.target_radius >= radius {
+                return invalid(format!(
+                    "phase {}: target_radius {} must be below the previous radius {}",
+                    i, phase.target_radius, radius
+                ));
+            }
+            if phase.shrink_duration <= 0.0 {
+                return invalid(format!(
+                    "phase {}: shrink_duration must be positive, got {}",
+                    i, phase.shrink_duration
+                ));
+            }
+            if phase.delay_after < 0.0 {
+                return invalid(format!(
+                    "phase {}: delay_after can't be negative, got {}",
+                    i, phase.delay_after
+                ));
+            }
+            if phase.damage_per_second < 0.0 {
+                return invalid(format!(
+                    "phase {}: damage_per_second can't be negative, got {}",
+                    i, phase.damage_per_second
+                ));
+            }
+            radius = phase.target_radius;
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors loading a zone config file
+#[derive(Debug, thiserror::Error)]
+pub enum ZoneConfigError {
+    #[error("Failed to read zone config {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse zone config {0}: {1}")]
+    Parse(String, serde_json::Error),
+
+    #[error("Invalid zone config: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZonePhase {
     /// Target radius for this phase
     pub target_radius: f32,
@@ -131,6 +468,7 @@ pub struct ZonePhase {
 impl Default for ZoneConfig {
     fn default() -> Self {
         Self {
+            shape: ZoneShape::Circle,
             initial_radius: 1500.0,
             initial_delay: 60.0,
             phases: vec![
@@ -159,6 +497,170 @@ impl Default for ZoneConfig {
                     delay_after: 0.0,
                 },
             ],
+            center_bias: default_center_bias(),
+        }
+    }
+}
+
+/// Per-match tunables chosen when the match is created
+#[derive(Debug, Clone)]
+pub struct MatchSettings {
+    /// Players needed to start the countdown
+    pub min_players: usize,
+    /// Maximum players in the match
+    pub max_players: usize,
+    /// Warmup between reaching `min_players` and the countdown, cut short
+    /// once every human has sent an input (seconds, skipped if 0)
+    pub warmup: f32,
+    /// Countdown between the warmup and the start (seconds)
+    pub countdown: f32,
+    /// Fill with bots up to `min_players` after waiting this long (seconds)
+    pub bot_fill_delay: Option<f32>,
+    /// Close the match if it waits this long without a human in it, so a
+    /// match its players never connected to doesn't wait forever (seconds)
+    pub empty_timeout: Option<f32>,
+    /// Encoding for player positions in snapshots
+    pub snapshot_precision: SnapshotPrecision,
+    /// Shared broadcast, or snapshots culled per player
+    pub snapshot_mode: SnapshotMode,
+    /// Include live projectiles in snapshots
+    pub snapshot_projectiles: bool,
+    /// Simulation ticks per second
+    pub tick_rate: u32,
+    /// Snapshots broadcast per second
+    pub snapshot_rate: u32,
+    /// Total time the host may keep the match paused (seconds)
+    pub max_pause: f32,
+    /// Map the match is played on, including its zone
+    pub arena: Arc<Arena>,
+    /// Broadcast buffer slots per player slot
+    pub broadcast_per_player: usize,
+    /// Write a replay of the match to this directory (not recorded if unset)
+    pub replay_dir: Option<PathBuf>,
+    /// Zone damage goes straight to health, ignoring shields
+    pub zone_bypasses_shield: bool,
+    /// Splash damage hits its owner too
+    pub self_damage: bool,
+    /// Shots from different players that touch cancel each other out
+    pub projectile_clash: bool,
+    /// Accept new players after the match has started
+    pub late_join: bool,
+    /// Time a ship neither takes nor deals damage after spawning, at the
+    /// start of the match or on a late join (seconds)
+    pub spawn_protection: f32,
+    /// Distance kept between a new spawn and every live ship, when there's room
+    pub spawn_separation: f32,
+    /// Live projectiles in the match; the oldest are dropped past this
+    pub max_projectiles: usize,
+    /// In-flight projectiles per player; firing is held back at this many
+    pub max_projectiles_per_player: usize,
+    /// Events broadcast per tick; shots are dropped first past this
+    pub max_events_per_tick: usize,
+    /// Events kept in the match's event log; the oldest are dropped past this
+    pub event_log_capacity: usize,
+    /// Ship and weapon stats
+    pub balance: Arc<BalanceTable>,
+    /// Seconds of notice `ZoneWarning` gives before each shrink (none if 0)
+    pub zone_warning_lead: f32,
+    /// Distance a client's own prediction may drift from the authoritative
+    /// position before an `InputAck` corrects it (0 = always correct)
+    pub prediction_tolerance: f32,
+    /// Time without an input after which a ship's controls are let go, so
+    /// it coasts to a stop (seconds, 0 holds the last input forever)
+    pub input_timeout: f32,
+    /// Time the match may run before sudden death (seconds, unlimited if unset)
+    pub max_duration: Option<f32>,
+    /// How long sudden death closes the zone before the survivors are ranked
+    /// and the match ends (seconds, 0 ends it as soon as time runs out)
+    pub sudden_death: f32,
+    /// Single-player practice: bots fill every other slot as soon as the
+    /// player joins, the zone never closes and nobody wins by outlasting
+    /// the rest, so the match runs until the player leaves
+    pub practice: bool,
+    /// Cone either side of the aim that aim assist looks for a target in,
+    /// for players who opted in (radians, off if 0)
+    pub aim_assist_cone: f32,
+    /// Share of the way aim assist turns a shot toward its target (0-1)
+    pub aim_assist_strength: f32,
+    /// Private game: its first human is the host, who may pause it.
+    /// Matchmade matches have no host
+    pub private: bool,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            min_players: 2,
+            max_players: 20,
+            warmup: 0.0,
+            countdown: 5.0,
+            bot_fill_delay: None,
+            empty_timeout: Some(60.0),
+            snapshot_precision: SnapshotPrecision::Full,
+            snapshot_mode: SnapshotMode::Broadcast,
+            snapshot_projectiles: false,
+            tick_rate: SIMULATION_TPS,
+            snapshot_rate: SNAPSHOT_TPS,
+            max_pause: 120.0,
+            arena: Arc::new(Arena::open(&ZoneConfig::default())),
+            broadcast_per_player: 8,
+            replay_dir: None,
+            zone_bypasses_shield: true,
+            self_damage: false,
+            projectile_clash: false,
+            late_join: false,
+            spawn_protection: 3.0,
+            spawn_separation: 300.0,
+            max_projectiles: 1000,
+            max_projectiles_per_player: 64,
+            max_events_per_tick: 256,
+            event_log_capacity: 10_000,
+            balance: Arc::new(BalanceTable::default()),
+            zone_warning_lead: 10.0,
+            prediction_tolerance: 0.0,
+            input_timeout: 0.5,
+            max_duration: None,
+            sudden_death: 30.0,
+            practice: false,
+            aim_assist_cone: 6f32.to_radians(),
+            aim_assist_strength: 0.5,
+            private: false,
+        }
+    }
+}
+
+impl MatchSettings {
+    /// Capacity of the match's broadcast channel. Bigger lobbies send more
+    /// one-off messages (joins, kills) between snapshots, so scale with them
+    pub fn broadcast_capacity(&self) -> usize {
+        (self.max_players * self.broadcast_per_player).max(MIN_BROADCAST_CAPACITY)
+    }
+}
+
+/// An arena's repair pad and where it is in its cycle
+#[derive(Debug, Clone)]
+pub struct RepairPadState {
+    pub pad: RepairPad,
+    /// Repaired a ship on the last tick
+    pub in_use: bool,
+    /// Match clock (ms) the pad comes back online at after a use
+    pub ready_at: u64,
+}
+
+impl RepairPadState {
+    pub fn new(pad: RepairPad) -> Self {
+        Self {
+            pad,
+            in_use: false,
+            ready_at: 0,
+        }
+    }
+
+    pub fn status(&self, now: u64) -> RepairPadStatus {
+        let remaining = self.ready_at.saturating_sub(now);
+        RepairPadStatus {
+            active: remaining == 0,
+            cooldown_remaining: remaining as f32 / 1000.0,
         }
     }
 }
@@ -169,24 +671,73 @@ pub struct MatchState {
     pub seed: u64,
     pub phase: MatchPhase,
     pub tick: u64,
-    pub players: HashMap<Uuid, PlayerState>,
+    /// Ordered by ID so every run of a seed iterates players the same way
+    pub players: BTreeMap<Uuid, PlayerState>,
     pub zone: ZoneState,
     pub zone_config: ZoneConfig,
+    /// Hard world boundary, fixed for the match
+    pub bounds: WorldBounds,
     pub zone_timer: f32,
     pub current_zone_phase: usize,
     pub is_shrinking: bool,
+    /// Center the next shrink heads for, chosen when the wait before it begins
+    pub next_zone_center: Option<(f32, f32)>,
+    /// `ZoneWarning` already sent for the upcoming shrink
+    pub zone_warning_sent: bool,
     pub projectiles: Vec<Projectile>,
+    pub mines: Vec<Mine>,
+    /// The arena's repair pads, in arena order
+    pub repair_pads: Vec<RepairPadState>,
+    /// Live ships by position, rebuilt each tick before collisions and
+    /// again before hit checks, as ships move in between
+    pub ship_grid: SpatialGrid,
+    /// The match's only source of randomness, seeded from `seed`. Draws
+    /// happen in a fixed order within each tick, so a seed and the inputs
+    /// applied on each tick pin down the whole match:
+    ///
+    /// 1. Inputs in the order they were queued: each join draws a spawn
+    ///    position (`generate_spawn_position`)
+    /// 2. While waiting, each backfilled bot draws its ID, hull and spawn
+    /// 3. When the countdown ends, the first zone center (`plan_next_zone`)
+    /// 4. In progress, combat rolls one crit per projectile hit, in
+    ///    projectile order, then a finished shrink draws the next zone center
+    ///
+    /// Nothing else may draw from it, and nothing in the simulation may use
+    /// another RNG or the wall clock.
     pub rng: ChaCha8Rng,
+    /// Unix millis
     pub start_time: Option<u64>,
+    /// Simulation clock when the match started (see `clock_ms`)
+    pub start_clock_ms: Option<u64>,
+    pub warmup_remaining: f32,
     pub countdown_remaining: f32,
-    pub min_players: usize,
-    pub max_players: usize,
+    pub settings: MatchSettings,
+    /// Seconds spent in the waiting phase
+    pub waiting_elapsed: f32,
+    /// Seconds the match has been waiting without a human, since it was
+    /// created or since the last one left
+    pub empty_elapsed: f32,
+    /// Players who left after the match started, kept for end-of-match stats
+    pub departed: Vec<PlayerState>,
+    /// First human to join; the only player who may pause
+    pub host_id: Option<Uuid>,
+    /// Simulation frozen by the host
+    pub paused: bool,
+    /// Seconds spent paused so far, capped by `settings.max_pause`
+    pub pause_elapsed: f32,
+    /// Ticks run while paused, which the match clock skips
+    pub paused_ticks: u64,
+    /// Seconds the match has been in progress, not counting pauses
+    pub match_elapsed: f32,
+    /// Zone radius when sudden death began, once it has
+    pub sudden_death_from: Option<f32>,
 }
 
 impl MatchState {
-    pub fn new(id: Uuid, seed: u64, min_players: usize, max_players: usize) -> Self {
-        let zone_config = ZoneConfig::default();
+    pub fn new(id: Uuid, seed: u64, settings: MatchSettings) -> Self {
+        let zone_config = settings.arena.zone.clone();
         let zone = ZoneState {
+            shape: zone_config.shape,
             center_x: 0.0,
             center_y: 0.0,
             radius: zone_config.initial_radius,
@@ -203,35 +754,159 @@ impl MatchState {
             seed,
             phase: MatchPhase::Waiting,
             tick: 0,
-            players: HashMap::new(),
+            players: BTreeMap::new(),
             zone,
+            bounds: settings.arena.bounds,
             zone_config,
             zone_timer: 0.0,
             current_zone_phase: 0,
             is_shrinking: false,
+            next_zone_center: None,
+            zone_warning_sent: false,
             projectiles: Vec::new(),
+            mines: Vec::new(),
+            repair_pads: settings
+                .arena
+                .repair_pads
+                .iter()
+                .copied()
+                .map(RepairPadState::new)
+                .collect(),
+            ship_grid: SpatialGrid::default(),
             rng: ChaCha8Rng::seed_from_u64(seed),
             start_time: None,
-            countdown_remaining: 5.0, // 5 second countdown
-            min_players,
-            max_players,
+            start_clock_ms: None,
+            warmup_remaining: settings.warmup,
+            countdown_remaining: settings.countdown,
+            settings,
+            waiting_elapsed: 0.0,
+            empty_elapsed: 0.0,
+            departed: Vec::new(),
+            host_id: None,
+            paused: false,
+            pause_elapsed: 0.0,
+            paused_ticks: 0,
+            match_elapsed: 0.0,
+            sudden_death_from: None,
+        }
+    }
+
+    /// Pick where the next shrink phase will center, if one is left, so it
+    /// can be announced before the shrink starts
+    fn plan_next_zone(&mut self) {
+        self.zone_warning_sent = false;
+        let survivors = self.survivor_centroid();
+        let bias = self.zone_config.center_bias;
+        self.next_zone_center = self
+            .zone_config
+            .phases
+            .get(self.current_zone_phase)
+            .map(|phase| phase.target_radius)
+            .map(|target_radius| {
+                // Randomize new zone center (within current zone)
+                let aspect = self.zone.shape.aspect();
+                let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+                let max_offset = (self.zone.radius - target_radius).max(0.0) * 0.5;
+                let offset = self.rng.gen_range(0.0..max_offset);
+                let (mut dx, mut dy) = (angle.cos() * offset, angle.sin() * offset);
+
+                // Pull it toward the survivors, no further than a random
+                // center could have landed
+                if let Some((x, y)) = survivors {
+                    let (to_x, to_y) = (x - self.zone.center_x, (y - self.zone.center_y) / aspect);
+                    let scale = (max_offset / to_x.hypot(to_y)).min(1.0);
+                    dx += (to_x * scale - dx) * bias;
+                    dy += (to_y * scale - dy) * bias;
+                }
+                (self.zone.center_x + dx, self.zone.center_y + dy * aspect)
+            });
+    }
+
+    /// Average position of the ships still alive
+    fn survivor_centroid(&self) -> Option<(f32, f32)> {
+        let alive: Vec<_> = self.players.values().filter(|p| p.alive).collect();
+        if alive.is_empty() {
+            return None;
         }
+        let n = alive.len() as f32;
+        let (x, y) = alive.iter().fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+        Some((x / n, y / n))
     }
 
-    /// Generate a spawn position for a new player
+    /// Spawn point for a new player. Candidates inside the zone are drawn
+    /// until one is at least `spawn_separation` from every live ship, up to
+    /// `SPAWN_ATTEMPTS`; if none is, the one with the most room wins. Once
+    /// the zone has shrunk, spawns pull toward its center, away from the
+    /// closing edge
     pub fn generate_spawn_position(&mut self) -> (f32, f32, f32) {
-        let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
-        let distance = self.rng.gen_range(200.0..self.zone.radius * 0.8);
-        let x = self.zone.center_x + angle.cos() * distance;
-        let y = self.zone.center_y + angle.sin() * distance;
+        let shrink = (self.zone.radius / self.zone_config.initial_radius).clamp(0.0, 1.0);
+        let max_distance = self.settings.arena.spawn_radius * shrink * (0.5 + 0.5 * shrink);
+
+        let mut best = (self.zone.center_x, self.zone.center_y);
+        let mut best_clearance = f32::MIN;
+        for _ in 0..SPAWN_ATTEMPTS {
+            let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            // sqrt spreads candidates evenly over the zone's area
+            let distance = max_distance * self.rng.gen_range(0.0f32..=1.0).sqrt();
+            let x = self.zone.center_x + angle.cos() * distance;
+            let y = self.zone.center_y + angle.sin() * distance * self.zone.shape.aspect();
+            let clearance = self.spawn_clearance(x, y);
+            if clearance > best_clearance {
+                best_clearance = clearance;
+                best = (x, y);
+            }
+            if clearance >= self.settings.spawn_separation {
+                break;
+            }
+        }
+
         let rotation = self.rng.gen_range(0.0..std::f32::consts::TAU);
-        (x, y, rotation)
+        (best.0, best.1, rotation)
+    }
+
+    /// Room around a candidate spawn point: the distance to the nearest live
+    /// ship or obstacle edge
+    fn spawn_clearance(&self, x: f32, y: f32) -> f32 {
+        let ships = self
+            .players
+            .values()
+            .filter(|p| p.alive)
+            .map(|p| (p.x - x).hypot(p.y - y));
+        let obstacles = self.settings.arena.obstacles.iter().map(|o| o.distance(x, y));
+        ships.chain(obstacles).fold(f32::MAX, f32::min)
     }
 
     /// Count alive players
     pub fn alive_count(&self) -> usize {
         self.players.values().filter(|p| p.alive).count()
     }
+
+    /// Count non-bot players
+    pub fn human_count(&self) -> usize {
+        self.players.values().filter(|p| !p.is_bot).count()
+    }
+
+    /// Seconds simulated per tick at this match's tick rate
+    pub fn tick_delta(&self) -> f32 {
+        tick_delta(self.settings.tick_rate)
+    }
+
+    /// Simulated time since the match was created (milliseconds). Derived
+    /// from the tick count rather than the wall clock, so replays reproduce it.
+    /// Stands still while the match is paused, so timers resume where they were
+    pub fn clock_ms(&self) -> u64 {
+        (self.tick - self.paused_ticks) * 1000 / self.settings.tick_rate.max(1) as u64
+    }
+
+    /// When spawn protection granted now runs out (match clock, ms)
+    pub fn spawn_protected_until(&self) -> u64 {
+        self.clock_ms() + (self.settings.spawn_protection * 1000.0) as u64
+    }
+
+    /// Pick a bot to make room for a human, if any
+    pub fn any_bot(&self) -> Option<Uuid> {
+        self.players.values().find(|p| p.is_bot).map(|p| p.user_id)
+    }
 }
 
 /// Handle to a running match
@@ -240,27 +915,73 @@ pub struct MatchHandle {
     pub id: Uuid,
     pub input_tx: mpsc::Sender<PlayerInput>,
     pub snapshot_tx: broadcast::Sender<ServerMsg>,
-    pub player_count: Arc<std::sync::atomic::AtomicUsize>,
+    pub player_count: Arc<AtomicUsize>,
+    pub phase: Arc<AtomicU8>,
+    pub max_players: usize,
+    /// How each player's forwarder passes on the match's messages
+    pub snapshot_mode: SnapshotMode,
+    /// Accepts new players after the match has started
+    pub late_join: bool,
+    /// A player's practice match, which nobody else may join
+    pub practice: bool,
+    /// Matchmaking region of its players (unset for cross-region matches)
+    pub region: Option<String>,
+    /// Tick timings and load, published by the match loop
+    pub metrics: Arc<MatchMetrics>,
+    /// Recent events, kept after the match ends for review
+    pub events: Arc<MatchEventLog>,
+    /// Copy of the match state, published by the match loop for admins
+    pub inspector: Arc<MatchInspector>,
 }
 
 impl MatchHandle {
     pub fn player_count(&self) -> usize {
-        self.player_count.load(std::sync::atomic::Ordering::Relaxed)
+        self.player_count.load(Ordering::Relaxed)
+    }
+
+    pub fn phase(&self) -> MatchPhase {
+        MatchPhase::from_u8(self.phase.load(Ordering::Relaxed))
+    }
+
+    /// Whether a new player could join right now
+    pub fn is_joinable(&self) -> bool {
+        !self.practice
+            && self.player_count() < self.max_players
+            && match self.phase() {
+                MatchPhase::Waiting | MatchPhase::Warmup | MatchPhase::Countdown => true,
+                MatchPhase::InProgress => self.late_join,
+                MatchPhase::Ended => false,
+            }
     }
 }
 
-/// Registry of all active matches
+/// How long a finished match's event log stays available by default
+const DEFAULT_EVENT_RETENTION: Duration = Duration::from_secs(600);
+
+/// Registry of all active matches, and the event logs of recently finished ones
 pub struct MatchRegistry {
     matches: DashMap<Uuid, MatchHandle>,
+    /// Event logs of finished matches and when each match finished
+    finished: DashMap<Uuid, (Instant, Arc<MatchEventLog>)>,
+    /// How long finished matches' event logs are kept
+    event_retention: Duration,
 }
 
 impl MatchRegistry {
     pub fn new() -> Self {
         Self {
             matches: DashMap::new(),
+            finished: DashMap::new(),
+            event_retention: DEFAULT_EVENT_RETENTION,
         }
     }
 
+    /// Set how long finished matches' event logs are kept
+    pub fn with_event_retention(mut self, retention: Duration) -> Self {
+        self.event_retention = retention;
+        self
+    }
+
     pub fn get(&self, id: &Uuid) -> Option<MatchHandle> {
         self.matches.get(id).map(|m| m.value().clone())
     }
@@ -269,8 +990,29 @@ impl MatchRegistry {
         self.matches.insert(handle.id, handle);
     }
 
-    pub fn remove(&self, id: &Uuid) -> Option<MatchHandle> {
-        self.matches.remove(id).map(|(_, h)| h)
+    /// Remove an ended match, keeping its event log for the retention window
+    pub fn finish(&self, id: &Uuid) -> Option<MatchHandle> {
+        self.finished
+            .retain(|_, (finished_at, _)| finished_at.elapsed() < self.event_retention);
+        let (_, handle) = self.matches.remove(id)?;
+        if !self.event_retention.is_zero() {
+            self.finished
+                .insert(*id, (Instant::now(), handle.events.clone()));
+        }
+        Some(handle)
+    }
+
+    /// Event log of a finished match, if it ended within the retention window
+    pub fn finished_events(&self, id: &Uuid) -> Option<Arc<MatchEventLog>> {
+        self.finished
+            .get(id)
+            .filter(|entry| entry.0.elapsed() < self.event_retention)
+            .map(|entry| entry.1.clone())
+    }
+
+    /// Snapshot of all active match handles
+    pub fn list(&self) -> Vec<MatchHandle> {
+        self.matches.iter().map(|m| m.value().clone()).collect()
     }
 
     pub fn active_matches(&self) -> usize {
@@ -284,14 +1026,14 @@ impl MatchRegistry {
             .sum()
     }
 
-    /// Find a match with available slots
-    pub fn find_available_match(&self, max_players: usize) -> Option<MatchHandle> {
-        for entry in self.matches.iter() {
-            if entry.value().player_count() < max_players {
-                return Some(entry.value().clone());
-            }
-        }
-        None
+    /// Find a match a player from `region` could join, including running
+    /// matches that allow late joining
+    pub fn find_available_match(&self, region: &str) -> Option<MatchHandle> {
+        self.matches
+            .iter()
+            .map(|entry| entry.value().clone())
+            .filter(|handle| handle.region.as_deref().is_none_or(|r| r == region))
+            .find(|handle| handle.is_joinable())
     }
 }
 
@@ -307,7 +1049,26 @@ pub struct GameMatch {
     input_rx: mpsc::Receiver<PlayerInput>,
     snapshot_tx: broadcast::Sender<ServerMsg>,
     snapshot_builder: SnapshotBuilder,
-    player_count: Arc<std::sync::atomic::AtomicUsize>,
+    player_count: Arc<AtomicUsize>,
+    phase: Arc<AtomicU8>,
+    /// Notified with the user ID whenever a player abandons
+    abandon_tx: Option<mpsc::UnboundedSender<Uuid>>,
+    /// Messages addressed to a single player
+    direct_tx: Option<mpsc::UnboundedSender<(Uuid, ServerMsg)>>,
+    metrics: Arc<MatchMetrics>,
+    tick_stats: TickStatsWindow,
+    /// Ticks between latency reports
+    latency_report_ticks: u64,
+    /// Ticks between time syncs
+    time_sync_ticks: u64,
+    /// Inputs recorded for a replay, if enabled
+    recorder: Option<ReplayRecorder>,
+    /// Persists results and XP at match end
+    stats_store: Option<StatsStore>,
+    /// Events kept for post-match review
+    event_log: Arc<MatchEventLog>,
+    /// Receives a copy of the match state once per second
+    inspector: Arc<MatchInspector>,
 }
 
 impl GameMatch {
@@ -315,241 +1076,794 @@ impl GameMatch {
     pub fn new(
         id: Uuid,
         seed: u64,
-        min_players: usize,
-        max_players: usize,
+        settings: MatchSettings,
     ) -> (Self, MatchHandle) {
         let (input_tx, input_rx) = mpsc::channel(256);
-        let (snapshot_tx, _) = broadcast::channel(64);
-        let player_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (snapshot_tx, _) = broadcast::channel(settings.broadcast_capacity());
+        let player_count = Arc::new(AtomicUsize::new(0));
+        let phase = Arc::new(AtomicU8::new(MatchPhase::Waiting as u8));
+        let tick_budget_micros = 1_000_000 / settings.tick_rate.max(1) as u64;
+        let metrics = Arc::new(MatchMetrics::new(tick_budget_micros));
+        // Publish metrics once per second of simulation
+        let tick_stats = TickStatsWindow::new(settings.tick_rate);
+        let latency_report_ticks = (LATENCY_REPORT_INTERVAL_SECS * settings.tick_rate).max(1) as u64;
+        let time_sync_ticks = (TIME_SYNC_INTERVAL_SECS * settings.tick_rate).max(1) as u64;
+        let event_log = Arc::new(MatchEventLog::new(settings.event_log_capacity));
+        let inspector = Arc::new(MatchInspector::default());
 
         let handle = MatchHandle {
             id,
             input_tx,
             snapshot_tx: snapshot_tx.clone(),
             player_count: player_count.clone(),
+            phase: phase.clone(),
+            max_players: settings.max_players,
+            snapshot_mode: settings.snapshot_mode,
+            late_join: settings.late_join,
+            practice: settings.practice,
+            region: None,
+            metrics: metrics.clone(),
+            events: event_log.clone(),
+            inspector: inspector.clone(),
         };
 
-        let snapshot_interval = SIMULATION_TPS / SNAPSHOT_TPS;
+        let recorder = settings
+            .replay_dir
+            .clone()
+            .map(|dir| ReplayRecorder::new(dir, id, seed, &settings));
+
         let game_match = Self {
-            state: MatchState::new(id, seed, min_players, max_players),
+            snapshot_builder: SnapshotBuilder::new(
+                settings.tick_rate,
+                settings.snapshot_rate,
+                settings.snapshot_precision,
+            )
+            .with_projectiles(settings.snapshot_projectiles),
+            state: MatchState::new(id, seed, settings),
             input_rx,
             snapshot_tx,
-            snapshot_builder: SnapshotBuilder::new(snapshot_interval),
             player_count,
+            phase,
+            abandon_tx: None,
+            direct_tx: None,
+            metrics,
+            tick_stats,
+            latency_report_ticks,
+            time_sync_ticks,
+            recorder,
+            stats_store: None,
+            event_log,
+            inspector,
         };
 
         (game_match, handle)
     }
 
-    /// Run the authoritative tick loop
-    pub async fn run(mut self) {
+    /// Report abandons to the given channel
+    pub fn set_abandon_tx(&mut self, abandon_tx: mpsc::UnboundedSender<Uuid>) {
+        self.abandon_tx = Some(abandon_tx);
+    }
+
+    /// Deliver per-player messages (input acks) through the given channel
+    pub fn set_direct_tx(&mut self, direct_tx: mpsc::UnboundedSender<(Uuid, ServerMsg)>) {
+        self.direct_tx = Some(direct_tx);
+    }
+
+    /// Persist results and XP through the given store when the match ends
+    pub fn set_stats_store(&mut self, stats_store: StatsStore) {
+        self.stats_store = Some(stats_store);
+    }
+
+    /// Run the authoritative tick loop. Returns the outcome if the match got
+    /// past the waiting phase
+    pub async fn run(mut self) -> Option<MatchOutcome> {
         info!(match_id = %self.state.id, "Match started");
 
-        let tick_duration = Duration::from_micros(1_000_000 / SIMULATION_TPS as u64);
+        let tick_duration =
+            Duration::from_micros(self.metrics.tick_budget_micros.load(Ordering::Relaxed));
         let mut tick_interval = interval(tick_duration);
         tick_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut timestep = FixedTimestep::new(tick_duration, MAX_CATCH_UP_STEPS);
 
-        loop {
-            tick_interval.tick().await;
+        'ticks: loop {
+            // Time is measured between the interval's scheduled instants
+            // rather than wake-ups, so wake-up jitter doesn't alternate passes
+            // of zero and two ticks, while ticks the interval skipped after a
+            // stall are still owed
+            let scheduled = tick_interval.tick().await.into_std();
+            let steps = timestep.advance(scheduled);
+            self.tick_stats.record_steps(steps.run);
+            if steps.dropped > 0 {
+                self.metrics
+                    .ticks_dropped
+                    .fetch_add(steps.dropped as u64, Ordering::Relaxed);
+                warn!(
+                    match_id = %self.state.id,
+                    dropped = steps.dropped,
+                    "Match loop fell too far behind, dropping ticks"
+                );
+            }
 
-            // Drain input queue
-            self.process_inputs();
+            for _ in 0..steps.run {
+                let tick_timer = Timer::new();
 
-            // Run simulation tick
-            let events = self.run_tick();
+                self.step_once();
 
-            // Build and broadcast snapshot if needed
-            if self.snapshot_builder.should_send() {
-                let snapshot = self.snapshot_builder.build(
-                    self.state.tick,
-                    &self.state.zone,
-                    &self.state.players,
-                    events,
+                self.tick_stats.record(
+                    tick_timer.elapsed_micros(),
+                    self.state.projectiles.len(),
+                    &self.metrics,
                 );
 
-                // Broadcast to all connected clients
-                let _ = self.snapshot_tx.send(snapshot);
-            }
-
-            // Check for match end
-            if self.state.phase == MatchPhase::Ended {
-                info!(match_id = %self.state.id, "Match ended");
-                break;
-            }
+                // Check for match end
+                if self.state.phase == MatchPhase::Ended {
+                    info!(match_id = %self.state.id, "Match ended");
+                    break 'ticks;
+                }
 
-            // Check if all players disconnected
-            if self.state.players.is_empty() && self.state.phase != MatchPhase::Waiting {
-                info!(match_id = %self.state.id, "All players left, ending match");
-                break;
+                // Check if all players disconnected (bots don't keep a match alive)
+                if self.state.human_count() == 0 && self.state.phase != MatchPhase::Waiting {
+                    info!(match_id = %self.state.id, "All players left, ending match");
+                    break 'ticks;
+                }
             }
         }
 
-        // Send final match end message
-        let winner = self
-            .state
-            .players
-            .values()
-            .find(|p| p.alive)
-            .map(|p| p.user_id);
-
+        // Send final match end message, won by the best placed survivor
         let stats = self.build_match_stats();
+        let winner = stats
+            .player_stats
+            .iter()
+            .find(|s| s.placement == 1)
+            .and_then(|s| self.state.players.get(&s.user_id))
+            .filter(|p| p.alive);
+        let winner_user_id = winner.map(|p| p.user_id);
+        let winner_persisted = winner.is_some_and(|p| !p.is_bot && !p.is_guest);
+
+        let Some(started_at) = self.state.start_time else {
+            let _ = self.snapshot_tx.send(ServerMsg::MatchEnd { winner_user_id, stats });
+            return None;
+        };
+
+        let mut outcome = MatchOutcome {
+            match_id: self.state.id,
+            seed: self.state.seed,
+            started_at,
+            ended_at: unix_millis(),
+            winner_user_id: winner_user_id.filter(|_| winner_persisted),
+            stats: stats.clone(),
+        };
+        self.record_results(&mut outcome).await;
         let _ = self.snapshot_tx.send(ServerMsg::MatchEnd {
-            winner_user_id: winner,
-            stats,
+            winner_user_id,
+            stats: outcome.stats.clone(),
         });
-    }
 
-    /// Process all pending inputs from players
-    fn process_inputs(&mut self) {
-        while let Ok(input) = self.input_rx.try_recv() {
-            match input.msg {
-                ClientMsg::JoinMatch { ship_type, .. } => {
-                    self.handle_join(input.user_id, ship_type);
-                }
-                ClientMsg::InputTick {
-                    seq,
-                    throttle,
-                    steer,
-                    shoot,
-                    aim_yaw,
-                } => {
-                    self.handle_input(input.user_id, seq, throttle, steer, shoot, aim_yaw);
-                }
-                ClientMsg::Ping { t } => {
-                    let _ = self.snapshot_tx.send(ServerMsg::Pong { t });
-                }
-                ClientMsg::LeaveMatch => {
-                    self.handle_leave(input.user_id);
+        // Replays record the simulated stats; levels depend on the database
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.finish(self.state.tick, &stats).await {
+                Ok(path) => {
+                    info!(match_id = %self.state.id, path = %path.display(), "Replay saved")
                 }
+                Err(e) => warn!(match_id = %self.state.id, error = %e, "Failed to save replay"),
             }
         }
+
+        Some(outcome)
     }
 
-    /// Handle player join request
-    fn handle_join(&mut self, user_id: Uuid, ship_type: ShipType) {
-        if self.state.players.contains_key(&user_id) {
-            warn!(user_id = %user_id, "Player already in match");
-            return;
+    /// Run one tick: apply queued inputs, simulate, then send acks,
+    /// snapshots and reports. `run` calls this once per tick owed; tests
+    /// can call it directly to step a match without a runtime or timers
+    pub fn step_once(&mut self) {
+        // Drain input queue
+        self.process_inputs();
+
+        // Run simulation tick
+        let events = self.run_tick();
+        self.event_log.record(self.state.tick, &events);
+        self.send_input_acks();
+
+        // Build and broadcast snapshot if needed
+        if self.snapshot_builder.should_send() {
+            let snapshot = self.snapshot_builder.build(
+                self.state.tick,
+                self.state.clock_ms(),
+                &self.state.zone,
+                &self.state.players,
+                &self.state.projectiles,
+                &self.state.repair_pads,
+                events,
+            );
+
+            // Broadcast to all connected clients
+            let _ = self.snapshot_tx.send(snapshot);
         }
 
-        if self.state.players.len() >= self.state.max_players {
-            let _ = self.snapshot_tx.send(ServerMsg::Error {
-                code: "match_full".to_string(),
-                message: "Match is full".to_string(),
+        if self.state.tick.is_multiple_of(self.latency_report_ticks) {
+            self.send_latency_report();
+        }
+        if self.state.tick.is_multiple_of(self.time_sync_ticks) {
+            let _ = self.snapshot_tx.send(ServerMsg::TimeSync {
+                server_time: unix_millis(),
+                tick: self.state.tick,
             });
+        }
+        if self.state.tick.is_multiple_of(self.state.settings.tick_rate.max(1) as u64) {
+            self.inspector.publish(MatchInspection::capture(&self.state));
+        }
+    }
+
+    /// Persist the match and its human players' results and XP, then fill in
+    /// each player's `new_level`. If XP can't be saved `new_level` stays
+    /// unset but `xp_gained` is kept, so the result screen still shows what
+    /// was earned
+    async fn record_results(&self, outcome: &mut MatchOutcome) {
+        let Some(store) = &self.stats_store else {
+            return;
+        };
+
+        let match_id = outcome.match_id;
+        let players: Vec<PlayerEndState> = outcome
+            .stats
+            .player_stats
+            .iter()
+            .filter(|p| !p.is_bot && !p.is_guest)
+            .map(PlayerEndState::from)
+            .collect();
+        if players.is_empty() {
             return;
         }
 
-        let (spawn_x, spawn_y, spawn_rotation) = self.state.generate_spawn_position();
-        let player = PlayerState::new(
-            user_id,
-            format!("Player_{}", &user_id.to_string()[..8]),
-            ship_type,
-            None,
-            spawn_x,
-            spawn_y,
-            spawn_rotation,
-        );
+        let writes = match store
+            .record_match_end(match_id, &MatchSummary::from(&*outcome), players)
+            .await
+        {
+            Ok(writes) => writes,
+            Err(e) => {
+                error!(match_id = %match_id, error = %e, "Failed to record match end");
+                return;
+            }
+        };
+        if !writes.results {
+            error!(match_id = %match_id, "Failed to record match history");
+        }
+        if !writes.progression {
+            warn!(match_id = %match_id, "Failed to award match XP");
+        }
+
+        for player in &mut outcome.stats.player_stats {
+            if let Some(&total) = writes.xp_totals.get(&player.user_id) {
+                player.new_level = Some(progression::level_for_xp(total));
+            }
+        }
+    }
 
-        let player_info = PlayerInfo {
-            user_id: player.user_id,
-            display_name: player.display_name.clone(),
-            ship_type: player.ship_type,
-            flag_skin_id: player.flag_skin_id,
+    /// Tell each player the result of their newest simulated input, once:
+    /// either that their own prediction holds, or the authoritative state
+    /// to correct it to
+    fn send_input_acks(&mut self) {
+        let Some(direct_tx) = &self.direct_tx else {
+            return;
         };
 
-        self.state.players.insert(user_id, player);
-        self.player_count
-            .store(self.state.players.len(), std::sync::atomic::Ordering::Relaxed);
+        let tolerance = self.state.settings.prediction_tolerance;
+        for player in self.state.players.values_mut() {
+            let seq = player.current_input.seq;
+            if player.is_bot || seq == player.last_acked_seq {
+                continue;
+            }
+            player.last_acked_seq = seq;
 
-        // Notify all players of the new player
-        let _ = self.snapshot_tx.send(ServerMsg::PlayerJoined {
-            player: player_info.clone(),
-        });
+            let actual = player.motion();
+            let prediction_ok = tolerance > 0.0
+                && player.predicted.is_some_and(|predicted| {
+                    PhysicsSystem::prediction_error(&predicted, &actual) <= tolerance
+                });
+            // A corrected client resets to the authoritative state
+            let correction = (!prediction_ok).then(|| {
+                player.predicted = Some(actual);
+                actual
+            });
 
-        // Send match joined to the new player
-        let players: Vec<PlayerInfo> = self
+            let ack = ServerMsg::InputAck {
+                seq,
+                tick: self.state.tick,
+                prediction_ok,
+                correction,
+            };
+            let _ = direct_tx.send((player.user_id, ack));
+        }
+    }
+
+    /// Broadcast every measured player's RTT
+    fn send_latency_report(&self) {
+        let players: Vec<PlayerLatency> = self
             .state
             .players
             .values()
-            .map(|p| PlayerInfo {
-                user_id: p.user_id,
-                display_name: p.display_name.clone(),
-                ship_type: p.ship_type,
-                flag_skin_id: p.flag_skin_id,
+            .filter_map(|p| {
+                p.rtt_ms().map(|rtt_ms| PlayerLatency {
+                    user_id: p.user_id,
+                    rtt_ms,
+                })
             })
             .collect();
 
-        let _ = self.snapshot_tx.send(ServerMsg::MatchJoined {
-            match_id: self.state.id,
-            seed: self.state.seed,
-            players,
-        });
+        if !players.is_empty() {
+            let _ = self.snapshot_tx.send(ServerMsg::LatencyReport { players });
+        }
+    }
 
-        info!(
-            match_id = %self.state.id,
-            user_id = %user_id,
-            player_count = self.state.players.len(),
-            "Player joined match"
-        );
+    /// Transition the match phase, publishing it to the registry handle
+    fn set_phase(&mut self, phase: MatchPhase) {
+        self.state.phase = phase;
+        self.phase.store(phase as u8, Ordering::Relaxed);
+    }
 
-        // Check if we should start countdown
-        if self.state.phase == MatchPhase::Waiting
-            && self.state.players.len() >= self.state.min_players
-        {
-            self.state.phase = MatchPhase::Countdown;
-            self.state.countdown_remaining = 5.0;
-            let _ = self.snapshot_tx.send(ServerMsg::MatchCountdown {
-                seconds_remaining: 5,
-            });
+    /// Re-run a recorded match offline, returning the stats it ends with
+    pub fn replay(recording: &Replay) -> MatchStats {
+        let header = &recording.header;
+        let (mut game_match, _handle) =
+            Self::new(header.match_id, header.seed, header.settings.to_settings());
+
+        let mut inputs = recording.inputs.iter().peekable();
+        while game_match.state.tick < recording.end_tick {
+            while let Some(input) = inputs.next_if(|i| i.tick <= game_match.state.tick) {
+                game_match.apply_input(input.to_player_input());
+            }
+            game_match.run_tick();
         }
+
+        game_match.build_match_stats()
     }
 
-    /// Handle player input
-    fn handle_input(
-        &mut self,
-        user_id: Uuid,
-        seq: u32,
-        throttle: f32,
-        steer: f32,
-        shoot: bool,
-        aim_yaw: f32,
-    ) {
-        if let Some(player) = self.state.players.get_mut(&user_id) {
-            if player.alive && seq > player.last_input_seq {
-                player.last_input_seq = seq;
-                player.current_input = TickInput {
-                    seq,
-                    throttle: throttle.clamp(-1.0, 1.0),
-                    steer: steer.clamp(-1.0, 1.0),
-                    shoot,
-                    aim_yaw,
-                };
+    /// Process all pending inputs from players
+    fn process_inputs(&mut self) {
+        while let Ok(input) = self.input_rx.try_recv() {
+            // Record before applying, in the order the match sees inputs
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record(self.state.tick, &input);
             }
+            self.apply_input(input);
         }
     }
 
-    /// Handle player leave
+    /// Apply a single player input
+    fn apply_input(&mut self, input: PlayerInput) {
+        match input.msg {
+            ClientMsg::JoinMatch {
+                ship_type,
+                weapon,
+                aim_assist,
+                ..
+            } => {
+                self.handle_join(
+                    input.user_id,
+                    input.display_name,
+                    input.latency,
+                    input.guest,
+                    input.cosmetics,
+                    ship_type,
+                    weapon,
+                    aim_assist,
+                );
+            }
+            ClientMsg::InputTick {
+                seq,
+                throttle,
+                steer,
+                strafe,
+                shoot,
+                aim_yaw,
+                boost,
+                deploy_mine,
+            } => {
+                self.handle_input(
+                    input.user_id,
+                    TickInput {
+                        seq,
+                        throttle,
+                        steer,
+                        strafe,
+                        shoot,
+                        aim_yaw,
+                        boost,
+                        deploy_mine,
+                    },
+                );
+            }
+            ClientMsg::Ping { t } => {
+                let pong = ServerMsg::Pong {
+                    t,
+                    server_time: unix_millis(),
+                };
+                self.send_to(input.user_id, pong);
+            }
+            ClientMsg::LeaveMatch => {
+                self.handle_leave(input.user_id);
+            }
+            ClientMsg::SetPause { paused } => {
+                self.handle_set_pause(input.user_id, paused);
+            }
+            // Handled by the connection
+            ClientMsg::Refresh { .. } => {}
+        }
+    }
+
+    /// Handle player join request. Joins only come from the matchmaking
+    /// service, which has already checked the loadout against inventory
+    #[allow(clippy::too_many_arguments)]
+    fn handle_join(
+        &mut self,
+        user_id: Uuid,
+        display_name: Option<String>,
+        latency: Option<Arc<LatencyTracker>>,
+        guest: bool,
+        cosmetics: Cosmetics,
+        ship_type: ShipType,
+        weapon: Option<WeaponLoadout>,
+        aim_assist: bool,
+    ) {
+        if self.state.players.contains_key(&user_id) {
+            warn!(user_id = %user_id, "Player already in match");
+            return;
+        }
+
+        // Bots give up their slot to real players
+        if self.state.players.len() >= self.state.settings.max_players {
+            if let Some(bot_id) = self.state.any_bot() {
+                self.remove_player(bot_id, "replaced");
+            }
+        }
+
+        if self.state.players.len() >= self.state.settings.max_players {
+            self.send_error(user_id, ErrorCode::MatchFull, "Match is full");
+            return;
+        }
+
+        let late_join = self.state.phase == MatchPhase::InProgress;
+        if late_join && !self.state.settings.late_join {
+            self.send_error(user_id, ErrorCode::MatchInProgress, "Match already in progress");
+            return;
+        }
+
+        let (spawn_x, spawn_y, spawn_rotation) = self.state.generate_spawn_position();
+        let mut player = PlayerState::new(
+            user_id,
+            display_name.unwrap_or_else(|| format!("Player_{}", &user_id.to_string()[..8])),
+            ship_type,
+            cosmetics,
+            spawn_x,
+            spawn_y,
+            spawn_rotation,
+            &self.state.settings.balance,
+        );
+        if let Some(weapon) = weapon {
+            player.equip_weapon(weapon, &self.state.settings.balance);
+        }
+        player.latency = latency;
+        player.is_guest = guest;
+        player.aim_assist = aim_assist;
+        if late_join {
+            player.spawn_protected_until = Some(self.state.spawn_protected_until());
+        }
+
+        self.add_player(player);
+        if self.state.settings.private && self.state.host_id.is_none() {
+            self.state.host_id = Some(user_id);
+        }
+
+        // Send match joined to the new player
+        let players: Vec<PlayerInfo> = self.state.players.values().map(|p| p.info()).collect();
+
+        let _ = self.snapshot_tx.send(ServerMsg::MatchJoined {
+            match_id: self.state.id,
+            seed: self.state.seed,
+            players,
+            bounds: self.state.bounds,
+            arena_id: self.state.settings.arena.id.clone(),
+            host_id: self.state.host_id,
+            repair_pads: self.state.settings.arena.repair_pads.clone(),
+        });
+        if late_join {
+            self.send_to(user_id, ServerMsg::MatchStarted { tick: self.state.tick });
+        }
+
+        info!(
+            match_id = %self.state.id,
+            user_id = %user_id,
+            player_count = self.state.players.len(),
+            late_join = late_join,
+            "Player joined match"
+        );
+
+        // Drop bots that are no longer needed to reach min_players
+        if self.state.phase != MatchPhase::InProgress {
+            while self.state.players.len() > self.state.settings.min_players {
+                match self.state.any_bot() {
+                    Some(bot_id) => self.remove_player(bot_id, "replaced"),
+                    None => break,
+                }
+            }
+        }
+
+        // A practice match's bots show up with its player
+        if self.state.settings.practice {
+            while self.state.players.len() < self.state.settings.max_players {
+                self.add_bot();
+            }
+        }
+
+        self.check_countdown();
+    }
+
+    /// Spawn a bot-controlled ship
+    fn add_bot(&mut self) {
+        // Drawn from the match RNG so replays give bots the same IDs
+        let user_id = uuid::Builder::from_random_bytes(self.state.rng.gen()).into_uuid();
+        let ship_type = BOT_SHIP_TYPES[self.state.rng.gen_range(0..BOT_SHIP_TYPES.len())];
+        let (spawn_x, spawn_y, spawn_rotation) = self.state.generate_spawn_position();
+        let mut player = PlayerState::new(
+            user_id,
+            format!("Bot_{}", &user_id.to_string()[..4]),
+            ship_type,
+            Cosmetics::default(),
+            spawn_x,
+            spawn_y,
+            spawn_rotation,
+            &self.state.settings.balance,
+        );
+        player.is_bot = true;
+
+        self.add_player(player);
+
+        info!(match_id = %self.state.id, bot_id = %user_id, "Bot joined match");
+    }
+
+    /// Insert a player and notify everyone in the match
+    fn add_player(&mut self, mut player: PlayerState) {
+        player.spawn_time = self.state.clock_ms();
+        let player_info = player.info();
+
+        self.state.players.insert(player.user_id, player);
+        self.player_count
+            .store(self.state.players.len(), Ordering::Relaxed);
+
+        // Notify all players of the new player
+        let _ = self.snapshot_tx.send(ServerMsg::PlayerJoined {
+            player: player_info,
+        });
+    }
+
+    /// Start the warmup, or the countdown if there is none, once enough
+    /// players are present
+    fn check_countdown(&mut self) {
+        if self.state.phase != MatchPhase::Waiting
+            || self.state.players.len() < self.state.settings.min_players
+        {
+            return;
+        }
+
+        let warmup = self.state.settings.warmup;
+        if warmup > 0.0 {
+            self.set_phase(MatchPhase::Warmup);
+            self.state.warmup_remaining = warmup;
+            let _ = self.snapshot_tx.send(ServerMsg::MatchWarmup {
+                seconds_remaining: warmup.ceil() as u32,
+            });
+        } else {
+            self.start_countdown();
+        }
+    }
+
+    fn start_countdown(&mut self) {
+        self.set_phase(MatchPhase::Countdown);
+        self.state.countdown_remaining = self.state.settings.countdown;
+        let _ = self.snapshot_tx.send(ServerMsg::MatchCountdown {
+            seconds_remaining: self.state.settings.countdown.ceil() as u32,
+        });
+    }
+
+    /// Players left during the run-up to the match and too few remain. A
+    /// match nobody is left in is ended by the loop instead
+    fn too_few_to_start(&self) -> bool {
+        self.state.players.len() < self.state.settings.min_players && self.state.human_count() > 0
+    }
+
+    /// Go back to waiting for players; the run-up starts over from the
+    /// warmup once enough are in again
+    fn cancel_countdown(&mut self) {
+        self.set_phase(MatchPhase::Waiting);
+        self.state.countdown_remaining = self.state.settings.countdown;
+        self.state.warmup_remaining = 0.0;
+        let _ = self.snapshot_tx.send(ServerMsg::CountdownCancelled {
+            players: self.state.players.len(),
+            min_players: self.state.settings.min_players,
+        });
+        info!(
+            match_id = %self.state.id,
+            players = self.state.players.len(),
+            "Too few players left, countdown cancelled"
+        );
+    }
+
+    /// Handle player input
+    fn handle_input(&mut self, user_id: Uuid, input: TickInput) {
+        let now = self.state.clock_ms();
+        let Some(player) = self.state.players.get_mut(&user_id) else {
+            return;
+        };
+        if !player.alive || input.seq <= player.last_input_seq {
+            return;
+        }
+
+        if let Some(violation) = player.input_guard.inspect(&input, player.last_input_seq, now) {
+            let suspicion = player.input_guard.suspicion();
+            let kick = player.input_guard.should_kick();
+            warn!(
+                match_id = %self.state.id,
+                user_id = %user_id,
+                violation = ?violation,
+                suspicion = suspicion,
+                "Rejected implausible input"
+            );
+            if kick {
+                warn!(match_id = %self.state.id, user_id = %user_id, "Kicking player for suspicious input");
+                if self.state.phase == MatchPhase::InProgress {
+                    player.die(now);
+                }
+                self.send_error(user_id, ErrorCode::Kicked, "Removed from the match for invalid input");
+                self.remove_player(user_id, "kicked");
+            }
+            return;
+        }
+
+        player.last_input_seq = input.seq;
+        player.last_input_at = Some(now);
+        player.input_buffer.push(TickInput {
+            throttle: input.throttle.clamp(-1.0, 1.0),
+            steer: input.steer.clamp(-1.0, 1.0),
+            strafe: input.strafe.clamp(-1.0, 1.0),
+            aim_yaw: normalize_yaw(input.aim_yaw),
+            ..input
+        });
+    }
+
+    /// Handle player leave
     fn handle_leave(&mut self, user_id: Uuid) {
+        // Leaving while still alive in a running match counts as an abandon
+        let abandoning = self.state.phase == MatchPhase::InProgress
+            && self
+                .state
+                .players
+                .get(&user_id)
+                .is_some_and(|p| p.alive && !p.is_bot);
+
+        if !abandoning {
+            self.remove_player(user_id, "disconnected");
+            return;
+        }
+
+        let now = self.state.clock_ms();
+        if let Some(player) = self.state.players.get_mut(&user_id) {
+            player.abandoned = true;
+            player.die(now);
+        }
+        // Guests can come back with a fresh identity, so only members are tracked
+        let guest = self.state.players.get(&user_id).is_some_and(|p| p.is_guest);
+        if let (Some(abandon_tx), false) = (&self.abandon_tx, guest) {
+            let _ = abandon_tx.send(user_id);
+        }
+
+        self.remove_player(user_id, "abandoned");
+    }
+
+    /// Remove a player from the match
+    fn remove_player(&mut self, user_id: Uuid, reason: &str) {
         if let Some(player) = self.state.players.remove(&user_id) {
+            if matches!(self.state.phase, MatchPhase::InProgress | MatchPhase::Ended) {
+                self.state.departed.push(player);
+            }
+
             self.player_count
-                .store(self.state.players.len(), std::sync::atomic::Ordering::Relaxed);
+                .store(self.state.players.len(), Ordering::Relaxed);
 
             let _ = self.snapshot_tx.send(ServerMsg::PlayerLeft {
                 user_id,
-                reason: "disconnected".to_string(),
+                reason: reason.to_string(),
             });
 
+            // Hand hosting to another human; a pause doesn't outlive its host
+            if self.state.host_id == Some(user_id) {
+                self.state.host_id = self
+                    .state
+                    .players
+                    .values()
+                    .find(|p| !p.is_bot)
+                    .map(|p| p.user_id);
+                if self.state.paused {
+                    self.resume();
+                }
+            }
+
             info!(
                 match_id = %self.state.id,
                 user_id = %user_id,
+                reason = reason,
                 "Player left match"
             );
 
             // Check win condition
             self.check_win_condition();
+        }
+    }
+
+    /// Handle a host's pause or resume request
+    fn handle_set_pause(&mut self, user_id: Uuid, paused: bool) {
+        if !self.state.settings.private {
+            self.send_error(user_id, ErrorCode::CannotPause, "Only private matches can be paused");
+            return;
+        }
+        if self.state.host_id != Some(user_id) {
+            self.send_error(user_id, ErrorCode::NotHost, "Only the match host can pause");
+            return;
+        }
+        if paused == self.state.paused {
+            return;
+        }
+        if !paused {
+            self.resume();
+            return;
+        }
+
+        if !matches!(self.state.phase, MatchPhase::Countdown | MatchPhase::InProgress) {
+            self.send_error(user_id, ErrorCode::CannotPause, "The match can't be paused now");
+            return;
+        }
+        let remaining_secs = self.state.settings.max_pause - self.state.pause_elapsed;
+        if remaining_secs <= 0.0 {
+            self.send_error(user_id, ErrorCode::PauseLimit, "No pause time left this match");
+            return;
+        }
+
+        self.state.paused = true;
+        let _ = self.snapshot_tx.send(ServerMsg::MatchPaused {
+            by: user_id,
+            remaining_secs,
+        });
+        info!(match_id = %self.state.id, user_id = %user_id, "Match paused");
+    }
+
+    /// Unfreeze the simulation
+    fn resume(&mut self) {
+        self.state.paused = false;
+        let _ = self.snapshot_tx.send(ServerMsg::MatchResumed {
+            tick: self.state.tick,
+        });
+        info!(match_id = %self.state.id, "Match resumed");
+    }
 
-            drop(player); // Silence unused warning
+    /// Send an error to one player
+    fn send_error(&self, user_id: Uuid, code: ErrorCode, message: &str) {
+        self.send_to(
+            user_id,
+            ServerMsg::Error {
+                code,
+                message: message.to_string(),
+            },
+        );
+    }
+
+    /// Send a message to one player, falling back to the match broadcast
+    fn send_to(&self, user_id: Uuid, msg: ServerMsg) {
+        match &self.direct_tx {
+            Some(direct_tx) => {
+                let _ = direct_tx.send((user_id, msg));
+            }
+            None => {
+                let _ = self.snapshot_tx.send(msg);
+            }
         }
     }
 
@@ -558,34 +1872,110 @@ impl GameMatch {
         let mut events = Vec::new();
         self.state.tick += 1;
 
+        // While paused only inputs are taken; timers, physics and the zone are frozen
+        if self.state.paused {
+            self.state.paused_ticks += 1;
+            self.state.pause_elapsed += self.state.tick_delta();
+            if self.state.pause_elapsed >= self.state.settings.max_pause {
+                self.resume();
+            }
+            return events;
+        }
+
         match self.state.phase {
             MatchPhase::Waiting => {
-                // Do nothing, wait for players
+                // Wait for players, backfilling with bots after the configured delay
+                self.state.waiting_elapsed += self.state.tick_delta();
+
+                // Nobody came (or everyone left): close the match instead
+                if self.state.human_count() == 0 {
+                    self.state.empty_elapsed += self.state.tick_delta();
+                    let timeout = self.state.settings.empty_timeout;
+                    if timeout.is_some_and(|secs| self.state.empty_elapsed >= secs) {
+                        info!(match_id = %self.state.id, "No players joined, closing match");
+                        self.set_phase(MatchPhase::Ended);
+                        return events;
+                    }
+                } else {
+                    self.state.empty_elapsed = 0.0;
+                }
+                if let Some(delay) = self.state.settings.bot_fill_delay {
+                    if self.state.waiting_elapsed >= delay && self.state.human_count() > 0 {
+                        while self.state.players.len() < self.state.settings.min_players {
+                            self.add_bot();
+                        }
+                        self.check_countdown();
+                    }
+                }
+            }
+            MatchPhase::Warmup => {
+                // Ships fly, but nothing fires and the zone waits
+                self.apply_buffered_inputs();
+                self.update_bots();
+                self.update_physics();
+
+                // Over once time runs out or every human is sending inputs
+                self.state.warmup_remaining -= self.state.tick_delta();
+                let everyone_in = self
+                    .state
+                    .players
+                    .values()
+                    .filter(|p| !p.is_bot)
+                    .all(|p| p.last_input_seq > 0);
+                if self.too_few_to_start() {
+                    self.cancel_countdown();
+                } else if self.state.warmup_remaining <= 0.0 || everyone_in {
+                    self.start_countdown();
+                }
             }
             MatchPhase::Countdown => {
-                self.state.countdown_remaining -= tick_delta();
-                if self.state.countdown_remaining <= 0.0 {
-                    self.state.phase = MatchPhase::InProgress;
-                    self.state.start_time = Some(unix_millis());
-                    self.state.zone_timer = self.state.zone_config.initial_delay;
-                    let _ = self.snapshot_tx.send(ServerMsg::MatchStarted {
-                        tick: self.state.tick,
-                    });
-                    info!(match_id = %self.state.id, "Match started!");
+                if self.too_few_to_start() {
+                    self.cancel_countdown();
+                } else {
+                    self.state.countdown_remaining -= self.state.tick_delta();
+                    if self.state.countdown_remaining <= 0.0 {
+                        self.set_phase(MatchPhase::InProgress);
+                        self.state.start_time = Some(unix_millis());
+                        self.state.start_clock_ms = Some(self.state.clock_ms());
+                        self.protect_spawns();
+                        self.state.zone_timer = self.state.zone_config.initial_delay;
+                        self.state.plan_next_zone();
+                        let _ = self.snapshot_tx.send(ServerMsg::MatchStarted {
+                            tick: self.state.tick,
+                        });
+                        info!(match_id = %self.state.id, "Match started!");
+                    }
                 }
             }
             MatchPhase::InProgress => {
+                self.state.match_elapsed += self.state.tick_delta();
+
+                // Take this tick's input from each player's buffer
+                self.apply_buffered_inputs();
+
+                // Drive bot inputs
+                self.update_bots();
+
                 // Update physics
                 self.update_physics();
 
+                // Recharge shields
+                self.update_shields();
+
+                // Repair ships parked on pads
+                events.extend(self.update_repair_pads());
+
                 // Process shooting and update projectiles
                 events.extend(self.update_combat());
 
-                // Update zone
-                events.extend(self.update_zone());
+                // Burn ships under status effects
+                events.extend(self.update_status_effects());
 
-                // Apply zone damage
-                events.extend(self.apply_zone_damage());
+                // Update zone and apply its damage (practice has no zone)
+                if !self.state.settings.practice {
+                    events.extend(self.update_zone());
+                    events.extend(self.apply_zone_damage());
+                }
 
                 // Check win condition
                 self.check_win_condition();
@@ -595,20 +1985,72 @@ impl GameMatch {
             }
         }
 
+        self.cap_events(&mut events);
         events
     }
 
+    /// Keep a tick's events within `max_events_per_tick` so a flood can't
+    /// build a huge snapshot. Shots go first: they are the bulk of any flood
+    /// and only drive client-side effects
+    fn cap_events(&self, events: &mut Vec<GameEvent>) {
+        let cap = self.state.settings.max_events_per_tick;
+        if events.len() <= cap {
+            return;
+        }
+
+        let total = events.len();
+        let mut excess = total - cap;
+        events.retain(|event| {
+            if excess > 0 && matches!(event, GameEvent::Shot { .. }) {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+        events.truncate(cap);
+        warn!(match_id = %self.state.id, tick = self.state.tick, total, cap, "Event cap reached, dropping events");
+    }
+
+    /// Compute this tick's input for every bot
+    fn update_bots(&mut self) {
+        let bot_inputs: Vec<(Uuid, TickInput)> = self
+            .state
+            .players
+            .values()
+            .filter(|p| p.is_bot && p.alive)
+            .map(|p| (p.user_id, BotController::think(p, &self.state.players, &self.state.zone)))
+            .collect();
+
+        for (bot_id, input) in bot_inputs {
+            if let Some(bot) = self.state.players.get_mut(&bot_id) {
+                bot.last_input_seq = input.seq;
+                bot.current_input = input;
+            }
+        }
+    }
+
+    /// Move each human player on to their input for this tick
+    fn apply_buffered_inputs(&mut self) {
+        for player in self.state.players.values_mut() {
+            if player.is_bot || !player.alive {
+                continue;
+            }
+            player.current_input = player.input_buffer.next(&player.current_input);
+        }
+    }
+
     /// Update ship physics
     fn update_physics(&mut self) {
+        let dt = self.state.tick_delta();
+        let now = self.state.clock_ms();
+        let input_timeout_ms = (self.state.settings.input_timeout * 1000.0) as u64;
         let player_positions: Vec<(Uuid, f32, f32, f32)> = self
             .state
             .players
             .values()
             .filter(|p| p.alive)
-            .map(|p| {
-                let stats = ShipStats::for_type(p.ship_type);
-                (p.user_id, p.x, p.y, stats.hitbox_radius)
-            })
+            .map(|p| (p.user_id, p.x, p.y, p.ship.hitbox_radius))
             .collect();
 
         for player in self.state.players.values_mut() {
@@ -616,30 +2058,67 @@ impl GameMatch {
                 continue;
             }
 
-            let stats = ShipStats::for_type(player.ship_type);
-            let input = &player.current_input;
+            // A client that went quiet (a backgrounded tab) lets go of the
+            // controls until its next input, rather than flying on forever
+            let stale = input_timeout_ms > 0
+                && player
+                    .last_input_at
+                    .is_some_and(|at| now.saturating_sub(at) >= input_timeout_ms);
+            let released;
+            let input = if stale {
+                released = TickInput {
+                    throttle: 0.0,
+                    steer: 0.0,
+                    strafe: 0.0,
+                    boost: false,
+                    ..player.current_input.clone()
+                };
+                &released
+            } else {
+                &player.current_input
+            };
 
-            let (new_x, new_y, new_rot, new_vel_x, new_vel_y) = PhysicsSystem::update_ship(
-                player.x,
-                player.y,
-                player.rotation,
-                player.vel_x,
-                player.vel_y,
-                input.throttle,
-                input.steer,
-                &stats,
+            let (boosting, boost_energy, boost_cooldown) = PhysicsSystem::update_boost(
+                input.boost,
+                player.boosting,
+                player.boost_energy,
+                player.boost_cooldown,
+                dt,
             );
+            player.boosting = boosting;
+            player.boost_energy = boost_energy;
+            player.boost_cooldown = boost_cooldown;
+
+            let stats = if boosting { player.ship.boosted() } else { player.ship };
+
+            // Clients predict one tick per input they send, so the
+            // prediction only moves when a new input is simulated
+            if !player.is_bot && input.seq != player.last_acked_seq {
+                player.predicted = player
+                    .predicted
+                    .map(|predicted| PhysicsSystem::predict(&predicted, input, &stats, dt));
+            }
 
-            player.x = new_x;
-            player.y = new_y;
-            player.rotation = new_rot;
-            player.vel_x = new_vel_x;
-            player.vel_y = new_vel_y;
+            let moved = PhysicsSystem::update_ship(&player.motion(), input, &stats, dt);
+            player.x = moved.x;
+            player.y = moved.y;
+            player.rotation = moved.rotation;
+            player.vel_x = moved.vel_x;
+            player.vel_y = moved.vel_y;
         }
 
-        // Resolve ship-to-ship collisions
+        // Resolve ship-to-ship collisions, in the same pair order as checking
+        // every pair, against nearby ships only
+        let largest_hitbox = player_positions.iter().map(|p| p.3).fold(0.0, f32::max);
+        self.state.ship_grid.rebuild(
+            largest_hitbox * 2.0,
+            player_positions.iter().map(|&(_, x, y, _)| (x, y)),
+        );
+        let mut nearby = Vec::new();
         for i in 0..player_positions.len() {
-            for j in (i + 1)..player_positions.len() {
+            let (_, x, y, radius) = player_positions[i];
+            self.state.ship_grid.query_into(x, y, radius + largest_hitbox, &mut nearby);
+            for &j in nearby.iter().filter(|&&j| j > i) {
                 let (id1, x1, y1, r1) = player_positions[i];
                 let (id2, x2, y2, r2) = player_positions[j];
 
@@ -658,119 +2137,496 @@ impl GameMatch {
                 }
             }
         }
+
+        // Push ships out of obstacles, then enforce the world boundary last so
+        // collision pushes can't escape it
+        let obstacles = &self.state.settings.arena.obstacles;
+        for player in self.state.players.values_mut().filter(|p| p.alive) {
+            for obstacle in obstacles {
+                (player.x, player.y, player.vel_x, player.vel_y) =
+                    PhysicsSystem::push_out_of_obstacle(
+                        player.x,
+                        player.y,
+                        player.vel_x,
+                        player.vel_y,
+                        player.ship.hitbox_radius,
+                        obstacle,
+                    );
+            }
+        }
+        let bounds = self.state.bounds;
+        for player in self.state.players.values_mut().filter(|p| p.alive) {
+            (player.x, player.y, player.vel_x, player.vel_y) = PhysicsSystem::clamp_to_bounds(
+                player.x,
+                player.y,
+                player.vel_x,
+                player.vel_y,
+                &bounds,
+            );
+        }
     }
 
-    /// Update combat (shooting, projectiles, hits)
-    fn update_combat(&mut self) -> Vec<GameEvent> {
-        let mut events = Vec::new();
-        let mut new_projectiles = Vec::new();
+    /// Recharge the shields of ships that have been out of combat long enough
+    fn update_shields(&mut self) {
+        let dt = self.state.tick_delta();
 
-        // Process shooting
         for player in self.state.players.values_mut() {
             if !player.alive {
                 continue;
             }
 
-            // Update weapon cooldown
-            player.weapon_cooldown = CombatSystem::update_cooldown(player.weapon_cooldown);
-
-            // Check for shooting
-            if player.current_input.shoot && CombatSystem::can_fire(player.weapon_cooldown) {
-                let weapon_stats = WeaponStats::for_type(player.ship_type);
-                let ship_stats = ShipStats::for_type(player.ship_type);
-
-                // Spawn projectile at ship front
-                let spawn_offset = ship_stats.hitbox_radius + 5.0;
-                let spawn_x = player.x + player.current_input.aim_yaw.cos() * spawn_offset;
-                let spawn_y = player.y + player.current_input.aim_yaw.sin() * spawn_offset;
-
-                let projectile = Projectile::new(
-                    player.user_id,
-                    spawn_x,
-                    spawn_y,
-                    player.current_input.aim_yaw,
-                    &weapon_stats,
-                );
-
-                events.push(GameEvent::Shot {
-                    shooter_id: player.user_id,
-                    projectile_id: projectile.id,
-                    x: spawn_x,
-                    y: spawn_y,
-                    direction: player.current_input.aim_yaw,
-                    speed: weapon_stats.projectile_speed,
-                });
-
-                new_projectiles.push(projectile);
-                player.weapon_cooldown = CombatSystem::fire_cooldown(&weapon_stats);
-                player.shots_fired += 1;
-            }
+            let (shield, regen_delay) = CombatSystem::update_shield(
+                player.shield,
+                player.max_shield,
+                player.shield_regen,
+                player.shield_regen_delay,
+                dt,
+            );
+            player.shield = shield;
+            player.shield_regen_delay = regen_delay;
         }
+    }
 
-        self.state.projectiles.extend(new_projectiles);
-
-        // Update projectiles and check hits
-        let mut hits: Vec<HitResult> = Vec::new();
-        let mut expired_projectiles: Vec<usize> = Vec::new();
+    /// Repair damaged ships parked on active pads. A pad that was repairing
+    /// and finds nobody left to repair goes dark for its cooldown
+    fn update_repair_pads(&mut self) -> Vec<GameEvent> {
+        let dt = self.state.tick_delta();
+        let now = self.state.clock_ms();
+        let mut events = Vec::new();
 
-        for (idx, projectile) in self.state.projectiles.iter_mut().enumerate() {
-            if !projectile.update() {
-                expired_projectiles.push(idx);
+        for pad in &mut self.state.repair_pads {
+            if now < pad.ready_at {
                 continue;
             }
 
-            // Check hits against all alive players (except owner)
-            for player in self.state.players.values() {
-                if !player.alive || player.user_id == projectile.owner_id {
+            let mut repaired = false;
+            for player in self.state.players.values_mut() {
+                if !player.alive
+                    || player.health >= player.ship.max_health
+                    || !pad.pad.covers(player.x, player.y)
+                {
                     continue;
                 }
+                let missing = player.ship.max_health - player.health;
+                let amount = (pad.pad.heal_per_sec * dt).min(missing);
+                player.health += amount;
+                repaired = true;
+                events.push(GameEvent::Heal {
+                    user_id: player.user_id,
+                    amount,
+                });
+            }
+
+            if pad.in_use && !repaired {
+                pad.ready_at = now + (pad.pad.cooldown * 1000.0) as u64;
+            }
+            pad.in_use = repaired;
+        }
+
+        events
+    }
+
+    /// Update combat (shooting, projectiles, hits)
+    fn update_combat(&mut self) -> Vec<GameEvent> {
+        let dt = self.state.tick_delta();
+        let mut events = Vec::new();
+        let mut new_projectiles = Vec::new();
+
+        let per_player_cap = self.state.settings.max_projectiles_per_player;
+        let mut in_flight: HashMap<Uuid, usize> = HashMap::new();
+        for projectile in &self.state.projectiles {
+            *in_flight.entry(projectile.owner_id).or_insert(0) += 1;
+        }
+
+        // Where every live ship is, for aim assist to pick targets from
+        let assist_cone = self.state.settings.aim_assist_cone;
+        let assist_strength = self.state.settings.aim_assist_strength;
+        let ships: Vec<(Uuid, f32, f32)> = self
+            .state
+            .players
+            .values()
+            .filter(|p| p.alive)
+            .map(|p| (p.user_id, p.x, p.y))
+            .collect();
+
+        // Process shooting
+        for player in self.state.players.values_mut() {
+            if !player.alive {
+                continue;
+            }
+
+            // Update weapon cooldown
+            player.weapon_cooldown = CombatSystem::update_cooldown(player.weapon_cooldown, dt);
+
+            // Check for shooting, held back while too many of this player's
+            // shots are in flight
+            let wants_to_fire =
+                player.current_input.shoot && CombatSystem::can_fire(player.weapon_cooldown);
+            let in_flight = in_flight.get(&player.user_id).copied().unwrap_or(0);
+            let pellets = player.weapon.pellet_count.max(1) as usize;
+            if wants_to_fire && in_flight + pellets > per_player_cap {
+                debug!(
+                    match_id = %self.state.id,
+                    user_id = %player.user_id,
+                    in_flight,
+                    cap = per_player_cap,
+                    "Per-player projectile cap reached, holding fire"
+                );
+            } else if wants_to_fire {
+                let weapon_stats = player.weapon;
+                let obstacles = &self.state.settings.arena.obstacles;
+
+                // Spawn projectiles at ship front, one per pellet
+                let spawn_offset = player.ship.hitbox_radius + 5.0;
+                let aim_yaw = if player.aim_assist && assist_cone > 0.0 {
+                    CombatSystem::assisted_aim(
+                        (player.x, player.y),
+                        player.current_input.aim_yaw,
+                        ships
+                            .iter()
+                            .filter(|(id, _, _)| *id != player.user_id)
+                            .map(|&(_, x, y)| (x, y)),
+                        assist_cone,
+                        assist_strength,
+                        weapon_stats.projectile_speed * weapon_stats.projectile_lifetime,
+                    )
+                } else {
+                    player.current_input.aim_yaw
+                };
+                let assisted_yaw = (aim_yaw != player.current_input.aim_yaw).then_some(aim_yaw);
+                for direction in CombatSystem::pellet_directions(aim_yaw, &weapon_stats) {
+                    let spawn_x = player.x + direction.cos() * spawn_offset;
+                    let spawn_y = player.y + direction.sin() * spawn_offset;
+
+                    let projectile = Projectile::new(
+                        player.user_id,
+                        spawn_x,
+                        spawn_y,
+                        direction,
+                        (player.vel_x, player.vel_y),
+                        &weapon_stats,
+                    );
+
+                    // A muzzle inside an obstacle would let the shot clip
+                    // through it, so the shot is lost instead
+                    if obstacles
+                        .iter()
+                        .any(|o| o.distance(spawn_x, spawn_y) <= projectile.radius)
+                    {
+                        continue;
+                    }
+
+                    // Velocity inheritance bends the path off the aim, so
+                    // report the projectile's actual heading and speed
+                    events.push(GameEvent::Shot {
+                        shooter_id: player.user_id,
+                        projectile_id: projectile.id,
+                        x: spawn_x,
+                        y: spawn_y,
+                        direction: projectile.direction(),
+                        speed: projectile.speed(),
+                        assisted_yaw,
+                    });
+
+                    new_projectiles.push(projectile);
+                }
+
+                player.weapon_cooldown = CombatSystem::fire_cooldown(&weapon_stats);
+                // Opening fire gives up spawn protection
+                player.spawn_protected_until = None;
+                // Each pellet can hit on its own, so accuracy counts pellets
+                player.shots_fired += weapon_stats.pellet_count.max(1);
+            }
+
+            // Check for mine deployment
+            player.mine_cooldown = CombatSystem::update_cooldown(player.mine_cooldown, dt);
+            if player.current_input.deploy_mine && CombatSystem::can_fire(player.mine_cooldown) {
+                let active = self
+                    .state
+                    .mines
+                    .iter()
+                    .filter(|m| m.owner_id == player.user_id)
+                    .count();
+                if active < MAX_MINES_PER_PLAYER {
+                    let mine = Mine::new(player.user_id, player.x, player.y);
+                    events.push(GameEvent::MineDeployed {
+                        owner_id: player.user_id,
+                        mine_id: mine.id,
+                        x: mine.x,
+                        y: mine.y,
+                        trigger_radius: mine.trigger_radius,
+                        blast_radius: mine.blast_radius,
+                        lifetime: mine.lifetime_remaining,
+                    });
+                    self.state.mines.push(mine);
+                    player.mine_cooldown = MINE_DEPLOY_COOLDOWN;
+                    // Mines are aggression too
+                    player.spawn_protected_until = None;
+                }
+            }
+        }
+
+        self.state.projectiles.extend(new_projectiles);
+
+        // Projectiles are kept in firing order, so the oldest go first
+        let cap = self.state.settings.max_projectiles;
+        if self.state.projectiles.len() > cap {
+            let dropped = self.state.projectiles.len() - cap;
+            self.state.projectiles.drain(..dropped);
+            warn!(match_id = %self.state.id, dropped, cap, "Projectile cap reached, dropping oldest");
+        }
+
+        // Update projectiles and check hits
+        let mut hits: Vec<HitResult> = Vec::new();
+        let mut on_hit_effects: Vec<(Uuid, StatusEffect)> = Vec::new();
+        let mut expired_projectiles: Vec<usize> = Vec::new();
+        let mut landed_shots: Vec<Uuid> = Vec::new();
+        let now = self.state.clock_ms();
+        let obstacles = &self.state.settings.arena.obstacles;
+
+        // Ships have moved since the collision pass, so index them again
+        let ships: Vec<&PlayerState> = self.state.players.values().filter(|p| p.alive).collect();
+        let largest_hitbox = ships.iter().map(|p| p.ship.hitbox_radius).fold(0.0, f32::max);
+        self.state
+            .ship_grid
+            .rebuild(largest_hitbox * 2.0, ships.iter().map(|p| (p.x, p.y)));
+        let mut nearby = Vec::new();
+
+        for (idx, projectile) in self.state.projectiles.iter_mut().enumerate() {
+            // Obstacles stop shots, even ones still arming
+            let armed = projectile.is_armed();
+            if !projectile.update(dt)
+                || obstacles
+                    .iter()
+                    .any(|o| o.distance(projectile.x, projectile.y) <= projectile.radius)
+            {
+                expired_projectiles.push(idx);
+                continue;
+            }
+            if !armed {
+                continue;
+            }
+
+            // A spawn-protected ship deals no damage; firing or deploying a
+            // mine ends protection before its shots could land anyway
+            let harmless = self
+                .state
+                .players
+                .get(&projectile.owner_id)
+                .is_some_and(|owner| owner.is_spawn_protected(now));
+
+            // Check hits against nearby alive players (except owner), in
+            // player order; shots pass through spawn-protected ships, and
+            // piercing shots through ships they already hit
+            let reach = projectile.radius + largest_hitbox;
+            self.state
+                .ship_grid
+                .query_into(projectile.x, projectile.y, reach, &mut nearby);
+            for player in nearby.iter().map(|&i| ships[i]) {
+                if harmless
+                    || player.user_id == projectile.owner_id
+                    || player.is_spawn_protected(now)
+                    || projectile.hit_targets.contains(&player.user_id)
+                {
+                    continue;
+                }
+
+                if projectile.check_hit(player.x, player.y, player.ship.hitbox_radius) {
+                    // Crits are rolled here, in projectile order, so the RNG
+                    // sequence is the same on every run of this seed
+                    let crit = CombatSystem::roll_crit(projectile.crit_chance, &mut self.state.rng);
+                    let modifier = if crit { projectile.crit_multiplier } else { 1.0 };
 
-                let ship_stats = ShipStats::for_type(player.ship_type);
-                if projectile.check_hit(player.x, player.y, ship_stats.hitbox_radius) {
                     hits.push(HitResult {
                         projectile_id: projectile.id,
                         shooter_id: projectile.owner_id,
                         target_id: player.user_id,
-                        damage: projectile.damage,
+                        damage: CombatSystem::calculate_damage(projectile.damage, modifier),
+                        crit,
                         x: projectile.x,
                         y: projectile.y,
                         target_killed: false,
                     });
-                    expired_projectiles.push(idx);
-                    break;
+                    if let Some(effect) = &projectile.on_hit_effect {
+                        on_hit_effects.push((
+                            player.user_id,
+                            StatusEffect::from_hit(effect, projectile.owner_id, dt),
+                        ));
+                    }
+
+                    // Accuracy counts a projectile once, however many ships
+                    // it goes through
+                    if projectile.hit_targets.is_empty() {
+                        landed_shots.push(projectile.owner_id);
+                    }
+                    projectile.hit_targets.push(player.user_id);
+                    projectile.hits_remaining = projectile.hits_remaining.saturating_sub(1);
+                    if projectile.hits_remaining == 0 {
+                        expired_projectiles.push(idx);
+                        break;
+                    }
                 }
             }
         }
 
-        // Remove expired/hit projectiles (in reverse order to maintain indices)
         expired_projectiles.sort_unstable();
         expired_projectiles.dedup();
+
+        // Opposing shots that meet cancel each other out. The projectile cap
+        // bounds the number checked
+        if self.state.settings.projectile_clash {
+            let candidates: Vec<usize> = (0..self.state.projectiles.len())
+                .filter(|idx| {
+                    self.state.projectiles[*idx].is_armed()
+                        && expired_projectiles.binary_search(idx).is_err()
+                })
+                .collect();
+            let clashes = CombatSystem::find_clashes(&self.state.projectiles, &candidates, dt);
+            for (a, b) in clashes {
+                let (first, second) = (&self.state.projectiles[a], &self.state.projectiles[b]);
+                events.push(GameEvent::ProjectileClash {
+                    projectile_ids: [first.id, second.id],
+                    owner_ids: [first.owner_id, second.owner_id],
+                    x: (first.x + second.x) / 2.0,
+                    y: (first.y + second.y) / 2.0,
+                });
+                expired_projectiles.extend([a, b]);
+            }
+            expired_projectiles.sort_unstable();
+        }
+
+        // Remove expired/hit projectiles (in reverse order to maintain indices)
         for idx in expired_projectiles.into_iter().rev() {
             if idx < self.state.projectiles.len() {
                 self.state.projectiles.remove(idx);
             }
         }
 
-        // Apply damage from hits
+        for shooter_id in landed_shots {
+            if let Some(shooter) = self.state.players.get_mut(&shooter_id) {
+                shooter.shots_hit += 1;
+            }
+        }
+        events.extend(self.apply_hits(hits, "shot"));
+
+        // Effects only take hold on ships that survived the hit
+        for (target_id, effect) in on_hit_effects {
+            if let Some(target) = self.state.players.get_mut(&target_id) {
+                if target.alive {
+                    effects::apply(&mut target.status_effects, effect);
+                }
+            }
+        }
+
+        // Trigger and expire mines
+        events.extend(self.update_mines());
+
+        events
+    }
+
+    /// Advance deployed mines, detonating those that were set off or expired
+    fn update_mines(&mut self) -> Vec<GameEvent> {
+        let dt = self.state.tick_delta();
+        let now = self.state.clock_ms();
+        let self_damage = self.state.settings.self_damage;
+        let mut events = Vec::new();
+        let mut hits: Vec<HitResult> = Vec::new();
+        let mut remaining = Vec::with_capacity(self.state.mines.len());
+
+        for mut mine in std::mem::take(&mut self.state.mines) {
+            let expired = !mine.update(dt);
+            let triggered_by = self
+                .state
+                .players
+                .values()
+                .filter(|p| p.alive && p.user_id != mine.owner_id && !p.is_spawn_protected(now))
+                .find(|p| mine.is_triggered_by(p.x, p.y, p.ship.hitbox_radius))
+                .map(|p| p.user_id);
+            if !expired && triggered_by.is_none() {
+                remaining.push(mine);
+                continue;
+            }
+
+            events.push(GameEvent::MineDetonated {
+                owner_id: mine.owner_id,
+                mine_id: mine.id,
+                x: mine.x,
+                y: mine.y,
+                blast_radius: mine.blast_radius,
+                triggered_by,
+            });
+
+            // Splash every enemy ship in reach, and the owner with self damage
+            // on, unless the owner is spawn-protected
+            let harmless = self
+                .state
+                .players
+                .get(&mine.owner_id)
+                .is_some_and(|owner| owner.is_spawn_protected(now));
+            for player in self.state.players.values() {
+                let spared = player.user_id == mine.owner_id && !self_damage;
+                if harmless || !player.alive || spared || player.is_spawn_protected(now) {
+                    continue;
+                }
+
+                if let Some(damage) = mine.blast_damage(player.x, player.y, player.ship.hitbox_radius) {
+                    hits.push(HitResult {
+                        projectile_id: mine.id,
+                        shooter_id: mine.owner_id,
+                        target_id: player.user_id,
+                        damage,
+                        crit: false,
+                        x: mine.x,
+                        y: mine.y,
+                        target_killed: false,
+                    });
+                }
+            }
+        }
+        self.state.mines = remaining;
+
+        events.extend(self.apply_hits(hits, "mine"));
+        events
+    }
+
+    /// Apply damage from hits, crediting the shooter. Hits on ships already
+    /// destroyed earlier in the tick are dropped. A ship caught in its own
+    /// splash earns nothing for it, and dying to it is a kill with cause "self"
+    fn apply_hits(&mut self, hits: Vec<HitResult>, cause: &str) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let now = self.state.clock_ms();
         for mut hit in hits {
+            let mut remaining = (0.0, 0.0);
             if let Some(target) = self.state.players.get_mut(&hit.target_id) {
-                let (new_health, killed) = CombatSystem::apply_damage(target.health, hit.damage);
+                if !target.alive {
+                    continue;
+                }
+                let (new_shield, new_health, killed) =
+                    CombatSystem::apply_damage(target.shield, target.health, hit.damage);
+                target.shield = new_shield;
                 target.health = new_health;
+                remaining = (new_health, new_shield);
+                target.shield_regen_delay = SHIELD_REGEN_DELAY;
                 target.damage_taken += hit.damage;
                 hit.target_killed = killed;
 
                 if killed {
-                    target.alive = false;
-                    target.death_time = Some(unix_millis());
+                    target.die(now);
                 }
             }
 
             // Update shooter stats
-            if let Some(shooter) = self.state.players.get_mut(&hit.shooter_id) {
-                shooter.shots_hit += 1;
+            let self_hit = hit.shooter_id == hit.target_id;
+            let mut multi_kill = None;
+            if let Some(shooter) = self
+                .state
+                .players
+                .get_mut(&hit.shooter_id)
+                .filter(|_| !self_hit)
+            {
                 shooter.damage_dealt += hit.damage;
                 if hit.target_killed {
-                    shooter.kills += 1;
+                    multi_kill = shooter.record_kill(now);
                 }
             }
 
@@ -778,15 +2634,94 @@ impl GameMatch {
                 shooter_id: hit.shooter_id,
                 target_id: hit.target_id,
                 damage: hit.damage,
+                crit: hit.crit,
                 x: hit.x,
                 y: hit.y,
+                target_remaining_health: remaining.0,
+                target_remaining_shield: remaining.1,
             });
 
             if hit.target_killed {
                 events.push(GameEvent::Kill {
                     killer_id: Some(hit.shooter_id),
                     victim_id: hit.target_id,
-                    cause: "shot".to_string(),
+                    cause: if self_hit { "self" } else { cause }.to_string(),
+                });
+            }
+            if let Some(count) = multi_kill {
+                events.push(GameEvent::MultiKill {
+                    killer_id: hit.shooter_id,
+                    count,
+                });
+            }
+        }
+
+        events
+    }
+
+    /// Deal this tick's damage from status effects, crediting each effect's
+    /// source like a hit. Runs before zone damage, and both skip ships
+    /// destroyed earlier in the tick, so a burning ship outside the zone is
+    /// killed once: by the burn if it finishes them, otherwise by the zone
+    fn update_status_effects(&mut self) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let now = self.state.clock_ms();
+
+        let mut ticks: Vec<(Uuid, StatusEffect)> = Vec::new();
+        for player in self.state.players.values_mut() {
+            if player.alive {
+                ticks.extend(
+                    effects::tick(&mut player.status_effects)
+                        .into_iter()
+                        .map(|effect| (player.user_id, effect)),
+                );
+            }
+        }
+
+        for (target_id, effect) in ticks {
+            let Some(target) = self.state.players.get_mut(&target_id) else {
+                continue;
+            };
+            if !target.alive {
+                continue;
+            }
+            let damage = effect.damage_per_tick;
+            let (new_shield, new_health, killed) =
+                CombatSystem::apply_damage(target.shield, target.health, damage);
+            target.shield = new_shield;
+            target.health = new_health;
+            target.shield_regen_delay = SHIELD_REGEN_DELAY;
+            target.damage_taken += damage;
+            if killed {
+                target.die(now);
+            }
+
+            // The source may have died or left since; the kill is still theirs
+            let mut multi_kill = None;
+            if let Some(source) = self.state.players.get_mut(&effect.source_id) {
+                source.damage_dealt += damage;
+                if killed {
+                    multi_kill = source.record_kill(now);
+                }
+            }
+
+            events.push(GameEvent::EffectDamage {
+                user_id: target_id,
+                source_id: effect.source_id,
+                effect: effect.kind,
+                damage,
+            });
+            if killed {
+                events.push(GameEvent::Kill {
+                    killer_id: Some(effect.source_id),
+                    victim_id: target_id,
+                    cause: "burn".to_string(),
+                });
+            }
+            if let Some(count) = multi_kill {
+                events.push(GameEvent::MultiKill {
+                    killer_id: effect.source_id,
+                    count,
                 });
             }
         }
@@ -797,9 +2732,19 @@ impl GameMatch {
     /// Update zone shrinking
     fn update_zone(&mut self) -> Vec<GameEvent> {
         let mut events = Vec::new();
-        let dt = tick_delta();
+        let dt = self.state.tick_delta();
+
+        // Past the time limit the phases stop and the zone closes instead
+        if let Some(limit) = self.state.settings.max_duration {
+            let overtime = self.state.match_elapsed - limit;
+            if overtime >= 0.0 {
+                self.update_sudden_death(overtime);
+                return events;
+            }
+        }
 
         self.state.zone_timer -= dt;
+        self.send_zone_warning();
 
         if self.state.zone_timer <= 0.0 {
             if self.state.is_shrinking {
@@ -813,18 +2758,19 @@ impl GameMatch {
                 if phase_idx < self.state.zone_config.phases.len() {
                     self.state.zone_timer = self.state.zone_config.phases[phase_idx].delay_after;
                     self.state.current_zone_phase += 1;
+                    self.state.plan_next_zone();
                 }
             } else if self.state.current_zone_phase < self.state.zone_config.phases.len() {
                 // Start new shrink phase
+                let (center_x, center_y) = self
+                    .state
+                    .next_zone_center
+                    .take()
+                    .unwrap_or((self.state.zone.center_x, self.state.zone.center_y));
                 let phase = &self.state.zone_config.phases[self.state.current_zone_phase];
 
-                // Randomize new zone center (within current zone)
-                let angle = self.state.rng.gen_range(0.0..std::f32::consts::TAU);
-                let max_offset = (self.state.zone.radius - phase.target_radius).max(0.0) * 0.5;
-                let offset = self.state.rng.gen_range(0.0..max_offset);
-
-                self.state.zone.target_center_x = self.state.zone.center_x + angle.cos() * offset;
-                self.state.zone.target_center_y = self.state.zone.center_y + angle.sin() * offset;
+                self.state.zone.target_center_x = center_x;
+                self.state.zone.target_center_y = center_y;
                 self.state.zone.target_radius = phase.target_radius;
                 self.state.zone.damage_per_second = phase.damage_per_second;
                 self.state.zone.phase = self.state.current_zone_phase as u32;
@@ -864,11 +2810,94 @@ impl GameMatch {
         events
     }
 
+    /// Close the zone on the spot it holds when time runs out, reaching
+    /// radius 0 once `overtime` covers the whole sudden death
+    fn update_sudden_death(&mut self, overtime: f32) {
+        let duration = self.state.settings.sudden_death;
+        let start_radius = match self.state.sudden_death_from {
+            Some(radius) => radius,
+            None => self.begin_sudden_death(),
+        };
+        let progress = if duration > 0.0 {
+            (overtime / duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        self.state.zone.radius = start_radius * (1.0 - progress);
+        self.state.zone.shrink_delay = (duration - overtime).max(0.0);
+    }
+
+    /// Protect every ship on the field as the match starts, while they're
+    /// still bunched up at their spawns
+    fn protect_spawns(&mut self) {
+        let until = self.state.spawn_protected_until();
+        for player in self.state.players.values_mut() {
+            player.spawn_protected_until = Some(until);
+        }
+    }
+
+    /// Stop the zone phases and aim the zone at radius 0 where it stands,
+    /// at no less than the final phase's damage. Returns the starting radius
+    fn begin_sudden_death(&mut self) -> f32 {
+        let final_damage = self
+            .state
+            .zone_config
+            .phases
+            .last()
+            .map_or(0.0, |phase| phase.damage_per_second);
+        let zone = &mut self.state.zone;
+        zone.target_center_x = zone.center_x;
+        zone.target_center_y = zone.center_y;
+        zone.target_radius = 0.0;
+        zone.damage_per_second = zone.damage_per_second.max(final_damage);
+        let radius = zone.radius;
+
+        self.state.is_shrinking = true;
+        self.state.next_zone_center = None;
+        self.state.sudden_death_from = Some(radius);
+        info!(match_id = %self.state.id, "Time limit reached, sudden death");
+        let _ = self.snapshot_tx.send(ServerMsg::SuddenDeath {
+            seconds_remaining: self.state.settings.sudden_death,
+        });
+        radius
+    }
+
+    /// Announce the next shrink once it is `zone_warning_lead` seconds away,
+    /// or as soon as the wait begins if it is shorter than that
+    fn send_zone_warning(&mut self) {
+        let lead = self.state.settings.zone_warning_lead;
+        if lead <= 0.0
+            || self.state.is_shrinking
+            || self.state.zone_warning_sent
+            || self.state.zone_timer > lead
+        {
+            return;
+        }
+        let phase_idx = self.state.current_zone_phase;
+        let (Some(phase), Some((center_x, center_y))) = (
+            self.state.zone_config.phases.get(phase_idx),
+            self.state.next_zone_center,
+        ) else {
+            return;
+        };
+
+        self.state.zone_warning_sent = true;
+        let _ = self.snapshot_tx.send(ServerMsg::ZoneWarning {
+            phase: phase_idx as u32,
+            seconds_until_shrink: self.state.zone_timer.max(0.0),
+            next_radius: phase.target_radius,
+            next_center_x: center_x,
+            next_center_y: center_y,
+        });
+    }
+
     /// Apply zone damage to players outside the zone
     fn apply_zone_damage(&mut self) -> Vec<GameEvent> {
         let mut events = Vec::new();
         let zone = &self.state.zone;
-        let damage = CombatSystem::zone_damage(zone.damage_per_second);
+        let damage = CombatSystem::zone_damage(zone.damage_per_second, self.state.tick_delta());
+        let bypass_shield = self.state.settings.zone_bypasses_shield;
+        let now = self.state.clock_ms();
 
         let mut deaths: Vec<Uuid> = Vec::new();
 
@@ -877,25 +2906,26 @@ impl GameMatch {
                 continue;
             }
 
-            if !PhysicsSystem::is_in_zone(
-                player.x,
-                player.y,
-                zone.center_x,
-                zone.center_y,
-                zone.radius,
-            ) {
-                let (new_health, killed) = CombatSystem::apply_damage(player.health, damage);
+            if !PhysicsSystem::is_in_zone(player.x, player.y, zone) && !player.is_spawn_protected(now) {
+                let shield = if bypass_shield { 0.0 } else { player.shield };
+                let (new_shield, new_health, killed) =
+                    CombatSystem::apply_damage(shield, player.health, damage);
+                if !bypass_shield {
+                    player.shield = new_shield;
+                }
                 player.health = new_health;
+                player.shield_regen_delay = SHIELD_REGEN_DELAY;
                 player.damage_taken += damage;
 
                 events.push(GameEvent::ZoneDamage {
                     user_id: player.user_id,
                     damage,
+                    remaining_health: player.health,
+                    remaining_shield: player.shield,
                 });
 
                 if killed {
-                    player.alive = false;
-                    player.death_time = Some(unix_millis());
+                    player.die(now);
                     deaths.push(player.user_id);
                 }
             }
@@ -914,13 +2944,20 @@ impl GameMatch {
 
     /// Check win condition
     fn check_win_condition(&mut self) {
-        if self.state.phase != MatchPhase::InProgress {
+        // Practice runs until its player leaves
+        if self.state.phase != MatchPhase::InProgress || self.state.settings.practice {
             return;
         }
 
         let alive = self.state.alive_count();
-        if alive <= 1 {
-            self.state.phase = MatchPhase::Ended;
+        let time_up = self.state.settings.max_duration.is_some_and(|limit| {
+            self.state.match_elapsed >= limit + self.state.settings.sudden_death
+        });
+        if alive <= 1 || time_up {
+            if alive > 1 {
+                info!(match_id = %self.state.id, alive, "Sudden death over, ranking the survivors");
+            }
+            self.set_phase(MatchPhase::Ended);
             self.snapshot_builder.force_next();
         }
     }
@@ -929,14 +2966,15 @@ impl GameMatch {
     fn build_match_stats(&self) -> MatchStats {
         let duration = self
             .state
-            .start_time
-            .map(|start| ((unix_millis() - start) / 1000) as u32)
+            .start_clock_ms
+            .map(|start| ((self.state.clock_ms() - start) / 1000) as u32)
             .unwrap_or(0);
 
         let mut player_stats: Vec<PlayerMatchStats> = self
             .state
             .players
             .values()
+            .chain(&self.state.departed)
             .map(|p| {
                 let alive_time = p
                     .death_time
@@ -945,27 +2983,1597 @@ impl GameMatch {
 
                 PlayerMatchStats {
                     user_id: p.user_id,
+                    is_bot: p.is_bot,
+                    is_guest: p.is_guest,
+                    ship_type: p.ship_type,
                     kills: p.kills,
+                    best_streak: p.best_streak,
                     damage_dealt: p.damage_dealt,
                     damage_taken: p.damage_taken,
                     shots_fired: p.shots_fired,
                     shots_hit: p.shots_hit,
                     placement: 0, // Will be calculated below
                     alive_time_secs: alive_time,
+                    abandoned: p.abandoned,
+                    xp_gained: 0,
+                    new_level: None,
                 }
             })
             .collect();
 
-        // Calculate placements based on alive time (longer = better)
-        player_stats.sort_by(|a, b| b.alive_time_secs.cmp(&a.alive_time_secs));
+        // Survivors first, by remaining health when more than one is left at
+        // the time limit, then longest alive, most kills, most damage, and
+        // finally user ID so ties always break the same way
+        let survivors: HashMap<Uuid, f32> = self
+            .state
+            .players
+            .values()
+            .filter(|p| p.alive)
+            .map(|p| (p.user_id, p.health))
+            .collect();
+        player_stats.sort_by(|a, b| {
+            let survived = |s: &PlayerMatchStats| survivors.contains_key(&s.user_id);
+            let health = |s: &PlayerMatchStats| survivors.get(&s.user_id).copied().unwrap_or(0.0);
+            survived(b)
+                .cmp(&survived(a))
+                .then(health(b).total_cmp(&health(a)))
+                .then(b.alive_time_secs.cmp(&a.alive_time_secs))
+                .then(b.kills.cmp(&a.kills))
+                .then(b.damage_dealt.total_cmp(&a.damage_dealt))
+                .then(a.user_id.cmp(&b.user_id))
+        });
         for (i, stat) in player_stats.iter_mut().enumerate() {
             stat.placement = (i + 1) as u32;
         }
 
+        // Matches that never started, and practice, award nothing
+        let total_players = (self.state.players.len() + self.state.departed.len()) as u32;
+        if self.state.start_time.is_some() && !self.state.settings.practice {
+            for stat in &mut player_stats {
+                stat.xp_gained = progression::match_xp(stat, total_players);
+            }
+        }
+
         MatchStats {
             duration_secs: duration,
-            total_players: self.state.players.len() as u32,
+            total_players,
             player_stats,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::arena::Obstacle;
+    use crate::game::combat::MAX_AIM_ASSIST_SNAP;
+    use crate::ws::protocol::{RepairPad, StatusEffectKind};
+
+    fn player_id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn join(user_id: Uuid, ship_type: ShipType) -> PlayerInput {
+        PlayerInput {
+            user_id,
+            msg: ClientMsg::JoinMatch {
+                match_id: None,
+                ship_type,
+                weapon: None,
+                aim_assist: false,
+            },
+            received_at: 0,
+            display_name: Some(format!("Pilot {}", user_id.as_u128())),
+            guest: false,
+            latency: None,
+            cosmetics: Cosmetics::default(),
+        }
+    }
+
+    fn tick_input(user_id: Uuid, seq: u32, input: TickInput) -> PlayerInput {
+        PlayerInput {
+            user_id,
+            msg: ClientMsg::InputTick {
+                seq,
+                throttle: input.throttle,
+                steer: input.steer,
+                strafe: input.strafe,
+                shoot: input.shoot,
+                aim_yaw: input.aim_yaw,
+                boost: input.boost,
+                deploy_mine: input.deploy_mine,
+            },
+            received_at: 0,
+            display_name: None,
+            guest: false,
+            latency: None,
+            cosmetics: Cosmetics::default(),
+        }
+    }
+
+    fn leave(user_id: Uuid) -> PlayerInput {
+        PlayerInput {
+            msg: ClientMsg::LeaveMatch,
+            ..tick_input(user_id, 0, TickInput::default())
+        }
+    }
+
+    fn set_pause(user_id: Uuid, paused: bool) -> PlayerInput {
+        PlayerInput {
+            msg: ClientMsg::SetPause { paused },
+            ..tick_input(user_id, 0, TickInput::default())
+        }
+    }
+
+    fn new_match(seed: u64, settings: MatchSettings) -> GameMatch {
+        GameMatch::new(Uuid::from_u128(seed as u128), seed, settings).0
+    }
+
+    /// A match past its countdown, with players 1, 2, ... flying `ships`
+    fn started_match(seed: u64, settings: MatchSettings, ships: &[ShipType]) -> GameMatch {
+        let mut game = new_match(seed, settings);
+        for (n, &ship_type) in (1..).zip(ships) {
+            game.apply_input(join(player_id(n), ship_type));
+        }
+        run_countdown(&mut game);
+        game
+    }
+
+    fn run_countdown(game: &mut GameMatch) {
+        while game.state.phase != MatchPhase::InProgress {
+            game.run_tick();
+        }
+    }
+
+    /// Park players 1, 2, ... at `positions`, at rest and open to hits
+    fn line_up(game: &mut GameMatch, positions: &[(f32, f32)]) {
+        for (n, &(x, y)) in (1..).zip(positions) {
+            let player = game.state.players.get_mut(&player_id(n)).unwrap();
+            (player.x, player.y) = (x, y);
+            (player.vel_x, player.vel_y) = (0.0, 0.0);
+            player.spawn_protected_until = None;
+        }
+    }
+
+    /// Hold down `user_id`'s trigger, aiming along `aim_yaw`
+    fn open_fire(game: &mut GameMatch, user_id: Uuid, aim_yaw: f32) -> &mut PlayerState {
+        let shooter = game.state.players.get_mut(&user_id).unwrap();
+        shooter.current_input.aim_yaw = aim_yaw;
+        shooter.current_input.shoot = true;
+        shooter
+    }
+
+    /// A scripted input for tick `t`: circling, firing in bursts
+    fn scripted(t: u32, n: u32) -> TickInput {
+        TickInput {
+            throttle: if (t / 40 + n).is_multiple_of(3) {
+                -0.5
+            } else {
+                1.0
+            },
+            steer: ((t + 17 * n) as f32 * 0.05).sin(),
+            shoot: (t / 15 + n).is_multiple_of(2),
+            aim_yaw: (t as f32 * 0.07 + n as f32 * 2.0).rem_euclid(std::f32::consts::TAU),
+            boost: t % 90 < 20,
+            ..TickInput::default()
+        }
+    }
+
+    /// Step `game` like the live loop does, recording inputs before applying them
+    fn recorded_step(
+        game: &mut GameMatch,
+        recorder: &mut ReplayRecorder,
+        inputs: Vec<PlayerInput>,
+    ) {
+        for input in inputs {
+            recorder.record(game.state.tick, &input);
+            game.apply_input(input);
+        }
+        game.run_tick();
+    }
+
+    #[test]
+    fn late_joiners_get_spawn_protection_only_when_allowed() {
+        for late_join in [false, true] {
+            let settings = MatchSettings {
+                late_join,
+                ..MatchSettings::default()
+            };
+            let mut game = new_match(5, settings);
+            game.apply_input(join(player_id(1), ShipType::Fighter));
+            game.apply_input(join(player_id(2), ShipType::Scout));
+            for _ in 0..1000 {
+                if game.state.phase == MatchPhase::InProgress {
+                    break;
+                }
+                game.run_tick();
+            }
+            assert_eq!(game.state.phase, MatchPhase::InProgress);
+
+            game.apply_input(join(player_id(3), ShipType::Cruiser));
+            let Some(newcomer) = game.state.players.get(&player_id(3)) else {
+                assert!(!late_join, "late join was refused");
+                continue;
+            };
+            assert!(late_join, "joined a running match without late join");
+            let now = game.state.clock_ms();
+            assert!(newcomer.is_spawn_protected(now));
+            assert!(PhysicsSystem::is_in_zone(newcomer.x, newcomer.y, &game.state.zone));
+            let health = newcomer.health;
+
+            // Outside the zone, but protected
+            let newcomer = game.state.players.get_mut(&player_id(3)).unwrap();
+            newcomer.x = game.state.zone.center_x + game.state.zone.radius * 3.0;
+            game.apply_zone_damage();
+            assert_eq!(game.state.players[&player_id(3)].health, health);
+        }
+    }
+
+    #[test]
+    fn ships_start_protected_until_they_open_fire() {
+        let mut game = started_match(9, MatchSettings::default(), &[ShipType::Fighter; 2]);
+        let now = game.state.clock_ms();
+        assert!(game.state.players.values().all(|p| p.is_spawn_protected(now)));
+
+        // Placed by hand, as line_up would lift the protection under test
+        for (n, player) in game.state.players.values_mut().enumerate() {
+            player.x = n as f32 * 80.0;
+            player.y = 0.0;
+            player.rotation = 0.0;
+        }
+        open_fire(&mut game, player_id(1), 0.0);
+        for _ in 0..15 {
+            game.run_tick();
+        }
+
+        let now = game.state.clock_ms();
+        let shooter = &game.state.players[&player_id(1)];
+        let target = &game.state.players[&player_id(2)];
+        assert!(shooter.shots_fired > 0);
+        assert!(!shooter.is_spawn_protected(now));
+        assert!(target.is_spawn_protected(now));
+        assert_eq!(target.health, target.ship.max_health);
+
+        let state = &game.state;
+        let msg = game.snapshot_builder.build(
+            state.tick,
+            now,
+            &state.zone,
+            &state.players,
+            &state.projectiles,
+            &state.repair_pads,
+            Vec::new(),
+        );
+        let snapshot = serde_json::to_value(&msg).unwrap();
+        let protected: Vec<bool> = snapshot["players"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["spawn_protected"].as_bool().unwrap())
+            .collect();
+        assert_eq!(protected, [false, true]);
+    }
+
+    #[test]
+    fn spawns_keep_their_distance_when_there_is_room() {
+        let settings = MatchSettings {
+            max_players: 12,
+            ..MatchSettings::default()
+        };
+        for seed in 0..20 {
+            let mut game = new_match(seed, settings.clone());
+            for n in 1..=12 {
+                game.apply_input(join(player_id(n), ShipType::Fighter));
+            }
+
+            let ships: Vec<&PlayerState> = game.state.players.values().collect();
+            assert_eq!(ships.len(), 12);
+            for (i, a) in ships.iter().enumerate() {
+                assert!(PhysicsSystem::is_in_zone(a.x, a.y, &game.state.zone));
+                for b in &ships[i + 1..] {
+                    let gap = (a.x - b.x).hypot(a.y - b.y);
+                    assert!(gap >= settings.spawn_separation, "seed {seed}: ships {gap} apart");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crowded_spawns_take_the_roomiest_candidate() {
+        let settings = MatchSettings {
+            spawn_separation: 1.0e6,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(3, settings);
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        let first = &game.state.players[&player_id(1)];
+        let (x, y) = (first.x, first.y);
+
+        // Nowhere is far enough, so every attempt is used and the best kept
+        let (spawn_x, spawn_y, _) = game.state.generate_spawn_position();
+        let gap = (spawn_x - x).hypot(spawn_y - y);
+        assert!(gap > game.state.settings.arena.spawn_radius, "only {gap} apart");
+        assert!(PhysicsSystem::is_in_zone(spawn_x, spawn_y, &game.state.zone));
+    }
+
+    #[test]
+    fn spawns_keep_clear_of_obstacles() {
+        let settings = MatchSettings {
+            arena: Arc::new(Arena::cluttered(&ZoneConfig::default())),
+            spawn_separation: 150.0,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(6, settings);
+        let arena = game.state.settings.arena.clone();
+        for _ in 0..100 {
+            let (x, y, _) = game.state.generate_spawn_position();
+            let room = arena.obstacles.iter().map(|o| o.distance(x, y)).fold(f32::MAX, f32::min);
+            assert!(room >= game.state.settings.spawn_separation, "{room} from an obstacle");
+        }
+    }
+
+    #[test]
+    fn spawns_pull_toward_the_center_of_a_shrunken_zone() {
+        let mut game = new_match(4, MatchSettings::default());
+        game.state.zone.radius = game.state.zone_config.initial_radius * 0.2;
+        let limit = game.state.settings.arena.spawn_radius * 0.2 * 0.6;
+        for _ in 0..100 {
+            let (x, y, _) = game.state.generate_spawn_position();
+            let from_center = (x - game.state.zone.center_x).hypot(y - game.state.zone.center_y);
+            assert!(from_center <= limit + 1e-3);
+        }
+    }
+
+    #[test]
+    fn finished_matches_keep_their_events_for_the_retention_window() {
+        for (retention, kept) in [(Duration::from_secs(60), true), (Duration::ZERO, false)] {
+            let registry = MatchRegistry::new().with_event_retention(retention);
+            let (_, handle) = GameMatch::new(player_id(1), 1, MatchSettings::default());
+            handle.events.record(
+                3,
+                &[GameEvent::ZoneDamage {
+                    user_id: player_id(2),
+                    damage: 1.0,
+                    remaining_health: 99.0,
+                    remaining_shield: 0.0,
+                }],
+            );
+            registry.insert(handle);
+            assert!(registry.finished_events(&player_id(1)).is_none());
+
+            assert!(registry.finish(&player_id(1)).is_some());
+            assert!(registry.get(&player_id(1)).is_none());
+            let log = registry.finished_events(&player_id(1));
+            assert_eq!(log.is_some(), kept);
+            if let Some(log) = log {
+                assert_eq!(log.query(&Default::default()).0.len(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn zone_centers_are_pulled_toward_the_survivors() {
+        let centers = |center_bias: f32| {
+            let zone = ZoneConfig {
+                center_bias,
+                ..ZoneConfig::default()
+            };
+            let settings = MatchSettings {
+                arena: Arc::new(Arena::open(&zone)),
+                ..MatchSettings::default()
+            };
+            let mut game = new_match(25, settings);
+            for n in 1..=2 {
+                game.apply_input(join(player_id(n), ShipType::Fighter));
+            }
+            for player in game.state.players.values_mut() {
+                (player.x, player.y) = (900.0, 0.0);
+            }
+            game.state.plan_next_zone();
+            game.state.next_zone_center.unwrap()
+        };
+
+        // Phase 0 may move the center by at most half of 1500 - 1000
+        let (x, y) = centers(1.0);
+        assert!((x - 250.0).abs() < 1e-3 && y.abs() < 1e-3, "({x}, {y})");
+        let (x, y) = centers(0.0);
+        assert!(x.hypot(y) <= 250.0);
+        assert!((x - 250.0).hypot(y) > 1.0, "({x}, {y})");
+    }
+
+    #[test]
+    fn each_shrink_is_announced_once_ahead_of_time() {
+        let zone = ZoneConfig {
+            initial_delay: 5.0,
+            phases: vec![
+                ZonePhase {
+                    target_radius: 1000.0,
+                    shrink_duration: 2.0,
+                    damage_per_second: 1.0,
+                    delay_after: 1.0,
+                },
+                ZonePhase {
+                    target_radius: 500.0,
+                    shrink_duration: 2.0,
+                    damage_per_second: 1.0,
+                    delay_after: 0.0,
+                },
+            ],
+            ..ZoneConfig::default()
+        };
+        let settings = MatchSettings {
+            arena: Arc::new(Arena::open(&zone)),
+            zone_warning_lead: 3.0,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(6, settings);
+        let mut rx = game.snapshot_tx.subscribe();
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(join(player_id(2), ShipType::Scout));
+
+        let mut shrinks = Vec::new();
+        for _ in 0..30 * game.state.settings.tick_rate {
+            for event in game.run_tick() {
+                if let GameEvent::ZoneShrink { phase, new_center_x, new_center_y, .. } = event {
+                    shrinks.push((phase, new_center_x, new_center_y));
+                }
+            }
+        }
+
+        let mut warnings = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let ServerMsg::ZoneWarning {
+                phase,
+                seconds_until_shrink,
+                next_center_x,
+                next_center_y,
+                ..
+            } = msg
+            {
+                warnings.push((phase, next_center_x, next_center_y));
+                // A full lead before phase 0; phase 1 only waits 1s
+                let expected = if phase == 0 { 3.0 } else { 1.0 };
+                assert!((seconds_until_shrink - expected).abs() < 0.1, "{seconds_until_shrink}");
+            }
+        }
+        assert_eq!(shrinks.len(), 2);
+        assert_eq!(warnings, shrinks);
+    }
+
+    #[test]
+    fn projectile_count_levels_off_at_the_caps() {
+        let settings = MatchSettings {
+            max_projectiles: 6,
+            max_projectiles_per_player: 4,
+            ..MatchSettings::default()
+        };
+        let mut game = started_match(9, settings, &[ShipType::Scout; 2]);
+
+        let mut most = 0;
+        for _ in 0..600 {
+            for (n, player) in game.state.players.values_mut().enumerate() {
+                player.current_input = TickInput {
+                    shoot: true,
+                    aim_yaw: n as f32 * std::f32::consts::PI,
+                    ..TickInput::default()
+                };
+            }
+            game.run_tick();
+
+            let projectiles = &game.state.projectiles;
+            assert!(projectiles.len() <= 6);
+            for id in [player_id(1), player_id(2)] {
+                assert!(projectiles.iter().filter(|p| p.owner_id == id).count() <= 4);
+            }
+            most = most.max(projectiles.len());
+        }
+        assert_eq!(most, 6);
+    }
+
+    #[test]
+    fn flamer_hits_set_targets_burning() {
+        let mut game = started_match(21, MatchSettings::default(), &[ShipType::Fighter; 2]);
+        let balance = game.state.settings.balance.clone();
+        line_up(&mut game, &[(0.0, 0.0), (80.0, 0.0)]);
+        open_fire(&mut game, player_id(1), 0.0).equip_weapon(WeaponLoadout::Flamer, &balance);
+
+        let mut burns = 0;
+        for _ in 0..30 {
+            for event in game.run_tick() {
+                if let GameEvent::EffectDamage { source_id, .. } = event {
+                    assert_eq!(source_id, player_id(1));
+                    burns += 1;
+                }
+            }
+        }
+        let target = &game.state.players[&player_id(2)];
+        assert_eq!(target.status_effects.len(), 1);
+        assert_eq!(effects::mask(&target.status_effects), StatusEffectKind::Burning.bit());
+        assert!(burns > 0);
+    }
+
+    /// Player 1 firing along +x at player 2, whose hull overlaps the muzzle;
+    /// returns the shots fired and hits landed over a few ticks
+    fn point_blank_volley(arming_ticks: u32, muzzle_obstacle: bool) -> (usize, usize) {
+        let mut game = started_match(22, MatchSettings::default(), &[ShipType::Fighter; 2]);
+        let radius = game.state.players[&player_id(1)].ship.hitbox_radius;
+        line_up(&mut game, &[(0.0, 0.0), (radius * 2.0, 0.0)]);
+        if muzzle_obstacle {
+            Arc::make_mut(&mut game.state.settings.arena).obstacles.push(Obstacle {
+                x: radius + 5.0,
+                y: 0.0,
+                radius: 3.0,
+            });
+        }
+        open_fire(&mut game, player_id(1), 0.0).weapon.arming_ticks = arming_ticks;
+
+        let (mut shots, mut hits) = (0, 0);
+        for _ in 0..5 {
+            for event in game.run_tick() {
+                match event {
+                    GameEvent::Shot { .. } => shots += 1,
+                    GameEvent::Hit { .. } => hits += 1,
+                    _ => {}
+                }
+            }
+        }
+        (shots, hits)
+    }
+
+    #[test]
+    fn arming_projectiles_pass_through_a_ship_at_the_muzzle() {
+        let (shots, hits) = point_blank_volley(0, false);
+        assert!(shots > 0 && hits > 0);
+
+        let (shots, hits) = point_blank_volley(10, false);
+        assert!(shots > 0);
+        assert_eq!(hits, 0);
+    }
+
+    #[test]
+    fn shots_fired_into_an_obstacle_are_lost() {
+        assert_eq!(point_blank_volley(0, true), (0, 0));
+    }
+
+    #[test]
+    fn piercing_shots_hit_each_ship_in_line_once() {
+        let cases = [(0, vec![player_id(2)]), (1, vec![player_id(2), player_id(3)])];
+        for (pierce, expected) in cases {
+            let mut game = started_match(23, MatchSettings::default(), &[ShipType::Fighter; 3]);
+            line_up(&mut game, &[(0.0, 0.0), (100.0, 0.0), (200.0, 0.0)]);
+            open_fire(&mut game, player_id(1), 0.0).weapon.pierce = pierce;
+
+            let mut hit = Vec::new();
+            for tick in 0..60 {
+                if tick == 1 {
+                    game.state.players.get_mut(&player_id(1)).unwrap().current_input.shoot = false;
+                }
+                for event in game.run_tick() {
+                    if let GameEvent::Hit { target_id, .. } = event {
+                        hit.push(target_id);
+                    }
+                }
+            }
+            assert_eq!(hit, expected, "pierce {pierce}");
+            assert_eq!(game.state.players[&player_id(1)].shots_hit, 1);
+        }
+    }
+
+    #[test]
+    fn aim_assist_nudges_only_the_shots_of_players_who_opted_in() {
+        for opted_in in [false, true] {
+            let mut game = new_match(25, MatchSettings::default());
+            let mut shooter = join(player_id(1), ShipType::Fighter);
+            if let ClientMsg::JoinMatch { aim_assist, .. } = &mut shooter.msg {
+                *aim_assist = opted_in;
+            }
+            game.apply_input(shooter);
+            game.apply_input(join(player_id(2), ShipType::Fighter));
+            run_countdown(&mut game);
+            // Target a few degrees off the shooter's aim
+            line_up(&mut game, &[(0.0, 0.0), (300.0, 20.0)]);
+            open_fire(&mut game, player_id(1), 0.0);
+
+            let assisted = game
+                .run_tick()
+                .into_iter()
+                .find_map(|event| match event {
+                    GameEvent::Shot { assisted_yaw, .. } => Some(assisted_yaw),
+                    _ => None,
+                })
+                .expect("a shot");
+            match assisted {
+                Some(yaw) => {
+                    assert!(opted_in);
+                    assert!(yaw > 0.0 && yaw <= MAX_AIM_ASSIST_SNAP);
+                }
+                None => assert!(!opted_in),
+            }
+        }
+    }
+
+    #[test]
+    fn hits_carry_the_targets_remaining_health_and_shield() {
+        let mut game = started_match(24, MatchSettings::default(), &[ShipType::Fighter; 2]);
+        line_up(&mut game, &[(0.0, 0.0), (100.0, 0.0)]);
+        open_fire(&mut game, player_id(1), 0.0);
+
+        let mut hits = 0;
+        for _ in 0..60 {
+            for event in game.run_tick() {
+                if let GameEvent::Hit {
+                    target_remaining_health,
+                    target_remaining_shield,
+                    ..
+                } = event
+                {
+                    let target = &game.state.players[&player_id(2)];
+                    assert_eq!(target_remaining_health, target.health);
+                    assert_eq!(target_remaining_shield, target.shield);
+                    hits += 1;
+                }
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn a_burning_ship_outside_the_zone_dies_once() {
+        let mut game = started_match(22, MatchSettings::default(), &[ShipType::Fighter; 3]);
+        let burn = game.state.settings.balance.weapon(WeaponLoadout::Flamer).on_hit_effect.unwrap();
+        let dt = game.state.tick_delta();
+        game.state.zone.radius = 10.0;
+        line_up(&mut game, &[(300.0, 0.0), (600.0, 0.0), (900.0, 0.0)]);
+        let victim = game.state.players.get_mut(&player_id(2)).unwrap();
+        victim.shield = 0.0;
+        victim.shield_regen_delay = SHIELD_REGEN_DELAY;
+        victim.health = 0.01;
+        effects::apply(
+            &mut victim.status_effects,
+            StatusEffect::from_hit(&burn, player_id(1), dt),
+        );
+
+        let kills: Vec<_> = game
+            .run_tick()
+            .into_iter()
+            .filter_map(|event| match event {
+                GameEvent::Kill { killer_id, victim_id, cause } => Some((killer_id, victim_id, cause)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(kills, vec![(Some(player_id(1)), player_id(2), "burn".to_string())]);
+        assert_eq!(game.state.players[&player_id(1)].kills, 1);
+        assert!(game.state.players[&player_id(2)].status_effects.is_empty());
+    }
+
+    #[test]
+    fn mine_blasts_hit_their_owner_only_with_self_damage() {
+        for self_damage in [false, true] {
+            let settings = MatchSettings {
+                self_damage,
+                ..MatchSettings::default()
+            };
+            let mut game = started_match(23, settings, &[ShipType::Fighter; 3]);
+            line_up(&mut game, &[(0.0, 0.0), (1000.0, 0.0), (2000.0, 0.0)]);
+            let owner = game.state.players.get_mut(&player_id(1)).unwrap();
+            owner.shield = 0.0;
+            owner.health = 1.0;
+            let mut mine = Mine::new(player_id(1), 0.0, 0.0);
+            mine.lifetime_remaining = 0.0;
+            game.state.mines.push(mine);
+
+            let kills: Vec<_> = game
+                .run_tick()
+                .into_iter()
+                .filter_map(|event| match event {
+                    GameEvent::Kill {
+                        killer_id,
+                        victim_id,
+                        cause,
+                    } => Some((killer_id, victim_id, cause)),
+                    _ => None,
+                })
+                .collect();
+            let owner = &game.state.players[&player_id(1)];
+            if self_damage {
+                let suicide = (Some(player_id(1)), player_id(1), "self".to_string());
+                assert_eq!(kills, vec![suicide]);
+                assert!(!owner.alive);
+            } else {
+                assert!(kills.is_empty());
+                assert!(owner.alive);
+            }
+            assert_eq!((owner.kills, owner.damage_dealt), (0, 0.0));
+        }
+    }
+
+    #[test]
+    fn warmup_ends_once_every_human_sends_input() {
+        let settings = MatchSettings {
+            warmup: 10.0,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(23, settings);
+        let mut rx = game.snapshot_tx.subscribe();
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(join(player_id(2), ShipType::Fighter));
+        assert_eq!(game.state.phase, MatchPhase::Warmup);
+
+        let fly_and_shoot = TickInput {
+            throttle: 1.0,
+            shoot: true,
+            ..TickInput::default()
+        };
+        let start = game.state.players[&player_id(1)].x;
+        game.apply_input(tick_input(player_id(1), 1, fly_and_shoot.clone()));
+        for _ in 0..30 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::Warmup);
+        assert_ne!(game.state.players[&player_id(1)].x, start);
+        assert!(game.state.projectiles.is_empty());
+
+        game.apply_input(tick_input(player_id(2), 1, fly_and_shoot));
+        game.run_tick();
+        assert_eq!(game.state.phase, MatchPhase::Countdown);
+
+        let mut announced = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                ServerMsg::MatchWarmup { seconds_remaining } => announced.push(("warmup", seconds_remaining)),
+                ServerMsg::MatchCountdown { seconds_remaining } => announced.push(("countdown", seconds_remaining)),
+                _ => {}
+            }
+        }
+        assert_eq!(announced, vec![("warmup", 10), ("countdown", 5)]);
+    }
+
+    #[test]
+    fn warmup_runs_out_without_everyone() {
+        let settings = MatchSettings {
+            warmup: 2.0,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(24, settings);
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(join(player_id(2), ShipType::Fighter));
+
+        for _ in 0..2 * game.state.settings.tick_rate - 1 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::Warmup);
+        // The f32 timer can run out a tick late
+        game.run_tick();
+        game.run_tick();
+        assert_eq!(game.state.phase, MatchPhase::Countdown);
+    }
+
+    /// Fly player 1 at full throttle toward the +x edge, one input per tick,
+    /// returning each ack's correction until the ship reaches the edge
+    fn acks_flying_into_the_edge(prediction_tolerance: f32) -> Vec<Option<ShipMotion>> {
+        let settings = MatchSettings {
+            prediction_tolerance,
+            ..MatchSettings::default()
+        };
+        let mut game = started_match(27, settings, &[ShipType::Fighter; 2]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        game.set_direct_tx(tx);
+
+        let edge = game.state.bounds.half_width;
+        line_up(&mut game, &[(edge - 150.0, 0.0), (-edge + 150.0, 0.0)]);
+        game.state.players.get_mut(&player_id(1)).unwrap().rotation = 0.0;
+
+        let full_throttle = TickInput {
+            throttle: 1.0,
+            ..TickInput::default()
+        };
+        let mut corrections = Vec::new();
+        for seq in 1.. {
+            game.apply_input(tick_input(player_id(1), seq, full_throttle.clone()));
+            game.step_once();
+            while let Ok((user_id, msg)) = rx.try_recv() {
+                if let ServerMsg::InputAck { prediction_ok, correction, .. } = msg {
+                    assert_eq!(user_id, player_id(1));
+                    assert_eq!(prediction_ok, correction.is_none());
+                    corrections.push(correction);
+                }
+            }
+            if game.state.players[&player_id(1)].x >= edge {
+                return corrections;
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn acks_correct_only_predictions_that_drift() {
+        let corrections = acks_flying_into_the_edge(1.0);
+        let (first, rest) = corrections.split_first().unwrap();
+        let (last, between) = rest.split_last().unwrap();
+
+        // Nothing to compare the first input against, then open space
+        // behaves exactly as predicted until the edge stops the ship
+        assert!(first.is_some());
+        assert!(between.len() > 10 && between.iter().all(Option::is_none));
+        let last = last.expect("hitting the edge is corrected");
+        assert_eq!(last.vel_x, 0.0);
+
+        // Without a tolerance every ack carries the authoritative state
+        let corrections = acks_flying_into_the_edge(0.0);
+        assert!(corrections.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn a_stalemate_ends_within_the_time_limit() {
+        let settings = MatchSettings {
+            max_duration: Some(5.0),
+            sudden_death: 3.0,
+            ..MatchSettings::default()
+        };
+        let mut game = started_match(28, settings, &[ShipType::Fighter; 2]);
+        let mut rx = game.snapshot_tx.subscribe();
+
+        // Two ships that never fight, one already worn down, both sitting
+        // where the zone closes last
+        let (center_x, center_y) = (game.state.zone.center_x, game.state.zone.center_y);
+        line_up(&mut game, &[(center_x + 60.0, center_y), (center_x - 60.0, center_y)]);
+        game.state.players.get_mut(&player_id(1)).unwrap().health = 40.0;
+
+        let tick_rate = game.state.settings.tick_rate;
+        let mut ticks = 0;
+        while game.state.phase == MatchPhase::InProgress {
+            game.run_tick();
+            ticks += 1;
+            assert!(ticks <= 8 * tick_rate + 1, "still running after the time limit");
+        }
+        assert!(ticks >= 8 * tick_rate - 1, "ended early, after {ticks} ticks");
+        assert_eq!(game.state.zone.radius, 0.0);
+
+        let mut sudden_death = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let ServerMsg::SuddenDeath { seconds_remaining } = msg {
+                sudden_death.push(seconds_remaining);
+            }
+        }
+        assert_eq!(sudden_death, vec![3.0]);
+
+        // Both survived, so the healthier one places first
+        let stats = game.build_match_stats();
+        let placement = |n| {
+            stats
+                .player_stats
+                .iter()
+                .find(|s| s.user_id == player_id(n))
+                .unwrap()
+                .placement
+        };
+        assert!(game.state.players.values().all(|p| p.alive));
+        assert_eq!((placement(2), placement(1)), (1, 2));
+    }
+
+    #[test]
+    fn a_gap_in_inputs_stops_the_ship_turning() {
+        let mut game = started_match(13, MatchSettings::default(), &[ShipType::Fighter; 2]);
+
+        let steer = TickInput {
+            steer: 1.0,
+            ..TickInput::default()
+        };
+        game.apply_input(tick_input(player_id(1), 1, steer));
+        let rotation = |game: &GameMatch| game.state.players[&player_id(1)].rotation;
+
+        // Held briefly at full steer, then eased off
+        game.run_tick();
+        let start = rotation(&game);
+        game.run_tick();
+        assert_ne!(rotation(&game), start);
+        for _ in 0..60 {
+            game.run_tick();
+        }
+        let settled = rotation(&game);
+        game.run_tick();
+        assert_eq!(rotation(&game), settled);
+        assert_eq!(game.state.players[&player_id(1)].current_input.steer, 0.0);
+    }
+
+    #[test]
+    fn stepping_a_seeded_match_is_reproducible() {
+        let mut settings = MatchSettings::default();
+        Arc::make_mut(&mut settings.arena).zone.initial_delay = 5.0;
+        let wait_ticks = (settings.arena.zone.initial_delay * settings.tick_rate as f32) as u64;
+        let players = [player_id(1), player_id(2)];
+
+        // Positions every tick, plus the ticks the match and first shrink started on
+        let run = || {
+            let (mut game, handle) = GameMatch::new(Uuid::from_u128(21), 21, settings.clone());
+            for id in players {
+                handle.input_tx.try_send(join(id, ShipType::Fighter)).unwrap();
+            }
+
+            let mut trace = Vec::new();
+            let (mut started, mut shrinking) = (None, None);
+            for t in 0..500 {
+                for (n, id) in players.into_iter().enumerate() {
+                    let input = tick_input(id, t + 1, scripted(t, n as u32));
+                    handle.input_tx.try_send(input).unwrap();
+                }
+                game.step_once();
+
+                let state = &game.state;
+                if state.phase == MatchPhase::InProgress && started.is_none() {
+                    started = Some((state.tick, state.next_zone_center.unwrap()));
+                }
+                if state.is_shrinking && shrinking.is_none() {
+                    let target = (state.zone.target_center_x, state.zone.target_center_y);
+                    shrinking = Some((state.tick, target));
+                }
+                let ships: Vec<_> = state.players.values().map(|p| (p.x, p.y, p.rotation)).collect();
+                trace.push(ships);
+            }
+            (trace, started.unwrap(), shrinking.unwrap())
+        };
+
+        let (trace, (start_tick, planned_center), (shrink_tick, shrink_center)) = run();
+        assert_eq!(trace, run().0);
+        assert!(trace.iter().all(|ships| ships.len() == 2));
+        // The zone waits out its delay (give or take a tick of f32 rounding),
+        // then heads for the center rolled at the start
+        assert!(shrink_tick.abs_diff(start_tick + wait_ticks) <= 1);
+        assert_eq!(shrink_center, planned_center);
+    }
+
+    #[tokio::test]
+    async fn replaying_a_recording_reproduces_its_stats() {
+        let seed = 11;
+        let settings = MatchSettings::default();
+        let mut game = new_match(seed, settings.clone());
+        let dir = std::env::temp_dir().join(format!("replay-test-{}", Uuid::new_v4()));
+        let mut recorder = ReplayRecorder::new(dir.clone(), game.state.id, seed, &settings);
+
+        let players = [
+            (player_id(1), ShipType::Fighter),
+            (player_id(2), ShipType::Scout),
+            (player_id(3), ShipType::Destroyer),
+        ];
+        let joins = players.iter().map(|&(id, ship)| join(id, ship)).collect();
+        recorded_step(&mut game, &mut recorder, joins);
+        for t in 0..800 {
+            let inputs = players
+                .iter()
+                .enumerate()
+                .map(|(n, &(id, _))| tick_input(id, t + 1, scripted(t, n as u32)))
+                .collect();
+            recorded_step(&mut game, &mut recorder, inputs);
+        }
+        recorded_step(&mut game, &mut recorder, vec![leave(player_id(2))]);
+        for _ in 0..10 {
+            recorded_step(&mut game, &mut recorder, Vec::new());
+        }
+
+        let stats = game.build_match_stats();
+        // The script has to fire and take damage for this to mean much
+        assert!(stats.player_stats.iter().all(|p| p.shots_fired > 0));
+        assert!(stats.player_stats.iter().any(|p| p.damage_taken > 0.0));
+        let path = recorder.finish(game.state.tick, &stats).await.unwrap();
+        let recording = Replay::from_file(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(recording.end_tick, game.state.tick);
+        assert_eq!(recording.stats, stats);
+        assert_eq!(GameMatch::replay(&recording), stats);
+    }
+
+    #[test]
+    fn placements_break_ties_the_same_way_every_time() {
+        let mut game = new_match(26, MatchSettings::default());
+        for n in 1..=7 {
+            game.apply_input(join(player_id(n), ShipType::Fighter));
+        }
+        // (id, died after ms, kills, damage dealt); all deaths tie on time
+        let cases = [
+            (1, None, 0, 0.0),
+            (2, Some(30_000), 1, 50.0),
+            (3, Some(30_000), 2, 10.0),
+            (4, Some(30_000), 2, 40.0),
+            (5, Some(60_000), 0, 0.0),
+            (6, Some(30_000), 1, 50.0),
+            (7, None, 3, 0.0),
+        ];
+        for (n, died_after, kills, damage) in cases {
+            let player = game.state.players.get_mut(&player_id(n)).unwrap();
+            player.kills = kills;
+            player.damage_dealt = damage;
+            if let Some(ms) = died_after {
+                player.alive = false;
+                player.death_time = Some(player.spawn_time + ms);
+            }
+        }
+
+        let placements = |stats: MatchStats| -> Vec<u128> {
+            let mut ranked = stats.player_stats;
+            ranked.sort_by_key(|p| p.placement);
+            ranked.iter().map(|p| p.user_id.as_u128()).collect()
+        };
+        // Survivors (by kills), longest alive, then kills, damage and id
+        let expected = [7, 1, 5, 4, 3, 2, 6];
+        assert_eq!(placements(game.build_match_stats()), expected);
+        assert_eq!(placements(game.build_match_stats()), expected);
+    }
+
+    #[test]
+    fn a_ship_coasts_to_a_stop_once_its_input_goes_stale() {
+        // Speed after one full-throttle input followed by two seconds of silence
+        let speed_after_silence = |input_timeout: f32| {
+            let settings = MatchSettings {
+                input_timeout,
+                ..MatchSettings::default()
+            };
+            let mut game = started_match(31, settings, &[ShipType::Fighter; 2]);
+            line_up(&mut game, &[(0.0, 0.0)]);
+
+            let full_throttle = TickInput {
+                throttle: 1.0,
+                ..TickInput::default()
+            };
+            game.apply_input(tick_input(player_id(1), 1, full_throttle.clone()));
+            for _ in 0..SIMULATION_TPS * 2 {
+                game.run_tick();
+            }
+            let ship = &game.state.players[&player_id(1)];
+            let coasted = ship.vel_x.hypot(ship.vel_y);
+
+            // The next input takes control again
+            game.apply_input(tick_input(player_id(1), 2, full_throttle));
+            game.run_tick();
+            let ship = &game.state.players[&player_id(1)];
+            (coasted, ship.vel_x.hypot(ship.vel_y))
+        };
+
+        let (held, _) = speed_after_silence(0.0);
+        let (coasted, resumed) = speed_after_silence(0.5);
+        assert!(held > 100.0, "held input keeps thrusting: {held}");
+        assert!(coasted < held * 0.1, "stale input still thrusting: {coasted}");
+        assert!(resumed > coasted);
+    }
+
+    #[test]
+    fn the_countdown_is_cancelled_when_players_leave_and_restarts_when_they_return() {
+        let mut game = new_match(33, MatchSettings::default());
+        let mut rx = game.snapshot_tx.subscribe();
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(join(player_id(2), ShipType::Fighter));
+        assert_eq!(game.state.phase, MatchPhase::Countdown);
+        for _ in 0..SIMULATION_TPS {
+            game.run_tick();
+        }
+
+        game.apply_input(leave(player_id(2)));
+        game.run_tick();
+        assert_eq!(game.state.phase, MatchPhase::Waiting);
+        assert_eq!(game.state.countdown_remaining, game.state.settings.countdown);
+        let cancelled = std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|msg| matches!(msg, ServerMsg::CountdownCancelled { players: 1, min_players: 2 }));
+        assert!(cancelled);
+
+        // Still waiting with one player, however long it takes
+        for _ in 0..SIMULATION_TPS * 10 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::Waiting);
+
+        game.apply_input(join(player_id(3), ShipType::Scout));
+        assert_eq!(game.state.phase, MatchPhase::Countdown);
+        let ticks = (game.state.settings.countdown * SIMULATION_TPS as f32).ceil() as u32;
+        for _ in 0..ticks + 1 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::InProgress);
+    }
+
+    #[test]
+    fn head_on_shots_annihilate_only_with_clashes_on() {
+        for projectile_clash in [false, true] {
+            let settings = MatchSettings {
+                projectile_clash,
+                ..MatchSettings::default()
+            };
+            let mut game = started_match(35, settings, &[ShipType::Fighter; 2]);
+
+            // Two shots flying at each other, well away from both ships
+            let stats = game.state.settings.balance.weapon(WeaponLoadout::Blaster);
+            let (cx, cy) = (game.state.zone.center_x, game.state.zone.center_y);
+            line_up(&mut game, &[(cx, cy + 400.0), (cx, cy + 800.0)]);
+            for (n, x, direction) in [(1, -60.0, 0.0), (2, 60.0, std::f32::consts::PI)] {
+                let mut shot =
+                    Projectile::new(player_id(n), cx + x, cy, direction, (0.0, 0.0), &stats);
+                shot.arming_ticks = 0;
+                game.state.projectiles.push(shot);
+            }
+
+            let mut clashes = 0;
+            for _ in 0..SIMULATION_TPS / 2 {
+                for event in game.run_tick() {
+                    if let GameEvent::ProjectileClash { owner_ids, .. } = event {
+                        assert_eq!(owner_ids, [player_id(1), player_id(2)]);
+                        clashes += 1;
+                    }
+                }
+            }
+
+            let projectiles = &game.state.projectiles;
+            if projectile_clash {
+                assert_eq!(clashes, 1);
+                assert!(projectiles.is_empty());
+            } else {
+                assert_eq!(clashes, 0);
+                assert_eq!(projectiles.len(), 2);
+                // Passed through each other
+                assert!(projectiles[0].x > projectiles[1].x);
+            }
+        }
+    }
+
+    #[test]
+    fn grid_collisions_and_hits_match_checking_every_pair() {
+        let mut rng = ChaCha8Rng::seed_from_u64(41);
+        for layout in 0..10 {
+            let settings = MatchSettings {
+                max_players: 20,
+                ..MatchSettings::default()
+            };
+            let fleet: Vec<ShipType> = (1..=20)
+                .map(|n| ShipType::ALL[n % ShipType::ALL.len()])
+                .collect();
+            let mut game = started_match(41 + layout, settings, &fleet);
+
+            // Crowd the ships together, at rest, so plenty of them touch
+            let crowd: Vec<(f32, f32)> = (0..20)
+                .map(|_| (rng.gen_range(-250.0..250.0), rng.gen_range(-250.0..250.0)))
+                .collect();
+            line_up(&mut game, &crowd);
+            for player in game.state.players.values_mut() {
+                player.health = 1.0e6;
+            }
+
+            // Every pair, in order, against the positions before the pass
+            let ships: Vec<(Uuid, f32, f32, f32)> = game
+                .state
+                .players
+                .values()
+                .map(|p| (p.user_id, p.x, p.y, p.ship.hitbox_radius))
+                .collect();
+            let mut expected: BTreeMap<Uuid, (f32, f32)> =
+                ships.iter().map(|&(id, x, y, _)| (id, (x, y))).collect();
+            for (i, &(id1, x1, y1, r1)) in ships.iter().enumerate() {
+                for &(id2, x2, y2, r2) in &ships[i + 1..] {
+                    if PhysicsSystem::check_ship_collision(x1, y1, r1, x2, y2, r2) {
+                        let (first, second) =
+                            PhysicsSystem::resolve_ship_collision(x1, y1, r1, x2, y2, r2);
+                        expected.insert(id1, first);
+                        expected.insert(id2, second);
+                    }
+                }
+            }
+            game.update_physics();
+            let resolved: BTreeMap<Uuid, (f32, f32)> =
+                game.state.players.values().map(|p| (p.user_id, (p.x, p.y))).collect();
+            assert_eq!(resolved, expected);
+
+            // A cloud of shots through the crowd
+            let stats = game.state.settings.balance.weapon(WeaponLoadout::Blaster);
+            for _ in 0..300 {
+                let owner = player_id(rng.gen_range(1..=20));
+                let (x, y) = (rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+                let direction = rng.gen_range(0.0..std::f32::consts::TAU);
+                let mut shot = Projectile::new(owner, x, y, direction, (0.0, 0.0), &stats);
+                shot.arming_ticks = 0;
+                game.state.projectiles.push(shot);
+            }
+
+            // Every shot against every ship, in order
+            let dt = game.state.tick_delta();
+            let mut expected = Vec::new();
+            for mut shot in game.state.projectiles.clone() {
+                if !shot.update(dt) {
+                    continue;
+                }
+                for player in game.state.players.values() {
+                    if player.user_id == shot.owner_id
+                        || !shot.check_hit(player.x, player.y, player.ship.hitbox_radius)
+                    {
+                        continue;
+                    }
+                    expected.push((shot.owner_id, player.user_id));
+                    shot.hits_remaining = shot.hits_remaining.saturating_sub(1);
+                    if shot.hits_remaining == 0 {
+                        break;
+                    }
+                }
+            }
+            let hits: Vec<(Uuid, Uuid)> = game
+                .update_combat()
+                .into_iter()
+                .filter_map(|event| match event {
+                    GameEvent::Hit {
+                        shooter_id,
+                        target_id,
+                        ..
+                    } => Some((shooter_id, target_id)),
+                    _ => None,
+                })
+                .collect();
+            assert!(!hits.is_empty());
+            assert_eq!(hits, expected);
+        }
+    }
+
+    /// Ship collision and hit checks per tick, the grid against every pair,
+    /// for a full lobby and a four times larger one, spread over the zone and
+    /// crowded together, behind the numbers in the README. Run with
+    /// `cargo test --release grid_speedup -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn grid_speedup() {
+        const RUNS: u32 = 2000;
+        const SHOTS: usize = 1000;
+        let settings = MatchSettings {
+            max_players: 20,
+            ..MatchSettings::default()
+        };
+        let fleet: Vec<ShipType> = (1..=20)
+            .map(|n| ShipType::ALL[n % ShipType::ALL.len()])
+            .collect();
+        let game = started_match(43, settings, &fleet);
+        let hitboxes: Vec<f32> =
+            game.state.players.values().map(|p| p.ship.hitbox_radius).collect();
+        let largest_hitbox = hitboxes.iter().copied().fold(0.0, f32::max);
+        let stats = game.state.settings.balance.weapon(WeaponLoadout::Blaster);
+        let mut rng = ChaCha8Rng::seed_from_u64(43);
+
+        let layouts = [("spread", game.state.zone.radius), ("crowded", 300.0)];
+        let runs = [20, 80].into_iter().flat_map(|count| layouts.map(|layout| (count, layout)));
+        for (count, (layout, spread)) in runs {
+            let mut scatter = || {
+                let distance = spread * rng.gen::<f32>().sqrt();
+                let bearing = rng.gen_range(0.0..std::f32::consts::TAU);
+                (distance * bearing.cos(), distance * bearing.sin())
+            };
+            let ships: Vec<(f32, f32, f32)> = hitboxes
+                .iter()
+                .cycle()
+                .take(count)
+                .map(|&radius| {
+                    let (x, y) = scatter();
+                    (x, y, radius)
+                })
+                .collect();
+            let shots: Vec<Projectile> = (0..SHOTS)
+                .map(|_| {
+                    let (x, y) = scatter();
+                    Projectile::new(Uuid::nil(), x, y, 0.0, (0.0, 0.0), &stats)
+                })
+                .collect();
+
+            let every_pair = || {
+                let mut found = 0;
+                for (i, &(x1, y1, r1)) in ships.iter().enumerate() {
+                    for &(x2, y2, r2) in &ships[i + 1..] {
+                        let touching = PhysicsSystem::check_ship_collision(x1, y1, r1, x2, y2, r2);
+                        found += touching as usize;
+                    }
+                }
+                for shot in &shots {
+                    for &(x, y, radius) in &ships {
+                        found += shot.check_hit(x, y, radius) as usize;
+                    }
+                }
+                found
+            };
+            // Built twice, as the match does for collisions and then hits
+            let mut grid = SpatialGrid::default();
+            let mut nearby = Vec::new();
+            let mut with_grid = || {
+                let mut found = 0;
+                grid.rebuild(largest_hitbox * 2.0, ships.iter().map(|&(x, y, _)| (x, y)));
+                for (i, &(x1, y1, r1)) in ships.iter().enumerate() {
+                    grid.query_into(x1, y1, r1 + largest_hitbox, &mut nearby);
+                    for &(x2, y2, r2) in nearby.iter().filter(|&&j| j > i).map(|&j| &ships[j]) {
+                        let touching = PhysicsSystem::check_ship_collision(x1, y1, r1, x2, y2, r2);
+                        found += touching as usize;
+                    }
+                }
+                grid.rebuild(largest_hitbox * 2.0, ships.iter().map(|&(x, y, _)| (x, y)));
+                for shot in &shots {
+                    grid.query_into(shot.x, shot.y, shot.radius + largest_hitbox, &mut nearby);
+                    for &(x, y, radius) in nearby.iter().map(|&j| &ships[j]) {
+                        found += shot.check_hit(x, y, radius) as usize;
+                    }
+                }
+                found
+            };
+            assert_eq!(with_grid(), every_pair());
+
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                std::hint::black_box(every_pair());
+            }
+            let brute = start.elapsed() / RUNS;
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                std::hint::black_box(with_grid());
+            }
+            let gridded = start.elapsed() / RUNS;
+            println!(
+                "{count} ships {layout}, {SHOTS} shots: every pair {brute:?} per tick, \
+                 grid {gridded:?} ({:.1}x)",
+                brute.as_secs_f64() / gridded.as_secs_f64(),
+            );
+        }
+    }
+
+    #[test]
+    fn repair_pads_heal_live_ships_then_cool_down() {
+        let mut settings = MatchSettings::default();
+        Arc::make_mut(&mut settings.arena).repair_pads.push(RepairPad {
+            x: 0.0,
+            y: 0.0,
+            radius: 100.0,
+            heal_per_sec: 20.0,
+            cooldown: 5.0,
+        });
+        let mut game = started_match(3, settings, &[ShipType::Fighter; 3]);
+
+        // A damaged ship and a wreck on the pad, and a bystander far away
+        line_up(&mut game, &[(0.0, 0.0), (0.0, 60.0), (600.0, 0.0)]);
+        let wreck = game.state.players.get_mut(&player_id(2)).unwrap();
+        wreck.alive = false;
+        wreck.health = 0.0;
+        let ship = game.state.players.get_mut(&player_id(1)).unwrap();
+        let max_health = ship.ship.max_health;
+        ship.health = max_health - 30.0;
+
+        let events = game.run_tick();
+        let heals: Vec<(Uuid, f32)> = events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Heal { user_id, amount } => Some((*user_id, *amount)),
+                _ => None,
+            })
+            .collect();
+        let per_tick = 20.0 * game.state.tick_delta();
+        assert_eq!(heals, vec![(player_id(1), per_tick)]);
+        assert_eq!(game.state.players[&player_id(1)].health, max_health - 30.0 + per_tick);
+        assert_eq!(game.state.players[&player_id(2)].health, 0.0);
+
+        // Topped up, the pad goes dark and stops repairing
+        for _ in 0..SIMULATION_TPS * 2 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.players[&player_id(1)].health, max_health);
+        let status = game.state.repair_pads[0].status(game.state.clock_ms());
+        assert!(!status.active);
+        assert!(status.cooldown_remaining > 4.0);
+
+        game.state.players.get_mut(&player_id(1)).unwrap().health = max_health - 30.0;
+        let events = game.run_tick();
+        assert!(!events.iter().any(|event| matches!(event, GameEvent::Heal { .. })));
+        assert_eq!(game.state.players[&player_id(1)].health, max_health - 30.0);
+    }
+
+    #[test]
+    fn practice_matches_fill_with_bots_and_run_until_the_player_leaves() {
+        let mut settings = MatchSettings {
+            min_players: 1,
+            max_players: 4,
+            practice: true,
+            spawn_protection: 0.0,
+            ..MatchSettings::default()
+        };
+        Arc::make_mut(&mut settings.arena).zone.initial_delay = 1.0;
+        let mut game = started_match(8, settings, &[ShipType::Fighter]);
+        assert_eq!(game.state.players.len(), 4);
+        assert_eq!(game.state.human_count(), 1);
+
+        // The last ship standing doesn't end it, and the zone never closes
+        let now = game.state.clock_ms();
+        for bot in game.state.players.values_mut().filter(|p| p.is_bot) {
+            bot.die(now);
+        }
+        let radius = game.state.zone.radius;
+        let player = game.state.players.get_mut(&player_id(1)).unwrap();
+        player.x = game.state.zone.center_x + radius * 2.0;
+        let health = player.health;
+        for _ in 0..SIMULATION_TPS * 3 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::InProgress);
+        assert_eq!(game.state.zone.radius, radius);
+        assert_eq!(game.state.players[&player_id(1)].health, health);
+
+        // Nothing is earned
+        game.apply_input(leave(player_id(1)));
+        assert_eq!(game.state.human_count(), 0);
+        let stats = game.build_match_stats();
+        assert!(stats.player_stats.iter().all(|p| p.xp_gained == 0));
+    }
+
+    #[test]
+    fn clients_get_the_server_clock_periodically_and_with_pongs() {
+        let mut game = new_match(9, MatchSettings::default());
+        let mut rx = game.snapshot_tx.subscribe();
+        let before = unix_millis();
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(PlayerInput {
+            msg: ClientMsg::Ping { t: 77 },
+            ..leave(player_id(1))
+        });
+        let mut messages = Vec::new();
+        for _ in 0..TIME_SYNC_INTERVAL_SECS * SIMULATION_TPS * 2 {
+            game.step_once();
+            messages.extend(std::iter::from_fn(|| rx.try_recv().ok()));
+        }
+
+        assert!(messages.iter().any(
+            |msg| matches!(msg, ServerMsg::Pong { t: 77, server_time } if *server_time >= before)
+        ));
+        let syncs: Vec<u64> = messages
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerMsg::TimeSync { server_time, tick } => {
+                    assert!(*server_time >= before);
+                    Some(*tick)
+                }
+                _ => None,
+            })
+            .collect();
+        let interval = (TIME_SYNC_INTERVAL_SECS * SIMULATION_TPS) as u64;
+        assert_eq!(syncs, vec![interval, interval * 2]);
+    }
+
+    fn pilot() -> PlayerState {
+        PlayerState::new(
+            player_id(1),
+            "Pilot".to_string(),
+            ShipType::Fighter,
+            Cosmetics::default(),
+            0.0,
+            0.0,
+            0.0,
+            &BalanceTable::default(),
+        )
+    }
+
+    #[test]
+    fn streaks_reset_on_death_but_best_streak_is_kept() {
+        let mut player = pilot();
+        for n in 0..3 {
+            player.record_kill(n * 10_000);
+        }
+        assert_eq!((player.current_streak, player.best_streak), (3, 3));
+
+        player.die(30_000);
+        assert_eq!((player.current_streak, player.best_streak), (0, 3));
+
+        player.record_kill(40_000);
+        assert_eq!((player.kills, player.current_streak, player.best_streak), (4, 1, 3));
+    }
+
+    #[test]
+    fn kills_within_the_window_chain_into_multi_kills() {
+        let mut player = pilot();
+        assert_eq!(player.record_kill(1_000), None);
+        assert_eq!(player.record_kill(1_000 + MULTI_KILL_WINDOW_MS), Some(2));
+        assert_eq!(player.record_kill(2_000 + MULTI_KILL_WINDOW_MS), Some(3));
+
+        // Too long after the last kill starts a new chain
+        let later = 3_000 + 2 * MULTI_KILL_WINDOW_MS;
+        assert_eq!(player.record_kill(later), None);
+        assert_eq!(player.record_kill(later + 1), Some(2));
+
+        // Dying breaks the chain, even if the next kill comes quickly
+        player.die(later + 2);
+        assert_eq!(player.record_kill(later + 3), None);
+    }
+
+    /// Two players in a counting down match, with player 2 then player 1
+    /// asking to pause; returns the host, whether it paused and the errors sent
+    fn pause_attempts(private: bool) -> (Option<Uuid>, bool, Vec<ErrorCode>) {
+        let settings = MatchSettings {
+            private,
+            ..MatchSettings::default()
+        };
+        let mut game = new_match(26, settings);
+        let mut rx = game.snapshot_tx.subscribe();
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.apply_input(join(player_id(2), ShipType::Fighter));
+        game.run_tick();
+        assert_eq!(game.state.phase, MatchPhase::Countdown);
+
+        game.apply_input(set_pause(player_id(2), true));
+        game.apply_input(set_pause(player_id(1), true));
+
+        let mut errors = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let ServerMsg::Error { code, .. } = msg {
+                errors.push(code);
+            }
+        }
+        (game.state.host_id, game.state.paused, errors)
+    }
+
+    #[test]
+    fn only_the_host_of_a_private_match_can_pause() {
+        let (host_id, paused, errors) = pause_attempts(true);
+        assert_eq!(host_id, Some(player_id(1)));
+        assert!(paused);
+        assert_eq!(errors, vec![ErrorCode::NotHost]);
+    }
+
+    #[test]
+    fn matchmade_matches_have_no_host_and_cannot_be_paused() {
+        let (host_id, paused, errors) = pause_attempts(false);
+        assert_eq!(host_id, None);
+        assert!(!paused);
+        assert_eq!(errors, vec![ErrorCode::CannotPause, ErrorCode::CannotPause]);
+    }
+
+    #[test]
+    fn pausing_stops_the_match_clock() {
+        let settings = MatchSettings {
+            private: true,
+            ..MatchSettings::default()
+        };
+        let mut game = started_match(27, settings, &[ShipType::Fighter, ShipType::Fighter]);
+        let now = game.state.clock_ms();
+        assert!(game.state.players[&player_id(1)].is_spawn_protected(now));
+
+        // Sit out well past the protection window, then pick up again
+        game.apply_input(set_pause(player_id(1), true));
+        let settings = &game.state.settings;
+        let pause_ticks = 2 * settings.spawn_protection as u32 * settings.tick_rate;
+        for _ in 0..pause_ticks {
+            game.run_tick();
+        }
+        game.apply_input(set_pause(player_id(1), false));
+        assert_eq!(game.state.clock_ms(), now);
+        game.run_tick();
+
+        let now = game.state.clock_ms();
+        assert!(game.state.players[&player_id(1)].is_spawn_protected(now));
+        assert!(game.state.players[&player_id(2)].is_spawn_protected(now));
+    }
+
+    #[tokio::test]
+    async fn a_match_nobody_joins_is_closed_after_the_timeout() {
+        let settings = MatchSettings {
+            empty_timeout: Some(0.2),
+            ..MatchSettings::default()
+        };
+
+        // A player who joins and leaves again restarts the wait
+        let mut game = new_match(12, settings.clone());
+        for _ in 0..SIMULATION_TPS / 10 {
+            game.run_tick();
+        }
+        game.apply_input(join(player_id(1), ShipType::Fighter));
+        game.run_tick();
+        game.apply_input(leave(player_id(1)));
+        for _ in 0..SIMULATION_TPS / 10 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::Waiting);
+        for _ in 0..SIMULATION_TPS / 5 {
+            game.run_tick();
+        }
+        assert_eq!(game.state.phase, MatchPhase::Ended);
+
+        // The live loop stops, so the match can be reaped
+        let (game, handle) = GameMatch::new(Uuid::from_u128(13), 13, settings);
+        let outcome = tokio::time::timeout(Duration::from_secs(5), game.run())
+            .await
+            .expect("an empty match ends on its own");
+        assert!(outcome.is_none());
+        assert_eq!(handle.phase(), MatchPhase::Ended);
+        assert!(!handle.is_joinable());
+    }
+}
@@ -0,0 +1,103 @@
+//! Per-match performance metrics
+//!
+//! The tick loop accumulates timings locally and publishes them to shared
+//! atomics once per window, so readers never contend with the hot loop.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Metrics published by a running match, read by the `/metrics` endpoint
+#[derive(Debug, Default)]
+pub struct MatchMetrics {
+    /// Tick budget at the match's tick rate (microseconds)
+    pub tick_budget_micros: AtomicU64,
+    /// Average tick duration over the last window (microseconds)
+    pub tick_avg_micros: AtomicU64,
+    /// Longest tick in the last window (microseconds)
+    pub tick_max_micros: AtomicU64,
+    /// Ticks simulated since the match started
+    pub ticks_total: AtomicU64,
+    /// Projectiles in flight at the end of the last window
+    pub projectile_count: AtomicUsize,
+    /// Messages dropped because a player's forwarder fell behind the match
+    pub lagged_messages: AtomicU64,
+    /// Most ticks one pass of the loop ran in the last window; above 1 the
+    /// loop is catching up after a stall
+    pub catch_up_steps_max: AtomicU64,
+    /// Ticks skipped since the match started because the loop fell too far
+    /// behind to catch up
+    pub ticks_dropped: AtomicU64,
+}
+
+impl MatchMetrics {
+    pub fn new(tick_budget_micros: u64) -> Self {
+        Self {
+            tick_budget_micros: AtomicU64::new(tick_budget_micros),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the match's ticks are taking longer than its budget on average
+    pub fn is_behind(&self) -> bool {
+        self.tick_avg_micros.load(Ordering::Relaxed)
+            > self.tick_budget_micros.load(Ordering::Relaxed)
+    }
+}
+
+/// Tick timings accumulated by the match loop between publishes
+#[derive(Debug)]
+pub struct TickStatsWindow {
+    /// Ticks per window
+    size: u32,
+    ticks: u32,
+    sum_micros: u64,
+    max_micros: u64,
+    /// Most ticks run by one pass of the loop
+    max_steps: u32,
+}
+
+impl TickStatsWindow {
+    pub fn new(size: u32) -> Self {
+        Self {
+            size: size.max(1),
+            ticks: 0,
+            sum_micros: 0,
+            max_micros: 0,
+            max_steps: 0,
+        }
+    }
+
+    /// Record how many ticks one pass of the loop is about to run
+    pub fn record_steps(&mut self, steps: u32) {
+        self.max_steps = self.max_steps.max(steps);
+    }
+
+    /// Record one tick, publishing to `metrics` when the window fills
+    pub fn record(&mut self, tick_micros: u64, projectile_count: usize, metrics: &MatchMetrics) {
+        self.ticks += 1;
+        self.sum_micros += tick_micros;
+        self.max_micros = self.max_micros.max(tick_micros);
+
+        if self.ticks < self.size {
+            return;
+        }
+
+        metrics
+            .tick_avg_micros
+            .store(self.sum_micros / self.ticks as u64, Ordering::Relaxed);
+        metrics.tick_max_micros.store(self.max_micros, Ordering::Relaxed);
+        metrics
+            .ticks_total
+            .fetch_add(self.ticks as u64, Ordering::Relaxed);
+        metrics
+            .projectile_count
+            .store(projectile_count, Ordering::Relaxed);
+        metrics
+            .catch_up_steps_max
+            .store(self.max_steps as u64, Ordering::Relaxed);
+
+        self.ticks = 0;
+        self.sum_micros = 0;
+        self.max_micros = 0;
+        self.max_steps = 0;
+    }
+}
@@ -1,13 +1,32 @@
 //! Game simulation modules
 
+pub mod anticheat;
+pub mod arena;
+pub mod balance;
+pub mod bot;
 pub mod combat;
+pub mod effects;
+pub mod event_log;
+pub mod input_buffer;
+pub mod inspect;
 pub mod r#match;
+pub mod metrics;
 pub mod physics;
+pub mod progression;
+pub mod replay;
 pub mod snapshot;
+pub mod spatial;
 
-pub use r#match::{GameMatch, MatchHandle, MatchRegistry, PlayerState};
+pub use r#match::{
+    GameMatch, MatchHandle, MatchOutcome, MatchPhase, MatchRegistry, MatchSettings, PlayerState,
+    ZoneConfig, ZoneConfigError,
+};
 
-use crate::ws::protocol::ClientMsg;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::util::latency::LatencyTracker;
+use crate::ws::protocol::{ClientMsg, CosmeticSlot};
 use uuid::Uuid;
 
 /// Player input received from WebSocket
@@ -16,6 +35,60 @@ pub struct PlayerInput {
     pub user_id: Uuid,
     pub msg: ClientMsg,
     pub received_at: u64,
+    /// Display name resolved by the server, set on join commands
+    pub display_name: Option<String>,
+    /// Connection RTT tracker, set on join commands
+    pub latency: Option<Arc<LatencyTracker>>,
+    /// Joining with a guest token, set on join commands
+    pub guest: bool,
+    /// Equipped cosmetics, set on join commands
+    pub cosmetics: Cosmetics,
+}
+
+/// Cosmetics a player has equipped, one item per slot, resolved from their
+/// inventory before they join since matches have no store access
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cosmetics {
+    pub flag_skin_id: Option<Uuid>,
+    pub hull_skin_id: Option<Uuid>,
+    pub trail_id: Option<Uuid>,
+    pub kill_effect_id: Option<Uuid>,
+}
+
+impl Cosmetics {
+    /// Item equipped in a slot
+    pub fn get(&self, slot: CosmeticSlot) -> Option<Uuid> {
+        match slot {
+            CosmeticSlot::Flag => self.flag_skin_id,
+            CosmeticSlot::HullSkin => self.hull_skin_id,
+            CosmeticSlot::Trail => self.trail_id,
+            CosmeticSlot::KillEffect => self.kill_effect_id,
+        }
+    }
+
+    /// Filled slots and their items
+    pub fn equipped(&self) -> BTreeMap<CosmeticSlot, Uuid> {
+        CosmeticSlot::ALL
+            .into_iter()
+            .filter_map(|slot| self.get(slot).map(|id| (slot, id)))
+            .collect()
+    }
+}
+
+impl FromIterator<(CosmeticSlot, Uuid)> for Cosmetics {
+    fn from_iter<I: IntoIterator<Item = (CosmeticSlot, Uuid)>>(iter: I) -> Self {
+        let mut cosmetics = Self::default();
+        for (slot, id) in iter {
+            let item = match slot {
+                CosmeticSlot::Flag => &mut cosmetics.flag_skin_id,
+                CosmeticSlot::HullSkin => &mut cosmetics.hull_skin_id,
+                CosmeticSlot::Trail => &mut cosmetics.trail_id,
+                CosmeticSlot::KillEffect => &mut cosmetics.kill_effect_id,
+            };
+            *item = Some(id);
+        }
+        cosmetics
+    }
 }
 
 /// Input state for a single tick (processed from ClientMsg::InputTick)
@@ -24,6 +97,9 @@ pub struct TickInput {
     pub seq: u32,
     pub throttle: f32,
     pub steer: f32,
+    pub strafe: f32,
     pub shoot: bool,
     pub aim_yaw: f32,
+    pub boost: bool,
+    pub deploy_mine: bool,
 }
@@ -1,10 +1,25 @@
 //! Ship physics and movement constraints
 
-use crate::util::time::tick_delta;
-use crate::ws::protocol::ShipType;
+use serde::{Deserialize, Serialize};
+
+use crate::util::time::SIMULATION_TPS;
+use crate::ws::protocol::{ShipMotion, ShipType, WorldBounds, ZoneShape, ZoneState};
+
+use super::arena::Obstacle;
+use super::TickInput;
+
+/// Boost meter capacity
+pub const BOOST_MAX_ENERGY: f32 = 100.0;
+/// Boost energy drained per second while boosting
+pub const BOOST_DRAIN_PER_SEC: f32 = 40.0;
+/// Boost energy regained per second while not boosting
+pub const BOOST_REGEN_PER_SEC: f32 = 15.0;
+/// Delay after a boost ends before it can be engaged again (seconds)
+pub const BOOST_COOLDOWN: f32 = 0.5;
 
 /// Ship physics constants per ship type
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ShipStats {
     /// Maximum forward speed
     pub max_speed: f32,
@@ -16,8 +31,26 @@ pub struct ShipStats {
     pub turn_rate: f32,
     /// Maximum health
     pub max_health: f32,
+    /// Shield capacity, absorbed before health
+    pub max_shield: f32,
+    /// Shield recharged per second once out of combat
+    pub shield_regen: f32,
     /// Ship hitbox radius
     pub hitbox_radius: f32,
+    /// Max speed multiplier while boosting
+    pub boost_speed_multiplier: f32,
+    /// Acceleration multiplier while boosting
+    pub boost_accel_multiplier: f32,
+    /// Fraction of `acceleration` available when thrusting in reverse
+    #[serde(default = "default_reverse_multiplier")]
+    pub reverse_multiplier: f32,
+    /// Whether `strafe` input thrusts sideways at full `acceleration`
+    #[serde(default)]
+    pub omnidirectional: bool,
+}
+
+fn default_reverse_multiplier() -> f32 {
+    0.5
 }
 
 impl ShipStats {
@@ -29,7 +62,13 @@ impl ShipStats {
                 drag: 0.95,
                 turn_rate: 4.0,
                 max_health: 60.0,
+                max_shield: 20.0,
+                shield_regen: 5.0,
                 hitbox_radius: 15.0,
+                boost_speed_multiplier: 1.8,
+                boost_accel_multiplier: 2.0,
+                reverse_multiplier: 0.5,
+                omnidirectional: false,
             },
             ShipType::Fighter => Self {
                 max_speed: 300.0,
@@ -37,7 +76,13 @@ impl ShipStats {
                 drag: 0.93,
                 turn_rate: 3.0,
                 max_health: 100.0,
+                max_shield: 40.0,
+                shield_regen: 8.0,
                 hitbox_radius: 20.0,
+                boost_speed_multiplier: 1.5,
+                boost_accel_multiplier: 1.6,
+                reverse_multiplier: 0.5,
+                omnidirectional: false,
             },
             ShipType::Cruiser => Self {
                 max_speed: 200.0,
@@ -45,7 +90,13 @@ impl ShipStats {
                 drag: 0.90,
                 turn_rate: 2.0,
                 max_health: 150.0,
+                max_shield: 80.0,
+                shield_regen: 12.0,
                 hitbox_radius: 30.0,
+                boost_speed_multiplier: 1.3,
+                boost_accel_multiplier: 1.4,
+                reverse_multiplier: 0.5,
+                omnidirectional: false,
             },
             ShipType::Destroyer => Self {
                 max_speed: 180.0,
@@ -53,33 +104,58 @@ impl ShipStats {
                 drag: 0.88,
                 turn_rate: 1.5,
                 max_health: 120.0,
+                max_shield: 50.0,
+                shield_regen: 8.0,
                 hitbox_radius: 35.0,
+                boost_speed_multiplier: 1.25,
+                boost_accel_multiplier: 1.3,
+                reverse_multiplier: 0.5,
+                omnidirectional: false,
+            },
+            ShipType::Corvette => Self {
+                max_speed: 280.0,
+                acceleration: 220.0,
+                drag: 0.92,
+                turn_rate: 3.2,
+                max_health: 80.0,
+                max_shield: 35.0,
+                shield_regen: 7.0,
+                hitbox_radius: 18.0,
+                boost_speed_multiplier: 1.5,
+                boost_accel_multiplier: 1.6,
+                reverse_multiplier: 0.8,
+                omnidirectional: true,
             },
         }
     }
+
+    /// Effective stats while boosting
+    pub fn boosted(&self) -> Self {
+        Self {
+            max_speed: self.max_speed * self.boost_speed_multiplier,
+            acceleration: self.acceleration * self.boost_accel_multiplier,
+            ..*self
+        }
+    }
 }
 
 /// Physics system for updating ship positions and velocities
 pub struct PhysicsSystem;
 
 impl PhysicsSystem {
-    /// Update a ship's physics based on input
-    /// Returns (new_x, new_y, new_rotation, new_vel_x, new_vel_y)
+    /// Update a ship's physics based on input, returning its new motion
     pub fn update_ship(
-        x: f32,
-        y: f32,
-        rotation: f32,
-        vel_x: f32,
-        vel_y: f32,
-        throttle: f32,
-        steer: f32,
+        motion: &ShipMotion,
+        input: &TickInput,
         stats: &ShipStats,
-    ) -> (f32, f32, f32, f32, f32) {
-        let dt = tick_delta();
+        dt: f32,
+    ) -> ShipMotion {
+        let ShipMotion { x, y, rotation, vel_x, vel_y } = *motion;
 
         // Clamp inputs
-        let throttle = throttle.clamp(-1.0, 1.0);
-        let steer = steer.clamp(-1.0, 1.0);
+        let throttle = input.throttle.clamp(-1.0, 1.0);
+        let steer = input.steer.clamp(-1.0, 1.0);
+        let strafe = if stats.omnidirectional { input.strafe.clamp(-1.0, 1.0) } else { 0.0 };
 
         // Update rotation
         let new_rotation = rotation + steer * stats.turn_rate * dt;
@@ -94,16 +170,19 @@ impl PhysicsSystem {
         let thrust_power = if throttle >= 0.0 {
             throttle * stats.acceleration
         } else {
-            throttle * stats.acceleration * 0.5 // Reverse is slower
+            throttle * stats.acceleration * stats.reverse_multiplier
         };
+        // Strafing pushes at right angles to the heading, positive to the right
+        let strafe_power = strafe * stats.acceleration;
 
         // Update velocity with thrust and drag
-        let mut new_vel_x = vel_x + thrust_x * thrust_power * dt;
-        let mut new_vel_y = vel_y + thrust_y * thrust_power * dt;
+        let mut new_vel_x = vel_x + (thrust_x * thrust_power - thrust_y * strafe_power) * dt;
+        let mut new_vel_y = vel_y + (thrust_y * thrust_power + thrust_x * strafe_power) * dt;
 
-        // Apply drag
-        new_vel_x *= stats.drag;
-        new_vel_y *= stats.drag;
+        // Apply drag (tuned per tick at the default rate, scaled so other rates match)
+        let drag = stats.drag.powf(dt * SIMULATION_TPS as f32);
+        new_vel_x *= drag;
+        new_vel_y *= drag;
 
         // Clamp to max speed
         let speed = (new_vel_x * new_vel_x + new_vel_y * new_vel_y).sqrt();
@@ -117,23 +196,116 @@ impl PhysicsSystem {
         let new_x = x + new_vel_x * dt;
         let new_y = y + new_vel_y * dt;
 
-        (new_x, new_y, new_rotation, new_vel_x, new_vel_y)
+        ShipMotion {
+            x: new_x,
+            y: new_y,
+            rotation: new_rotation,
+            vel_x: new_vel_x,
+            vel_y: new_vel_y,
+        }
+    }
+
+    /// Where a client predicting its own ship expects it after one more
+    /// input: the ship's movement alone, without collisions, obstacles or
+    /// the world boundary
+    pub fn predict(motion: &ShipMotion, input: &TickInput, stats: &ShipStats, dt: f32) -> ShipMotion {
+        Self::update_ship(motion, input, stats, dt)
+    }
+
+    /// How far a predicted position is from the authoritative one
+    pub fn prediction_error(predicted: &ShipMotion, actual: &ShipMotion) -> f32 {
+        (actual.x - predicted.x).hypot(actual.y - predicted.y)
+    }
+
+    /// Advance the boost meter by one tick
+    /// Returns (boosting, new_energy, new_cooldown)
+    pub fn update_boost(
+        wants_boost: bool,
+        was_boosting: bool,
+        energy: f32,
+        cooldown: f32,
+        dt: f32,
+    ) -> (bool, f32, f32) {
+
+        // Holding boost continues it; starting a new one waits out the cooldown
+        let can_engage = was_boosting || cooldown <= 0.0;
+        if wants_boost && can_engage && energy > 0.0 {
+            let new_energy = (energy - BOOST_DRAIN_PER_SEC * dt).max(0.0);
+            return (true, new_energy, 0.0);
+        }
+
+        let new_cooldown = if was_boosting {
+            BOOST_COOLDOWN
+        } else {
+            (cooldown - dt).max(0.0)
+        };
+        let new_energy = (energy + BOOST_REGEN_PER_SEC * dt).min(BOOST_MAX_ENERGY);
+        (false, new_energy, new_cooldown)
     }
 
     /// Check if a point is inside the zone
-    pub fn is_in_zone(x: f32, y: f32, zone_center_x: f32, zone_center_y: f32, zone_radius: f32) -> bool {
-        let dx = x - zone_center_x;
-        let dy = y - zone_center_y;
-        let dist_sq = dx * dx + dy * dy;
-        dist_sq <= zone_radius * zone_radius
+    pub fn is_in_zone(x: f32, y: f32, zone: &ZoneState) -> bool {
+        Self::zone_distance(x, y, zone) <= 0.0
     }
 
     /// Calculate distance from zone edge (negative = inside, positive = outside)
-    pub fn zone_distance(x: f32, y: f32, zone_center_x: f32, zone_center_y: f32, zone_radius: f32) -> f32 {
-        let dx = x - zone_center_x;
-        let dy = y - zone_center_y;
-        let dist = (dx * dx + dy * dy).sqrt();
-        dist - zone_radius
+    pub fn zone_distance(x: f32, y: f32, zone: &ZoneState) -> f32 {
+        let dx = x - zone.center_x;
+        let dy = y - zone.center_y;
+        match zone.shape {
+            ZoneShape::Circle => (dx * dx + dy * dy).sqrt() - zone.radius,
+            ZoneShape::Rect { aspect } => {
+                // Signed distance to an axis-aligned box
+                let qx = dx.abs() - zone.radius;
+                let qy = dy.abs() - zone.radius * aspect;
+                let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+                let inside = qx.max(qy).min(0.0);
+                outside + inside
+            }
+        }
+    }
+
+    /// Keep a ship inside the world boundary, zeroing the outward velocity
+    /// component on any edge it hits. Returns (x, y, vel_x, vel_y)
+    pub fn clamp_to_bounds(
+        x: f32,
+        y: f32,
+        vel_x: f32,
+        vel_y: f32,
+        bounds: &WorldBounds,
+    ) -> (f32, f32, f32, f32) {
+        let (x, vel_x) = clamp_axis(x, vel_x, bounds.half_width);
+        let (y, vel_y) = clamp_axis(y, vel_y, bounds.half_height);
+        (x, y, vel_x, vel_y)
+    }
+
+    /// Push a ship out of an obstacle it overlaps, zeroing the velocity
+    /// component into the obstacle. Returns (x, y, vel_x, vel_y)
+    pub fn push_out_of_obstacle(
+        x: f32,
+        y: f32,
+        vel_x: f32,
+        vel_y: f32,
+        radius: f32,
+        obstacle: &Obstacle,
+    ) -> (f32, f32, f32, f32) {
+        let dx = x - obstacle.x;
+        let dy = y - obstacle.y;
+        let dist = dx.hypot(dy);
+        let min_dist = obstacle.radius + radius;
+        if dist >= min_dist {
+            return (x, y, vel_x, vel_y);
+        }
+
+        // A ship dead on the center is pushed out along +x
+        let (nx, ny) = if dist < 0.001 { (1.0, 0.0) } else { (dx / dist, dy / dist) };
+        let inward = (vel_x * nx + vel_y * ny).min(0.0);
+        (
+            obstacle.x + nx * min_dist,
+            obstacle.y + ny * min_dist,
+            vel_x - inward * nx,
+            vel_y - inward * ny,
+        )
     }
 
     /// Check collision between two ships
@@ -185,3 +357,199 @@ impl PhysicsSystem {
         ((new_x1, new_y1), (new_x2, new_y2))
     }
 }
+
+/// Clamp one axis to `[-half_extent, half_extent]`, stopping outward motion at the edge
+fn clamp_axis(pos: f32, vel: f32, half_extent: f32) -> (f32, f32) {
+    if pos >= half_extent {
+        (half_extent, vel.min(0.0))
+    } else if pos <= -half_extent {
+        (-half_extent, vel.max(0.0))
+    } else {
+        (pos, vel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::time::SIMULATION_TPS;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    const DT: f32 = 1.0 / SIMULATION_TPS as f32;
+
+    fn throttle(throttle: f32, strafe: f32) -> TickInput {
+        TickInput {
+            throttle,
+            strafe,
+            ..TickInput::default()
+        }
+    }
+
+    fn at_rest(x: f32, y: f32, rotation: f32) -> ShipMotion {
+        ShipMotion {
+            x,
+            y,
+            rotation,
+            vel_x: 0.0,
+            vel_y: 0.0,
+        }
+    }
+
+    /// Step a ship at full throttle like the match does: integrate, then
+    /// clamp to the bounds
+    fn step_in_bounds(motion: ShipMotion, stats: &ShipStats, bounds: &WorldBounds) -> ShipMotion {
+        let moved = PhysicsSystem::update_ship(&motion, &throttle(1.0, 0.0), stats, DT);
+        let (x, y, vel_x, vel_y) =
+            PhysicsSystem::clamp_to_bounds(moved.x, moved.y, moved.vel_x, moved.vel_y, bounds);
+        ShipMotion { x, y, vel_x, vel_y, ..moved }
+    }
+
+    #[test]
+    fn ship_thrusting_outward_never_leaves_bounds() {
+        let bounds = WorldBounds {
+            half_width: 500.0,
+            half_height: 300.0,
+        };
+        let stats = ShipStats::for_type(ShipType::Scout).boosted();
+        let headings = [
+            0.0,
+            FRAC_PI_4,
+            FRAC_PI_2,
+            PI,
+            3.0 * FRAC_PI_2,
+            7.0 * FRAC_PI_4,
+        ];
+
+        for rotation in headings {
+            let mut motion = at_rest(0.0, 0.0, rotation);
+            for _ in 0..10 * SIMULATION_TPS {
+                motion = step_in_bounds(motion, &stats, &bounds);
+                let ShipMotion { x, y, .. } = motion;
+                assert!(
+                    x.abs() <= bounds.half_width && y.abs() <= bounds.half_height,
+                    "heading {rotation} left the bounds at ({x}, {y})"
+                );
+            }
+
+            // Pinned against the edge with no outward velocity left
+            let ShipMotion { x, y, vel_x, vel_y, .. } = motion;
+            let (dir_x, dir_y) = (rotation.cos(), rotation.sin());
+            if dir_x.abs() > 0.1 {
+                assert_eq!(x.abs(), bounds.half_width);
+                assert!(vel_x * dir_x <= 0.0);
+            }
+            if dir_y.abs() > 0.1 {
+                assert_eq!(y.abs(), bounds.half_height);
+                assert!(vel_y * dir_y <= 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn ships_slide_around_obstacles_without_entering() {
+        let obstacle = Obstacle {
+            x: 200.0,
+            y: 10.0,
+            radius: 60.0,
+        };
+        let stats = ShipStats::for_type(ShipType::Fighter);
+        let (mut x, mut y, mut vel_x, mut vel_y) = (0.0, 0.0, 0.0, 0.0);
+        for _ in 0..300 {
+            let motion = ShipMotion { x, y, rotation: 0.0, vel_x, vel_y };
+            let moved = PhysicsSystem::update_ship(&motion, &throttle(1.0, 0.0), &stats, DT);
+            (x, y, vel_x, vel_y) = PhysicsSystem::push_out_of_obstacle(
+                moved.x,
+                moved.y,
+                moved.vel_x,
+                moved.vel_y,
+                stats.hitbox_radius,
+                &obstacle,
+            );
+            assert!(obstacle.distance(x, y) >= stats.hitbox_radius - 1e-3);
+        }
+        // Deflected past it rather than stuck on its face
+        assert!(x > obstacle.x, "stuck at {x}, {y}");
+    }
+
+    #[test]
+    fn only_omnidirectional_hulls_strafe() {
+        // Facing +x, so strafing right pushes toward +y
+        let strafe_right = |stats: &ShipStats| {
+            PhysicsSystem::update_ship(&at_rest(0.0, 0.0, 0.0), &throttle(0.0, 1.0), stats, DT)
+        };
+
+        let fighter = ShipStats::for_type(ShipType::Fighter);
+        assert_eq!(strafe_right(&fighter), at_rest(0.0, 0.0, 0.0));
+
+        let corvette = ShipStats::for_type(ShipType::Corvette);
+        let ShipMotion { x, y, rotation, vel_x, vel_y } = strafe_right(&corvette);
+        assert_eq!(rotation, 0.0);
+        assert!(vel_y > 0.0 && y > 0.0);
+        assert!(vel_x.abs() < 1e-3 && x.abs() < 1e-3);
+    }
+
+    #[test]
+    fn reverse_thrust_scales_with_the_hull() {
+        let mut stats = ShipStats::for_type(ShipType::Fighter);
+        let reverse = |stats: &ShipStats| {
+            let rest = at_rest(0.0, 0.0, 0.0);
+            PhysicsSystem::update_ship(&rest, &throttle(-1.0, 0.0), stats, DT).vel_x
+        };
+        let half = reverse(&stats);
+        stats.reverse_multiplier = 1.0;
+        assert!(half < 0.0);
+        assert!((reverse(&stats) - half * 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn prediction_error_measures_what_the_boundary_took_away() {
+        let bounds = WorldBounds {
+            half_width: 100.0,
+            half_height: 100.0,
+        };
+        let stats = ShipStats::for_type(ShipType::Fighter);
+        let input = TickInput {
+            throttle: 1.0,
+            ..TickInput::default()
+        };
+        let start = ShipMotion {
+            x: 99.0,
+            y: 0.0,
+            rotation: 0.0,
+            vel_x: 120.0,
+            vel_y: 0.0,
+        };
+
+        let predicted = PhysicsSystem::predict(&start, &input, &stats, DT);
+        let actual = step_in_bounds(start, &stats, &bounds);
+
+        assert_eq!(PhysicsSystem::prediction_error(&predicted, &predicted), 0.0);
+        assert_eq!(actual.x, 100.0);
+        let error = PhysicsSystem::prediction_error(&predicted, &actual);
+        assert!((error - (predicted.x - 100.0)).abs() < 1e-4, "error {error}");
+
+        // Only distance counts, in any direction
+        let sideways = ShipMotion {
+            x: predicted.x + 4.0,
+            y: predicted.y - 3.0,
+            ..predicted
+        };
+        assert!((PhysicsSystem::prediction_error(&predicted, &sideways) - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamp_keeps_inward_velocity_at_edge() {
+        let bounds = WorldBounds {
+            half_width: 100.0,
+            half_height: 100.0,
+        };
+        let (x, y, vel_x, vel_y) =
+            PhysicsSystem::clamp_to_bounds(120.0, -130.0, -5.0, 7.0, &bounds);
+        assert_eq!((x, y), (100.0, -100.0));
+        assert_eq!((vel_x, vel_y), (-5.0, 7.0));
+
+        let (_, _, vel_x, vel_y) =
+            PhysicsSystem::clamp_to_bounds(120.0, -130.0, 5.0, -7.0, &bounds);
+        assert_eq!((vel_x, vel_y), (0.0, 0.0));
+    }
+}
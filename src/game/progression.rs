@@ -0,0 +1,138 @@
+//! Experience and levels earned from match results
+//!
+//! XP is awarded once per finished match and accumulates for life. Levels are
+//! derived from total XP rather than stored, so the curve can be retuned
+//! without migrating anyone's progress.
+
+use crate::ws::protocol::PlayerMatchStats;
+
+/// XP for finishing a match at all
+const PARTICIPATION_XP: u32 = 20;
+
+/// XP per kill
+const KILL_XP: u32 = 10;
+
+/// Damage dealt per point of XP
+const DAMAGE_PER_XP: f32 = 10.0;
+
+/// Bonus XP for 1st, 2nd and 3rd place
+const PODIUM_XP: [u32; 3] = [100, 60, 40];
+
+/// Bonus XP for placing in the top half outside the podium
+const TOP_HALF_XP: u32 = 20;
+
+/// XP needed to go from level 1 to level 2. Each further level costs this
+/// much more than the one before
+const LEVEL_STEP_XP: u64 = 100;
+
+/// XP earned for a match result. Bots, guests and players who abandoned
+/// earn nothing
+pub fn match_xp(stats: &PlayerMatchStats, total_players: u32) -> u32 {
+    if stats.is_bot || stats.is_guest || stats.abandoned {
+        return 0;
+    }
+
+    let placement_bonus = match stats.placement {
+        1..=3 => PODIUM_XP[stats.placement as usize - 1],
+        p if p <= total_players.div_ceil(2) => TOP_HALF_XP,
+        _ => 0,
+    };
+    let damage_xp = (stats.damage_dealt.max(0.0) / DAMAGE_PER_XP) as u32;
+
+    PARTICIPATION_XP + placement_bonus + stats.kills * KILL_XP + damage_xp
+}
+
+/// Total XP at which `level` is reached. Going from level `n` to `n + 1`
+/// costs `LEVEL_STEP_XP * n`, so the threshold is `50 * level * (level - 1)`:
+/// level 1 at 0 XP, level 2 at 100, level 3 at 300, level 4 at 600
+pub fn xp_for_level(level: u32) -> u64 {
+    let level = level.max(1) as u64;
+    LEVEL_STEP_XP * level * (level - 1) / 2
+}
+
+/// Level reached with `total_xp`, starting from level 1
+pub fn level_for_xp(total_xp: u64) -> u32 {
+    // Invert the threshold, then correct for float rounding either way
+    let estimate = ((1.0 + (1.0 + 8.0 * total_xp as f64 / LEVEL_STEP_XP as f64).sqrt()) / 2.0)
+        .floor() as u32;
+    let mut level = estimate.max(1);
+    while level > 1 && xp_for_level(level) > total_xp {
+        level -= 1;
+    }
+    while xp_for_level(level + 1) <= total_xp {
+        level += 1;
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::protocol::ShipType;
+    use uuid::Uuid;
+
+    fn stats(placement: u32, kills: u32, damage_dealt: f32) -> PlayerMatchStats {
+        PlayerMatchStats {
+            user_id: Uuid::from_u128(1),
+            is_bot: false,
+            is_guest: false,
+            ship_type: ShipType::Fighter,
+            kills,
+            best_streak: kills,
+            damage_dealt,
+            damage_taken: 0.0,
+            shots_fired: 0,
+            shots_hit: 0,
+            placement,
+            alive_time_secs: 60,
+            abandoned: false,
+            xp_gained: 0,
+            new_level: None,
+        }
+    }
+
+    #[test]
+    fn level_thresholds_follow_the_curve() {
+        assert_eq!(xp_for_level(0), 0);
+        assert_eq!(xp_for_level(1), 0);
+        assert_eq!(xp_for_level(2), 100);
+        assert_eq!(xp_for_level(3), 300);
+        assert_eq!(xp_for_level(10), 4500);
+    }
+
+    #[test]
+    fn levels_change_exactly_at_thresholds() {
+        assert_eq!(level_for_xp(0), 1);
+        for level in 2..=200 {
+            let threshold = xp_for_level(level);
+            assert_eq!(level_for_xp(threshold - 1), level - 1);
+            assert_eq!(level_for_xp(threshold), level);
+        }
+    }
+
+    #[test]
+    fn huge_totals_do_not_overflow() {
+        let level = level_for_xp(u64::MAX / 1000);
+        assert!(xp_for_level(level) <= u64::MAX / 1000);
+        assert!(xp_for_level(level + 1) > u64::MAX / 1000);
+    }
+
+    #[test]
+    fn xp_counts_placement_kills_and_damage() {
+        // 20 participation + 100 for 1st + 3 kills + 250 damage
+        assert_eq!(match_xp(&stats(1, 3, 250.0), 8), 20 + 100 + 30 + 25);
+        // Top half of eight, no kills
+        assert_eq!(match_xp(&stats(4, 0, 0.0), 8), 40);
+        assert_eq!(match_xp(&stats(5, 0, 0.0), 8), 20);
+    }
+
+    #[test]
+    fn bots_guests_and_abandons_earn_nothing() {
+        let bot = PlayerMatchStats { is_bot: true, ..stats(1, 5, 500.0) };
+        let guest = PlayerMatchStats { is_guest: true, ..stats(1, 5, 500.0) };
+        let quitter = PlayerMatchStats { abandoned: true, ..stats(1, 5, 500.0) };
+        assert_eq!(match_xp(&bot, 4), 0);
+        assert_eq!(match_xp(&guest, 4), 0);
+        assert_eq!(match_xp(&quitter, 4), 0);
+    }
+}
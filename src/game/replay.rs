@@ -0,0 +1,348 @@
+//! Deterministic match recordings
+//!
+//! A match's outcome depends only on its seed, its settings and the inputs
+//! it processed on each tick, so recording those is enough to re-run it
+//! offline. Recordings are gzipped JSON lines: a header, one line per input
+//! in processing order, and an end line with the tick count and final stats.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::ws::protocol::{ClientMsg, MatchStats};
+
+use super::arena::Arena;
+use super::balance::BalanceTable;
+use super::r#match::MatchSettings;
+use super::{Cosmetics, PlayerInput};
+
+/// Bumped whenever a change to the simulation would alter replayed outcomes
+pub const REPLAY_VERSION: u32 = 29;
+
+/// Settings that affect the simulation (network-only settings are left out)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySettings {
+    pub min_players: usize,
+    pub max_players: usize,
+    pub warmup: f32,
+    pub countdown: f32,
+    pub bot_fill_delay: Option<f32>,
+    pub tick_rate: u32,
+    pub max_pause: f32,
+    pub arena: Arena,
+    pub zone_bypasses_shield: bool,
+    pub self_damage: bool,
+    pub projectile_clash: bool,
+    pub late_join: bool,
+    pub spawn_protection: f32,
+    pub spawn_separation: f32,
+    pub max_projectiles: usize,
+    pub max_projectiles_per_player: usize,
+    pub balance: BalanceTable,
+    pub max_duration: Option<f32>,
+    pub sudden_death: f32,
+    pub input_timeout: f32,
+    pub practice: bool,
+    pub aim_assist_cone: f32,
+    pub aim_assist_strength: f32,
+    pub private: bool,
+}
+
+impl ReplaySettings {
+    pub fn from_settings(settings: &MatchSettings) -> Self {
+        Self {
+            min_players: settings.min_players,
+            max_players: settings.max_players,
+            warmup: settings.warmup,
+            countdown: settings.countdown,
+            bot_fill_delay: settings.bot_fill_delay,
+            tick_rate: settings.tick_rate,
+            max_pause: settings.max_pause,
+            arena: Arena::clone(&settings.arena),
+            zone_bypasses_shield: settings.zone_bypasses_shield,
+            self_damage: settings.self_damage,
+            projectile_clash: settings.projectile_clash,
+            late_join: settings.late_join,
+            spawn_protection: settings.spawn_protection,
+            spawn_separation: settings.spawn_separation,
+            max_projectiles: settings.max_projectiles,
+            max_projectiles_per_player: settings.max_projectiles_per_player,
+            balance: BalanceTable::clone(&settings.balance),
+            max_duration: settings.max_duration,
+            sudden_death: settings.sudden_death,
+            input_timeout: settings.input_timeout,
+            practice: settings.practice,
+            aim_assist_cone: settings.aim_assist_cone,
+            aim_assist_strength: settings.aim_assist_strength,
+            private: settings.private,
+        }
+    }
+
+    /// Match settings to re-run the recording with
+    pub fn to_settings(&self) -> MatchSettings {
+        MatchSettings {
+            min_players: self.min_players,
+            max_players: self.max_players,
+            warmup: self.warmup,
+            countdown: self.countdown,
+            bot_fill_delay: self.bot_fill_delay,
+            tick_rate: self.tick_rate,
+            max_pause: self.max_pause,
+            arena: Arc::new(self.arena.clone()),
+            zone_bypasses_shield: self.zone_bypasses_shield,
+            self_damage: self.self_damage,
+            projectile_clash: self.projectile_clash,
+            late_join: self.late_join,
+            spawn_protection: self.spawn_protection,
+            spawn_separation: self.spawn_separation,
+            max_projectiles: self.max_projectiles,
+            max_projectiles_per_player: self.max_projectiles_per_player,
+            balance: Arc::new(self.balance.clone()),
+            max_duration: self.max_duration,
+            sudden_death: self.sudden_death,
+            input_timeout: self.input_timeout,
+            practice: self.practice,
+            aim_assist_cone: self.aim_assist_cone,
+            aim_assist_strength: self.aim_assist_strength,
+            private: self.private,
+            ..MatchSettings::default()
+        }
+    }
+}
+
+/// Everything needed to recreate the match before any input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub match_id: Uuid,
+    pub seed: u64,
+    pub settings: ReplaySettings,
+}
+
+/// An input as the match processed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// Match tick the input was applied before
+    pub tick: u64,
+    pub user_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub guest: bool,
+    pub msg: ClientMsg,
+}
+
+impl RecordedInput {
+    pub fn to_player_input(&self) -> PlayerInput {
+        PlayerInput {
+            user_id: self.user_id,
+            msg: self.msg.clone(),
+            received_at: 0,
+            display_name: self.display_name.clone(),
+            latency: None,
+            guest: self.guest,
+            cosmetics: Cosmetics::default(),
+        }
+    }
+}
+
+/// One line of a recording
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ReplayLine {
+    Header(Box<ReplayHeader>),
+    Input(RecordedInput),
+    End { tick: u64, stats: MatchStats },
+}
+
+/// Replay errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Replay I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid replay line {0}: {1}")]
+    Parse(usize, serde_json::Error),
+
+    #[error("Invalid replay: {0}")]
+    Invalid(String),
+}
+
+/// A match recording loaded from disk
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub inputs: Vec<RecordedInput>,
+    /// Ticks the match ran for
+    pub end_tick: u64,
+    /// Stats the live match ended with
+    pub stats: MatchStats,
+}
+
+impl Replay {
+    /// Load a recording written by `ReplayRecorder`
+    pub fn from_file(path: &Path) -> Result<Self, ReplayError> {
+        let reader = BufReader::new(GzDecoder::new(std::fs::File::open(path)?));
+
+        let mut header = None;
+        let mut inputs = Vec::new();
+        let mut end = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            match serde_json::from_str(&line).map_err(|e| ReplayError::Parse(i + 1, e))? {
+                ReplayLine::Header(h) => header = Some(*h),
+                ReplayLine::Input(input) => inputs.push(input),
+                ReplayLine::End { tick, stats } => end = Some((tick, stats)),
+            }
+        }
+
+        let header = header.ok_or_else(|| ReplayError::Invalid("missing header".to_string()))?;
+        if header.version != REPLAY_VERSION {
+            return Err(ReplayError::Invalid(format!(
+                "recorded with version {}, this server replays version {}",
+                header.version, REPLAY_VERSION
+            )));
+        }
+        let (end_tick, stats) =
+            end.ok_or_else(|| ReplayError::Invalid("missing end of match".to_string()))?;
+
+        Ok(Self {
+            header,
+            inputs,
+            end_tick,
+            stats,
+        })
+    }
+}
+
+/// Records a live match's inputs, compressing as it goes
+pub struct ReplayRecorder {
+    dir: PathBuf,
+    match_id: Uuid,
+    encoder: GzEncoder<Vec<u8>>,
+}
+
+impl ReplayRecorder {
+    pub fn new(dir: PathBuf, match_id: Uuid, seed: u64, settings: &MatchSettings) -> Self {
+        let mut recorder = Self {
+            dir,
+            match_id,
+            encoder: GzEncoder::new(Vec::new(), Compression::fast()),
+        };
+        recorder.write(&ReplayLine::Header(Box::new(ReplayHeader {
+            version: REPLAY_VERSION,
+            match_id,
+            seed,
+            settings: ReplaySettings::from_settings(settings),
+        })));
+        recorder
+    }
+
+    /// Record an input about to be applied before `tick`. Pings are skipped
+    /// since they never touch match state
+    pub fn record(&mut self, tick: u64, input: &PlayerInput) {
+        if matches!(input.msg, ClientMsg::Ping { .. }) {
+            return;
+        }
+
+        self.write(&ReplayLine::Input(RecordedInput {
+            tick,
+            user_id: input.user_id,
+            display_name: input.display_name.clone(),
+            guest: input.guest,
+            msg: input.msg.clone(),
+        }));
+    }
+
+    /// Write the recording to `<dir>/<match_id>.jsonl.gz`
+    pub async fn finish(mut self, tick: u64, stats: &MatchStats) -> Result<PathBuf, ReplayError> {
+        self.write(&ReplayLine::End {
+            tick,
+            stats: stats.clone(),
+        });
+        let data = self.encoder.finish()?;
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{}.jsonl.gz", self.match_id));
+        tokio::fs::write(&path, data).await?;
+        Ok(path)
+    }
+
+    fn write(&mut self, line: &ReplayLine) {
+        // Writing into memory only fails if serialization does
+        let result = serde_json::to_writer(&mut self.encoder, line)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.encoder.write_all(b"\n"));
+        if let Err(e) = result {
+            warn!(match_id = %self.match_id, error = %e, "Failed to record replay line");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_lines(lines: &[ReplayLine]) -> PathBuf {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        for line in lines {
+            serde_json::to_writer(&mut encoder, line).unwrap();
+            encoder.write_all(b"\n").unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("{}.jsonl.gz", Uuid::new_v4()));
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+        path
+    }
+
+    fn header(version: u32) -> ReplayLine {
+        ReplayLine::Header(Box::new(ReplayHeader {
+            version,
+            match_id: Uuid::new_v4(),
+            seed: 1,
+            settings: ReplaySettings::from_settings(&MatchSettings::default()),
+        }))
+    }
+
+    fn end() -> ReplayLine {
+        ReplayLine::End {
+            tick: 10,
+            stats: MatchStats {
+                duration_secs: 0,
+                total_players: 0,
+                player_stats: Vec::new(),
+            },
+        }
+    }
+
+    fn load(lines: &[ReplayLine]) -> Result<Replay, ReplayError> {
+        let path = write_lines(lines);
+        let result = Replay::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn loads_complete_recording() {
+        let replay = load(&[header(REPLAY_VERSION), end()]).unwrap();
+        assert_eq!(replay.end_tick, 10);
+        assert!(replay.inputs.is_empty());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let result = load(&[header(REPLAY_VERSION + 1), end()]);
+        assert!(matches!(result, Err(ReplayError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_recording_without_end() {
+        let result = load(&[header(REPLAY_VERSION)]);
+        assert!(matches!(result, Err(ReplayError::Invalid(_))));
+    }
+}
@@ -1,18 +1,31 @@
 //! Snapshot building and compression
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
-use crate::ws::protocol::{GameEvent, PlayerSnapshot, ServerMsg, ZoneState};
+use crate::ws::protocol::{
+    GameEvent, PlayerSnapshot, ProjectileSnapshot, ServerMsg, SnapshotPlayers,
+    SnapshotPrecision, ZoneState,
+};
 
+/// Shots are culled with a wider radius so incoming fire is visible before it arrives
+const SHOT_VIEW_MARGIN: f32 = 1.5;
+
+use super::combat::Projectile;
+use super::effects;
+use super::r#match::RepairPadState;
 use super::PlayerState;
 
 /// Builds snapshots for network transmission
 pub struct SnapshotBuilder {
     /// Tick counter since last snapshot
-    ticks_since_snapshot: u32,
-    /// Snapshot interval in ticks
-    snapshot_interval: u32,
+    ticks_since_snapshot: f32,
+    /// Snapshot interval in ticks (fractional when the rates don't divide evenly)
+    snapshot_interval: f32,
+    /// Wire encoding for player state
+    precision: SnapshotPrecision,
+    /// Include live projectiles
+    projectiles: bool,
     /// Last snapshot for delta calculation (future use)
     _last_snapshot: Option<SnapshotData>,
 }
@@ -24,19 +37,31 @@ struct SnapshotData {
 }
 
 impl SnapshotBuilder {
-    pub fn new(snapshot_interval: u32) -> Self {
+    /// Builder sending `snapshot_rate` snapshots per second from a `tick_rate` simulation
+    pub fn new(tick_rate: u32, snapshot_rate: u32, precision: SnapshotPrecision) -> Self {
+        let snapshot_interval = (tick_rate as f32 / snapshot_rate.max(1) as f32).max(1.0);
         Self {
-            ticks_since_snapshot: 0,
+            ticks_since_snapshot: 0.0,
             snapshot_interval,
+            precision,
+            projectiles: false,
             _last_snapshot: None,
         }
     }
 
+    /// Include live projectiles in each snapshot
+    pub fn with_projectiles(mut self, projectiles: bool) -> Self {
+        self.projectiles = projectiles;
+        self
+    }
+
     /// Check if it's time to send a snapshot
     pub fn should_send(&mut self) -> bool {
-        self.ticks_since_snapshot += 1;
+        // Carry the remainder so the average cadence matches the requested rate
+        self.ticks_since_snapshot += 1.0;
         if self.ticks_since_snapshot >= self.snapshot_interval {
-            self.ticks_since_snapshot = 0;
+            self.ticks_since_snapshot =
+                (self.ticks_since_snapshot - self.snapshot_interval).min(self.snapshot_interval);
             true
         } else {
             false
@@ -48,12 +73,16 @@ impl SnapshotBuilder {
         self.ticks_since_snapshot = self.snapshot_interval;
     }
 
-    /// Build a snapshot message
+    /// Build a snapshot message; `now` is the match clock (ms)
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         &mut self,
         tick: u64,
+        now: u64,
         zone: &ZoneState,
-        players: &HashMap<Uuid, PlayerState>,
+        players: &BTreeMap<Uuid, PlayerState>,
+        projectiles: &[Projectile],
+        repair_pads: &[RepairPadState],
         events: Vec<GameEvent>,
     ) -> ServerMsg {
         let player_snapshots: Vec<PlayerSnapshot> = players
@@ -66,9 +95,13 @@ impl SnapshotBuilder {
                 vel_x: p.vel_x,
                 vel_y: p.vel_y,
                 health: p.health,
+                shield: p.shield,
                 alive: p.alive,
-                last_input_seq: p.last_input_seq,
+                last_input_seq: p.current_input.seq,
                 weapon_cooldown: p.weapon_cooldown,
+                boost_energy: p.boost_energy,
+                effects: effects::mask(&p.status_effects),
+                spawn_protected: p.is_spawn_protected(now),
             })
             .collect();
 
@@ -78,11 +111,40 @@ impl SnapshotBuilder {
             players: player_snapshots.clone(),
         });
 
+        let players = match self.precision {
+            SnapshotPrecision::Full => SnapshotPlayers::Full(player_snapshots),
+            SnapshotPrecision::Quantized { position_scale } => SnapshotPlayers::Quantized(
+                player_snapshots
+                    .iter()
+                    .map(|p| p.quantize(position_scale))
+                    .collect(),
+            ),
+        };
+
+        let projectiles = if self.projectiles {
+            projectiles
+                .iter()
+                .map(|p| ProjectileSnapshot {
+                    id: p.id,
+                    x: p.x,
+                    y: p.y,
+                    vel_x: p.vel_x,
+                    vel_y: p.vel_y,
+                    radius: p.radius,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         ServerMsg::Snapshot {
             tick,
             zone: zone.clone(),
-            players: player_snapshots,
+            players,
             events,
+            precision: self.precision,
+            projectiles,
+            repair_pads: repair_pads.iter().map(|pad| pad.status(now)).collect(),
         }
     }
 
@@ -92,7 +154,7 @@ impl SnapshotBuilder {
         &self,
         _tick: u64,
         _zone: &ZoneState,
-        _players: &HashMap<Uuid, PlayerState>,
+        _players: &BTreeMap<Uuid, PlayerState>,
         _events: Vec<GameEvent>,
     ) -> ServerMsg {
         // TODO: Implement delta compression
@@ -101,6 +163,138 @@ impl SnapshotBuilder {
     }
 }
 
+/// How a match's messages fan out to its players. Both send the same
+/// messages; per-player snapshots just list fewer ships
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SnapshotMode {
+    /// Every player is forwarded the shared message as is: no per-player
+    /// work, but everyone receives every ship
+    #[default]
+    Broadcast,
+    /// Each player's forwarder culls snapshots to `view_radius` around them:
+    /// a copy and filter per player per snapshot, in exchange for smaller
+    /// messages
+    PerPlayer { view_radius: f32 },
+}
+
+impl SnapshotMode {
+    /// The message to send `viewer` for a match broadcast
+    pub fn for_viewer(&self, msg: ServerMsg, viewer: Uuid) -> ServerMsg {
+        match self {
+            SnapshotMode::Broadcast => msg,
+            SnapshotMode::PerPlayer { view_radius } => cull_for_viewer(&msg, viewer, *view_radius),
+        }
+    }
+}
+
+/// Cull a snapshot down to what `viewer` can see: ships within `view_radius`
+/// (always including the viewer) and nearby shots and projectiles. The zone and all other
+/// messages pass through unchanged, as do snapshots the viewer isn't in.
+pub fn cull_for_viewer(msg: &ServerMsg, viewer: Uuid, view_radius: f32) -> ServerMsg {
+    let ServerMsg::Snapshot {
+        tick,
+        zone,
+        players,
+        events,
+        precision,
+        projectiles,
+        repair_pads,
+    } = msg
+    else {
+        return msg.clone();
+    };
+
+    let scale = match precision {
+        SnapshotPrecision::Full => 1.0,
+        SnapshotPrecision::Quantized { position_scale } => *position_scale as f64,
+    };
+    let radius = view_radius as f64 * scale;
+
+    let (players, viewer_x, viewer_y) = match players {
+        SnapshotPlayers::Full(list) => match viewer_position(list, viewer) {
+            Some((x, y)) => (
+                SnapshotPlayers::Full(visible_players(list, viewer, x, y, radius)),
+                x,
+                y,
+            ),
+            None => return msg.clone(),
+        },
+        SnapshotPlayers::Quantized(list) => match viewer_position(list, viewer) {
+            Some((x, y)) => (
+                SnapshotPlayers::Quantized(visible_players(list, viewer, x, y, radius)),
+                x,
+                y,
+            ),
+            None => return msg.clone(),
+        },
+    };
+    let (viewer_x, viewer_y) = ((viewer_x / scale) as f32, (viewer_y / scale) as f32);
+
+    let shot_radius_sq = (view_radius * SHOT_VIEW_MARGIN).powi(2);
+    let in_shot_range =
+        |x: f32, y: f32| (x - viewer_x).powi(2) + (y - viewer_y).powi(2) <= shot_radius_sq;
+    let events = events
+        .iter()
+        .filter(|event| match event {
+            GameEvent::Shot { x, y, .. } | GameEvent::ProjectileClash { x, y, .. } => {
+                in_shot_range(*x, *y)
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect();
+    let projectiles = projectiles
+        .iter()
+        .filter(|p| in_shot_range(p.x, p.y))
+        .cloned()
+        .collect();
+
+    ServerMsg::Snapshot {
+        tick: *tick,
+        zone: zone.clone(),
+        players,
+        events,
+        precision: *precision,
+        projectiles,
+        repair_pads: repair_pads.clone(),
+    }
+}
+
+/// Position of the viewer in snapshot units
+fn viewer_position<P: Copy + Into<f64>, R>(
+    players: &[PlayerSnapshot<P, R>],
+    viewer: Uuid,
+) -> Option<(f64, f64)> {
+    players
+        .iter()
+        .find(|p| p.user_id == viewer)
+        .map(|p| (p.x.into(), p.y.into()))
+}
+
+/// Players within `radius` of (`x`, `y`), always including the viewer
+fn visible_players<P, R>(
+    players: &[PlayerSnapshot<P, R>],
+    viewer: Uuid,
+    x: f64,
+    y: f64,
+    radius: f64,
+) -> Vec<PlayerSnapshot<P, R>>
+where
+    P: Copy + Into<f64>,
+    PlayerSnapshot<P, R>: Clone,
+{
+    let radius_sq = radius * radius;
+    players
+        .iter()
+        .filter(|p| {
+            let dx = p.x.into() - x;
+            let dy = p.y.into() - y;
+            p.user_id == viewer || dx * dx + dy * dy <= radius_sq
+        })
+        .cloned()
+        .collect()
+}
+
 /// Snapshot compression stats for debugging
 #[derive(Debug, Default)]
 pub struct SnapshotStats {
@@ -120,3 +314,176 @@ impl SnapshotStats {
             self.avg_players_per_snapshot * ((n - 1.0) / n) + (player_count as f32 / n);
     }
 }
+
+/// A full lobby's snapshot for benchmarks: 20 ships spread evenly over the
+/// initial zone, four of them firing
+#[cfg(test)]
+pub(crate) fn full_lobby_snapshot() -> ServerMsg {
+    use super::balance::BalanceTable;
+    use super::r#match::MatchState;
+    use super::{Cosmetics, MatchSettings};
+    use crate::ws::protocol::ShipType;
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(7);
+    let balance = BalanceTable::default();
+    let mut state = MatchState::new(Uuid::from_u128(1), 7, MatchSettings::default());
+    for n in 0..20 {
+        let id = Uuid::from_u128(rng.gen());
+        let distance = state.zone.radius * rng.gen::<f32>().sqrt();
+        let bearing = rng.gen_range(0.0..std::f32::consts::TAU);
+        let mut ship = PlayerState::new(
+            id,
+            format!("Pilot {n}"),
+            ShipType::Fighter,
+            Cosmetics::default(),
+            state.zone.center_x + distance * bearing.cos(),
+            state.zone.center_y + distance * bearing.sin(),
+            rng.gen_range(0.0..std::f32::consts::TAU),
+            &balance,
+        );
+        (ship.vel_x, ship.vel_y) = (rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+        ship.health = rng.gen_range(1.0..ship.health);
+        ship.boost_energy = rng.gen_range(0.0..100.0);
+        state.players.insert(id, ship);
+    }
+    let events = state
+        .players
+        .values()
+        .take(4)
+        .map(|p| GameEvent::Shot {
+            shooter_id: p.user_id,
+            projectile_id: Uuid::from_u128(rng.gen()),
+            x: p.x,
+            y: p.y,
+            direction: p.rotation,
+            speed: 600.0,
+            assisted_yaw: None,
+        })
+        .collect();
+
+    let mut builder = SnapshotBuilder::new(30, 20, SnapshotPrecision::Full);
+    builder.build(1, 0, &state.zone, &state.players, &[], &[], events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::balance::BalanceTable;
+    use crate::game::r#match::MatchState;
+    use crate::game::{Cosmetics, MatchSettings};
+    use crate::ws::protocol::{ShipType, WeaponLoadout};
+
+    #[test]
+    fn projectiles_are_opt_in_and_culled_like_shots() {
+        let balance = BalanceTable::default();
+        let mut state = MatchState::new(Uuid::from_u128(1), 7, MatchSettings::default());
+        let viewer = Uuid::from_u128(2);
+        state.players.insert(
+            viewer,
+            PlayerState::new(
+                viewer,
+                "Pilot".to_string(),
+                ShipType::Fighter,
+                Cosmetics::default(),
+                0.0,
+                0.0,
+                0.0,
+                &balance,
+            ),
+        );
+        let stats = balance.weapon(WeaponLoadout::Pulse);
+        let near = Projectile::new(viewer, 100.0, 0.0, 0.0, (0.0, 0.0), &stats);
+        let far = Projectile::new(viewer, 5000.0, 0.0, 0.0, (0.0, 0.0), &stats);
+        let projectiles = [near.clone(), far];
+
+        let mut builder = SnapshotBuilder::new(30, 20, SnapshotPrecision::Full);
+        let msg = builder.build(1, 0, &state.zone, &state.players, &projectiles, &[], Vec::new());
+        assert!(matches!(&msg, ServerMsg::Snapshot { projectiles, .. } if projectiles.is_empty()));
+
+        let mut builder = builder.with_projectiles(true);
+        let msg = builder.build(2, 0, &state.zone, &state.players, &projectiles, &[], Vec::new());
+        let ServerMsg::Snapshot { projectiles, .. } = cull_for_viewer(&msg, viewer, 800.0) else {
+            panic!("expected a snapshot");
+        };
+        assert_eq!(projectiles.len(), 1);
+        assert_eq!(projectiles[0].id, near.id);
+    }
+
+    #[test]
+    fn both_modes_send_the_same_message_shape() {
+        let balance = BalanceTable::default();
+        let mut state = MatchState::new(Uuid::from_u128(1), 7, MatchSettings::default());
+        for (n, x) in [(2, 0.0), (3, 100.0), (4, 5000.0)] {
+            let id = Uuid::from_u128(n);
+            let ship = PlayerState::new(
+                id,
+                format!("Pilot {n}"),
+                ShipType::Fighter,
+                Cosmetics::default(),
+                x,
+                0.0,
+                0.0,
+                &balance,
+            );
+            state.players.insert(id, ship);
+        }
+        let mut builder = SnapshotBuilder::new(30, 20, SnapshotPrecision::Full);
+        let msg = builder.build(1, 0, &state.zone, &state.players, &[], &[], Vec::new());
+
+        let viewer = Uuid::from_u128(2);
+        let broadcast = SnapshotMode::Broadcast.for_viewer(msg.clone(), viewer);
+        let culled = SnapshotMode::PerPlayer { view_radius: 800.0 }.for_viewer(msg, viewer);
+        let (broadcast, culled) = (
+            serde_json::to_value(&broadcast).unwrap(),
+            serde_json::to_value(&culled).unwrap(),
+        );
+
+        let keys = |v: &serde_json::Value| v.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&broadcast), keys(&culled));
+        assert_eq!(keys(&broadcast["players"][0]), keys(&culled["players"][0]));
+        assert_eq!(broadcast["players"].as_array().unwrap().len(), 3);
+        assert_eq!(culled["players"].as_array().unwrap().len(), 2);
+    }
+
+    /// Bandwidth saved by culling a full lobby's snapshots to an 800-unit
+    /// view radius, behind the numbers in the README. Run with
+    /// `cargo test --release culling_savings -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn culling_savings() {
+        const RUNS: u32 = 2000;
+        let msg = full_lobby_snapshot();
+        let ServerMsg::Snapshot { players: SnapshotPlayers::Full(players), .. } = &msg else {
+            panic!("expected a full-precision snapshot");
+        };
+        let viewers: Vec<Uuid> = players.iter().map(|p| p.user_id).collect();
+        let full = serde_json::to_string(&msg).unwrap().len();
+
+        let (mut culled, mut visible) = (0, 0);
+        for &viewer in &viewers {
+            let view = cull_for_viewer(&msg, viewer, 800.0);
+            culled += serde_json::to_string(&view).unwrap().len();
+            if let ServerMsg::Snapshot { players: SnapshotPlayers::Full(list), .. } = view {
+                visible += list.len();
+            }
+        }
+        let culled = culled / viewers.len();
+        let visible = visible as f32 / viewers.len() as f32;
+
+        let start = std::time::Instant::now();
+        for _ in 0..RUNS {
+            for &viewer in &viewers {
+                std::hint::black_box(cull_for_viewer(std::hint::black_box(&msg), viewer, 800.0));
+            }
+        }
+        let per_viewer = start.elapsed() / (RUNS * viewers.len() as u32);
+
+        println!("broadcast: {full} bytes, {} KB/s per client at 20 snapshots/s", full * 20 / 1000);
+        println!(
+            "culled: {culled} bytes ({:.0}%), {visible:.1} ships visible, {} KB/s per client, culled in {per_viewer:?} per viewer",
+            100.0 * culled as f64 / full as f64,
+            culled * 20 / 1000,
+        );
+    }
+}
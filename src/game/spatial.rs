@@ -0,0 +1,163 @@
+//! Uniform spatial hash grid for broad-phase collision checks
+//!
+//! Checking every ship against every other ship, and every projectile
+//! against every ship, grows quadratically. The grid buckets points by the
+//! square cell they fall in, so a query only looks at the cells overlapping
+//! the square around it. It's a broad phase only: callers still run the exact
+//! check on each candidate. Candidates come back in ascending index order,
+//! so code that walks them visits things in the same order a full scan
+//! would, and produces the same results.
+
+/// Cells the grid may allocate per point before it coarsens instead, so a
+/// few far-flung points can't blow up the bucket table
+const CELLS_PER_POINT: usize = 4;
+
+/// Points bucketed by grid cell, identified by their index in the slice
+/// they were inserted from. Cells are stored flat over the bounding box of
+/// the points, so lookups are index arithmetic rather than hashing
+#[derive(Debug, Clone, Default)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    origin: (f32, f32),
+    cols: usize,
+    rows: usize,
+    /// `entries[starts[c]..starts[c + 1]]` holds the points in cell `c`
+    starts: Vec<usize>,
+    entries: Vec<usize>,
+    points: Vec<(f32, f32)>,
+}
+
+impl SpatialGrid {
+    /// Replace the contents with `points`, each known by its position in the
+    /// iterator. Cells are `cell_size` across (at least 1), or coarser when
+    /// the points are spread too thin for that to be worth it
+    pub fn rebuild(&mut self, cell_size: f32, points: impl IntoIterator<Item = (f32, f32)>) {
+        self.points.clear();
+        self.points.extend(points);
+        self.entries.clear();
+        self.starts.clear();
+        self.cols = 0;
+        self.rows = 0;
+        if self.points.is_empty() {
+            return;
+        }
+
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for &(x, y) in &self.points {
+            (min_x, min_y) = (min_x.min(x), min_y.min(y));
+            (max_x, max_y) = (max_x.max(x), max_y.max(y));
+        }
+        self.origin = (min_x, min_y);
+        self.cell_size = cell_size.max(1.0);
+        let max_cells = (self.points.len() * CELLS_PER_POINT).max(64);
+        loop {
+            self.cols = ((max_x - min_x) / self.cell_size) as usize + 1;
+            self.rows = ((max_y - min_y) / self.cell_size) as usize + 1;
+            if self.cols.saturating_mul(self.rows) <= max_cells {
+                break;
+            }
+            self.cell_size *= 2.0;
+        }
+
+        // Counting sort into cells; walking points in order keeps each
+        // cell's indices ascending
+        self.starts.resize(self.cols * self.rows + 1, 0);
+        for i in 0..self.points.len() {
+            let cell = self.cell_index(self.points[i]);
+            self.starts[cell + 1] += 1;
+        }
+        for c in 1..self.starts.len() {
+            self.starts[c] += self.starts[c - 1];
+        }
+        self.entries.resize(self.points.len(), 0);
+        let mut next = self.starts.clone();
+        for i in 0..self.points.len() {
+            let cell = self.cell_index(self.points[i]);
+            self.entries[next[cell]] = i;
+            next[cell] += 1;
+        }
+    }
+
+    /// Indices of every point that may lie within `reach` of (`x`, `y`),
+    /// ascending, written to `out`. Includes every point that does, plus
+    /// some that don't
+    pub fn query_into(&self, x: f32, y: f32, reach: f32, out: &mut Vec<usize>) {
+        out.clear();
+        if self.entries.is_empty() {
+            return;
+        }
+        let (Some((min_col, max_col)), Some((min_row, max_row))) = (
+            self.span(x - self.origin.0, reach, self.cols),
+            self.span(y - self.origin.1, reach, self.rows),
+        ) else {
+            return;
+        };
+        for row in min_row..=max_row {
+            let first = self.starts[row * self.cols + min_col];
+            let last = self.starts[row * self.cols + max_col + 1];
+            out.extend_from_slice(&self.entries[first..last]);
+        }
+        if max_row > min_row || max_col > min_col {
+            out.sort_unstable();
+        }
+    }
+
+    fn cell_index(&self, (x, y): (f32, f32)) -> usize {
+        let col = (((x - self.origin.0) / self.cell_size) as usize).min(self.cols - 1);
+        let row = (((y - self.origin.1) / self.cell_size) as usize).min(self.rows - 1);
+        row * self.cols + col
+    }
+
+    /// The cells along one axis within `reach` of `offset`, or `None` when
+    /// that range misses the grid entirely
+    fn span(&self, offset: f32, reach: f32, cells: usize) -> Option<(usize, usize)> {
+        let low = ((offset - reach) / self.cell_size).floor();
+        let high = ((offset + reach) / self.cell_size).floor();
+        if high < 0.0 || low >= cells as f32 {
+            return None;
+        }
+        Some((low.max(0.0) as usize, (high as usize).min(cells - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn queries_find_everything_a_full_scan_does() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut grid = SpatialGrid::default();
+        let mut nearby = Vec::new();
+
+        for _ in 0..20 {
+            let points: Vec<(f32, f32)> = (0..200)
+                .map(|_| (rng.gen_range(-1500.0..1500.0), rng.gen_range(-1500.0..1500.0)))
+                .collect();
+            grid.rebuild(rng.gen_range(10.0..200.0), points.iter().copied());
+
+            for _ in 0..50 {
+                let (x, y) = (rng.gen_range(-1600.0..1600.0), rng.gen_range(-1600.0..1600.0));
+                let reach: f32 = rng.gen_range(0.0..300.0);
+                grid.query_into(x, y, reach, &mut nearby);
+
+                let within: Vec<usize> = points
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &(px, py))| (px - x).powi(2) + (py - y).powi(2) <= reach * reach)
+                    .map(|(i, _)| i)
+                    .collect();
+                let found: Vec<usize> = nearby
+                    .iter()
+                    .copied()
+                    .filter(|i| within.binary_search(i).is_ok())
+                    .collect();
+                assert_eq!(found, within);
+                assert!(nearby.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+    }
+}
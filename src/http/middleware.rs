@@ -1,18 +1,26 @@
 //! Authentication middleware and JWT verification
 
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
+    Json,
 };
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use governor::clock::{Clock, DefaultClock};
 use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::app::AppState;
+use crate::config::Config;
+use crate::util::ip_limit::{ClientIp, IpLimiter};
+use crate::util::rate_limit::KeyedLimiter;
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -24,6 +32,9 @@ pub struct JwtClaims {
     /// Audience
     #[serde(default)]
     pub aud: Option<String>,
+    /// Issuer
+    #[serde(default)]
+    pub iss: Option<String>,
     /// Expiration time (Unix timestamp)
     pub exp: u64,
     /// Issued at (Unix timestamp)
@@ -35,10 +46,77 @@ pub struct JwtClaims {
     /// Role
     #[serde(default)]
     pub role: Option<String>,
+    /// Server-controlled metadata; users can't edit it, unlike `user_metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_metadata: Option<AppMetadata>,
+}
+
+/// `app_metadata` claim of Supabase JWTs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppMetadata {
+    /// Application role, e.g. "admin"
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+impl JwtClaims {
+    /// Token minted by `POST /guest` rather than Supabase
+    pub fn is_guest(&self) -> bool {
+        self.role.as_deref() == Some(GUEST_ROLE)
+    }
+
+    /// Signed-in user whose `app_metadata` grants the admin role
+    pub fn is_admin(&self) -> bool {
+        !self.is_guest()
+            && self
+                .app_metadata
+                .as_ref()
+                .and_then(|m| m.role.as_deref())
+                == Some(ADMIN_ROLE)
+    }
+}
+
+/// Role Supabase assigns to signed-in users
+const AUTHENTICATED_ROLE: &str = "authenticated";
+
+/// Role of tokens this server mints for guests
+const GUEST_ROLE: &str = "guest";
+
+/// `iss` of guest tokens, so they can't pass for Supabase ones
+const GUEST_ISSUER: &str = "ship-game-guest";
+
+/// `app_metadata.role` of users allowed on admin routes
+const ADMIN_ROLE: &str = "admin";
+
+/// Expected token properties for JWT verification
+#[derive(Debug, Clone, Copy)]
+pub struct JwtValidation<'a> {
+    /// HMAC secret used to sign tokens
+    pub secret: &'a str,
+    /// Required `aud` claim
+    pub audience: &'a str,
+    /// Required `iss` claim, if configured
+    pub issuer: Option<&'a str>,
+    /// Clock skew tolerated on the `exp` check (seconds)
+    pub leeway_secs: u64,
+    /// Accept guest tokens
+    pub allow_guests: bool,
+}
+
+impl<'a> JwtValidation<'a> {
+    pub fn from_config(config: &'a Config) -> Self {
+        Self {
+            secret: &config.supabase_jwt_secret,
+            audience: &config.jwt_audience,
+            issuer: config.jwt_issuer.as_deref(),
+            leeway_secs: config.jwt_leeway_secs,
+            allow_guests: config.guest_play,
+        }
+    }
 }
 
 /// Verify a JWT token and extract claims
-pub fn verify_jwt(token: &str, secret: &str) -> Result<JwtClaims, AuthError> {
+pub fn verify_jwt(token: &str, validation: &JwtValidation) -> Result<JwtClaims, AuthError> {
     // Split token into parts
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 3 {
@@ -52,7 +130,7 @@ pub fn verify_jwt(token: &str, secret: &str) -> Result<JwtClaims, AuthError> {
     // Verify signature (HMAC-SHA256)
     let message = format!("{}.{}", header_b64, payload_b64);
     
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+    let mut mac = HmacSha256::new_from_slice(validation.secret.as_bytes())
         .map_err(|_| AuthError::InvalidToken)?;
     mac.update(message.as_bytes());
     
@@ -79,13 +157,67 @@ pub fn verify_jwt(token: &str, secret: &str) -> Result<JwtClaims, AuthError> {
         .unwrap()
         .as_secs();
     
-    if claims.exp < now {
+    if claims.exp.saturating_add(validation.leeway_secs) < now {
         return Err(AuthError::TokenExpired);
     }
 
+    if claims.aud.as_deref() != Some(validation.audience) {
+        return Err(AuthError::InvalidAudience);
+    }
+
+    if claims.is_guest() {
+        if !validation.allow_guests {
+            return Err(AuthError::InvalidRole);
+        }
+        if claims.iss.as_deref() != Some(GUEST_ISSUER) {
+            return Err(AuthError::InvalidIssuer);
+        }
+        return Ok(claims);
+    }
+
+    if let Some(issuer) = validation.issuer {
+        if claims.iss.as_deref() != Some(issuer) {
+            return Err(AuthError::InvalidIssuer);
+        }
+    }
+
+    if claims.role.as_deref() != Some(AUTHENTICATED_ROLE) {
+        return Err(AuthError::InvalidRole);
+    }
+
     Ok(claims)
 }
 
+/// Mint a guest token for a fresh user ID, valid for `ttl_secs`
+pub fn issue_guest_token(validation: &JwtValidation, ttl_secs: u64) -> (Uuid, String) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let claims = JwtClaims {
+        sub: Uuid::new_v4(),
+        aud: Some(validation.audience.to_string()),
+        iss: Some(GUEST_ISSUER.to_string()),
+        exp: now + ttl_secs,
+        iat: now,
+        email: None,
+        role: Some(GUEST_ROLE.to_string()),
+        app_metadata: None,
+    };
+
+    let header_b64 = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+    // Serializing plain claims can't fail
+    let payload_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default());
+    let message = format!("{}.{}", header_b64, payload_b64);
+
+    let mut mac = HmacSha256::new_from_slice(validation.secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    let signature_b64 = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    (claims.sub, format!("{}.{}", message, signature_b64))
+}
+
 /// Extract JWT from Authorization header
 pub fn extract_bearer_token(auth_header: &str) -> Option<&str> {
     auth_header.strip_prefix("Bearer ")
@@ -108,6 +240,18 @@ pub enum AuthError {
 
     #[error("Invalid audience")]
     InvalidAudience,
+
+    #[error("Invalid issuer")]
+    InvalidIssuer,
+
+    #[error("Invalid role")]
+    InvalidRole,
+
+    #[error("Not available to guests")]
+    GuestNotAllowed,
+
+    #[error("Admin access required")]
+    NotAdmin,
 }
 
 impl IntoResponse for AuthError {
@@ -118,6 +262,10 @@ impl IntoResponse for AuthError {
             AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
             AuthError::TokenExpired => StatusCode::UNAUTHORIZED,
             AuthError::InvalidAudience => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidIssuer => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidRole => StatusCode::FORBIDDEN,
+            AuthError::GuestNotAllowed => StatusCode::FORBIDDEN,
+            AuthError::NotAdmin => StatusCode::FORBIDDEN,
         };
 
         (status, self.to_string()).into_response()
@@ -145,7 +293,7 @@ pub async fn require_auth(
 
     let token = extract_bearer_token(auth_header).ok_or(AuthError::InvalidFormat)?;
 
-    let claims = verify_jwt(token, &state.config.supabase_jwt_secret)?;
+    let claims = verify_jwt(token, &JwtValidation::from_config(&state.config))?;
 
     let auth_user = AuthenticatedUser {
         user_id: claims.sub,
@@ -158,7 +306,490 @@ pub async fn require_auth(
     Ok(next.run(request).await)
 }
 
+impl AuthenticatedUser {
+    pub fn is_guest(&self) -> bool {
+        self.claims.is_guest()
+    }
+}
+
+/// Middleware to keep guests out of account routes (payments, inventory).
+/// Must run after `require_auth`
+pub async fn reject_guests(request: Request, next: Next) -> Result<Response, AuthError> {
+    if get_auth_user(&request).is_some_and(|user| user.is_guest()) {
+        return Err(AuthError::GuestNotAllowed);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compare a presented token to the configured one without leaking how
+/// much of it matched through timing
+fn token_matches(presented: &str, expected: &str) -> bool {
+    // Compare fixed-length MACs of both, so lengths don't leak either
+    let tag = |token: &str| {
+        let mut mac = HmacSha256::new_from_slice(expected.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(token.as_bytes());
+        mac
+    };
+    let expected_tag = tag(expected).finalize().into_bytes();
+    tag(presented).verify_slice(&expected_tag).is_ok()
+}
+
+/// Middleware to require admin access: either the static `ADMIN_TOKEN`, or
+/// a valid user JWT whose `app_metadata.role` is "admin". Admin JWT users
+/// are inserted like `require_auth` does
+pub async fn require_admin(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let auth_header = request
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(AuthError::MissingHeader)?;
+
+    let token = extract_bearer_token(auth_header).ok_or(AuthError::InvalidFormat)?;
+
+    if let Some(admin_token) = &state.config.admin_token {
+        if token_matches(token, admin_token) {
+            return Ok(next.run(request).await);
+        }
+    }
+
+    let claims = verify_jwt(token, &JwtValidation::from_config(&state.config))?;
+    if !claims.is_admin() {
+        return Err(AuthError::NotAdmin);
+    }
+
+    request.extensions_mut().insert(AuthenticatedUser {
+        user_id: claims.sub,
+        claims,
+    });
+
+    Ok(next.run(request).await)
+}
+
 /// Extract authenticated user from request extensions
 pub fn get_auth_user(request: &Request) -> Option<&AuthenticatedUser> {
     request.extensions().get::<AuthenticatedUser>()
 }
+
+/// Middleware to rate-limit an authenticated route per user. Must run after
+/// `require_auth`; requests without a user pass through
+pub async fn rate_limit(
+    State(limiter): State<Arc<KeyedLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(user_id) = get_auth_user(&request).map(|user| user.user_id) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check_key(&user_id) {
+        Ok(()) => next.run(request).await,
+        Err(not_until) => too_many_requests(not_until.wait_time_from(DefaultClock::default().now())),
+    }
+}
+
+/// Middleware to rate-limit public routes per client IP. The IP is left on
+/// the request as a `ClientIp` extension for handlers that limit further by
+/// it; requests whose IP can't be told pass through
+pub async fn ip_rate_limit(
+    State(limiter): State<Arc<IpLimiter>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let Some(ip) = limiter.client_ip(peer, request.headers()) else {
+        return next.run(request).await;
+    };
+
+    if let Err(wait) = limiter.check_request(ip) {
+        return too_many_requests(wait);
+    }
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
+
+/// `429` asking the client to retry after `wait`
+pub fn too_many_requests(wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f32().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(serde_json::json!({ "error": "Too many requests" })),
+    )
+        .into_response()
+}
+
+/// Parse a comma-separated origin list, skipping entries that aren't valid
+/// header values
+pub fn parse_origins(list: &str) -> Vec<HeaderValue> {
+    list.split(',')
+        .filter_map(|s| s.trim().parse::<HeaderValue>().ok())
+        .collect()
+}
+
+/// Middleware to refuse credentialed requests sent by a browser from an
+/// origin outside the allowlist. Requests without an `Origin` (servers,
+/// native clients) pass through
+pub async fn require_allowed_origin(
+    State(allowed): State<Arc<Vec<HeaderValue>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match request.headers().get(header::ORIGIN) {
+        Some(origin) if !allowed.contains(origin) => (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Origin not allowed" })),
+        )
+            .into_response(),
+        _ => next.run(request).await,
+    }
+}
+
+/// Security headers added to responses
+#[derive(Debug, Clone, Default)]
+pub struct SecurityHeaders {
+    /// Added to every response
+    headers: Vec<(HeaderName, HeaderValue)>,
+    /// Content-Security-Policy for HTML responses
+    html_csp: Option<HeaderValue>,
+}
+
+impl SecurityHeaders {
+    /// Headers from config: none when `SECURITY_HEADERS` is off, and policies
+    /// configured empty are left out
+    pub fn from_config(config: &Config) -> Self {
+        if !config.security_headers {
+            return Self::default();
+        }
+        Self::new(&config.referrer_policy, &config.content_security_policy)
+    }
+
+    /// `X-Content-Type-Options: nosniff` plus the given policies, each left
+    /// out when empty
+    pub fn new(referrer_policy: &str, content_security_policy: &str) -> Self {
+        let policy = |value: &str| {
+            Some(value)
+                .filter(|v| !v.is_empty())
+                .and_then(|v| HeaderValue::from_str(v).ok())
+        };
+        let mut headers = vec![(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        )];
+        if let Some(referrer_policy) = policy(referrer_policy) {
+            headers.push((header::REFERRER_POLICY, referrer_policy));
+        }
+
+        Self {
+            headers,
+            html_csp: policy(content_security_policy),
+        }
+    }
+}
+
+/// Middleware adding security headers to responses, without overriding
+/// ones a handler already set
+pub async fn security_headers(
+    State(security): State<Arc<SecurityHeaders>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+
+    let headers = response.headers_mut();
+    for (name, value) in &security.headers {
+        if !headers.contains_key(name) {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+    if let (true, Some(csp)) = (is_html, &security.html_csp) {
+        if !headers.contains_key(header::CONTENT_SECURITY_POLICY) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, csp.clone());
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::response::Html;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use crate::util::ip_limit::IpLimitPolicy;
+
+    const SECRET: &str = "test-secret";
+
+    fn validation() -> JwtValidation<'static> {
+        JwtValidation {
+            secret: SECRET,
+            audience: "authenticated",
+            issuer: None,
+            leeway_secs: 30,
+            allow_guests: true,
+        }
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Claims of a signed-in Supabase user expiring in an hour
+    fn user_claims() -> JwtClaims {
+        JwtClaims {
+            sub: Uuid::new_v4(),
+            aud: Some("authenticated".to_string()),
+            iss: Some("https://example.supabase.co/auth/v1".to_string()),
+            exp: now() + 3600,
+            iat: now(),
+            email: None,
+            role: Some(AUTHENTICATED_ROLE.to_string()),
+            app_metadata: None,
+        }
+    }
+
+    fn sign(claims: &JwtClaims, secret: &str) -> String {
+        let header_b64 = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap());
+        let message = format!("{}.{}", header_b64, payload_b64);
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(message.as_bytes());
+        let signature_b64 = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", message, signature_b64)
+    }
+
+    fn verify(claims: &JwtClaims) -> Result<JwtClaims, AuthError> {
+        verify_jwt(&sign(claims, SECRET), &validation())
+    }
+
+    #[test]
+    fn accepts_valid_user_token() {
+        let claims = user_claims();
+        let verified = verify(&claims).unwrap();
+        assert_eq!(verified.sub, claims.sub);
+    }
+
+    #[test]
+    fn rejects_wrong_signature() {
+        let token = sign(&user_claims(), "other-secret");
+        assert!(matches!(
+            verify_jwt(&token, &validation()),
+            Err(AuthError::InvalidToken)
+        ));
+        assert!(matches!(
+            verify_jwt("not-a-jwt", &validation()),
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_or_missing_audience() {
+        let mut claims = user_claims();
+        claims.aud = Some("anon".to_string());
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidAudience)));
+        claims.aud = None;
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidAudience)));
+    }
+
+    #[test]
+    fn rejects_wrong_or_missing_role() {
+        let mut claims = user_claims();
+        claims.role = Some("anon".to_string());
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidRole)));
+        claims.role = Some("service_role".to_string());
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidRole)));
+        claims.role = None;
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidRole)));
+    }
+
+    #[test]
+    fn expiry_allows_leeway() {
+        let mut claims = user_claims();
+        claims.exp = now() - 10;
+        assert!(verify(&claims).is_ok());
+
+        claims.exp = now() - 60;
+        assert!(matches!(verify(&claims), Err(AuthError::TokenExpired)));
+
+        let strict = JwtValidation {
+            leeway_secs: 0,
+            ..validation()
+        };
+        claims.exp = now() - 10;
+        assert!(matches!(
+            verify_jwt(&sign(&claims, SECRET), &strict),
+            Err(AuthError::TokenExpired)
+        ));
+    }
+
+    #[test]
+    fn checks_issuer_when_configured() {
+        let claims = user_claims();
+        let token = sign(&claims, SECRET);
+        let pinned = JwtValidation {
+            issuer: Some("https://example.supabase.co/auth/v1"),
+            ..validation()
+        };
+        assert!(verify_jwt(&token, &pinned).is_ok());
+
+        let other = JwtValidation {
+            issuer: Some("https://other.supabase.co/auth/v1"),
+            ..validation()
+        };
+        assert!(matches!(
+            verify_jwt(&token, &other),
+            Err(AuthError::InvalidIssuer)
+        ));
+    }
+
+    #[test]
+    fn guest_tokens_only_accepted_when_allowed() {
+        let (user_id, token) = issue_guest_token(&validation(), 600);
+        let claims = verify_jwt(&token, &validation()).unwrap();
+        assert_eq!(claims.sub, user_id);
+        assert!(claims.is_guest());
+
+        let no_guests = JwtValidation {
+            allow_guests: false,
+            ..validation()
+        };
+        assert!(matches!(
+            verify_jwt(&token, &no_guests),
+            Err(AuthError::InvalidRole)
+        ));
+    }
+
+    #[test]
+    fn admin_role_comes_from_app_metadata_only() {
+        let mut claims = user_claims();
+        assert!(!claims.is_admin());
+
+        claims.app_metadata = Some(AppMetadata {
+            role: Some(ADMIN_ROLE.to_string()),
+        });
+        assert!(verify(&claims).unwrap().is_admin());
+
+        // A role in the user-editable metadata is ignored
+        let mut payload = serde_json::to_value(user_claims()).unwrap();
+        payload["user_metadata"] = serde_json::json!({ "role": ADMIN_ROLE });
+        let claims: JwtClaims = serde_json::from_value(payload).unwrap();
+        assert!(!claims.is_admin());
+    }
+
+    #[test]
+    fn admin_token_must_match_exactly() {
+        let expected = "0123456789abcdef0123456789abcdef";
+        assert!(token_matches(expected, expected));
+        assert!(!token_matches("0123456789abcdef0123456789abcdee", expected));
+        assert!(!token_matches("0123456789abcdef", expected));
+        assert!(!token_matches("", expected));
+    }
+
+    #[test]
+    fn guest_role_requires_guest_issuer() {
+        let mut claims = user_claims();
+        claims.role = Some(GUEST_ROLE.to_string());
+        assert!(matches!(verify(&claims), Err(AuthError::InvalidIssuer)));
+    }
+
+    fn app(security: SecurityHeaders, origins: &str) -> Router {
+        Router::new()
+            .route("/json", get(|| async { Json(serde_json::json!({})) }))
+            .route("/page", get(|| async { Html("<p>hi</p>") }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(parse_origins(origins)),
+                require_allowed_origin,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(security),
+                security_headers,
+            ))
+    }
+
+    async fn get_with_origin(app: Router, uri: &str, origin: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(origin) = origin {
+            request = request.header(header::ORIGIN, origin);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn csp_is_only_added_to_html() {
+        let security = SecurityHeaders::new("no-referrer", "default-src 'none'");
+
+        let json = get_with_origin(app(security.clone(), ""), "/json", None).await;
+        assert_eq!(json.headers()[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(json.headers()[header::REFERRER_POLICY], "no-referrer");
+        assert!(!json.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+
+        let page = get_with_origin(app(security, ""), "/page", None).await;
+        assert_eq!(page.headers()[header::CONTENT_SECURITY_POLICY], "default-src 'none'");
+    }
+
+    #[tokio::test]
+    async fn empty_policies_are_left_out() {
+        let page = get_with_origin(app(SecurityHeaders::new("", ""), ""), "/page", None).await;
+        assert!(page.headers().contains_key(header::X_CONTENT_TYPE_OPTIONS));
+        assert!(!page.headers().contains_key(header::REFERRER_POLICY));
+        assert!(!page.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    #[tokio::test]
+    async fn foreign_origins_are_refused() {
+        let origins = "https://game.example, https://beta.game.example";
+        let allowed = |origin| get_with_origin(app(SecurityHeaders::default(), origins), "/json", origin);
+
+        assert_eq!(allowed(Some("https://beta.game.example")).await.status(), StatusCode::OK);
+        assert_eq!(allowed(None).await.status(), StatusCode::OK);
+        let foreign = allowed(Some("https://evil.example")).await;
+        assert_eq!(foreign.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn a_burst_from_one_ip_is_throttled_while_others_proceed() {
+        let limiter = Arc::new(IpLimiter::new(IpLimitPolicy {
+            requests_per_second: 2,
+            max_connections: 0,
+            trusted_proxy_hops: 1,
+        }));
+        let app = Router::new()
+            .route("/json", get(|| async { Json(serde_json::json!({})) }))
+            .layer(axum::middleware::from_fn_with_state(limiter, ip_rate_limit));
+        let from = |ip: &str| {
+            let request = Request::get("/json")
+                .header("x-forwarded-for", ip)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            assert_eq!(from("198.51.100.1").await.unwrap().status(), StatusCode::OK);
+        }
+        let throttled = from("198.51.100.1").await.unwrap();
+        assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(throttled.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(from("198.51.100.2").await.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -1,7 +1,7 @@
 //! HTTP route definitions
 
 use axum::{
-    extract::{Extension, State},
+    extract::{Extension, Path, Query, State},
     http::{header, Method, StatusCode},
     middleware,
     response::{IntoResponse, Json},
@@ -9,55 +9,157 @@ use axum::{
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::{
     compression::CompressionLayer,
     cors::CorsLayer,
     trace::TraceLayer,
 };
+use tracing::{info, warn};
 use uuid::Uuid;
 
 use crate::app::AppState;
-use crate::http::middleware::{require_auth, AuthenticatedUser};
+use crate::game::balance::BalanceTable;
+use crate::game::combat::WeaponStats;
+use crate::game::physics::ShipStats;
+use crate::game::event_log::{EventFilter, LoggedEvent};
+use crate::game::inspect::MatchInspection;
+use crate::game::metrics::MatchMetrics;
+use crate::game::MatchPhase;
+use crate::http::middleware::{
+    ip_rate_limit, issue_guest_token, parse_origins, rate_limit, reject_guests, require_admin,
+    require_allowed_origin, require_auth, security_headers, AuthenticatedUser, JwtValidation,
+    SecurityHeaders,
+};
 use crate::matchmaking::queue::QueuedPlayer;
+use crate::matchmaking::service::PracticeError;
+use crate::payments::stripe::{CheckoutMode, StripeError};
 use crate::payments::webhook::stripe_webhook_handler;
-use crate::util::time::uptime_secs;
+use crate::store::inventory::{
+    is_not_equippable, is_pass_required, BulkGrantResult, GrantRecipients, GrantSource,
+    InventoryItemWithDetails,
+};
+use crate::store::profiles::{ProfileUpdate, UserProfile};
+use crate::store::stats::CareerStats;
+use crate::store::supabase::SupabaseError;
+use crate::util::display_name::{guest_display_name, validate_display_name};
+use crate::util::time::{unix_millis, uptime_secs};
 use crate::ws::handler::ws_handler;
-use crate::ws::protocol::ShipType;
+use crate::ws::protocol::{CosmeticSlot, ShipType, WeaponLoadout, PROTOCOL_VERSION};
+use crate::ws::ticket::{Credentials, TICKET_TTL};
 
 /// Build the application router
 pub fn build_router(state: AppState) -> Router {
     // CORS configuration - support multiple origins (comma-separated in CLIENT_ORIGIN)
-    let allowed_origins: Vec<header::HeaderValue> = state
-        .config
-        .client_origin
-        .split(',')
-        .filter_map(|s| s.trim().parse::<header::HeaderValue>().ok())
-        .collect();
-    
+    let allowed_origins = parse_origins(&state.config.client_origin);
+
+    // Responses depend on the request's origin, so caches must key on it
     let cors = CorsLayer::new()
-        .allow_origin(allowed_origins)
+        .allow_origin(allowed_origins.clone())
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
         .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
-        .allow_credentials(true);
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(state.config.cors_max_age_secs))
+        .vary([
+            header::ORIGIN,
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+        ]);
+    let security = Arc::new(SecurityHeaders::from_config(&state.config));
 
     // Public routes (no auth required)
     let public_routes = Router::new()
         .route("/health", get(health_handler))
+        .route("/version", get(version_handler))
+        .route("/ready", get(ready_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/balance", get(balance_handler))
+        .route("/ships", get(ships_handler))
+        .route("/matches", get(list_matches_handler))
+        .route("/matches/:match_id/events", get(match_events_handler))
+        .route("/stats/:user_id", get(user_stats_handler))
+        .route("/guest", post(guest_handler))
         .route("/ws", get(ws_handler))
-        .route("/payments/webhook", post(stripe_webhook_handler));
+        .route("/payments/webhook", post(stripe_webhook_handler))
+        .layer(middleware::from_fn_with_state(
+            state.rate_limiters.ip.clone(),
+            ip_rate_limit,
+        ));
+
+    // Per-user rate limits (route layers run after auth)
+    let limiters = &state.rate_limiters;
+    let matchmaking_limit =
+        middleware::from_fn_with_state(limiters.matchmaking.clone(), rate_limit);
+    let inventory_limit = middleware::from_fn_with_state(limiters.inventory.clone(), rate_limit);
+    let checkout_limit = middleware::from_fn_with_state(limiters.checkout.clone(), rate_limit);
+
+    // Account routes (auth required, guests refused)
+    let account_routes = Router::new()
+        .route("/profile", post(update_profile_handler))
+        .route(
+            "/payments/checkout",
+            post(checkout_handler).layer(checkout_limit),
+        )
+        .route("/wallet", get(wallet_handler))
+        .route(
+            "/inventory",
+            get(inventory_handler).layer(inventory_limit.clone()),
+        )
+        .route(
+            "/inventory/history",
+            get(inventory_history_handler).layer(inventory_limit.clone()),
+        )
+        .route(
+            "/inventory/equip",
+            post(equip_handler).layer(inventory_limit.clone()),
+        )
+        .route(
+            "/inventory/unequip",
+            post(unequip_handler).layer(inventory_limit),
+        )
+        .layer(middleware::from_fn(reject_guests));
+
+    // Admin routes (admin token or admin JWT required)
+    let admin_routes = Router::new()
+        .route("/admin/matches/:match_id", get(admin_match_handler))
+        .route("/admin/grant", post(admin_grant_handler))
+        .layer(middleware::from_fn_with_state(state.clone(), require_admin));
 
     // Protected routes (auth required)
     let protected_routes = Router::new()
-        .route("/matchmaking/join", post(matchmaking_join_handler))
-        .route("/payments/checkout", post(checkout_handler))
-        .route("/inventory", get(inventory_handler))
-        .route("/inventory/equip", post(equip_handler))
-        .layer(middleware::from_fn_with_state(state.clone(), require_auth));
+        .route("/ws-ticket", post(ws_ticket_handler))
+        .route(
+            "/matchmaking/join",
+            post(matchmaking_join_handler).layer(matchmaking_limit.clone()),
+        )
+        .route(
+            "/matchmaking/leave",
+            post(matchmaking_leave_handler).layer(matchmaking_limit.clone()),
+        )
+        .route(
+            "/matchmaking/practice",
+            post(matchmaking_practice_handler).layer(matchmaking_limit),
+        )
+        .route("/stats/me", get(my_stats_handler))
+        .merge(account_routes)
+        .layer(middleware::from_fn_with_state(state.clone(), require_auth))
+        .merge(admin_routes)
+        // Credentialed routes refuse foreign browser origins outright rather
+        // than only withholding the CORS headers
+        .layer(middleware::from_fn_with_state(
+            Arc::new(allowed_origins),
+            require_allowed_origin,
+        ));
 
     Router::new()
         .merge(public_routes)
         .merge(protected_routes)
         .layer(CompressionLayer::new())
+        .layer(middleware::from_fn_with_state(security, security_headers))
         .layer(TraceLayer::new_for_http())
         .layer(cors)
         .with_state(state)
@@ -74,6 +176,8 @@ struct HealthResponse {
     active_matches: usize,
     active_players: usize,
     queue_size: usize,
+    /// Queued players per matchmaking region
+    queue_by_region: BTreeMap<String, usize>,
 }
 
 async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
@@ -85,9 +189,720 @@ async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
         active_matches: state.match_registry.active_matches(),
         active_players: state.match_registry.total_players(),
         queue_size,
+        queue_by_region: state.matchmaking.queue_by_region().await,
+    })
+}
+
+// ============================================================================
+// Version endpoint
+// ============================================================================
+
+#[derive(Serialize)]
+struct VersionResponse {
+    /// Crate version
+    version: &'static str,
+    /// Git commit the binary was built from ("unknown" if it couldn't be found)
+    commit: &'static str,
+    protocol_version: u32,
+    /// Simulation ticks and snapshots per second of new matches
+    simulation_tps: u32,
+    snapshot_tps: u32,
+}
+
+/// What is deployed, for bug reports and client debug overlays
+async fn version_handler(State(state): State<AppState>) -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("GIT_COMMIT"),
+        protocol_version: PROTOCOL_VERSION,
+        simulation_tps: state.config.simulation_tps,
+        snapshot_tps: state.config.snapshot_tps,
+    })
+}
+
+// ============================================================================
+// Readiness endpoint
+// ============================================================================
+
+#[derive(Serialize)]
+struct ReadyResponse {
+    status: &'static str,
+    /// Dependency name -> "ok" or why it failed
+    checks: BTreeMap<&'static str, String>,
+}
+
+/// Ready only when Supabase and Stripe are reachable with our credentials
+async fn ready_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let timeout = Duration::from_secs(state.config.ready_check_timeout_secs);
+    let (supabase, stripe) = tokio::join!(
+        tokio::time::timeout(timeout, state.supabase.ping()),
+        tokio::time::timeout(timeout, state.stripe.ping()),
+    );
+
+    let mut checks = BTreeMap::new();
+    checks.insert("supabase", check_result(supabase));
+    checks.insert("stripe", check_result(stripe));
+
+    let ready = checks.values().all(|result| result == "ok");
+    if !ready {
+        warn!(checks = ?checks, "Readiness check failed");
+    }
+    let (code, status) = if ready {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    };
+    (code, Json(ReadyResponse { status, checks }))
+}
+
+/// "ok", or why a dependency check failed
+fn check_result<E: std::fmt::Display>(
+    result: Result<Result<(), E>, tokio::time::error::Elapsed>,
+) -> String {
+    match result {
+        Ok(Ok(())) => "ok".to_string(),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "timed out".to_string(),
+    }
+}
+
+// ============================================================================
+// Metrics endpoint (Prometheus text format)
+// ============================================================================
+
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let queue_size = state.matchmaking.queue_size().await;
+    let matches = state.match_registry.list();
+
+    let per_match: Vec<(Uuid, usize, &MatchMetrics)> = matches
+        .iter()
+        .map(|m| (m.id, m.player_count(), m.metrics.as_ref()))
+        .collect();
+    let micros_to_secs = |micros: u64| micros as f64 / 1_000_000.0;
+    let load = |metric: &AtomicU64| metric.load(Ordering::Relaxed);
+
+    let worst_tick_micros = per_match
+        .iter()
+        .map(|(_, _, m)| load(&m.tick_max_micros))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    write_gauge(
+        &mut out,
+        "ship_game_active_matches",
+        "Active matches",
+        [(None, matches.len() as f64)],
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_active_players",
+        "Players in active matches",
+        [(None, state.match_registry.total_players() as f64)],
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_queue_size",
+        "Players in the matchmaking queue",
+        [(None, queue_size as f64)],
+    );
+    write_gauge_by(
+        &mut out,
+        "ship_game_region_queue_size",
+        "Players in the matchmaking queue per region",
+        "region",
+        state
+            .matchmaking
+            .queue_by_region()
+            .await
+            .into_iter()
+            .map(|(region, size)| (Some(region), size as f64)),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_matches_behind",
+        "Matches whose average tick exceeds the tick budget",
+        [(
+            None,
+            per_match.iter().filter(|(_, _, m)| m.is_behind()).count() as f64,
+        )],
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_tick_max_seconds",
+        "Longest tick across all matches in the last window",
+        [(None, micros_to_secs(worst_tick_micros))],
+    );
+    write_counter(
+        &mut out,
+        "ship_game_ws_lagged_messages_total",
+        "Messages dropped because a WebSocket client fell behind",
+        load(&state.lag_metrics.lagged_messages),
+    );
+    write_counter(
+        &mut out,
+        "ship_game_ws_lag_disconnects_total",
+        "WebSocket connections closed for lagging too often",
+        load(&state.lag_metrics.disconnects),
+    );
+
+    let counters = &state.counters;
+    write_gauge(
+        &mut out,
+        "ship_game_ws_connections",
+        "Open WebSocket connections",
+        [(None, counters.open_connections() as f64)],
+    );
+    for (name, help, counter) in [
+        (
+            "ship_game_ws_connections_opened_total",
+            "WebSocket connections opened",
+            &counters.connections_opened,
+        ),
+        (
+            "ship_game_ws_connections_closed_total",
+            "WebSocket connections closed",
+            &counters.connections_closed,
+        ),
+        (
+            "ship_game_ws_auth_failures_total",
+            "WebSocket upgrades refused for bad or missing credentials",
+            &counters.auth_failures,
+        ),
+        (
+            "ship_game_matches_created_total",
+            "Matches created by matchmaking",
+            &counters.matches_created,
+        ),
+        (
+            "ship_game_matches_ended_total",
+            "Matches that ended and were cleaned up",
+            &counters.matches_ended,
+        ),
+        (
+            "ship_game_queue_joins_total",
+            "Players added to the matchmaking queue",
+            &counters.queue_joins,
+        ),
+        (
+            "ship_game_queue_leaves_total",
+            "Players who left the matchmaking queue or disconnected while queued",
+            &counters.queue_leaves,
+        ),
+        (
+            "ship_game_webhooks_succeeded_total",
+            "Stripe webhooks handled",
+            &counters.webhooks_succeeded,
+        ),
+        (
+            "ship_game_webhooks_failed_total",
+            "Stripe webhooks rejected or failed",
+            &counters.webhooks_failed,
+        ),
+    ] {
+        write_counter(&mut out, name, help, load(counter));
+    }
+
+    write_gauge(
+        &mut out,
+        "ship_game_match_players",
+        "Players in the match",
+        per_match
+            .iter()
+            .map(|(id, players, _)| (Some(*id), *players as f64)),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_projectiles",
+        "Projectiles in flight",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), m.projectile_count.load(Ordering::Relaxed) as f64)),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_tick_avg_seconds",
+        "Average tick duration over the last window",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), micros_to_secs(load(&m.tick_avg_micros)))),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_tick_max_seconds",
+        "Longest tick in the last window",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), micros_to_secs(load(&m.tick_max_micros)))),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_tick_budget_seconds",
+        "Tick budget at the match's tick rate",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), micros_to_secs(load(&m.tick_budget_micros)))),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_behind",
+        "1 if the match's average tick exceeds its budget",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), if m.is_behind() { 1.0 } else { 0.0 })),
+    );
+    write_match_counter(
+        &mut out,
+        "ship_game_match_ticks_total",
+        "Ticks simulated since the match started",
+        per_match.iter().map(|(id, _, m)| (*id, load(&m.ticks_total))),
+    );
+    write_gauge(
+        &mut out,
+        "ship_game_match_catch_up_steps_max",
+        "Most ticks one pass of the match loop ran in the last window",
+        per_match
+            .iter()
+            .map(|(id, _, m)| (Some(*id), load(&m.catch_up_steps_max) as f64)),
+    );
+    write_match_counter(
+        &mut out,
+        "ship_game_match_ticks_dropped_total",
+        "Ticks skipped because the match loop fell too far behind to catch up",
+        per_match.iter().map(|(id, _, m)| (*id, load(&m.ticks_dropped))),
+    );
+    write_match_counter(
+        &mut out,
+        "ship_game_match_lagged_messages_total",
+        "Messages dropped because a player's forwarder fell behind the match",
+        per_match.iter().map(|(id, _, m)| (*id, load(&m.lagged_messages))),
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+/// Append a gauge in Prometheus text format, optionally labelled by match ID
+fn write_gauge(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (Option<Uuid>, f64)>,
+) {
+    write_gauge_by(out, name, help, "match_id", samples);
+}
+
+/// Append an unlabelled counter in Prometheus text format
+fn write_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Append a counter labelled by match ID in Prometheus text format
+fn write_match_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (Uuid, u64)>,
+) {
+    let samples = samples.into_iter().map(|(id, value)| (Some(id), value));
+    write_samples(out, name, help, "counter", "match_id", samples);
+}
+
+/// Write a gauge whose samples are labelled by `label` (unlabelled if `None`)
+fn write_gauge_by<L: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    samples: impl IntoIterator<Item = (Option<L>, f64)>,
+) {
+    write_samples(out, name, help, "gauge", label, samples);
+}
+
+/// Write a metric of type `kind` whose samples are labelled by `label`
+/// (unlabelled if `None`)
+fn write_samples<L: std::fmt::Display, V: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    label: &str,
+    samples: impl IntoIterator<Item = (Option<L>, V)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (label_value, value) in samples {
+        match label_value {
+            Some(label_value) => {
+                let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, label_value, value);
+            }
+            None => {
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Balance and ship catalog endpoints
+// ============================================================================
+
+/// Ship and weapon stats in use, so clients predict movement with the same
+/// numbers the server simulates
+async fn balance_handler(State(state): State<AppState>) -> Json<BalanceTable> {
+    Json(BalanceTable::clone(&state.balance))
+}
+
+/// One hull in the ship catalog
+#[derive(Serialize)]
+struct ShipCatalogEntry {
+    ship_type: ShipType,
+    stats: ShipStats,
+    /// Weapon the hull flies with unless another loadout is chosen
+    default_weapon: WeaponLoadout,
+    weapon_stats: WeaponStats,
+}
+
+/// Every hull with the stats in use, for selection screens and tooltips
+async fn ships_handler(State(state): State<AppState>) -> Json<Vec<ShipCatalogEntry>> {
+    let ships = ShipType::ALL
+        .into_iter()
+        .map(|ship_type| {
+            let default_weapon = WeaponLoadout::default_for(ship_type);
+            ShipCatalogEntry {
+                ship_type,
+                stats: state.balance.ship(ship_type),
+                default_weapon,
+                weapon_stats: state.balance.weapon(default_weapon),
+            }
+        })
+        .collect();
+    Json(ships)
+}
+
+// ============================================================================
+// Match browser endpoint
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ListMatchesQuery {
+    /// Only include matches a new player could join
+    #[serde(default)]
+    joinable: bool,
+}
+
+#[derive(Serialize)]
+struct ListMatchesResponse {
+    matches: Vec<MatchSummary>,
+}
+
+#[derive(Serialize)]
+struct MatchSummary {
+    match_id: Uuid,
+    player_count: usize,
+    max_players: usize,
+    phase: MatchPhase,
+}
+
+async fn list_matches_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ListMatchesQuery>,
+) -> Json<ListMatchesResponse> {
+    let matches = state
+        .match_registry
+        .list()
+        .into_iter()
+        .filter(|m| !m.practice && (!query.joinable || m.is_joinable()))
+        .map(|m| MatchSummary {
+            match_id: m.id,
+            player_count: m.player_count(),
+            max_players: m.max_players,
+            phase: m.phase(),
+        })
+        .collect();
+
+    Json(ListMatchesResponse { matches })
+}
+
+#[derive(Deserialize)]
+struct MatchEventsQuery {
+    /// Only this `event_type`, e.g. "kill"
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// Only events this player took part in
+    player: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+struct MatchEventsResponse {
+    match_id: Uuid,
+    events: Vec<LoggedEvent>,
+    /// Older events dropped to keep the log bounded
+    dropped: u64,
+}
+
+/// Events of a recently finished match. Running matches are refused so the
+/// log can't be used to track other players mid-match
+async fn match_events_handler(
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    Query(query): Query<MatchEventsQuery>,
+) -> Result<Json<MatchEventsResponse>, AppError> {
+    let Some(log) = state.match_registry.finished_events(&match_id) else {
+        return Err(if state.match_registry.get(&match_id).is_some() {
+            AppError::Conflict("Match is still running".to_string())
+        } else {
+            AppError::NotFound("Match events not found".to_string())
+        });
+    };
+
+    let filter = EventFilter {
+        event_type: query.event_type,
+        player: query.player,
+    };
+    let (events, dropped) = log.query(&filter);
+
+    Ok(Json(MatchEventsResponse {
+        match_id,
+        events,
+        dropped,
+    }))
+}
+
+#[derive(Serialize)]
+struct AdminMatchResponse {
+    match_id: Uuid,
+    phase: MatchPhase,
+    player_count: usize,
+    /// Latest state published by the match loop, absent until its first
+    /// publish
+    state: Option<MatchInspection>,
+    /// How old `state` is; a large age means the match loop is stuck
+    state_age_ms: Option<u64>,
+}
+
+/// Live state of a running match, for admins investigating a report
+async fn admin_match_handler(
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    admin: Option<Extension<AuthenticatedUser>>,
+) -> Result<Json<AdminMatchResponse>, AppError> {
+    let handle = state
+        .match_registry
+        .get(&match_id)
+        .ok_or_else(|| AppError::NotFound("Match not found".to_string()))?;
+
+    info!(
+        match_id = %match_id,
+        admin = %admin.map_or("admin-token".to_string(), |Extension(user)| user.user_id.to_string()),
+        "Admin match inspection"
+    );
+
+    let inspection = handle.inspector.latest().map(|i| MatchInspection::clone(&i));
+    let state_age_ms = inspection
+        .as_ref()
+        .map(|i| unix_millis().saturating_sub(i.published_at));
+
+    Ok(Json(AdminMatchResponse {
+        match_id,
+        phase: handle.phase(),
+        player_count: handle.player_count(),
+        state: inspection,
+        state_age_ms,
+    }))
+}
+
+#[derive(Deserialize)]
+struct AdminGrantRequest {
+    item_id: Uuid,
+    /// Users to grant the item to
+    #[serde(default)]
+    user_ids: Vec<Uuid>,
+    /// Grant to every user instead of listing them
+    #[serde(default)]
+    all_users: bool,
+    /// What the grant is for, e.g. a promo name; granting again under the
+    /// same reference is a no-op
+    reference: String,
+}
+
+/// Grant an item to many users at once, for promotions
+async fn admin_grant_handler(
+    State(state): State<AppState>,
+    admin: Option<Extension<AuthenticatedUser>>,
+    Json(req): Json<AdminGrantRequest>,
+) -> Result<Json<BulkGrantResult>, AppError> {
+    let recipients = match (req.all_users, req.user_ids.is_empty()) {
+        (true, true) => GrantRecipients::Everyone,
+        (false, false) => GrantRecipients::Users(req.user_ids),
+        (true, false) => {
+            return Err(AppError::BadRequest(
+                "Give either user_ids or all_users, not both".to_string(),
+            ))
+        }
+        (false, true) => return Err(AppError::BadRequest("No users to grant to".to_string())),
+    };
+    let reference = req.reference.trim();
+    if reference.is_empty() {
+        return Err(AppError::BadRequest("A reference is required".to_string()));
+    }
+
+    let result = state
+        .inventory_store
+        .grant_item_bulk(&recipients, req.item_id, GrantSource::Admin, reference)
+        .await
+        .map_err(|e| match e {
+            SupabaseError::Api { status: 404, .. } => {
+                AppError::NotFound("Item not found".to_string())
+            }
+            e if is_pass_required(&e) => {
+                AppError::BadRequest("Battle pass items can't be granted in bulk".to_string())
+            }
+            e => AppError::Internal(e.to_string()),
+        })?;
+
+    info!(
+        item_id = %req.item_id,
+        reference = %reference,
+        all_users = req.all_users,
+        granted = result.granted,
+        already_owned = result.already_owned,
+        not_found = result.not_found,
+        admin = %admin.map_or("admin-token".to_string(), |Extension(user)| user.user_id.to_string()),
+        "Admin bulk grant"
+    );
+
+    Ok(Json(result))
+}
+
+// ============================================================================
+// Guest endpoint
+// ============================================================================
+
+#[derive(Serialize)]
+struct GuestResponse {
+    access_token: String,
+    user_id: Uuid,
+    display_name: String,
+    expires_in_secs: u64,
+}
+
+async fn guest_handler(State(state): State<AppState>) -> Result<Json<GuestResponse>, AppError> {
+    if !state.config.guest_play {
+        return Err(AppError::NotFound("Guest play is disabled".to_string()));
+    }
+
+    let ttl_secs = state.config.guest_token_ttl_secs;
+    let (user_id, access_token) =
+        issue_guest_token(&JwtValidation::from_config(&state.config), ttl_secs);
+
+    Ok(Json(GuestResponse {
+        access_token,
+        user_id,
+        display_name: guest_display_name(user_id),
+        expires_in_secs: ttl_secs,
+    }))
+}
+
+// ============================================================================
+// WebSocket ticket endpoint
+// ============================================================================
+
+#[derive(Serialize)]
+struct WsTicketResponse {
+    ticket: Uuid,
+    expires_in_secs: u64,
+}
+
+async fn ws_ticket_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+) -> Json<WsTicketResponse> {
+    Json(WsTicketResponse {
+        ticket: state.ws_tickets.issue(Credentials::from_claims(&auth.claims)),
+        expires_in_secs: TICKET_TTL.as_secs(),
     })
 }
 
+// ============================================================================
+// Profile endpoints
+// ============================================================================
+
+#[derive(Deserialize)]
+struct UpdateProfileRequest {
+    display_name: String,
+}
+
+async fn update_profile_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Json(req): Json<UpdateProfileRequest>,
+) -> Result<Json<UserProfile>, AppError> {
+    let display_name =
+        validate_display_name(&req.display_name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let default_name = format!("Player_{}", &auth.user_id.to_string()[..8]);
+    let profile = state
+        .profile_store
+        .ensure_profile(auth.user_id, &default_name)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if profile.display_name.as_deref() == Some(display_name.as_str()) {
+        return Ok(Json(profile));
+    }
+
+    // Rate limit name changes
+    let now = chrono::Utc::now();
+    if let Some(changed_at) = profile.display_name_changed_at {
+        let elapsed = (now - changed_at).num_seconds().max(0) as u64;
+        let cooldown = state.config.display_name_cooldown_secs;
+        if elapsed < cooldown {
+            return Err(AppError::BadRequest(format!(
+                "Display name can be changed again in {} minutes",
+                (cooldown - elapsed).div_ceil(60)
+            )));
+        }
+    }
+
+    let taken = state
+        .profile_store
+        .display_name_taken(&display_name, auth.user_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if taken {
+        return Err(AppError::BadRequest("Display name is taken".to_string()));
+    }
+
+    state
+        .profile_store
+        .update_profile(
+            auth.user_id,
+            ProfileUpdate {
+                display_name: Some(display_name.clone()),
+                display_name_changed_at: Some(now),
+                ..ProfileUpdate::default()
+            },
+        )
+        .await
+        .map_err(|e| match e {
+            // Lost a race with another rename to the same name (unique index)
+            SupabaseError::Api { status: 409, .. } => {
+                AppError::BadRequest("Display name is taken".to_string())
+            }
+            _ => AppError::Internal(e.to_string()),
+        })?;
+
+    state.matchmaking.set_display_name(auth.user_id, &display_name);
+
+    Ok(Json(UserProfile {
+        display_name: Some(display_name),
+        display_name_changed_at: Some(now),
+        ..profile
+    }))
+}
+
 // ============================================================================
 // Matchmaking endpoints
 // ============================================================================
@@ -95,6 +910,15 @@ async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
 #[derive(Deserialize)]
 struct JoinMatchRequest {
     ship_type: ShipType,
+    /// Weapon loadout (defaults to the ship type's weapon)
+    #[serde(default)]
+    weapon: Option<WeaponLoadout>,
+    /// Matchmaking region (defaults to the profile's last region)
+    #[serde(default)]
+    region: Option<String>,
+    /// Accessibility aim assist in the match
+    #[serde(default)]
+    aim_assist: bool,
 }
 
 #[derive(Serialize)]
@@ -102,6 +926,7 @@ struct JoinMatchResponse {
     status: &'static str,
     message: String,
     ws_url: String,
+    region: String,
 }
 
 async fn matchmaking_join_handler(
@@ -109,11 +934,20 @@ async fn matchmaking_join_handler(
     Extension(auth): Extension<AuthenticatedUser>,
     Json(req): Json<JoinMatchRequest>,
 ) -> Result<Json<JoinMatchResponse>, AppError> {
-    let player = QueuedPlayer::new(
+    let display_name = if auth.is_guest() {
+        guest_display_name(auth.user_id)
+    } else {
+        format!("Player_{}", &auth.user_id.to_string()[..8])
+    };
+    let region = resolve_region(&state, &auth, req.region).await?;
+    let mut player = QueuedPlayer::new(
         auth.user_id,
-        format!("Player_{}", &auth.user_id.to_string()[..8]),
+        display_name,
         req.ship_type,
+        req.weapon,
+        region.clone(),
     );
+    player.aim_assist = req.aim_assist;
 
     state
         .matchmaking
@@ -129,22 +963,179 @@ async fn matchmaking_join_handler(
         status: "queued",
         message: "Added to matchmaking queue".to_string(),
         ws_url,
+        region,
+    }))
+}
+
+#[derive(Serialize)]
+struct LeaveQueueResponse {
+    /// "left", or "not_queued" if the player wasn't in the queue
+    status: &'static str,
+    was_queued: bool,
+}
+
+/// Cancel a search. A player already placed in a match has to leave it
+/// over the WebSocket instead
+async fn matchmaking_leave_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+) -> Result<Json<LeaveQueueResponse>, AppError> {
+    let was_queued = state
+        .matchmaking
+        .leave_queue(auth.user_id)
+        .await
+        .map_err(|e| AppError::Conflict(e.to_string()))?;
+
+    Ok(Json(LeaveQueueResponse {
+        status: if was_queued { "left" } else { "not_queued" },
+        was_queued,
+    }))
+}
+
+#[derive(Deserialize)]
+struct PracticeRequest {
+    ship_type: ShipType,
+    /// Weapon loadout (defaults to the ship type's weapon)
+    #[serde(default)]
+    weapon: Option<WeaponLoadout>,
+    /// Accessibility aim assist in the match
+    #[serde(default)]
+    aim_assist: bool,
+}
+
+#[derive(Serialize)]
+struct PracticeResponse {
+    status: &'static str,
+    match_id: Uuid,
+}
+
+/// Start a practice match against bots right away, skipping the queue.
+/// The player must already be connected over the WebSocket, where the
+/// match is joined
+async fn matchmaking_practice_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Json(req): Json<PracticeRequest>,
+) -> Result<Json<PracticeResponse>, AppError> {
+    let match_id = state
+        .matchmaking
+        .start_practice(auth.user_id, req.ship_type, req.weapon, req.aim_assist)
+        .await
+        .map_err(|e| match e {
+            PracticeError::NotConnected | PracticeError::InMatch(_) => {
+                AppError::Conflict(e.to_string())
+            }
+            PracticeError::Loadout(_) => AppError::BadRequest(e.to_string()),
+        })?;
+
+    Ok(Json(PracticeResponse {
+        status: "started",
+        match_id,
     }))
 }
 
+/// Region to queue a player in: the requested one, remembered on the
+/// profile for next time, or else the profile's saved region. Guests
+/// have no profile and fall back to the server default
+async fn resolve_region(
+    state: &AppState,
+    auth: &AuthenticatedUser,
+    requested: Option<String>,
+) -> Result<String, AppError> {
+    let regions = &state.config.matchmaking_regions;
+    let requested = requested.map(|region| region.trim().to_lowercase());
+    if let Some(region) = &requested {
+        if !regions.contains(region) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown region '{}' (expected one of: {})",
+                region,
+                regions.join(", ")
+            )));
+        }
+    }
+    if auth.is_guest() {
+        return Ok(requested.unwrap_or_else(|| state.config.default_region().to_string()));
+    }
+
+    // The profile only stores a default, so a failed lookup shouldn't block matchmaking
+    let saved = match state.profile_store.get_profile(auth.user_id).await {
+        Ok(profile) => profile.and_then(|p| p.region),
+        Err(e) => {
+            warn!(user_id = %auth.user_id, error = %e, "Failed to load profile region");
+            None
+        }
+    };
+
+    match requested {
+        Some(region) => {
+            if saved.as_ref() != Some(&region) {
+                let update = ProfileUpdate {
+                    region: Some(region.clone()),
+                    ..ProfileUpdate::default()
+                };
+                if let Err(e) = state.profile_store.update_profile(auth.user_id, update).await {
+                    warn!(user_id = %auth.user_id, error = %e, "Failed to save profile region");
+                }
+            }
+            Ok(region)
+        }
+        None => Ok(saved
+            .filter(|region| regions.contains(region))
+            .unwrap_or_else(|| state.config.default_region().to_string())),
+    }
+}
+
 // ============================================================================
 // Payment endpoints
 // ============================================================================
 
 #[derive(Deserialize)]
 struct CheckoutRequest {
+    /// Cart lines to purchase
+    #[serde(default)]
+    items: Vec<CartLine>,
+    /// Legacy single-item checkout
+    item_id: Option<Uuid>,
+    /// How to pay for the cart
+    #[serde(default)]
+    pay_with: PayWith,
+    /// One-time purchase, or a recurring battle pass (Stripe only)
+    #[serde(default)]
+    mode: CheckoutMode,
+    /// Client-generated token for coin purchases; retries with the same token
+    /// and cart are charged once
+    idempotency_key: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum PayWith {
+    #[default]
+    Stripe,
+    Coins,
+}
+
+#[derive(Deserialize)]
+struct CartLine {
     item_id: Uuid,
+    #[serde(default = "default_quantity")]
+    quantity: u32,
+}
+
+fn default_quantity() -> u32 {
+    1
 }
 
 #[derive(Serialize)]
 struct CheckoutResponse {
-    session_id: String,
-    url: String,
+    /// Stripe session (Stripe checkout only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// Coin balance after the purchase (coin checkout only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance: Option<i64>,
 }
 
 async fn checkout_handler(
@@ -152,18 +1143,122 @@ async fn checkout_handler(
     Extension(auth): Extension<AuthenticatedUser>,
     Json(req): Json<CheckoutRequest>,
 ) -> Result<Json<CheckoutResponse>, AppError> {
+    let mut cart: Vec<(Uuid, u32)> = req
+        .items
+        .iter()
+        .map(|line| (line.item_id, line.quantity))
+        .collect();
+    if let Some(item_id) = req.item_id {
+        cart.push((item_id, 1));
+    }
+
+    let map_err = |e: StripeError| match e {
+        StripeError::ItemNotFound
+        | StripeError::InvalidCart
+        | StripeError::InvalidQuantity
+        | StripeError::NotStackable
+        | StripeError::NotCoinPriced
+        | StripeError::InsufficientFunds
+        | StripeError::InvalidMode
+        | StripeError::AlreadySubscribed
+        | StripeError::PassRequired => AppError::BadRequest(e.to_string()),
+        _ => AppError::Internal(e.to_string()),
+    };
+
+    if req.pay_with == PayWith::Coins {
+        if req.mode == CheckoutMode::Subscription {
+            return Err(AppError::BadRequest(
+                "Subscriptions can only be paid through Stripe".to_string(),
+            ));
+        }
+        let idempotency_key = req
+            .idempotency_key
+            .as_deref()
+            .filter(|key| {
+                (8..=64).contains(&key.len())
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            })
+            .ok_or_else(|| {
+                AppError::BadRequest(
+                    "Coin checkout requires an idempotency_key of 8-64 letters, digits, '-' or '_'"
+                        .to_string(),
+                )
+            })?;
+
+        let balance = state
+            .stripe
+            .checkout_with_coins(auth.user_id, &cart, idempotency_key)
+            .await
+            .map_err(map_err)?;
+
+        return Ok(Json(CheckoutResponse {
+            session_id: None,
+            url: None,
+            balance: Some(balance),
+        }));
+    }
+
     let response = state
         .stripe
-        .create_checkout_session(auth.user_id, req.item_id)
+        .create_checkout_session(auth.user_id, &cart, req.mode)
         .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+        .map_err(map_err)?;
 
     Ok(Json(CheckoutResponse {
-        session_id: response.session_id,
-        url: response.url,
+        session_id: Some(response.session_id),
+        url: Some(response.url),
+        balance: None,
     }))
 }
 
+// ============================================================================
+// Wallet endpoint
+// ============================================================================
+
+#[derive(Serialize)]
+struct WalletResponse {
+    balance: i64,
+}
+
+async fn wallet_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+) -> Result<Json<WalletResponse>, AppError> {
+    let balance = state
+        .wallet_store
+        .get_balance(auth.user_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(WalletResponse { balance }))
+}
+
+// ============================================================================
+// Stats endpoints
+// ============================================================================
+
+async fn my_stats_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+) -> Result<Json<CareerStats>, AppError> {
+    user_stats_handler(State(state), Path(auth.user_id)).await
+}
+
+async fn user_stats_handler(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<CareerStats>, AppError> {
+    let stats = state
+        .stats_store
+        .get_career_stats(user_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(stats))
+}
+
 // ============================================================================
 // Inventory endpoints
 // ============================================================================
@@ -178,6 +1273,9 @@ struct InventoryItem {
     item_id: Uuid,
     name: String,
     item_type: String,
+    /// Cosmetic slot the item fills, if it's a cosmetic
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<CosmeticSlot>,
     owned: bool,
     equipped: bool,
 }
@@ -192,22 +1290,68 @@ async fn inventory_handler(
         .await
         .map_err(|e| AppError::Internal(e.to_string()))?;
 
-    let response_items: Vec<InventoryItem> = items
+    Ok(Json(InventoryResponse {
+        items: inventory_items(items),
+    }))
+}
+
+/// Inventory entries with their item details, skipping any without
+fn inventory_items(items: Vec<InventoryItemWithDetails>) -> Vec<InventoryItem> {
+    items
         .into_iter()
         .filter_map(|i| {
             i.item.map(|details| InventoryItem {
                 item_id: i.item_id,
                 name: details.name,
+                slot: CosmeticSlot::for_item_type(&details.item_type),
                 item_type: details.item_type,
                 owned: i.owned,
                 equipped: i.equipped,
             })
         })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct InventoryHistoryResponse {
+    grants: Vec<InventoryGrantEntry>,
+}
+
+#[derive(Serialize)]
+struct InventoryGrantEntry {
+    item_id: Uuid,
+    name: String,
+    item_type: String,
+    source: GrantSource,
+    reference_id: String,
+    granted_at: chrono::DateTime<chrono::Utc>,
+}
+
+async fn inventory_history_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+) -> Result<Json<InventoryHistoryResponse>, AppError> {
+    let grants = state
+        .inventory_store
+        .grant_history(auth.user_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let grants = grants
+        .into_iter()
+        .filter_map(|g| {
+            g.item.map(|details| InventoryGrantEntry {
+                item_id: g.item_id,
+                name: details.name,
+                item_type: details.item_type,
+                source: g.source,
+                reference_id: g.reference_id,
+                granted_at: g.granted_at,
+            })
+        })
         .collect();
 
-    Ok(Json(InventoryResponse {
-        items: response_items,
-    }))
+    Ok(Json(InventoryHistoryResponse { grants }))
 }
 
 #[derive(Deserialize)]
@@ -241,7 +1385,15 @@ async fn equip_handler(
         .inventory_store
         .equip_item(auth.user_id, req.item_id)
         .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+        .map_err(|e| {
+            if is_pass_required(&e) {
+                AppError::BadRequest("This item requires an active battle pass".to_string())
+            } else if is_not_equippable(&e) {
+                AppError::BadRequest("This item can't be equipped".to_string())
+            } else {
+                AppError::Internal(e.to_string())
+            }
+        })?;
 
     Ok(Json(EquipResponse {
         success: true,
@@ -249,6 +1401,50 @@ async fn equip_handler(
     }))
 }
 
+#[derive(Serialize)]
+struct UnequipResponse {
+    success: bool,
+    message: String,
+    /// Items still equipped after the change
+    equipped: Vec<InventoryItem>,
+}
+
+/// Unequip an owned item. Unequipping an item that isn't equipped succeeds
+/// without changing anything
+async fn unequip_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Json(req): Json<EquipRequest>,
+) -> Result<Json<UnequipResponse>, AppError> {
+    let owns = state
+        .inventory_store
+        .user_owns_item(auth.user_id, req.item_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if !owns {
+        return Err(AppError::BadRequest("You don't own this item".to_string()));
+    }
+
+    state
+        .inventory_store
+        .unequip_item(auth.user_id, req.item_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let equipped = state
+        .inventory_store
+        .get_equipped_items(auth.user_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(UnequipResponse {
+        success: true,
+        message: "Item unequipped".to_string(),
+        equipped: inventory_items(equipped.into_values().collect()),
+    }))
+}
+
 // ============================================================================
 // Error handling
 // ============================================================================
@@ -261,6 +1457,9 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Unauthorized")]
     Unauthorized,
 
@@ -273,6 +1472,7 @@ impl IntoResponse for AppError {
         let (status, message) = match &self {
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
         };
@@ -2,6 +2,7 @@
 //!
 //! This is the main entry point for the game server. It handles:
 //! - WebSocket connections for real-time gameplay
+//! - Optional built-in TLS for deployments without a terminating proxy
 //! - HTTP endpoints for matchmaking, inventory, and payments
 //! - Stripe webhook processing
 //! - Supabase integration for user data
@@ -17,18 +18,31 @@ mod util;
 mod ws;
 
 use std::net::SocketAddr;
+use std::path::Path;
 
+use anyhow::Context;
+use axum_server::tls_rustls::RustlsConfig;
 use tokio::net::TcpListener;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::app::AppState;
-use crate::config::Config;
+use crate::config::{Config, LogFormat};
+use crate::game::replay::Replay;
+use crate::game::GameMatch;
 use crate::http::build_router;
 use crate::util::time::init_server_time;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // `replay <file>` re-runs a recorded match instead of starting the server
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, command, path] = args.as_slice() {
+        if command == "replay" {
+            return verify_replay(Path::new(path));
+        }
+    }
+
     // Load environment variables
     dotenvy::dotenv().ok();
 
@@ -36,7 +50,7 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::from_env()?;
 
     // Initialize tracing
-    init_tracing(&config.log_level);
+    init_tracing(&config.log_level, config.log_format);
 
     // Initialize server time tracking
     init_server_time();
@@ -45,7 +59,7 @@ async fn main() -> anyhow::Result<()> {
     info!("Server address: {}", config.server_addr);
 
     // Create application state
-    let state = AppState::new(config.clone());
+    let state = AppState::new(config.clone())?;
 
     // Spawn matchmaking service
     let matchmaking = state.matchmaking.clone();
@@ -53,34 +67,119 @@ async fn main() -> anyhow::Result<()> {
         matchmaking.run().await;
     });
 
+    // Drop idle per-user rate limiter state
+    let rate_limiters = state.rate_limiters.clone();
+    tokio::spawn(async move {
+        rate_limiters.run_cleanup().await;
+    });
+
     // Build router
     let router = build_router(state);
 
     // Start server
     let addr: SocketAddr = config.server_addr;
-    let listener = TcpListener::bind(addr).await?;
+    // Peer addresses feed the per-IP limits
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+    match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls = load_tls(cert_path, key_path).await?;
+
+            info!("Server listening on {} (TLS)", addr);
+            info!("Health check: https://{}/health", addr);
+            info!("WebSocket endpoint: wss://{}/ws", addr);
+
+            let handle = axum_server::Handle::new();
+            let shutdown = handle.clone();
+            tokio::spawn(async move {
+                shutdown_signal().await;
+                shutdown.graceful_shutdown(None);
+            });
+
+            axum_server::bind_rustls(addr, tls)
+                .handle(handle)
+                .serve(service)
+                .await?;
+        }
+        _ => {
+            let listener = TcpListener::bind(addr).await?;
 
-    info!("Server listening on {}", addr);
-    info!("Health check: http://{}/health", addr);
-    info!("WebSocket endpoint: ws://{}/ws", addr);
+            info!("Server listening on {}", addr);
+            info!("Health check: http://{}/health", addr);
+            info!("WebSocket endpoint: ws://{}/ws", addr);
 
-    axum::serve(listener, router)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+            axum::serve(listener, service)
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+        }
+    }
 
     info!("Server shutdown complete");
     Ok(())
 }
 
+/// Re-run a recording and check it reproduces the live match's stats
+fn verify_replay(path: &Path) -> anyhow::Result<()> {
+    let replay = Replay::from_file(path)?;
+    let stats = GameMatch::replay(&replay);
+
+    println!(
+        "match {}: {} inputs over {} ticks",
+        replay.header.match_id,
+        replay.inputs.len(),
+        replay.end_tick
+    );
+    if stats != replay.stats {
+        println!("recorded: {}", serde_json::to_string_pretty(&replay.stats)?);
+        println!("replayed: {}", serde_json::to_string_pretty(&stats)?);
+        anyhow::bail!("replay diverged from the recorded match");
+    }
+
+    println!("replay matches the recorded stats");
+    Ok(())
+}
+
+/// Load the certificate chain and private key for serving TLS directly,
+/// naming the file at fault if either is missing or isn't usable PEM
+async fn load_tls(cert_path: &str, key_path: &str) -> anyhow::Result<RustlsConfig> {
+    let cert = tokio::fs::read(cert_path)
+        .await
+        .with_context(|| format!("Failed to read TLS_CERT_PATH {}", cert_path))?;
+    let key = tokio::fs::read(key_path)
+        .await
+        .with_context(|| format!("Failed to read TLS_KEY_PATH {}", key_path))?;
+
+    RustlsConfig::from_pem(cert, key).await.with_context(|| {
+        format!(
+            "Invalid TLS certificate {} or key {} (expected a PEM certificate chain and one PEM private key that matches it)",
+            cert_path, key_path
+        )
+    })
+}
+
 /// Initialize tracing/logging
-fn init_tracing(log_level: &str) {
+fn init_tracing(log_level: &str, format: LogFormat) {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(log_level));
 
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(tracing_subscriber::fmt::layer().with_target(true))
-        .init();
+    let registry = tracing_subscriber::registry().with(env_filter);
+    match format {
+        // Event fields (user_id, match_id, ...) become top-level keys next to
+        // `message`, and the fields of the enclosing span go under `span`
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_target(true),
+            )
+            .init(),
+        LogFormat::Pretty => registry
+            .with(tracing_subscriber::fmt::layer().with_target(true))
+            .init(),
+    }
 }
 
 /// Graceful shutdown signal handler
@@ -0,0 +1,129 @@
+//! Tracking of players who leave matches early, and the resulting queue cooldown
+
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// When repeated abandons trigger a matchmaking cooldown
+#[derive(Debug, Clone, Copy)]
+pub struct AbandonPolicy {
+    /// Abandons within `window` that trigger the cooldown
+    pub threshold: usize,
+    /// How far back abandons count towards the threshold
+    pub window: Duration,
+    /// Time after the latest abandon before the player may queue again
+    pub cooldown: Duration,
+}
+
+impl Default for AbandonPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 2,
+            window: Duration::from_secs(30 * 60),
+            cooldown: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Recent abandons per player (in memory; the lifetime count lives in `profiles`)
+pub struct AbandonTracker {
+    policy: AbandonPolicy,
+    recent: DashMap<Uuid, Vec<Instant>>,
+}
+
+impl AbandonTracker {
+    pub fn new(policy: AbandonPolicy) -> Self {
+        Self {
+            policy,
+            recent: DashMap::new(),
+        }
+    }
+
+    /// Record that a player abandoned a match
+    pub fn record(&self, user_id: Uuid) {
+        self.record_at(user_id, Instant::now());
+    }
+
+    /// Record an abandon that happened at `now`
+    fn record_at(&self, user_id: Uuid, now: Instant) {
+        let mut entry = self.recent.entry(user_id).or_default();
+        entry.retain(|t| now.duration_since(*t) < self.policy.window);
+        entry.push(now);
+    }
+
+    /// Remaining cooldown for a player, if they are currently penalized
+    pub fn cooldown_remaining(&self, user_id: Uuid) -> Option<Duration> {
+        self.cooldown_remaining_at(user_id, Instant::now())
+    }
+
+    /// Remaining cooldown for a player as of `now`
+    fn cooldown_remaining_at(&self, user_id: Uuid, now: Instant) -> Option<Duration> {
+        let entry = self.recent.get(&user_id)?;
+
+        let recent = entry
+            .iter()
+            .filter(|t| now.duration_since(**t) < self.policy.window)
+            .count();
+        if recent < self.policy.threshold {
+            return None;
+        }
+
+        let latest = entry.iter().max()?;
+        self.policy
+            .cooldown
+            .checked_sub(now.duration_since(*latest))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER: Uuid = Uuid::from_u128(1);
+
+    fn tracker() -> AbandonTracker {
+        AbandonTracker::new(AbandonPolicy {
+            threshold: 2,
+            window: Duration::from_secs(600),
+            cooldown: Duration::from_secs(120),
+        })
+    }
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn cooldown_starts_at_the_threshold_and_restarts_with_each_abandon() {
+        let tracker = tracker();
+        let start = Instant::now();
+
+        tracker.record_at(PLAYER, start);
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start), None);
+
+        tracker.record_at(PLAYER, start + secs(60));
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start + secs(90)), Some(secs(90)));
+
+        // Another abandon runs the cooldown from the latest one
+        tracker.record_at(PLAYER, start + secs(100));
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start + secs(100)), Some(secs(120)));
+
+        assert_eq!(tracker.cooldown_remaining_at(Uuid::from_u128(2), start + secs(100)), None);
+    }
+
+    #[test]
+    fn cooldown_expires_and_old_abandons_stop_counting() {
+        let tracker = tracker();
+        let start = Instant::now();
+        tracker.record_at(PLAYER, start);
+        tracker.record_at(PLAYER, start + secs(10));
+
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start + secs(129)), Some(secs(1)));
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start + secs(130)), None);
+
+        // The first abandon has left the window, so one more isn't enough
+        tracker.record_at(PLAYER, start + secs(700));
+        assert_eq!(tracker.cooldown_remaining_at(PLAYER, start + secs(700)), None);
+    }
+}
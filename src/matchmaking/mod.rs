@@ -1,6 +1,8 @@
 //! Matchmaking system modules
 
+pub mod abandon;
 pub mod queue;
+pub mod rewards;
 pub mod service;
 
 pub use service::MatchmakingService;
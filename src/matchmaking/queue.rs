@@ -1,10 +1,11 @@
 //! Matchmaking queue implementation
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::ws::protocol::ShipType;
+use crate::game::Cosmetics;
+use crate::ws::protocol::{ShipType, WeaponLoadout};
 
 /// Player in the matchmaking queue
 #[derive(Debug, Clone)]
@@ -12,17 +13,34 @@ pub struct QueuedPlayer {
     pub user_id: Uuid,
     pub display_name: String,
     pub ship_type: ShipType,
-    pub flag_skin_id: Option<Uuid>,
+    /// Chosen weapon (ship type default if unset), checked against inventory on join
+    pub weapon: Option<WeaponLoadout>,
+    /// Equipped cosmetics, resolved from inventory when queueing
+    pub cosmetics: Cosmetics,
+    /// Asked for accessibility aim assist
+    pub aim_assist: bool,
+    /// Latency region (e.g. "us-east"); matches only group one region
+    /// until someone has waited long enough for cross-region fill
+    pub region: String,
     pub queued_at: Instant,
 }
 
 impl QueuedPlayer {
-    pub fn new(user_id: Uuid, display_name: String, ship_type: ShipType) -> Self {
+    pub fn new(
+        user_id: Uuid,
+        display_name: String,
+        ship_type: ShipType,
+        weapon: Option<WeaponLoadout>,
+        region: String,
+    ) -> Self {
         Self {
             user_id,
             display_name,
             ship_type,
-            flag_skin_id: None,
+            weapon,
+            cosmetics: Cosmetics::default(),
+            aim_assist: false,
+            region,
             queued_at: Instant::now(),
         }
     }
@@ -33,15 +51,48 @@ impl QueuedPlayer {
     }
 }
 
+/// Where a queued player stands, reported to them while they wait
+#[derive(Debug, Clone, Copy)]
+pub struct QueuePosition {
+    pub user_id: Uuid,
+    /// 1-based place among connected queued players in the same region
+    pub position: usize,
+    /// Connected queued players in the same region
+    pub queue_size: usize,
+    pub estimated_wait: Duration,
+}
+
 /// The matchmaking queue
 pub struct MatchmakingQueue {
     queue: VecDeque<QueuedPlayer>,
     /// Minimum players to start a match
     min_players: usize,
-    /// Maximum players per match
+    /// Largest match size, given to new matches once the queue can fill it
     max_players: usize,
+    /// Smallest match size given to a new match, however short the queue
+    smallest_match: usize,
     /// Max time to wait before starting with fewer players
     max_wait_time: Duration,
+    /// Wait after which a region short of players fills from other regions
+    cross_region_wait: Duration,
+}
+
+/// Players the next match should be formed from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchGroup {
+    /// Only players from this region
+    Region(String),
+    /// Anyone, after a long wait in a region without enough players
+    AnyRegion,
+}
+
+impl MatchGroup {
+    fn admits(&self, player: &QueuedPlayer) -> bool {
+        match self {
+            MatchGroup::Region(region) => &player.region == region,
+            MatchGroup::AnyRegion => true,
+        }
+    }
 }
 
 impl MatchmakingQueue {
@@ -50,10 +101,49 @@ impl MatchmakingQueue {
             queue: VecDeque::new(),
             min_players,
             max_players,
+            smallest_match: max_players,
             max_wait_time: Duration::from_secs(max_wait_secs),
+            cross_region_wait: Duration::from_secs(30),
         }
     }
 
+    /// Set how long a player waits before their match may take players
+    /// from other regions
+    pub fn with_cross_region_wait(mut self, wait: Duration) -> Self {
+        self.cross_region_wait = wait;
+        self
+    }
+
+    /// Size new matches from queue pressure, between `smallest` and
+    /// `max_players`, instead of always at `max_players`
+    pub fn with_smallest_match(mut self, smallest: usize) -> Self {
+        self.smallest_match = smallest;
+        self
+    }
+
+    /// Size of a new match taking from `waiting` players: as few matches as
+    /// `max_players` allows, split evenly, but at least the smallest match
+    /// size so a short queue still leaves room for latecomers
+    pub fn match_size(&self, waiting: usize) -> usize {
+        let max = self.max_players.max(1);
+        let matches = waiting.div_ceil(max).max(1);
+        waiting
+            .div_ceil(matches)
+            .max(self.smallest_match.clamp(self.min_players, max))
+            .min(max)
+    }
+
+    /// Size of the next match formed from a group, by how many of its
+    /// connected players are waiting
+    pub fn group_match_size(&self, connected_ids: &HashSet<Uuid>, group: &MatchGroup) -> usize {
+        let waiting = self
+            .queue
+            .iter()
+            .filter(|p| connected_ids.contains(&p.user_id) && group.admits(p))
+            .count();
+        self.match_size(waiting)
+    }
+
     /// Add a player to the queue
     pub fn enqueue(&mut self, player: QueuedPlayer) {
         // Remove if already in queue (rejoin)
@@ -123,25 +213,96 @@ impl MatchmakingQueue {
         self.queue.iter()
     }
 
-    /// Check if any connected player has waited too long
-    pub fn has_waited_too_long(&self, connected_ids: &std::collections::HashSet<Uuid>) -> bool {
-        self.queue
-            .iter()
-            .filter(|p| connected_ids.contains(&p.user_id))
-            .any(|p| p.wait_time() >= self.max_wait_time)
+    /// Connected queued players per region, in queue order. Regions are
+    /// ordered by their longest-waiting player
+    fn connected_by_region<'a>(
+        &'a self,
+        connected_ids: &HashSet<Uuid>,
+    ) -> Vec<(&'a str, Vec<&'a QueuedPlayer>)> {
+        let mut regions: Vec<(&str, Vec<&QueuedPlayer>)> = Vec::new();
+        for player in self.queue.iter().filter(|p| connected_ids.contains(&p.user_id)) {
+            match regions.iter_mut().find(|(region, _)| *region == player.region) {
+                Some((_, players)) => players.push(player),
+                None => regions.push((&player.region, vec![player])),
+            }
+        }
+        regions
+    }
+
+    /// Pick the group to form the next match from, if any is ready. A region
+    /// is ready once it has `min_players`, or its longest-waiting player has
+    /// waited `max_wait_time`. Before that, a region still short of players
+    /// after `cross_region_wait` fills from every region
+    pub fn next_group(&self, connected_ids: &HashSet<Uuid>) -> Option<MatchGroup> {
+        let regions = self.connected_by_region(connected_ids);
+        let total: usize = regions.iter().map(|(_, players)| players.len()).sum();
+
+        for (region, players) in &regions {
+            let oldest_wait = players[0].wait_time();
+            if players.len() >= self.min_players {
+                return Some(MatchGroup::Region(region.to_string()));
+            }
+            if oldest_wait >= self.cross_region_wait && total >= self.min_players {
+                return Some(MatchGroup::AnyRegion);
+            }
+            if oldest_wait >= self.max_wait_time {
+                return Some(MatchGroup::Region(region.to_string()));
+            }
+        }
+        None
     }
 
-    /// Drain connected players up to max_count for match formation
+    /// Queued players per region
+    pub fn region_sizes(&self) -> BTreeMap<String, usize> {
+        let mut sizes = BTreeMap::new();
+        for player in &self.queue {
+            *sizes.entry(player.region.clone()).or_insert(0) += 1;
+        }
+        sizes
+    }
+
+    /// Position and estimated wait of every connected queued player within
+    /// their region. Each region is matched in batches of the match size its
+    /// queue calls for; a batch starts as soon as it has `min_players`, otherwise once its
+    /// oldest player has waited `max_wait_time`
+    pub fn positions(&self, connected_ids: &HashSet<Uuid>) -> Vec<QueuePosition> {
+        let mut positions = Vec::new();
+        for (_, players) in self.connected_by_region(connected_ids) {
+            let batch = self.match_size(players.len()).max(1);
+            for (i, player) in players.iter().enumerate() {
+                let batch_start = i - i % batch;
+                let estimated_wait = if players.len() - batch_start >= self.min_players {
+                    Duration::ZERO
+                } else {
+                    self.max_wait_time
+                        .saturating_sub(players[batch_start].wait_time())
+                };
+                positions.push(QueuePosition {
+                    user_id: player.user_id,
+                    position: i + 1,
+                    queue_size: players.len(),
+                    estimated_wait,
+                });
+            }
+        }
+        positions
+    }
+
+    /// Drain connected players of a group, up to max_count, for match formation
     pub fn drain_connected(
         &mut self,
-        connected_ids: &std::collections::HashSet<Uuid>,
+        connected_ids: &HashSet<Uuid>,
+        group: &MatchGroup,
         max_count: usize,
     ) -> impl Iterator<Item = QueuedPlayer> + '_ {
         let mut extracted = Vec::new();
         let mut remaining = VecDeque::new();
 
         while let Some(player) = self.queue.pop_front() {
-            if connected_ids.contains(&player.user_id) && extracted.len() < max_count {
+            if connected_ids.contains(&player.user_id)
+                && group.admits(&player)
+                && extracted.len() < max_count
+            {
                 extracted.push(player);
             } else {
                 remaining.push_back(player);
@@ -153,8 +314,109 @@ impl MatchmakingQueue {
     }
 }
 
-impl Default for MatchmakingQueue {
-    fn default() -> Self {
-        Self::new(1, 20, 5) // 1-20 players, 5 second max wait (solo play allowed for testing)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(n: u128, region: &str, waited_secs: u64) -> QueuedPlayer {
+        QueuedPlayer {
+            queued_at: Instant::now() - Duration::from_secs(waited_secs),
+            ..QueuedPlayer::new(
+                Uuid::from_u128(n),
+                format!("Pilot {n}"),
+                ShipType::Fighter,
+                None,
+                region.to_string(),
+            )
+        }
+    }
+
+    fn queue_of(players: Vec<QueuedPlayer>) -> (MatchmakingQueue, HashSet<Uuid>) {
+        let mut queue = MatchmakingQueue::new(2, 4, 60).with_cross_region_wait(Duration::from_secs(20));
+        let connected = players.iter().map(|p| p.user_id).collect();
+        for player in players {
+            queue.enqueue(player);
+        }
+        (queue, connected)
+    }
+
+    #[test]
+    fn matches_only_group_one_region() {
+        let (mut queue, connected) = queue_of(vec![
+            queued(1, "us-east", 0),
+            queued(2, "eu-west", 0),
+            queued(3, "us-east", 0),
+        ]);
+
+        let group = queue.next_group(&connected).unwrap();
+        assert_eq!(group, MatchGroup::Region("us-east".to_string()));
+        let ids: Vec<u128> = queue
+            .drain_connected(&connected, &group, 4)
+            .map(|p| p.user_id.as_u128())
+            .collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn short_regions_wait_for_more_players() {
+        let (queue, connected) = queue_of(vec![
+            queued(1, "us-east", 5),
+            queued(2, "eu-west", 5),
+        ]);
+        assert_eq!(queue.next_group(&connected), None);
+    }
+
+    #[test]
+    fn long_waits_fill_from_other_regions() {
+        let (mut queue, connected) = queue_of(vec![
+            queued(1, "us-east", 25),
+            queued(2, "eu-west", 5),
+        ]);
+
+        let group = queue.next_group(&connected).unwrap();
+        assert_eq!(group, MatchGroup::AnyRegion);
+        assert_eq!(queue.drain_connected(&connected, &group, 4).count(), 2);
+    }
+
+    #[test]
+    fn positions_count_within_a_region() {
+        let (queue, connected) = queue_of(vec![
+            queued(1, "us-east", 0),
+            queued(2, "eu-west", 0),
+            queued(3, "eu-west", 0),
+        ]);
+
+        let positions = queue.positions(&connected);
+        let third = positions.iter().find(|p| p.user_id.as_u128() == 3).unwrap();
+        assert_eq!((third.position, third.queue_size), (2, 2));
+        assert_eq!(third.estimated_wait, Duration::ZERO);
+        let first = positions.iter().find(|p| p.user_id.as_u128() == 1).unwrap();
+        assert_eq!((first.position, first.queue_size), (1, 1));
+        assert!(first.estimated_wait > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn a_long_queue_splits_into_even_full_matches() {
+        let mut queue = MatchmakingQueue::new(2, 20, 60).with_smallest_match(8);
+        let mut connected = HashSet::new();
+        for n in 1..=45 {
+            connected.insert(Uuid::from_u128(n));
+            queue.enqueue(queued(n, "us-east", 0));
+        }
+
+        let mut sizes = Vec::new();
+        while let Some(group) = queue.next_group(&connected) {
+            let size = queue.group_match_size(&connected, &group);
+            let drained = queue.drain_connected(&connected, &group, size).count();
+            assert_eq!(drained, size, "every match starts full");
+            sizes.push(size);
+        }
+        assert_eq!(sizes, [15, 15, 15]);
+
+        // A short queue still gets room to grow, up to the largest size
+        assert_eq!(queue.match_size(3), 8);
+        assert_eq!(queue.match_size(20), 20);
+        assert_eq!(queue.match_size(21), 11);
     }
 }
@@ -0,0 +1,47 @@
+//! Soft currency rewards for logins and match results
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::ws::protocol::PlayerMatchStats;
+
+/// Coins for the first connection of each (UTC) day
+pub const DAILY_LOGIN_REWARD: u32 = 50;
+
+/// Coins for finishing a match at all
+const PARTICIPATION_REWARD: u32 = 10;
+
+/// Coins per kill
+const KILL_REWARD: u32 = 5;
+
+/// Bonus coins for 1st, 2nd and 3rd place
+const PODIUM_REWARDS: [u32; 3] = [100, 60, 40];
+
+/// Bonus coins for placing in the top half outside the podium
+const TOP_HALF_REWARD: u32 = 20;
+
+/// Coins earned for a match result. Bots, guests and players who abandoned
+/// earn nothing
+pub fn match_reward(stats: &PlayerMatchStats, total_players: u32) -> u32 {
+    if stats.is_bot || stats.is_guest || stats.abandoned {
+        return 0;
+    }
+
+    let placement_bonus = match stats.placement {
+        1..=3 => PODIUM_REWARDS[stats.placement as usize - 1],
+        p if p <= total_players.div_ceil(2) => TOP_HALF_REWARD,
+        _ => 0,
+    };
+
+    PARTICIPATION_REWARD + placement_bonus + stats.kills * KILL_REWARD
+}
+
+/// Idempotency key for a user's daily login reward
+pub fn daily_login_key(user_id: Uuid, day: NaiveDate) -> String {
+    format!("daily_login:{}:{}", user_id, day)
+}
+
+/// Idempotency key for a user's reward from a match
+pub fn match_reward_key(match_id: Uuid, user_id: Uuid) -> String {
+    format!("match_reward:{}:{}", match_id, user_id)
+}
@@ -1,24 +1,75 @@
 //! Matchmaking service - manages queue and match creation
 
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
-use tracing::{error, info, warn};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::game::{GameMatch, MatchRegistry, PlayerInput};
-use crate::ws::protocol::ServerMsg;
+use crate::game::arena::Arena;
+use crate::game::metrics::MatchMetrics;
+use crate::game::snapshot::SnapshotMode;
+use crate::game::{Cosmetics, GameMatch, MatchOutcome, MatchRegistry, MatchSettings, PlayerInput};
+use crate::store::{InventoryStore, ProfileStore, StatsStore, WalletStore};
+use crate::util::counters::ServerCounters;
+use crate::util::latency::LatencyTracker;
+use crate::ws::protocol::{ClientMsg, ErrorCode, ServerMsg, ShipType, WeaponLoadout};
 
+use super::abandon::{AbandonPolicy, AbandonTracker};
 use super::queue::{MatchmakingQueue, QueuedPlayer};
+use super::rewards::{match_reward, match_reward_key};
+
+/// How often queued players are sent `ServerMsg::QueueStatus`
+const QUEUE_STATUS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// What to do when a user connects while already connected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateConnectionPolicy {
+    /// Close the older connection; the new one takes over its match or
+    /// queue spot, so a reconnect after a dropped link just works
+    #[default]
+    EvictOld,
+    /// Refuse the new connection with `already_connected`
+    RejectNew,
+}
+
+/// A new connection was refused because the user already has one
+#[derive(Debug, thiserror::Error)]
+#[error("Already connected from another session")]
+pub struct AlreadyConnected;
+
+/// Channels of a registered connection
+pub struct Registration {
+    /// Tells this connection apart from other connections of the same user
+    pub connection_id: Uuid,
+    pub input_tx: mpsc::Sender<PlayerInput>,
+    pub snapshot_rx: broadcast::Receiver<ServerMsg>,
+    /// Notified when a newer connection of the same user replaces this one
+    pub evicted: Arc<Notify>,
+}
 
 /// Player connection handle for routing messages
 #[derive(Clone)]
 pub struct PlayerConnection {
     pub user_id: Uuid,
+    /// Distinguishes this connection from earlier or later ones of the user
+    pub connection_id: Uuid,
+    /// Name shown to other players in matches
+    pub display_name: String,
+    /// Connected with a guest token
+    pub guest: bool,
+    /// Measured round-trip time of the WebSocket
+    pub latency: Arc<LatencyTracker>,
     /// Channel to send inputs to current match
     pub input_tx: mpsc::Sender<PlayerInput>,
     /// Channel to receive snapshots from current match
     pub snapshot_rx: broadcast::Sender<ServerMsg>,
+    /// Tells the session it has been replaced
+    evicted: Arc<Notify>,
 }
 
 /// Matchmaking service
@@ -26,60 +77,261 @@ pub struct MatchmakingService {
     queue: Arc<Mutex<MatchmakingQueue>>,
     registry: Arc<MatchRegistry>,
     /// Connected players awaiting or in matches
-    players: DashMap<Uuid, PlayerConnection>,
+    players: Arc<DashMap<Uuid, PlayerConnection>>,
     /// Map of player -> current match
-    player_matches: DashMap<Uuid, Uuid>,
+    player_matches: Arc<DashMap<Uuid, Uuid>>,
+    /// Settings applied to every match this service creates
+    match_settings: MatchSettings,
+    /// Arenas new matches are played on, picked per match by its seed
+    arenas: Vec<Arc<Arena>>,
+    /// Persists lifetime abandon counts
+    profile_store: ProfileStore,
+    /// Weapon loadout unlocks
+    inventory_store: InventoryStore,
+    /// Currency rewards for match results
+    wallet_store: WalletStore,
+    /// Persists finished matches and XP
+    stats_store: StatsStore,
+    /// Recent abandons, for the matchmaking cooldown
+    abandons: Arc<AbandonTracker>,
+    /// Match and queue counters for `/metrics`
+    counters: Arc<ServerCounters>,
+    /// Handling of a second connection for an already connected user
+    duplicate_policy: DuplicateConnectionPolicy,
+    /// Bots flying against the player in a practice match
+    practice_bots: usize,
+}
+
+/// Reasons a direct join by match ID can be refused
+#[derive(Debug, thiserror::Error)]
+pub enum DirectJoinError {
+    #[error("Already in a match")]
+    AlreadyInMatch,
+
+    #[error("Match not found")]
+    MatchNotFound,
+
+    #[error("Match is full")]
+    MatchFull,
+
+    #[error("Match already in progress")]
+    MatchInProgress,
+
+    #[error("Matchmaking cooldown for leaving matches early: try again in {0} seconds")]
+    Cooldown(u64),
+
+    #[error("Weapon loadout is not unlocked")]
+    LoadoutLocked,
+
+    #[error("Could not verify weapon loadout")]
+    LoadoutCheckFailed,
+}
+
+/// Reasons a practice match can't be started
+#[derive(Debug, thiserror::Error)]
+pub enum PracticeError {
+    #[error("Connect over the WebSocket before starting practice")]
+    NotConnected,
+
+    #[error("Already in match {0}; leave it with leave_match over the WebSocket")]
+    InMatch(Uuid),
+
+    #[error(transparent)]
+    Loadout(#[from] DirectJoinError),
+}
+
+/// Reasons leaving the queue can be refused
+#[derive(Debug, thiserror::Error)]
+pub enum LeaveQueueError {
+    #[error("Already in match {0}; leave it with leave_match over the WebSocket")]
+    InMatch(Uuid),
+}
+
+impl DirectJoinError {
+    /// Error code sent to the client in `ServerMsg::Error`
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            DirectJoinError::AlreadyInMatch => ErrorCode::AlreadyInMatch,
+            DirectJoinError::MatchNotFound => ErrorCode::MatchNotFound,
+            DirectJoinError::MatchFull => ErrorCode::MatchFull,
+            DirectJoinError::MatchInProgress => ErrorCode::MatchInProgress,
+            DirectJoinError::Cooldown(_) => ErrorCode::MatchmakingCooldown,
+            DirectJoinError::LoadoutLocked => ErrorCode::LoadoutLocked,
+            DirectJoinError::LoadoutCheckFailed => ErrorCode::LoadoutCheckFailed,
+        }
+    }
 }
 
 impl MatchmakingService {
-    pub fn new(registry: Arc<MatchRegistry>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        queue: MatchmakingQueue,
+        registry: Arc<MatchRegistry>,
+        match_settings: MatchSettings,
+        profile_store: ProfileStore,
+        inventory_store: InventoryStore,
+        wallet_store: WalletStore,
+        stats_store: StatsStore,
+        abandon_policy: AbandonPolicy,
+    ) -> Self {
         Self {
-            queue: Arc::new(Mutex::new(MatchmakingQueue::default())),
+            queue: Arc::new(Mutex::new(queue)),
             registry,
-            players: DashMap::new(),
-            player_matches: DashMap::new(),
+            players: Arc::new(DashMap::new()),
+            player_matches: Arc::new(DashMap::new()),
+            arenas: vec![match_settings.arena.clone()],
+            match_settings,
+            profile_store,
+            inventory_store,
+            wallet_store,
+            stats_store,
+            abandons: Arc::new(AbandonTracker::new(abandon_policy)),
+            counters: Arc::new(ServerCounters::default()),
+            duplicate_policy: DuplicateConnectionPolicy::default(),
+            practice_bots: 3,
         }
     }
 
+    /// Rotate new matches through these arenas instead of only the one in
+    /// the match settings. Ignored if empty
+    pub fn with_arenas(mut self, arenas: Vec<Arc<Arena>>) -> Self {
+        if !arenas.is_empty() {
+            self.arenas = arenas;
+        }
+        self
+    }
+
+    /// Count matches and queue changes in the given counters
+    pub fn with_counters(mut self, counters: Arc<ServerCounters>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Handle a user connecting again while still connected this way
+    pub fn with_duplicate_policy(mut self, policy: DuplicateConnectionPolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Fly this many bots against the player in practice matches
+    pub fn with_practice_bots(mut self, bots: usize) -> Self {
+        self.practice_bots = bots;
+        self
+    }
+
     /// Register a player connection (called when WebSocket connects)
-    /// Returns channels for communication
+    /// Returns channels for communication, or an error if the user is
+    /// already connected and new connections are refused
     pub async fn register_player(
         &self,
         user_id: Uuid,
-    ) -> (mpsc::Sender<PlayerInput>, broadcast::Receiver<ServerMsg>) {
+        display_name: String,
+        guest: bool,
+        latency: Arc<LatencyTracker>,
+    ) -> Result<Registration, AlreadyConnected> {
         info!(user_id = %user_id, "Registering player connection");
         
         // Create personal channels for this player
+        let connection_id = Uuid::new_v4();
         let (input_tx, mut input_rx) = mpsc::channel::<PlayerInput>(64);
         let (snapshot_tx, snapshot_rx) = broadcast::channel::<ServerMsg>(64);
+        let evicted = Arc::new(Notify::new());
 
         let connection = PlayerConnection {
             user_id,
+            connection_id,
+            display_name,
+            guest,
+            latency,
             input_tx: input_tx.clone(),
             snapshot_rx: snapshot_tx.clone(),
+            evicted: evicted.clone(),
         };
 
-        self.players.insert(user_id, connection);
+        match self.players.entry(user_id) {
+            Entry::Occupied(mut entry) => {
+                if self.duplicate_policy == DuplicateConnectionPolicy::RejectNew {
+                    warn!(user_id = %user_id, "Refusing second connection");
+                    return Err(AlreadyConnected);
+                }
+                // The old connection's routing tasks stop once they see
+                // they've been replaced; its session is told to close
+                let old = entry.insert(connection);
+                old.evicted.notify_one();
+                info!(user_id = %user_id, "Replacing older connection");
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(connection);
+            }
+        }
         info!(user_id = %user_id, total_connected = self.players.len(), "Player connection registered");
 
         // Spawn a task to route messages from personal channel to match channel
-        let registry = self.registry.clone();
-        let player_matches = self.player_matches.clone();
-        let players_for_input = self.players.clone();
+        let service = self.clone();
+        let reply_tx = snapshot_tx.clone();
 
         tokio::spawn(async move {
             while let Some(input) = input_rx.recv().await {
-                // Find player's current match and forward input
-                if let Some(match_id) = player_matches.get(&user_id) {
-                    if let Some(match_handle) = registry.get(&match_id) {
-                        if match_handle.input_tx.send(input).await.is_err() {
-                            warn!(user_id = %user_id, "Failed to send input to match");
+                // A replaced connection's inputs (including the leave it
+                // sends on closing) must not reach the match
+                if !service.is_current(user_id, connection_id) {
+                    break;
+                }
+                let current_match = service.player_matches.get(&user_id).map(|r| *r);
+                match (current_match, &input.msg) {
+                    // Joins only reach a match from the service, after the
+                    // loadout check; a client can't pick a new one mid-match
+                    (Some(_), ClientMsg::JoinMatch { .. }) => {
+                        let _ = reply_tx.send(ServerMsg::Error {
+                            code: DirectJoinError::AlreadyInMatch.code(),
+                            message: DirectJoinError::AlreadyInMatch.to_string(),
+                        });
+                    }
+                    // Find player's current match and forward input
+                    (Some(match_id), msg) => {
+                        // Leaving frees the player to queue or join again,
+                        // through the usual checks
+                        if matches!(msg, ClientMsg::LeaveMatch) {
+                            service.player_matches.remove_if(&user_id, |_, m| *m == match_id);
+                        }
+                        if let Some(match_handle) = service.registry.get(&match_id) {
+                            if match_handle.input_tx.send(input).await.is_err() {
+                                warn!(user_id = %user_id, "Failed to send input to match");
+                            }
                         }
                     }
+                    // Not in a match yet: a targeted join bypasses the queue
+                    (
+                        None,
+                        ClientMsg::JoinMatch {
+                            match_id: Some(match_id),
+                            ship_type,
+                            weapon,
+                            aim_assist,
+                        },
+                    ) => {
+                        if let Err(e) = service
+                            .join_match_direct(user_id, *match_id, *ship_type, *weapon, *aim_assist)
+                            .await
+                        {
+                            let _ = reply_tx.send(ServerMsg::Error {
+                                code: e.code(),
+                                message: e.to_string(),
+                            });
+                        }
+                    }
+                    // Pings still get a pong between matches, for clock sync
+                    (None, ClientMsg::Ping { t }) => {
+                        let _ = reply_tx.send(ServerMsg::Pong {
+                            t: *t,
+                            server_time: crate::util::time::unix_millis(),
+                        });
+                    }
+                    (None, _) => {}
                 }
             }
             // Cleanup when channel closes
-            players_for_input.remove(&user_id);
+            service.players.remove_if(&user_id, |_, c| c.connection_id == connection_id);
         });
 
         // Spawn a task to route snapshots from match to player
@@ -92,6 +344,10 @@ impl MatchmakingService {
             // This task subscribes to match broadcasts and forwards to player
             let mut current_match_rx: Option<broadcast::Receiver<ServerMsg>> = None;
             let mut current_match_id: Option<Uuid> = None;
+            let mut snapshot_mode = SnapshotMode::Broadcast;
+            let mut match_metrics: Option<Arc<MatchMetrics>> = None;
+            // The current match sent `match_end`
+            let mut match_ended = false;
 
             loop {
                 // Check if player's match changed
@@ -99,22 +355,47 @@ impl MatchmakingService {
 
                 if new_match_id != current_match_id {
                     current_match_id = new_match_id;
-                    current_match_rx = new_match_id.and_then(|mid| {
-                        registry_clone.get(&mid).map(|h| h.snapshot_tx.subscribe())
-                    });
+                    match_ended = false;
+                    let handle = new_match_id.and_then(|mid| registry_clone.get(&mid));
+                    snapshot_mode = handle.as_ref().map_or(SnapshotMode::Broadcast, |h| h.snapshot_mode);
+                    match_metrics = handle.as_ref().map(|h| h.metrics.clone());
+                    current_match_rx = handle.map(|h| h.snapshot_tx.subscribe());
                 }
 
                 if let Some(ref mut rx) = current_match_rx {
                     match rx.recv().await {
                         Ok(msg) => {
-                            let _ = snapshot_tx_clone.send(msg);
+                            match_ended |= matches!(msg, ServerMsg::MatchEnd { .. });
+                            // Interest management: in per-player mode only
+                            // forward what this player can see
+                            let _ = snapshot_tx_clone.send(snapshot_mode.for_viewer(msg, user_id));
                         }
                         Err(broadcast::error::RecvError::Lagged(n)) => {
+                            // Snapshots are full state, so the next one resyncs the player
                             warn!(user_id = %user_id, lagged = n, "Snapshot receiver lagged");
+                            if let Some(metrics) = &match_metrics {
+                                metrics.lagged_messages.fetch_add(n, Ordering::Relaxed);
+                            }
                         }
                         Err(broadcast::error::RecvError::Closed) => {
                             current_match_rx = None;
-                            current_match_id = None;
+                            // A match still registered is resubscribed to on the
+                            // next pass. One that is gone without sending
+                            // `match_end` crashed, so free the player to queue again
+                            if let Some(match_id) = current_match_id.take() {
+                                if !match_ended && registry_clone.get(&match_id).is_none() {
+                                    warn!(
+                                        user_id = %user_id,
+                                        match_id = %match_id,
+                                        "Match closed without ending, releasing player"
+                                    );
+                                    player_matches_clone.remove_if(&user_id, |_, m| *m == match_id);
+                                    let _ = snapshot_tx_clone.send(ServerMsg::Error {
+                                        code: ErrorCode::MatchEnded,
+                                        message: "The match stopped unexpectedly".to_string(),
+                                    });
+                                }
+                            }
                         }
                     }
                 } else {
@@ -123,38 +404,104 @@ impl MatchmakingService {
                     tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                 }
 
-                // Check if player disconnected
-                if !players_for_snapshot.contains_key(&user_id) {
+                // Check if player disconnected or connected again elsewhere
+                let current = players_for_snapshot
+                    .get(&user_id)
+                    .is_some_and(|c| c.connection_id == connection_id);
+                if !current {
                     break;
                 }
             }
         });
 
-        (input_tx, snapshot_rx)
+        Ok(Registration {
+            connection_id,
+            input_tx,
+            snapshot_rx,
+            evicted,
+        })
+    }
+
+    /// Whether this is the user's current connection
+    fn is_current(&self, user_id: Uuid, connection_id: Uuid) -> bool {
+        self.players
+            .get(&user_id)
+            .is_some_and(|c| c.connection_id == connection_id)
+    }
+
+    /// Update a connected player's display name for matches they join from
+    /// now on, and tell their client
+    pub fn set_display_name(&self, user_id: Uuid, display_name: &str) {
+        if let Some(mut conn) = self.players.get_mut(&user_id) {
+            conn.display_name = display_name.to_string();
+            let _ = conn.snapshot_rx.send(ServerMsg::DisplayNameUpdated {
+                display_name: display_name.to_string(),
+            });
+        }
     }
 
-    /// Unregister a player (called when WebSocket disconnects)
-    pub async fn unregister_player(&self, user_id: Uuid) {
-        self.players.remove(&user_id);
+    /// Unregister a player (called when WebSocket disconnects). A connection
+    /// that has been replaced leaves the match and queue spot to its successor
+    pub async fn unregister_player(&self, user_id: Uuid, connection_id: Uuid) {
+        if self
+            .players
+            .remove_if(&user_id, |_, c| c.connection_id == connection_id)
+            .is_none()
+        {
+            info!(user_id = %user_id, "Replaced connection closed");
+            return;
+        }
         self.player_matches.remove(&user_id);
 
         let mut queue = self.queue.lock().await;
-        queue.dequeue(user_id);
+        if queue.dequeue(user_id).is_some() {
+            ServerCounters::bump(&self.counters.queue_leaves);
+        }
 
         info!(user_id = %user_id, "Player unregistered from matchmaking");
     }
 
     /// Join matchmaking queue
-    pub async fn join_queue(&self, player: QueuedPlayer) -> Result<(), String> {
+    pub async fn join_queue(&self, mut player: QueuedPlayer) -> Result<(), String> {
         let user_id = player.user_id;
 
+        // Connected players drop straight into a match of their region that
+        // has room before a new one is made (the direct join runs the usual
+        // checks). Running matches only count with late joining
+        if self.players.contains_key(&user_id) {
+            if let Some(handle) = self.registry.find_available_match(&player.region) {
+                info!(user_id = %user_id, match_id = %handle.id, "Routing player into existing match");
+                return self
+                    .join_match_direct(
+                        user_id,
+                        handle.id,
+                        player.ship_type,
+                        player.weapon,
+                        player.aim_assist,
+                    )
+                    .await
+                    .map_err(|e| e.to_string());
+            }
+        }
+
         // Check if already in a match
         if self.player_matches.contains_key(&user_id) {
             return Err("Already in a match".to_string());
         }
 
+        // Penalize repeated early quitters
+        if let Some(remaining) = self.abandon_cooldown_secs(user_id) {
+            return Err(DirectJoinError::Cooldown(remaining).to_string());
+        }
+
+        self.check_loadout(user_id, player.ship_type, player.weapon)
+            .await
+            .map_err(|e| e.to_string())?;
+        player.cosmetics = self.equipped_cosmetics(user_id).await;
+
         // Check if player is connected via WebSocket
         let is_connected = self.players.contains_key(&user_id);
+
         info!(
             user_id = %user_id, 
             is_connected = is_connected,
@@ -163,6 +510,7 @@ impl MatchmakingService {
 
         let mut queue = self.queue.lock().await;
         queue.enqueue(player);
+        ServerCounters::bump(&self.counters.queue_joins);
 
         info!(user_id = %user_id, queue_size = queue.len(), "Player joined matchmaking queue");
 
@@ -170,26 +518,17 @@ impl MatchmakingService {
         if is_connected {
             let connected_ids: std::collections::HashSet<Uuid> = 
                 self.players.iter().map(|entry| *entry.key()).collect();
-            
-            let connected_count = queue.iter().filter(|p| connected_ids.contains(&p.user_id)).count();
-            let min_players = queue.min_players();
-            let max_players = queue.max_players();
-            
-            info!(
-                connected_count = connected_count,
-                min_players = min_players,
-                "Checking if can form match immediately"
-            );
-            
-            if connected_count >= min_players {
+
+            if let Some(group) = queue.next_group(&connected_ids) {
+                let size = queue.group_match_size(&connected_ids, &group);
                 let players: Vec<QueuedPlayer> = queue
-                    .drain_connected(&connected_ids, max_players)
+                    .drain_connected(&connected_ids, &group, size)
                     .collect();
-                
+
                 if !players.is_empty() {
-                    info!(player_count = players.len(), "Forming match immediately");
+                    info!(player_count = players.len(), size, group = ?group, "Forming match immediately");
                     drop(queue); // Release lock before spawning match
-                    self.create_match(players).await;
+                    self.create_match(players, size).await;
                 }
             }
         }
@@ -197,23 +536,251 @@ impl MatchmakingService {
         Ok(())
     }
 
-    /// Leave matchmaking queue
-    pub async fn leave_queue(&self, user_id: Uuid) {
-        let mut queue = self.queue.lock().await;
-        queue.dequeue(user_id);
+    /// Join a specific match by ID, bypassing the queue
+    pub async fn join_match_direct(
+        &self,
+        user_id: Uuid,
+        match_id: Uuid,
+        ship_type: ShipType,
+        weapon: Option<WeaponLoadout>,
+        aim_assist: bool,
+    ) -> Result<(), DirectJoinError> {
+        if self.player_matches.contains_key(&user_id) {
+            return Err(DirectJoinError::AlreadyInMatch);
+        }
+        if let Some(remaining) = self.abandon_cooldown_secs(user_id) {
+            return Err(DirectJoinError::Cooldown(remaining));
+        }
+        self.check_loadout(user_id, ship_type, weapon).await?;
+        let cosmetics = self.equipped_cosmetics(user_id).await;
+
+        // Practice matches are private to their player
+        let handle = self
+            .registry
+            .get(&match_id)
+            .filter(|handle| !handle.practice)
+            .ok_or(DirectJoinError::MatchNotFound)?;
+
+        if handle.player_count() >= handle.max_players {
+            return Err(DirectJoinError::MatchFull);
+        }
+        if !handle.is_joinable() {
+            return Err(DirectJoinError::MatchInProgress);
+        }
+
+        self.queue.lock().await.dequeue(user_id);
+        self.player_matches.insert(user_id, match_id);
+
+        info!(user_id = %user_id, match_id = %match_id, "Player joining match directly");
+
+        // Same as create_match: let the snapshot router subscribe before MatchJoined is sent
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let conn = self.players.get(&user_id).map(|c| c.clone());
+        let join_input = PlayerInput {
+            user_id,
+            msg: ClientMsg::JoinMatch {
+                match_id: Some(match_id),
+                ship_type,
+                weapon,
+                aim_assist,
+            },
+            received_at: crate::util::time::unix_millis(),
+            display_name: conn.as_ref().map(|c| c.display_name.clone()),
+            guest: conn.as_ref().is_some_and(|c| c.guest),
+            latency: conn.map(|c| c.latency),
+            cosmetics,
+        };
+
+        if handle.input_tx.send(join_input).await.is_err() {
+            // Match ended in the meantime
+            self.player_matches.remove(&user_id);
+            return Err(DirectJoinError::MatchNotFound);
+        }
+
+        Ok(())
     }
 
-    /// Create a match with the given players
-    async fn create_match(&self, players: Vec<QueuedPlayer>) {
-        let match_id = Uuid::new_v4();
+    /// Make sure a player may use the chosen loadout: hull defaults are free,
+    /// anything else must be unlocked in their inventory
+    async fn check_loadout(
+        &self,
+        user_id: Uuid,
+        ship_type: ShipType,
+        weapon: Option<WeaponLoadout>,
+    ) -> Result<(), DirectJoinError> {
+        let Some(weapon) = weapon else {
+            return Ok(());
+        };
+        if weapon == WeaponLoadout::default_for(ship_type) {
+            return Ok(());
+        }
+
+        match self.inventory_store.user_owns_loadout(user_id, weapon).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(DirectJoinError::LoadoutLocked),
+            Err(e) => {
+                error!(user_id = %user_id, error = %e, "Failed to check weapon loadout");
+                Err(DirectJoinError::LoadoutCheckFailed)
+            }
+        }
+    }
+
+    /// Cosmetics a player has equipped. Guests own nothing, and a failed
+    /// lookup only costs the player their cosmetics for this match
+    async fn equipped_cosmetics(&self, user_id: Uuid) -> Cosmetics {
+        if self.players.get(&user_id).is_some_and(|c| c.guest) {
+            return Cosmetics::default();
+        }
+
+        match self.inventory_store.get_equipped_items(user_id).await {
+            Ok(items) => items.into_iter().map(|(slot, i)| (slot, i.item_id)).collect(),
+            Err(e) => {
+                warn!(user_id = %user_id, error = %e, "Failed to fetch equipped cosmetics");
+                Cosmetics::default()
+            }
+        }
+    }
+
+    /// Remaining matchmaking cooldown for a player who recently abandoned matches
+    fn abandon_cooldown_secs(&self, user_id: Uuid) -> Option<u64> {
+        self.abandons
+            .cooldown_remaining(user_id)
+            .map(|remaining| remaining.as_secs_f32().ceil() as u64)
+    }
+
+    /// Record abandons reported by a match until it ends
+    fn spawn_abandon_handler(&self) -> mpsc::UnboundedSender<Uuid> {
+        let (abandon_tx, mut abandon_rx) = mpsc::unbounded_channel::<Uuid>();
+        let abandons = self.abandons.clone();
+        let profile_store = self.profile_store.clone();
+
+        tokio::spawn(async move {
+            while let Some(user_id) = abandon_rx.recv().await {
+                info!(user_id = %user_id, "Player abandoned match");
+                abandons.record(user_id);
+
+                if let Err(e) = profile_store.record_abandon(user_id).await {
+                    error!(user_id = %user_id, error = %e, "Failed to persist abandon");
+                }
+            }
+        });
+
+        abandon_tx
+    }
+
+    /// Route a match's per-player messages straight to each player's
+    /// connection, bypassing the match broadcast
+    fn spawn_direct_router(&self) -> mpsc::UnboundedSender<(Uuid, ServerMsg)> {
+        let (direct_tx, mut direct_rx) = mpsc::unbounded_channel::<(Uuid, ServerMsg)>();
+        let players = self.players.clone();
+
+        tokio::spawn(async move {
+            while let Some((user_id, msg)) = direct_rx.recv().await {
+                if let Some(conn) = players.get(&user_id) {
+                    let _ = conn.snapshot_rx.send(msg);
+                }
+            }
+        });
+
+        direct_tx
+    }
+
+    /// Leave matchmaking queue. Returns whether the player was queued
+    pub async fn leave_queue(&self, user_id: Uuid) -> Result<bool, LeaveQueueError> {
+        if self.queue.lock().await.dequeue(user_id).is_some() {
+            ServerCounters::bump(&self.counters.queue_leaves);
+            info!(user_id = %user_id, "Player left matchmaking queue");
+            return Ok(true);
+        }
+        match self.get_player_match(&user_id) {
+            Some(match_id) => Err(LeaveQueueError::InMatch(match_id)),
+            None => Ok(false),
+        }
+    }
+
+    /// Start a practice match for a connected player straight away, without
+    /// queueing: just them and bots, with no zone, and nothing it awards or
+    /// records. Returns the match ID
+    pub async fn start_practice(
+        &self,
+        user_id: Uuid,
+        ship_type: ShipType,
+        weapon: Option<WeaponLoadout>,
+        aim_assist: bool,
+    ) -> Result<Uuid, PracticeError> {
+        if !self.players.contains_key(&user_id) {
+            return Err(PracticeError::NotConnected);
+        }
+        if let Some(match_id) = self.get_player_match(&user_id) {
+            return Err(PracticeError::InMatch(match_id));
+        }
+        self.check_loadout(user_id, ship_type, weapon).await?;
+
+        let mut player = QueuedPlayer::new(
+            user_id,
+            String::new(),
+            ship_type,
+            weapon,
+            String::new(),
+        );
+        player.cosmetics = self.equipped_cosmetics(user_id).await;
+        player.aim_assist = aim_assist;
+        if self.queue.lock().await.dequeue(user_id).is_some() {
+            ServerCounters::bump(&self.counters.queue_leaves);
+        }
+
         let seed = rand::random::<u64>();
-        let min_players = 2;
-        let max_players = 20;
+        let settings = MatchSettings {
+            arena: self.arenas[(seed % self.arenas.len() as u64) as usize].clone(),
+            min_players: 1,
+            max_players: 1 + self.practice_bots,
+            warmup: 0.0,
+            bot_fill_delay: None,
+            late_join: false,
+            practice: true,
+            private: true,
+            ..self.match_settings.clone()
+        };
+        Ok(self.launch_match(vec![player], seed, settings).await)
+    }
+
+    /// Create a match of `max_players` with the given players
+    async fn create_match(&self, players: Vec<QueuedPlayer>, max_players: usize) {
+        let seed = rand::random::<u64>();
+        let settings = MatchSettings {
+            arena: self.arenas[(seed % self.arenas.len() as u64) as usize].clone(),
+            max_players,
+            ..self.match_settings.clone()
+        };
+        self.launch_match(players, seed, settings).await;
+    }
 
-        let (game_match, handle) = GameMatch::new(match_id, seed, min_players, max_players);
+    /// Register and run a new match, then move the given players into it.
+    /// Practice matches report no abandons and record no results or rewards
+    async fn launch_match(
+        &self,
+        players: Vec<QueuedPlayer>,
+        seed: u64,
+        settings: MatchSettings,
+    ) -> Uuid {
+        let match_id = Uuid::new_v4();
+        let practice = settings.practice;
+        let (mut game_match, mut handle) = GameMatch::new(match_id, seed, settings);
+        // Late joiners are routed by region, so remember which one this is
+        handle.region = players
+            .first()
+            .map(|p| p.region.clone())
+            .filter(|region| players.iter().all(|p| &p.region == region));
+        game_match.set_direct_tx(self.spawn_direct_router());
+        if !practice {
+            game_match.set_abandon_tx(self.spawn_abandon_handler());
+            game_match.set_stats_store(self.stats_store.clone());
+        }
 
         // Register match
         self.registry.insert(handle.clone());
+        ServerCounters::bump(&self.counters.matches_created);
 
         // Associate players with match
         for player in &players {
@@ -223,6 +790,7 @@ impl MatchmakingService {
         info!(
             match_id = %match_id,
             player_count = players.len(),
+            practice,
             "Created new match"
         );
 
@@ -230,15 +798,33 @@ impl MatchmakingService {
         let registry = self.registry.clone();
         let player_matches = self.player_matches.clone();
         let match_player_ids: Vec<Uuid> = players.iter().map(|p| p.user_id).collect();
+        let wallet_store = self.wallet_store.clone();
+        let counters = self.counters.clone();
 
         tokio::spawn(async move {
-            game_match.run().await;
+            // A panicking match must still be cleaned up, or its players stay
+            // assigned to it and can never queue again
+            match AssertUnwindSafe(game_match.run()).catch_unwind().await {
+                Ok(Some(outcome)) if !practice => {
+                    credit_match_rewards(&wallet_store, &outcome).await
+                }
+                Ok(_) => {}
+                Err(panic) => {
+                    let reason = panic
+                        .downcast_ref::<&str>()
+                        .copied()
+                        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                        .unwrap_or("unknown panic");
+                    error!(match_id = %match_id, reason, "Match task panicked");
+                }
+            }
 
             // Cleanup after match ends
-            registry.remove(&match_id);
+            registry.finish(&match_id);
             for pid in match_player_ids {
-                player_matches.remove(&pid);
+                player_matches.remove_if(&pid, |_, m| *m == match_id);
             }
+            ServerCounters::bump(&counters.matches_ended);
 
             info!(match_id = %match_id, "Match removed from registry");
         });
@@ -256,8 +842,14 @@ impl MatchmakingService {
                     msg: crate::ws::protocol::ClientMsg::JoinMatch {
                         match_id: Some(match_id),
                         ship_type: player.ship_type,
+                        weapon: player.weapon,
+                        aim_assist: player.aim_assist,
                     },
                     received_at: crate::util::time::unix_millis(),
+                    display_name: Some(conn.display_name.clone()),
+                    latency: Some(conn.latency.clone()),
+                    guest: conn.guest,
+                    cosmetics: player.cosmetics,
                 };
 
                 if let Some(match_handle) = self.registry.get(&match_id) {
@@ -269,16 +861,26 @@ impl MatchmakingService {
                 drop(conn);
             }
         }
+
+        match_id
     }
 
     /// Run the matchmaking service (periodic queue processing)
     pub async fn run(&self) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
+        let mut last_queue_status = std::time::Instant::now();
         info!("Matchmaking service started");
 
         loop {
             interval.tick().await;
 
+            if last_queue_status.elapsed() >= QUEUE_STATUS_INTERVAL {
+                last_queue_status = std::time::Instant::now();
+                self.send_queue_status().await;
+            }
+
+            self.fill_available_matches().await;
+
             // Get connected player IDs
             let connected_ids: std::collections::HashSet<Uuid> = 
                 self.players.iter().map(|entry| *entry.key()).collect();
@@ -295,43 +897,96 @@ impl MatchmakingService {
                     queue_len = queue_len,
                     connected_players = connected_ids.len(),
                     connected_in_queue = connected_count,
+                    min_players = queue.min_players(),
+                    max_players = queue.max_players(),
                     "Matchmaking tick"
                 );
             }
             
-            let min_players = queue.min_players();
-            let max_players = queue.max_players();
-            let waited_too_long = queue.has_waited_too_long(&connected_ids);
-            
-            if connected_count >= min_players || (connected_count >= 1 && waited_too_long) {
-                info!(
-                    connected_count = connected_count,
-                    min_players = min_players,
-                    waited_too_long = waited_too_long,
-                    "Attempting to form match"
-                );
-                
+            if let Some(group) = queue.next_group(&connected_ids) {
+                info!(connected_count = connected_count, group = ?group, "Attempting to form match");
+
                 // Extract connected players for match
+                let size = queue.group_match_size(&connected_ids, &group);
                 let players: Vec<QueuedPlayer> = queue
-                    .drain_connected(&connected_ids, max_players)
+                    .drain_connected(&connected_ids, &group, size)
                     .collect();
                 
                 if !players.is_empty() {
-                    info!(player_count = players.len(), "Forming match with players");
+                    info!(player_count = players.len(), size, "Forming match with players");
                     drop(queue); // Release lock for match creation
-                    self.create_match(players).await;
+                    self.create_match(players, size).await;
                 } else {
-                    warn!("drain_connected returned empty despite connected_count > 0");
+                    warn!("drain_connected returned empty for a ready group");
                 }
             }
         }
     }
 
+    /// Move connected queued players into matches of their region that have
+    /// room, so open slots fill before new matches are made
+    async fn fill_available_matches(&self) {
+        let waiting: Vec<QueuedPlayer> = self
+            .queue
+            .lock()
+            .await
+            .iter()
+            .filter(|p| self.players.contains_key(&p.user_id))
+            .cloned()
+            .collect();
+
+        for player in waiting {
+            let user_id = player.user_id;
+            let Some(handle) = self.registry.find_available_match(&player.region) else {
+                continue;
+            };
+            match self
+                .join_match_direct(
+                    user_id,
+                    handle.id,
+                    player.ship_type,
+                    player.weapon,
+                    player.aim_assist,
+                )
+                .await
+            {
+                Ok(()) => info!(user_id = %user_id, match_id = %handle.id, "Moved queued player into existing match"),
+                Err(e) => debug!(user_id = %user_id, match_id = %handle.id, error = %e, "Queued player couldn't join existing match"),
+            }
+        }
+    }
+
+    /// Tell each connected queued player where they stand
+    async fn send_queue_status(&self) {
+        let connected_ids: std::collections::HashSet<Uuid> =
+            self.players.iter().map(|entry| *entry.key()).collect();
+        let positions = self.queue.lock().await.positions(&connected_ids);
+
+        for entry in positions {
+            // Placed in a match since the queue was read
+            if self.player_matches.contains_key(&entry.user_id) {
+                continue;
+            }
+            if let Some(conn) = self.players.get(&entry.user_id) {
+                let _ = conn.snapshot_rx.send(ServerMsg::QueueStatus {
+                    position: entry.position as u32,
+                    queue_size: entry.queue_size as u32,
+                    estimated_wait_secs: entry.estimated_wait.as_secs_f32().ceil() as u32,
+                });
+            }
+        }
+    }
+
     /// Get current queue size
     pub async fn queue_size(&self) -> usize {
         self.queue.lock().await.len()
     }
 
+    /// Queued players per region
+    pub async fn queue_by_region(&self) -> std::collections::BTreeMap<String, usize> {
+        self.queue.lock().await.region_sizes()
+    }
+
     /// Check if player is in queue
     pub async fn is_in_queue(&self, user_id: &Uuid) -> bool {
         self.queue.lock().await.contains(user_id)
@@ -343,6 +998,31 @@ impl MatchmakingService {
     }
 }
 
+/// Credit each player's currency reward for a finished match
+async fn credit_match_rewards(wallet_store: &WalletStore, outcome: &MatchOutcome) {
+    let match_id = outcome.match_id;
+    let stats = &outcome.stats;
+    for player in &stats.player_stats {
+        let amount = match_reward(player, stats.total_players);
+        if amount == 0 {
+            continue;
+        }
+
+        let key = match_reward_key(match_id, player.user_id);
+        if let Err(e) = wallet_store
+            .credit(player.user_id, amount, "match_reward", &key)
+            .await
+        {
+            error!(
+                match_id = %match_id,
+                user_id = %player.user_id,
+                error = %e,
+                "Failed to credit match reward"
+            );
+        }
+    }
+}
+
 impl Clone for MatchmakingService {
     fn clone(&self) -> Self {
         Self {
@@ -350,6 +1030,277 @@ impl Clone for MatchmakingService {
             registry: self.registry.clone(),
             players: self.players.clone(),
             player_matches: self.player_matches.clone(),
+            match_settings: self.match_settings.clone(),
+            arenas: self.arenas.clone(),
+            profile_store: self.profile_store.clone(),
+            inventory_store: self.inventory_store.clone(),
+            wallet_store: self.wallet_store.clone(),
+            stats_store: self.stats_store.clone(),
+            abandons: self.abandons.clone(),
+            counters: self.counters.clone(),
+            duplicate_policy: self.duplicate_policy,
+            practice_bots: self.practice_bots,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+    use crate::ws::protocol::SnapshotPlayers;
+    use std::time::Duration;
+
+    async fn service(policy: DuplicateConnectionPolicy) -> (MatchmakingService, MockSupabase) {
+        let mock = MockSupabase::start(&[(200, "[]")]).await;
+        let service = MatchmakingService::new(
+            MatchmakingQueue::new(2, 4, 60),
+            Arc::new(MatchRegistry::new()),
+            MatchSettings::default(),
+            ProfileStore::new(mock.client(0)),
+            InventoryStore::new(mock.client(0)),
+            WalletStore::new(mock.client(0)),
+            StatsStore::new(mock.client(0)),
+            AbandonPolicy::default(),
+        )
+        .with_duplicate_policy(policy);
+        (service, mock)
+    }
+
+    async fn connect(
+        service: &MatchmakingService,
+        user_id: Uuid,
+    ) -> Result<Registration, AlreadyConnected> {
+        let latency = Arc::new(LatencyTracker::new());
+        service
+            .register_player(user_id, "Pilot".to_string(), false, latency)
+            .await
+    }
+
+    fn join(user_id: Uuid) -> PlayerInput {
+        PlayerInput {
+            user_id,
+            msg: ClientMsg::JoinMatch {
+                match_id: None,
+                ship_type: ShipType::Fighter,
+                weapon: None,
+                aim_assist: false,
+            },
+            received_at: 0,
+            display_name: Some("Pilot".to_string()),
+            latency: None,
+            guest: false,
+            cosmetics: Cosmetics::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_second_connection_evicts_the_first_without_orphaned_tasks() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::EvictOld).await;
+        let user_id = Uuid::from_u128(1);
+        let first = connect(&service, user_id).await.unwrap();
+        let second = connect(&service, user_id).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(1), first.evicted.notified())
+            .await
+            .expect("the first session is told to close");
+
+        // Once its session lets go of the input channel, both routing tasks
+        // of the first connection end and drop their snapshot senders
+        let Registration {
+            input_tx,
+            mut snapshot_rx,
+            ..
+        } = first;
+        drop(input_tx);
+        let closed = tokio::time::timeout(Duration::from_secs(1), snapshot_rx.recv()).await;
+        assert!(matches!(closed, Ok(Err(broadcast::error::RecvError::Closed))));
+
+        // The replaced session's cleanup leaves the new connection alone
+        service.unregister_player(user_id, first.connection_id).await;
+        assert!(service.is_current(user_id, second.connection_id));
+        service.unregister_player(user_id, second.connection_id).await;
+        assert!(!service.players.contains_key(&user_id));
+    }
+
+    #[tokio::test]
+    async fn a_second_connection_can_be_refused() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::RejectNew).await;
+        let user_id = Uuid::from_u128(1);
+        let first = connect(&service, user_id).await.unwrap();
+
+        assert!(connect(&service, user_id).await.is_err());
+        assert!(service.is_current(user_id, first.connection_id));
+    }
+
+    #[tokio::test]
+    async fn direct_joins_carry_the_connection_name() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::EvictOld).await;
+        let user_id = Uuid::from_u128(1);
+        let mut registration = connect(&service, user_id).await.unwrap();
+        service.set_display_name(user_id, "Nova");
+        let (mut game, handle) = GameMatch::new(Uuid::from_u128(99), 5, MatchSettings::default());
+        service.registry.insert(handle.clone());
+
+        service
+            .join_match_direct(user_id, handle.id, ShipType::Fighter, None, false)
+            .await
+            .unwrap();
+        game.step_once();
+        let joined = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                let msg = registration.snapshot_rx.recv().await;
+                if let Ok(ServerMsg::PlayerJoined { player }) = msg {
+                    return player;
+                }
+            }
+        })
+        .await
+        .expect("the join reaches the match");
+        assert_eq!(joined.display_name, "Nova");
+    }
+
+    #[tokio::test]
+    async fn practice_matches_need_a_connection_and_stay_private() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::EvictOld).await;
+        let (player, other) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        assert!(matches!(
+            service.start_practice(player, ShipType::Fighter, None, false).await,
+            Err(PracticeError::NotConnected)
+        ));
+
+        let _player = connect(&service, player).await.unwrap();
+        let _other = connect(&service, other).await.unwrap();
+        let match_id = service
+            .start_practice(player, ShipType::Fighter, None, false)
+            .await
+            .unwrap();
+        assert_eq!(service.get_player_match(&player), Some(match_id));
+        let handle = service.registry.get(&match_id).unwrap();
+        assert!(handle.practice && !handle.is_joinable());
+
+        assert!(matches!(
+            service.start_practice(player, ShipType::Fighter, None, false).await,
+            Err(PracticeError::InMatch(id)) if id == match_id
+        ));
+        assert!(matches!(
+            service.join_match_direct(other, match_id, ShipType::Fighter, None, false).await,
+            Err(DirectJoinError::MatchNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_lagging_player_catches_up_with_the_match_state() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::EvictOld).await;
+        let user_id = Uuid::from_u128(1);
+        let mut registration = connect(&service, user_id).await.unwrap();
+
+        // A snapshot every tick, so ticks count snapshots
+        let defaults = MatchSettings::default();
+        let settings = MatchSettings {
+            max_players: 2,
+            snapshot_rate: defaults.tick_rate,
+            ..defaults
+        };
+        let capacity = settings.broadcast_capacity() as u64;
+        let ticks = 10 * settings.tick_rate as u64;
+        let (mut game, handle) = GameMatch::new(Uuid::from_u128(99), 5, settings);
+        for n in 1..=2 {
+            handle.input_tx.try_send(join(Uuid::from_u128(n))).unwrap();
+        }
+        service.registry.insert(handle.clone());
+        service.player_matches.insert(user_id, handle.id);
+        while handle.snapshot_tx.receiver_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // Many buffers' worth, all sent before the forwarder gets to run
+        assert!(ticks > capacity * 2);
+        for _ in 0..ticks {
+            game.step_once();
+        }
+
+        let mut seen = Vec::new();
+        let players = loop {
+            let msg = tokio::time::timeout(Duration::from_secs(1), registration.snapshot_rx.recv())
+                .await
+                .expect("forwarding resumes after the lag")
+                .expect("the player's own channel keeps up");
+            if let ServerMsg::Snapshot { tick, players, .. } = msg {
+                seen.push(tick);
+                if tick == ticks {
+                    break players;
+                }
+            }
+        };
+        assert!(handle.metrics.lagged_messages.load(Ordering::Relaxed) > 0);
+        // The oldest snapshots were skipped, then the rest arrive in order
+        assert!(seen[0] > ticks - capacity);
+        assert!(seen.windows(2).all(|pair| pair[1] == pair[0] + 1));
+
+        // The latest one agrees with the match
+        let state = handle.inspector.latest().unwrap();
+        assert_eq!(state.tick, ticks);
+        let SnapshotPlayers::Full(players) = players else {
+            panic!("expected full-precision players");
+        };
+        let sent: Vec<_> = players.iter().map(|p| (p.user_id, p.x, p.y, p.alive)).collect();
+        let actual: Vec<_> = state.players.iter().map(|p| (p.user_id, p.x, p.y, p.alive)).collect();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent, actual);
+    }
+
+    #[tokio::test]
+    async fn joins_sent_from_inside_a_match_are_refused_until_the_player_leaves() {
+        let (service, _mock) = service(DuplicateConnectionPolicy::EvictOld).await;
+        let user_id = Uuid::from_u128(1);
+        let mut registration = connect(&service, user_id).await.unwrap();
+        let (mut game, handle) = GameMatch::new(Uuid::from_u128(99), 5, MatchSettings::default());
+        service.registry.insert(handle.clone());
+        service.player_matches.insert(user_id, handle.id);
+
+        // A weapon the player doesn't own, sent straight from the client
+        let railgun = PlayerInput {
+            msg: ClientMsg::JoinMatch {
+                match_id: Some(handle.id),
+                ship_type: ShipType::Fighter,
+                weapon: Some(WeaponLoadout::Railgun),
+                aim_assist: false,
+            },
+            ..join(user_id)
+        };
+        registration.input_tx.send(railgun).await.unwrap();
+        let reply = tokio::time::timeout(Duration::from_secs(1), registration.snapshot_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            reply,
+            ServerMsg::Error {
+                code: ErrorCode::AlreadyInMatch,
+                ..
+            }
+        ));
+        game.step_once();
+        assert_eq!(handle.player_count(), 0);
+
+        // Leaving frees the player, and joining again checks the loadout
+        let leave = PlayerInput {
+            msg: ClientMsg::LeaveMatch,
+            ..join(user_id)
+        };
+        registration.input_tx.send(leave).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while service.get_player_match(&user_id).is_some() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("leaving clears the player's match");
+        let railgun = Some(WeaponLoadout::Railgun);
+        let rejoin = service
+            .join_match_direct(user_id, handle.id, ShipType::Fighter, railgun, false)
+            .await;
+        assert!(matches!(rejoin, Err(DirectJoinError::LoadoutLocked)));
+    }
+}
@@ -1,17 +1,58 @@
-//! Stripe checkout session creation
+//! Stripe checkout session creation (one-time purchases and battle pass
+//! subscriptions), and checkout with soft currency
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use crate::config::Config;
+use crate::store::inventory::{is_pass_required, GrantSource};
 use crate::store::supabase::{NewPurchase, StoreItem, SupabaseClient, SupabaseError};
+use crate::store::wallet::WalletError;
+use crate::store::{InventoryStore, SubscriptionStore, WalletStore};
+
+/// Maximum number of distinct items in one checkout
+const MAX_CART_LINES: usize = 20;
+
+/// Maximum quantity for a single cart line
+const MAX_LINE_QUANTITY: u32 = 99;
+
+/// Item type for the recurring battle pass
+pub const BATTLE_PASS_ITEM_TYPE: &str = "battle_pass";
+
+/// Billing interval of battle pass prices created on the fly
+const BATTLE_PASS_INTERVAL: &str = "month";
+
+/// What a Stripe checkout session sells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutMode {
+    /// One-time purchase of cosmetics
+    #[default]
+    Payment,
+    /// Recurring battle pass
+    Subscription,
+}
+
+impl CheckoutMode {
+    /// Stripe's name for the mode
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckoutMode::Payment => "payment",
+            CheckoutMode::Subscription => "subscription",
+        }
+    }
+}
 
 /// Stripe service for payment operations
 #[derive(Clone)]
 pub struct StripeService {
     client: Client,
     supabase: SupabaseClient,
+    inventory_store: InventoryStore,
+    subscription_store: SubscriptionStore,
+    wallet_store: WalletStore,
     stripe_secret_key: String,
     public_base_url: String,
     client_origin: String,
@@ -21,6 +62,9 @@ impl StripeService {
     pub fn new(config: &Config, supabase: SupabaseClient) -> Self {
         Self {
             client: Client::new(),
+            inventory_store: InventoryStore::new(supabase.clone()),
+            subscription_store: SubscriptionStore::new(supabase.clone()),
+            wallet_store: WalletStore::new(supabase.clone()),
             supabase,
             stripe_secret_key: config.stripe_secret_key.clone(),
             public_base_url: config.public_base_url.clone(),
@@ -28,56 +72,96 @@ impl StripeService {
         }
     }
 
-    /// Create a checkout session for an item
-    pub async fn create_checkout_session(
-        &self,
-        user_id: Uuid,
-        item_id: Uuid,
-    ) -> Result<CheckoutSessionResponse, StripeError> {
-        // Fetch the item from Supabase
+    /// Validate a cart of (item_id, quantity) lines and fetch its items
+    async fn load_cart(&self, cart: &[(Uuid, u32)]) -> Result<Vec<(StoreItem, u32)>, StripeError> {
+        if cart.is_empty() || cart.len() > MAX_CART_LINES {
+            return Err(StripeError::InvalidCart);
+        }
+        for (i, (item_id, quantity)) in cart.iter().enumerate() {
+            if *quantity == 0 || *quantity > MAX_LINE_QUANTITY {
+                return Err(StripeError::InvalidQuantity);
+            }
+            if cart[..i].iter().any(|(other, _)| other == item_id) {
+                return Err(StripeError::InvalidCart);
+            }
+        }
+
+        // Fetch all items from Supabase in one query
+        let ids: Vec<String> = cart.iter().map(|(id, _)| id.to_string()).collect();
         let items: Vec<StoreItem> = self
             .supabase
-            .get("items", &format!("id=eq.{}&active=eq.true", item_id))
+            .get("items", &format!("id=in.({})&active=eq.true", ids.join(",")))
             .await
             .map_err(StripeError::Database)?;
 
-        let item = items
-            .into_iter()
-            .next()
-            .ok_or(StripeError::ItemNotFound)?;
-
-        // Generate purchase ID
-        let purchase_id = Uuid::new_v4();
-
-        // Build Stripe API request
-        let success_url = format!(
-            "{}/checkout/success?session_id={{CHECKOUT_SESSION_ID}}",
-            self.client_origin
-        );
-        let cancel_url = format!("{}/checkout/cancel", self.client_origin);
-
-        // Create checkout session request body
-        let mut form_data: Vec<(&str, String)> = vec![
-            ("mode", "payment".to_string()),
-            ("success_url", success_url),
-            ("cancel_url", cancel_url),
-            ("client_reference_id", user_id.to_string()),
-            ("metadata[user_id]", user_id.to_string()),
-            ("metadata[item_id]", item_id.to_string()),
-            ("metadata[purchase_id]", purchase_id.to_string()),
-        ];
+        cart_lines(cart, &items)
+    }
 
-        // Use existing price ID if available, otherwise create price data
-        if let Some(price_id) = &item.stripe_price_id {
-            form_data.push(("line_items[0][price]", price_id.clone()));
-            form_data.push(("line_items[0][quantity]", "1".to_string()));
-        } else {
-            form_data.push(("line_items[0][price_data][currency]", "usd".to_string()));
-            form_data.push(("line_items[0][price_data][unit_amount]", item.price_usd.to_string()));
-            form_data.push(("line_items[0][price_data][product_data][name]", item.name.clone()));
-            form_data.push(("line_items[0][price_data][product_data][description]", format!("Ship Game - {}", item.item_type)));
-            form_data.push(("line_items[0][quantity]", "1".to_string()));
+    /// Make sure the user may own every item in the cart, before charging
+    async fn check_pass_items(
+        &self,
+        user_id: Uuid,
+        lines: &[(StoreItem, u32)],
+    ) -> Result<(), StripeError> {
+        for (item, _) in lines {
+            self.inventory_store
+                .check_pass(user_id, item)
+                .await
+                .map_err(|e| {
+                    if is_pass_required(&e) {
+                        StripeError::PassRequired
+                    } else {
+                        StripeError::Database(e)
+                    }
+                })?;
         }
+        Ok(())
+    }
+
+    /// Check a cart fits the checkout mode: a subscription is exactly one
+    /// battle pass, which can't be bought any other way
+    async fn check_mode(
+        &self,
+        user_id: Uuid,
+        lines: &[(StoreItem, u32)],
+        mode: CheckoutMode,
+    ) -> Result<(), StripeError> {
+        let is_pass = |item: &StoreItem| item.item_type == BATTLE_PASS_ITEM_TYPE;
+        match mode {
+            CheckoutMode::Payment => {
+                if lines.iter().any(|(item, _)| is_pass(item)) {
+                    return Err(StripeError::InvalidMode);
+                }
+            }
+            CheckoutMode::Subscription => {
+                let [(item, 1)] = lines else {
+                    return Err(StripeError::InvalidMode);
+                };
+                if !is_pass(item) {
+                    return Err(StripeError::InvalidMode);
+                }
+                if self.subscription_store.has_active_pass(user_id).await? {
+                    return Err(StripeError::AlreadySubscribed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a checkout session for a cart of (item_id, quantity) lines.
+    /// Subscription sessions grant nothing on completion; the battle pass is
+    /// tracked through the subscription's own webhooks
+    pub async fn create_checkout_session(
+        &self,
+        user_id: Uuid,
+        cart: &[(Uuid, u32)],
+        mode: CheckoutMode,
+    ) -> Result<CheckoutSessionResponse, StripeError> {
+        let lines = self.load_cart(cart).await?;
+        self.check_mode(user_id, &lines, mode).await?;
+        self.check_pass_items(user_id, &lines).await?;
+
+        let form_data = checkout_form(&self.client_origin, user_id, &lines, mode);
 
         // Call Stripe API
         let response = self
@@ -100,17 +184,28 @@ impl StripeService {
         let session_id = session.id.clone();
         let session_url = session.url.ok_or(StripeError::NoSessionUrl)?;
 
-        // Create pending purchase record
-        let purchase = NewPurchase {
-            id: purchase_id,
-            user_id,
-            stripe_session_id: session_id.clone(),
-            item_id,
-            status: "pending".to_string(),
-        };
+        if mode == CheckoutMode::Subscription {
+            return Ok(CheckoutSessionResponse {
+                session_id,
+                url: session_url,
+            });
+        }
+
+        // Create one pending purchase record per cart line
+        let purchases: Vec<NewPurchase> = lines
+            .iter()
+            .map(|(item, quantity)| NewPurchase {
+                id: Uuid::new_v4(),
+                user_id,
+                stripe_session_id: session_id.clone(),
+                item_id: item.id,
+                quantity: *quantity,
+                status: "pending".to_string(),
+            })
+            .collect();
 
         self.supabase
-            .insert::<_, serde_json::Value>("purchases", &purchase)
+            .insert::<_, serde_json::Value>("purchases", &purchases)
             .await
             .map_err(StripeError::Database)?;
 
@@ -120,12 +215,161 @@ impl StripeService {
         })
     }
 
+    /// Buy a cart of coin-priced items with soft currency, skipping Stripe.
+    ///
+    /// The debit is keyed on the client's idempotency key and the cart
+    /// contents, so retrying the same purchase charges once and re-grants
+    /// the (already owned) items. Returns the new balance.
+    pub async fn checkout_with_coins(
+        &self,
+        user_id: Uuid,
+        cart: &[(Uuid, u32)],
+        idempotency_key: &str,
+    ) -> Result<i64, StripeError> {
+        let lines = self.load_cart(cart).await?;
+        self.check_pass_items(user_id, &lines).await?;
+
+        let total = coin_total(&lines)?;
+
+        let key = coin_purchase_key(user_id, idempotency_key, cart);
+        let balance = self
+            .wallet_store
+            .debit(user_id, total, "purchase", &key)
+            .await
+            .map_err(|e| match e {
+                WalletError::InsufficientFunds => StripeError::InsufficientFunds,
+                WalletError::Database(e) => StripeError::Database(e),
+            })?;
+
+        for (item, _) in &lines {
+            self.inventory_store
+                .grant_item(user_id, item.id, GrantSource::Purchase, &key)
+                .await?;
+        }
+
+        Ok(balance)
+    }
+
+    /// Check the secret key is accepted with a lightweight authenticated read
+    pub async fn ping(&self) -> Result<(), StripeError> {
+        let response = self
+            .client
+            .get("https://api.stripe.com/v1/balance")
+            .basic_auth(&self.stripe_secret_key, None::<&str>)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(StripeError::Api { status, body });
+        }
+        Ok(())
+    }
+
     /// Get the Stripe secret key for webhook verification
     pub fn secret_key(&self) -> &str {
         &self.stripe_secret_key
     }
 }
 
+/// Pair each cart line with its fetched item. Every requested item must
+/// exist and be active, and since inventory records ownership rather than
+/// counts, each item can only be bought once
+fn cart_lines(cart: &[(Uuid, u32)], items: &[StoreItem]) -> Result<Vec<(StoreItem, u32)>, StripeError> {
+    cart.iter()
+        .map(|(item_id, quantity)| {
+            let item = items
+                .iter()
+                .find(|item| item.id == *item_id)
+                .ok_or(StripeError::ItemNotFound)?;
+            if *quantity > 1 {
+                return Err(StripeError::NotStackable);
+            }
+            Ok((item.clone(), *quantity))
+        })
+        .collect()
+}
+
+/// Coin price of a cart; a total too large to add up is refused rather than
+/// wrapped around to a small charge
+fn coin_total(lines: &[(StoreItem, u32)]) -> Result<u32, StripeError> {
+    let mut total: u32 = 0;
+    for (item, quantity) in lines {
+        let price = item
+            .price_coins
+            .filter(|price| *price >= 0)
+            .ok_or(StripeError::NotCoinPriced)?;
+        total = (price as u32)
+            .checked_mul(*quantity)
+            .and_then(|line| total.checked_add(line))
+            .ok_or(StripeError::InvalidQuantity)?;
+    }
+    Ok(total)
+}
+
+/// Form body for a Stripe checkout session selling `lines`
+fn checkout_form(
+    client_origin: &str,
+    user_id: Uuid,
+    lines: &[(StoreItem, u32)],
+    mode: CheckoutMode,
+) -> Vec<(String, String)> {
+    let success_url = format!(
+        "{}/checkout/success?session_id={{CHECKOUT_SESSION_ID}}",
+        client_origin
+    );
+    let cancel_url = format!("{}/checkout/cancel", client_origin);
+
+    let mut form_data: Vec<(String, String)> = vec![
+        ("mode".to_string(), mode.as_str().to_string()),
+        ("success_url".to_string(), success_url),
+        ("cancel_url".to_string(), cancel_url),
+        ("client_reference_id".to_string(), user_id.to_string()),
+        ("metadata[user_id]".to_string(), user_id.to_string()),
+    ];
+    if mode == CheckoutMode::Subscription {
+        // Copied onto the subscription, whose webhooks carry no session
+        form_data.push((
+            "subscription_data[metadata][user_id]".to_string(),
+            user_id.to_string(),
+        ));
+    }
+
+    for (n, (item, quantity)) in lines.iter().enumerate() {
+        // Use existing price ID if available, otherwise create price data
+        if let Some(price_id) = &item.stripe_price_id {
+            form_data.push((format!("line_items[{}][price]", n), price_id.clone()));
+        } else {
+            form_data.push((format!("line_items[{}][price_data][currency]", n), "usd".to_string()));
+            form_data.push((format!("line_items[{}][price_data][unit_amount]", n), item.price_usd.to_string()));
+            form_data.push((format!("line_items[{}][price_data][product_data][name]", n), item.name.clone()));
+            form_data.push((format!("line_items[{}][price_data][product_data][description]", n), format!("Ship Game - {}", item.item_type)));
+            if mode == CheckoutMode::Subscription {
+                form_data.push((format!("line_items[{}][price_data][recurring][interval]", n), BATTLE_PASS_INTERVAL.to_string()));
+            }
+        }
+        form_data.push((format!("line_items[{}][quantity]", n), quantity.to_string()));
+    }
+    form_data
+}
+
+/// Wallet idempotency key for a coin purchase. Includes a digest of the cart
+/// so reusing a client key for a different cart can't skip the charge
+fn coin_purchase_key(user_id: Uuid, idempotency_key: &str, cart: &[(Uuid, u32)]) -> String {
+    let mut lines = cart.to_vec();
+    lines.sort();
+
+    let mut hasher = Sha256::new();
+    for (item_id, quantity) in &lines {
+        hasher.update(item_id.as_bytes());
+        hasher.update(quantity.to_be_bytes());
+    }
+    let digest = hex::encode(hasher.finalize());
+
+    format!("purchase:{}:{}:{}", user_id, idempotency_key, &digest[..16])
+}
+
 /// Stripe checkout session response
 #[derive(Debug, Deserialize)]
 struct StripeSession {
@@ -149,6 +393,15 @@ pub enum StripeError {
     #[error("Item not found or inactive")]
     ItemNotFound,
 
+    #[error("Cart must contain between 1 and {MAX_CART_LINES} distinct items")]
+    InvalidCart,
+
+    #[error("Item quantity must be between 1 and {MAX_LINE_QUANTITY}")]
+    InvalidQuantity,
+
+    #[error("Items are owned, not stacked, so each can only be bought once")]
+    NotStackable,
+
     #[error("HTTP request failed: {0}")]
     Request(#[from] reqwest::Error),
 
@@ -157,4 +410,90 @@ pub enum StripeError {
 
     #[error("No session URL returned")]
     NoSessionUrl,
+
+    #[error("Item cannot be bought with coins")]
+    NotCoinPriced,
+
+    #[error("Insufficient coins")]
+    InsufficientFunds,
+
+    #[error("A battle pass is bought alone with mode \"subscription\", other items with mode \"payment\"")]
+    InvalidMode,
+
+    #[error("Battle pass is already active")]
+    AlreadySubscribed,
+
+    #[error("Item requires an active battle pass")]
+    PassRequired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(n: u128, stripe_price_id: Option<&str>) -> StoreItem {
+        StoreItem {
+            id: Uuid::from_u128(n),
+            item_type: "flag_skin".to_string(),
+            name: format!("Flag {n}"),
+            price_usd: 199,
+            price_coins: Some(500),
+            stripe_price_id: stripe_price_id.map(str::to_string),
+            requires_pass: false,
+            active: true,
+        }
+    }
+
+    fn field<'a>(form: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        form.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn checkout_has_a_line_item_per_cart_line() {
+        let lines = vec![(item(1, Some("price_123")), 1), (item(2, None), 1)];
+        let form = checkout_form("https://game.test", Uuid::from_u128(9), &lines, CheckoutMode::Payment);
+
+        assert_eq!(field(&form, "mode"), Some("payment"));
+        assert_eq!(field(&form, "line_items[0][price]"), Some("price_123"));
+        assert_eq!(field(&form, "line_items[0][quantity]"), Some("1"));
+        assert_eq!(field(&form, "line_items[1][price]"), None);
+        assert_eq!(field(&form, "line_items[1][price_data][unit_amount]"), Some("199"));
+        assert_eq!(field(&form, "line_items[1][price_data][product_data][name]"), Some("Flag 2"));
+        assert_eq!(field(&form, "line_items[1][quantity]"), Some("1"));
+        assert_eq!(field(&form, "line_items[2][quantity]"), None);
+    }
+
+    #[test]
+    fn coin_totals_that_overflow_are_refused() {
+        let lines = vec![(item(1, None), 1), (item(2, None), 1)];
+        assert_eq!(coin_total(&lines).unwrap(), 1000);
+
+        let mut pricey = item(3, None);
+        pricey.price_coins = Some(i32::MAX);
+        assert!(matches!(
+            coin_total(&[(pricey.clone(), u32::MAX)]),
+            Err(StripeError::InvalidQuantity)
+        ));
+        // Each line fits, the sum doesn't
+        assert!(matches!(
+            coin_total(&[(pricey.clone(), 2), (pricey, 1)]),
+            Err(StripeError::InvalidQuantity)
+        ));
+    }
+
+    #[test]
+    fn cart_lines_need_every_item_once() {
+        let items = [item(1, None), item(2, None)];
+        let lines = cart_lines(&[(Uuid::from_u128(1), 1), (Uuid::from_u128(2), 1)], &items).unwrap();
+        assert_eq!(lines.len(), 2);
+
+        assert!(matches!(
+            cart_lines(&[(Uuid::from_u128(3), 1)], &items),
+            Err(StripeError::ItemNotFound)
+        ));
+        assert!(matches!(
+            cart_lines(&[(Uuid::from_u128(1), 3)], &items),
+            Err(StripeError::NotStackable)
+        ));
+    }
 }
@@ -9,11 +9,15 @@ use axum::{
 use hmac::{Hmac, Mac};
 use serde::Deserialize;
 use sha2::Sha256;
+use std::future::Future;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::app::AppState;
+use crate::store::inventory::{is_pass_required, GrantSource};
 use crate::store::supabase::SupabaseError;
+use crate::store::{InventoryStore, StripeEventStore};
+use crate::util::counters::ServerCounters;
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -23,6 +27,21 @@ pub async fn stripe_webhook_handler(
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, WebhookError> {
+    let result = handle_webhook(&state, &headers, &body).await;
+    let counter = match result {
+        Ok(()) => &state.counters.webhooks_succeeded,
+        Err(_) => &state.counters.webhooks_failed,
+    };
+    ServerCounters::bump(counter);
+    result.map(|()| StatusCode::OK)
+}
+
+/// Verify and apply one webhook event
+async fn handle_webhook(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> Result<(), WebhookError> {
     // Get the Stripe-Signature header
     let signature = headers
         .get("Stripe-Signature")
@@ -30,7 +49,7 @@ pub async fn stripe_webhook_handler(
         .ok_or(WebhookError::MissingSignature)?;
 
     // Get the raw body as string for verification
-    let payload = std::str::from_utf8(&body).map_err(|_| WebhookError::InvalidPayload)?;
+    let payload = std::str::from_utf8(body).map_err(|_| WebhookError::InvalidPayload)?;
 
     // Verify webhook signature
     verify_stripe_signature(payload, signature, &state.config.stripe_webhook_secret)?;
@@ -48,11 +67,46 @@ pub async fn stripe_webhook_handler(
         "Received Stripe webhook"
     );
 
-    // Handle the event
+    let handled = handle_once(&state.stripe_events, &event.id, &event.event_type, || {
+        dispatch_event(state, &event)
+    })
+    .await?;
+    if !handled {
+        info!(event_id = %event.id, "Stripe event already processed, skipping");
+    }
+    Ok(())
+}
+
+/// Run `handle` unless the event was processed before, then record it.
+/// Returns whether the handler ran
+async fn handle_once<F, Fut>(
+    events: &StripeEventStore,
+    event_id: &str,
+    event_type: &str,
+    handle: F,
+) -> Result<bool, WebhookError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(), WebhookError>>,
+{
+    if events.is_processed(event_id).await? {
+        return Ok(false);
+    }
+    handle().await?;
+
+    // Failing to record only means a redelivery runs the handler again
+    if let Err(e) = events.mark_processed(event_id, event_type).await {
+        warn!(error = %e, event_id = %event_id, "Failed to record processed Stripe event");
+    }
+    Ok(true)
+}
+
+/// Apply an event to whatever it concerns
+async fn dispatch_event(state: &AppState, event: &StripeEvent) -> Result<(), WebhookError> {
     match event.event_type.as_str() {
         "checkout.session.completed" => {
             if let Some(session) = event.data.object.as_checkout_session() {
-                handle_checkout_completed(&state, session).await?;
+                handle_checkout_completed(state, session).await?;
             }
         }
         "payment_intent.succeeded" => {
@@ -60,7 +114,14 @@ pub async fn stripe_webhook_handler(
         }
         "payment_intent.payment_failed" => {
             if let Some(intent) = event.data.object.as_payment_intent() {
-                handle_payment_failed(&state, &intent.id).await?;
+                handle_payment_failed(state, &intent.id).await?;
+            }
+        }
+        "customer.subscription.created"
+        | "customer.subscription.updated"
+        | "customer.subscription.deleted" => {
+            if let Some(subscription) = event.data.object.as_subscription() {
+                handle_subscription_changed(state, subscription, event.created).await?;
             }
         }
         _ => {
@@ -68,7 +129,7 @@ pub async fn stripe_webhook_handler(
         }
     }
 
-    Ok(StatusCode::OK)
+    Ok(())
 }
 
 /// Verify Stripe webhook signature
@@ -131,6 +192,12 @@ async fn handle_checkout_completed(
 ) -> Result<(), WebhookError> {
     info!(session_id = %session.id, "Processing checkout completion");
 
+    // Battle passes are granted by the subscription's own events
+    if session.mode.as_deref() == Some("subscription") {
+        info!(session_id = %session.id, "Subscription checkout completed");
+        return Ok(());
+    }
+
     // Extract metadata
     let user_id: Uuid = session
         .metadata
@@ -141,17 +208,8 @@ async fn handle_checkout_completed(
             WebhookError::InvalidMetadata
         })?;
 
-    let item_id: Uuid = session
-        .metadata
-        .get("item_id")
-        .and_then(|s| s.parse().ok())
-        .ok_or_else(|| {
-            error!("Missing item_id in session metadata");
-            WebhookError::InvalidMetadata
-        })?;
-
-    // Check if already processed (idempotency)
-    let existing: Vec<PurchaseStatus> = state
+    // Load the purchase lines created at checkout
+    let purchases: Vec<PurchaseLine> = state
         .supabase
         .get(
             "purchases",
@@ -160,11 +218,15 @@ async fn handle_checkout_completed(
         .await
         .map_err(WebhookError::Database)?;
 
-    if let Some(purchase) = existing.first() {
-        if purchase.status == "paid" {
-            info!(session_id = %session.id, "Purchase already processed (idempotent)");
-            return Ok(());
-        }
+    if purchases.is_empty() {
+        error!(session_id = %session.id, "No purchase records for session");
+        return Err(WebhookError::InvalidMetadata);
+    }
+
+    // Check if already processed (idempotency)
+    if purchases.iter().all(|p| p.status == "paid") {
+        info!(session_id = %session.id, "Purchase already processed (idempotent)");
+        return Ok(());
     }
 
     // Update purchase status to paid
@@ -187,20 +249,85 @@ async fn handle_checkout_completed(
         .await
         .map_err(WebhookError::Database)?;
 
-    // Grant item to user
-    state
-        .inventory_store
-        .grant_item(user_id, item_id)
+    grant_purchases(&state.inventory_store, user_id, &purchases, &session.id).await
+}
+
+/// Grant every item bought in a checkout session to the user
+async fn grant_purchases(
+    inventory: &InventoryStore,
+    user_id: Uuid,
+    purchases: &[PurchaseLine],
+    session_id: &str,
+) -> Result<(), WebhookError> {
+    for purchase in purchases {
+        match inventory
+            .grant_item(user_id, purchase.item_id, GrantSource::Purchase, session_id)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(
+                    user_id = %user_id,
+                    item_id = %purchase.item_id,
+                    session_id = %session_id,
+                    "Item already granted for this session (idempotent)"
+                );
+                continue;
+            }
+            // Checkout requires the pass, so it lapsed while paying; retrying won't help
+            Err(e) if is_pass_required(&e) => {
+                warn!(
+                    user_id = %user_id,
+                    item_id = %purchase.item_id,
+                    session_id = %session_id,
+                    "Paid for a pass-exclusive item without an active battle pass, not granted"
+                );
+                continue;
+            }
+            Err(e) => return Err(WebhookError::Database(e)),
+        }
+
+        info!(
+            user_id = %user_id,
+            item_id = %purchase.item_id,
+            session_id = %session_id,
+            "Item granted successfully"
+        );
+    }
+
+    Ok(())
+}
+
+/// Mirror a battle pass subscription's status. Stripe sends the full object
+/// on every change (a cancellation arrives as `deleted` with status
+/// `canceled`), so each event simply records the status it carries
+async fn handle_subscription_changed(
+    state: &AppState,
+    subscription: &SubscriptionData,
+    event_created: i64,
+) -> Result<(), WebhookError> {
+    let user_id: Uuid = subscription
+        .metadata
+        .get("user_id")
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| {
+            error!(subscription_id = %subscription.id, "Missing user_id in subscription metadata");
+            WebhookError::InvalidMetadata
+        })?;
+
+    let applied = state
+        .subscription_store
+        .apply(&subscription.id, user_id, &subscription.status, event_created)
         .await
         .map_err(WebhookError::Database)?;
 
     info!(
         user_id = %user_id,
-        item_id = %item_id,
-        session_id = %session.id,
-        "Item granted successfully"
+        subscription_id = %subscription.id,
+        status = %subscription.status,
+        applied,
+        "Subscription status recorded"
     );
-
     Ok(())
 }
 
@@ -236,6 +363,9 @@ struct StripeEvent {
     id: String,
     #[serde(rename = "type")]
     event_type: String,
+    /// Unix seconds; orders events for the same object
+    #[serde(default)]
+    created: i64,
     data: StripeEventData,
 }
 
@@ -247,6 +377,8 @@ struct StripeEventData {
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum StripeObject {
+    // Tried first: it only matches objects tagged as subscriptions
+    Subscription(SubscriptionData),
     CheckoutSession(CheckoutSessionData),
     PaymentIntent(PaymentIntentData),
     Unknown(serde_json::Value),
@@ -266,11 +398,20 @@ impl StripeObject {
             _ => None,
         }
     }
+
+    fn as_subscription(&self) -> Option<&SubscriptionData> {
+        match self {
+            StripeObject::Subscription(s) => Some(s),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct CheckoutSessionData {
     id: String,
+    /// `payment` or `subscription`
+    mode: Option<String>,
     payment_intent: Option<String>,
     #[serde(default)]
     metadata: std::collections::HashMap<String, String>,
@@ -281,8 +422,26 @@ struct PaymentIntentData {
     id: String,
 }
 
+/// Stripe's `object` value for subscriptions; only deserializes from that
+#[derive(Debug, Deserialize)]
+enum SubscriptionTag {
+    #[serde(rename = "subscription")]
+    Subscription,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionData {
+    id: String,
+    #[serde(rename = "object")]
+    _object: SubscriptionTag,
+    status: String,
+    #[serde(default)]
+    metadata: std::collections::HashMap<String, String>,
+}
+
 #[derive(Debug, Deserialize)]
-struct PurchaseStatus {
+struct PurchaseLine {
+    item_id: Uuid,
     status: String,
 }
 
@@ -321,3 +480,98 @@ impl IntoResponse for WebhookError {
         (status, self.to_string()).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn object(json: serde_json::Value) -> StripeObject {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn subscriptions_are_told_apart_from_checkout_sessions() {
+        let subscription = object(serde_json::json!({
+            "id": "sub_123",
+            "object": "subscription",
+            "status": "active",
+            "metadata": {"user_id": "00000000-0000-0000-0000-000000000001"}
+        }));
+        let subscription = subscription.as_subscription().unwrap();
+        assert_eq!(subscription.status, "active");
+        assert!(subscription.metadata.contains_key("user_id"));
+
+        let session = object(serde_json::json!({
+            "id": "cs_123",
+            "object": "checkout.session",
+            "mode": "subscription",
+            "status": "complete",
+            "payment_intent": null,
+            "metadata": {}
+        }));
+        assert!(session.as_subscription().is_none());
+        assert_eq!(
+            session.as_checkout_session().unwrap().mode.as_deref(),
+            Some("subscription")
+        );
+    }
+
+    #[tokio::test]
+    async fn a_redelivered_event_is_not_handled_again() {
+        let mock = MockSupabase::start(&[(200, "[]"), (201, "")]).await;
+        let events = StripeEventStore::new(mock.client(0));
+        let runs = AtomicU32::new(0);
+        let handle = || async {
+            runs.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        };
+
+        let event = ("evt_1", "payment_intent.payment_failed");
+        assert!(handle_once(&events, event.0, event.1, handle).await.unwrap());
+        assert!(!handle_once(&events, event.0, event.1, handle).await.unwrap());
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+        // Looked up and recorded once; the redelivery never reached the database
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({"id": "evt_1", "event_type": "payment_intent.payment_failed"})
+        );
+    }
+
+    #[tokio::test]
+    async fn every_line_of_a_session_is_granted() {
+        let item = r#"{"id": "00000000-0000-0000-0000-000000000002", "type": "flag_skin",
+            "name": "Comet", "price_usd": 199, "stripe_price_id": null, "active": true}"#;
+        // Item lookup then grant, for each line
+        let mock = MockSupabase::start(&[(200, item), (200, "true"), (200, item), (200, "true")]).await;
+        let inventory = InventoryStore::new(mock.client(0));
+        let purchases: Vec<PurchaseLine> = (2..4)
+            .map(|n| PurchaseLine {
+                item_id: Uuid::from_u128(n),
+                status: "pending".to_string(),
+            })
+            .collect();
+
+        grant_purchases(&inventory, Uuid::from_u128(1), &purchases, "cs_test_1")
+            .await
+            .unwrap();
+
+        let granted: Vec<serde_json::Value> = mock
+            .requests()
+            .iter()
+            .filter(|r| r.uri == "/rest/v1/rpc/inventory_grant")
+            .map(|r| r.json()["p_item_id"].clone())
+            .collect();
+        assert_eq!(
+            granted,
+            vec![
+                serde_json::json!(Uuid::from_u128(2)),
+                serde_json::json!(Uuid::from_u128(3))
+            ]
+        );
+    }
+}
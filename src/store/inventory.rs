@@ -1,9 +1,38 @@
 //! Inventory management - server-side only
+//!
+//! Grants go through the `inventory_grant` Postgres function, which marks the
+//! item owned and writes an `inventory_grants` audit row atomically. Each
+//! grant carries a reference (a Stripe session, a purchase's idempotency
+//! key), and granting the same item again under the same reference is a
+//! no-op, so replayed webhooks and retried purchases are logged once.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
-use super::supabase::{SupabaseClient, SupabaseError};
+use super::subscriptions::SubscriptionStore;
+use super::supabase::{StoreItem, SupabaseClient, SupabaseError};
+use crate::ws::protocol::{CosmeticSlot, WeaponLoadout};
+
+/// Item type for weapon loadout unlocks
+pub const WEAPON_LOADOUT_ITEM_TYPE: &str = "weapon_loadout";
+
+/// Error body when a pass-exclusive item is granted or equipped without an
+/// active battle pass
+pub const PASS_REQUIRED: &str = "battle_pass_required";
+
+/// Error body when equipping an item that doesn't fill a cosmetic slot
+pub const NOT_EQUIPPABLE: &str = "not_equippable";
+
+/// Whether an inventory error is a missing battle pass
+pub fn is_pass_required(error: &SupabaseError) -> bool {
+    matches!(error, SupabaseError::Api { status: 403, body } if body == PASS_REQUIRED)
+}
+
+/// Whether an inventory error is an item that can't be equipped
+pub fn is_not_equippable(error: &SupabaseError) -> bool {
+    matches!(error, SupabaseError::Api { status: 400, body } if body == NOT_EQUIPPABLE)
+}
 
 /// User inventory item
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,24 +62,110 @@ pub struct ItemDetails {
     pub item_type: String,
 }
 
-/// New inventory entry for insertion
-#[derive(Debug, Clone, Serialize)]
-pub struct NewInventoryEntry {
-    pub user_id: Uuid,
+/// Equipped items by the cosmetic slot they fill
+pub type EquippedItems = BTreeMap<CosmeticSlot, InventoryItemWithDetails>;
+
+/// Why an item was granted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantSource {
+    /// Bought with money or coins
+    Purchase,
+    /// Earned in game
+    #[allow(dead_code)] // No item rewards yet; the audit table accepts them
+    Reward,
+    /// Granted by an operator
+    Admin,
+}
+
+/// An entry in a user's grant history, with item details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryGrant {
     pub item_id: Uuid,
-    pub owned: bool,
-    pub equipped: bool,
+    pub source: GrantSource,
+    pub reference_id: String,
+    pub granted_at: chrono::DateTime<chrono::Utc>,
+    #[serde(rename = "items")]
+    pub item: Option<ItemDetails>,
+}
+
+/// Arguments to the `inventory_grant` RPC
+#[derive(Debug, Serialize)]
+struct InventoryGrantArgs<'a> {
+    p_user_id: Uuid,
+    p_item_id: Uuid,
+    p_source: GrantSource,
+    p_reference_id: &'a str,
 }
 
+/// Arguments to the `inventory_grant_bulk` RPC
+#[derive(Debug, Serialize)]
+struct InventoryGrantBulkArgs<'a> {
+    /// Null grants to every user with a profile
+    p_user_ids: Option<&'a [Uuid]>,
+    p_item_id: Uuid,
+    p_source: GrantSource,
+    p_reference_id: &'a str,
+}
+
+/// Who a bulk grant goes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrantRecipients {
+    Users(Vec<Uuid>),
+    /// Every user with a profile
+    Everyone,
+}
+
+/// Outcome of a bulk grant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkGrantResult {
+    /// Users the item was granted to
+    pub granted: u64,
+    /// Users who owned the item already, or got it under the same reference
+    pub already_owned: u64,
+    /// Listed user IDs without a profile, skipped
+    pub not_found: u64,
+}
+
+/// Most grants `grant_history` returns
+const GRANT_HISTORY_LIMIT: usize = 100;
+
 /// Inventory store operations
 #[derive(Clone)]
 pub struct InventoryStore {
     client: SupabaseClient,
+    subscriptions: SubscriptionStore,
 }
 
 impl InventoryStore {
     pub fn new(client: SupabaseClient) -> Self {
-        Self { client }
+        Self {
+            subscriptions: SubscriptionStore::new(client.clone()),
+            client,
+        }
+    }
+
+    /// Fetch an item by ID
+    async fn get_item(&self, item_id: Uuid) -> Result<StoreItem, SupabaseError> {
+        let item: Option<StoreItem> = self
+            .client
+            .get_one("items", &format!("id=eq.{}", item_id))
+            .await?;
+        item.ok_or_else(|| SupabaseError::Api {
+            status: 404,
+            body: "Item not found".to_string(),
+        })
+    }
+
+    /// Refuse pass-exclusive items to users without an active battle pass
+    pub async fn check_pass(&self, user_id: Uuid, item: &StoreItem) -> Result<(), SupabaseError> {
+        if item.requires_pass && !self.subscriptions.has_active_pass(user_id).await? {
+            return Err(SupabaseError::Api {
+                status: 403,
+                body: PASS_REQUIRED.to_string(),
+            });
+        }
+        Ok(())
     }
 
     /// Get all inventory items for a user
@@ -88,75 +203,128 @@ impl InventoryStore {
         Ok(!items.is_empty())
     }
 
-    /// Grant an item to a user (set owned = true)
-    pub async fn grant_item(&self, user_id: Uuid, item_id: Uuid) -> Result<(), SupabaseError> {
-        let entry = NewInventoryEntry {
+    /// Check if user has unlocked a weapon loadout (a `weapon_loadout` item named by its key)
+    pub async fn user_owns_loadout(
+        &self,
+        user_id: Uuid,
+        loadout: WeaponLoadout,
+    ) -> Result<bool, SupabaseError> {
+        let query = format!(
+            "user_id=eq.{}&owned=eq.true&select=item_id,owned,equipped,items!inner(id,name,type)\
+             &items.type=eq.{}&items.name=eq.{}",
             user_id,
-            item_id,
-            owned: true,
-            equipped: false,
+            WEAPON_LOADOUT_ITEM_TYPE,
+            loadout.key()
+        );
+        let items: Vec<InventoryItemWithDetails> =
+            self.client.get("user_inventory", &query).await?;
+        Ok(!items.is_empty())
+    }
+
+    /// Grant an item to a user (set owned = true) and record the grant.
+    /// Returns false if the item was already granted under `reference_id`,
+    /// in which case nothing changes. Pass-exclusive items need an active
+    /// battle pass
+    pub async fn grant_item(
+        &self,
+        user_id: Uuid,
+        item_id: Uuid,
+        source: GrantSource,
+        reference_id: &str,
+    ) -> Result<bool, SupabaseError> {
+        let item = self.get_item(item_id).await?;
+        self.check_pass(user_id, &item).await?;
+
+        let args = InventoryGrantArgs {
+            p_user_id: user_id,
+            p_item_id: item_id,
+            p_source: source,
+            p_reference_id: reference_id,
         };
 
-        self.client
-            .upsert("user_inventory", &entry, "user_id,item_id")
-            .await
+        // Safe to retry: the reference makes repeated grants no-ops
+        self.client.rpc("inventory_grant", &args, true).await
     }
 
-    /// Equip an item (only one flag skin can be equipped at a time)
-    pub async fn equip_item(&self, user_id: Uuid, item_id: Uuid) -> Result<(), SupabaseError> {
-        // First, unequip all other flag skins for this user
-        // We need to get the item type first
-        let item_query = format!("id=eq.{}", item_id);
-        let items: Vec<super::supabase::StoreItem> =
-            self.client.get("items", &item_query).await?;
-
-        if items.is_empty() {
+    /// Grant an item to many users in one transaction, recording a grant
+    /// for each user who didn't own it yet. Repeating the grant under the
+    /// same reference changes nothing. Pass-exclusive items are refused, as
+    /// each user's battle pass would have to be checked
+    pub async fn grant_item_bulk(
+        &self,
+        recipients: &GrantRecipients,
+        item_id: Uuid,
+        source: GrantSource,
+        reference_id: &str,
+    ) -> Result<BulkGrantResult, SupabaseError> {
+        let item = self.get_item(item_id).await?;
+        if item.requires_pass {
             return Err(SupabaseError::Api {
-                status: 404,
-                body: "Item not found".to_string(),
+                status: 403,
+                body: PASS_REQUIRED.to_string(),
             });
         }
 
-        let item = &items[0];
+        let args = InventoryGrantBulkArgs {
+            p_user_ids: match recipients {
+                GrantRecipients::Users(user_ids) => Some(user_ids),
+                GrantRecipients::Everyone => None,
+            },
+            p_item_id: item_id,
+            p_source: source,
+            p_reference_id: reference_id,
+        };
 
-        #[derive(Serialize)]
-        struct UnequipUpdate {
-            equipped: bool,
-        }
-        
-        // Get all equipped items of same type to unequip
-        let inventory: Vec<InventoryItemWithDetails> = self
-            .client
-            .get(
-                "user_inventory",
-                &format!(
-                    "user_id=eq.{}&equipped=eq.true&select=item_id,owned,equipped,items(id,name,type)",
-                    user_id
-                ),
-            )
-            .await?;
+        // Safe to retry for the same reason as single grants
+        self.client.rpc("inventory_grant_bulk", &args, true).await
+    }
 
-        // Unequip items of the same type
-        for inv_item in inventory {
-            if let Some(details) = &inv_item.item {
-                if details.item_type == item.item_type {
-                    self.client
-                        .update(
-                            "user_inventory",
-                            &format!("user_id=eq.{}&item_id=eq.{}", user_id, inv_item.item_id),
-                            &UnequipUpdate { equipped: false },
-                        )
-                        .await?;
-                }
-            }
-        }
+    /// A user's most recent grants, newest first
+    pub async fn grant_history(&self, user_id: Uuid) -> Result<Vec<InventoryGrant>, SupabaseError> {
+        let query = format!(
+            "user_id=eq.{}&select=item_id,source,reference_id,granted_at,items(id,name,type)\
+             &order=granted_at.desc&limit={}",
+            user_id, GRANT_HISTORY_LIMIT
+        );
+        self.client.get("inventory_grants", &query).await
+    }
+
+    /// Equip a cosmetic, unequipping whatever else fills its slot.
+    /// Pass-exclusive items need an active battle pass
+    pub async fn equip_item(&self, user_id: Uuid, item_id: Uuid) -> Result<(), SupabaseError> {
+        let item = self.get_item(item_id).await?;
+        let slot =
+            CosmeticSlot::for_item_type(&item.item_type).ok_or_else(|| SupabaseError::Api {
+                status: 400,
+                body: NOT_EQUIPPABLE.to_string(),
+            })?;
+        self.check_pass(user_id, &item).await?;
 
-        // Equip the requested item
         #[derive(Serialize)]
         struct EquipUpdate {
             equipped: bool,
         }
 
+        // Empty the slot first; other slots keep their items
+        let query = format!(
+            "user_id=eq.{}&equipped=eq.true&item_id=neq.{}\
+             &select=item_id,owned,equipped,items!inner(id,name,type)&items.type=eq.{}",
+            user_id,
+            item_id,
+            slot.item_type()
+        );
+        let in_slot: Vec<InventoryItemWithDetails> =
+            self.client.get("user_inventory", &query).await?;
+        for inv_item in in_slot {
+            self.client
+                .update(
+                    "user_inventory",
+                    &format!("user_id=eq.{}&item_id=eq.{}", user_id, inv_item.item_id),
+                    &EquipUpdate { equipped: false },
+                )
+                .await?;
+        }
+
         self.client
             .update(
                 "user_inventory",
@@ -182,12 +350,128 @@ impl InventoryStore {
             .await
     }
 
-    /// Get all equipped items for a user
-    pub async fn get_equipped_items(
-        &self,
-        user_id: Uuid,
-    ) -> Result<Vec<UserInventoryItem>, SupabaseError> {
-        let query = format!("user_id=eq.{}&equipped=eq.true", user_id);
-        self.client.get("user_inventory", &query).await
+    /// A user's equipped cosmetics by slot, with item details. Equipped
+    /// items that aren't cosmetics are left out
+    pub async fn get_equipped_items(&self, user_id: Uuid) -> Result<EquippedItems, SupabaseError> {
+        let query = format!(
+            "user_id=eq.{}&equipped=eq.true&select=item_id,owned,equipped,items(id,name,type)",
+            user_id
+        );
+        let items: Vec<InventoryItemWithDetails> =
+            self.client.get("user_inventory", &query).await?;
+        Ok(items
+            .into_iter()
+            .filter_map(|i| {
+                let details = i.item.as_ref()?;
+                Some((CosmeticSlot::for_item_type(&details.item_type)?, i))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+
+    #[tokio::test]
+    async fn grants_are_recorded_with_their_reference() {
+        let item = r#"{"id": "00000000-0000-0000-0000-000000000002", "type": "flag_skin",
+            "name": "Comet", "price_usd": 199, "stripe_price_id": null, "active": true}"#;
+        let mock = MockSupabase::start(&[(200, item), (200, "false")]).await;
+        let store = InventoryStore::new(mock.client(0));
+
+        let granted = store
+            .grant_item(Uuid::from_u128(1), Uuid::from_u128(2), GrantSource::Purchase, "cs_test_1")
+            .await
+            .unwrap();
+
+        // Already granted under this reference
+        assert!(!granted);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri, "/rest/v1/rpc/inventory_grant");
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({
+                "p_user_id": Uuid::from_u128(1),
+                "p_item_id": Uuid::from_u128(2),
+                "p_source": "purchase",
+                "p_reference_id": "cs_test_1",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn bulk_grants_go_out_in_one_request() {
+        let item = r#"{"id": "00000000-0000-0000-0000-000000000002", "type": "flag_skin",
+            "name": "Comet", "price_usd": 199, "stripe_price_id": null, "active": true}"#;
+        let result = r#"{"granted": 2, "already_owned": 1, "not_found": 0}"#;
+        let mock = MockSupabase::start(&[(200, item), (200, result)]).await;
+        let store = InventoryStore::new(mock.client(0));
+
+        let user_ids: Vec<Uuid> = (10..13).map(Uuid::from_u128).collect();
+        let result = store
+            .grant_item_bulk(
+                &GrantRecipients::Users(user_ids.clone()),
+                Uuid::from_u128(2),
+                GrantSource::Admin,
+                "spring_promo",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!((result.granted, result.already_owned), (2, 1));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri, "/rest/v1/rpc/inventory_grant_bulk");
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({
+                "p_user_ids": user_ids,
+                "p_item_id": Uuid::from_u128(2),
+                "p_source": "admin",
+                "p_reference_id": "spring_promo",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn equipping_empties_only_the_items_slot() {
+        let item = r#"{"id": "00000000-0000-0000-0000-000000000002", "type": "ship_skin",
+            "name": "Nebula", "price_usd": 299, "stripe_price_id": null, "active": true}"#;
+        let in_slot = r#"[{"item_id": "00000000-0000-0000-0000-000000000003", "owned": true,
+            "equipped": true, "items": {"id": "00000000-0000-0000-0000-000000000003",
+            "name": "Rust", "type": "ship_skin"}}]"#;
+        let mock = MockSupabase::start(&[(200, item), (200, in_slot), (204, "")]).await;
+        let store = InventoryStore::new(mock.client(0));
+
+        store
+            .equip_item(Uuid::from_u128(1), Uuid::from_u128(2))
+            .await
+            .unwrap();
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].uri.contains("items.type=eq.ship_skin"));
+        assert!(requests[2].uri.ends_with(&format!("item_id=eq.{}", Uuid::from_u128(3))));
+        assert_eq!(requests[2].json(), serde_json::json!({ "equipped": false }));
+        assert!(requests[3].uri.ends_with(&format!("item_id=eq.{}", Uuid::from_u128(2))));
+        assert_eq!(requests[3].json(), serde_json::json!({ "equipped": true }));
+    }
+
+    #[tokio::test]
+    async fn items_without_a_slot_cant_be_equipped() {
+        let item = r#"{"id": "00000000-0000-0000-0000-000000000002", "type": "battle_pass",
+            "name": "Battle Pass", "price_usd": 999, "stripe_price_id": null, "active": true}"#;
+        let mock = MockSupabase::start(&[(200, item)]).await;
+        let store = InventoryStore::new(mock.client(0));
+
+        let err = store
+            .equip_item(Uuid::from_u128(1), Uuid::from_u128(2))
+            .await
+            .unwrap_err();
+        assert!(is_not_equippable(&err));
+        assert_eq!(mock.requests().len(), 1);
     }
 }
@@ -0,0 +1,123 @@
+//! Local stand-in for the Supabase REST API in tests
+//!
+//! Serves canned responses in order and records every request, so tests can
+//! check what a store sent and how the client reacted to each status.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::IntoResponse;
+use axum::Router;
+use parking_lot::Mutex;
+
+use super::supabase::{RetryPolicy, SupabaseClient};
+
+/// Request received by the mock
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// Path and query, e.g. `/rest/v1/items?select=id`
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is JSON")
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Responses still to serve; the last one repeats once the rest are used
+    responses: VecDeque<(u16, String)>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Mock Supabase server listening on a local port until dropped
+pub struct MockSupabase {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockSupabase {
+    /// Start a server answering with `responses` (status, body) in order
+    pub async fn start(responses: &[(u16, &str)]) -> Self {
+        assert!(!responses.is_empty(), "mock needs at least one response");
+        let state = Arc::new(Mutex::new(MockState {
+            responses: responses
+                .iter()
+                .map(|(status, body)| (*status, body.to_string()))
+                .collect(),
+            requests: Vec::new(),
+        }));
+
+        let handler_state = state.clone();
+        let app = Router::new().fallback(
+            move |method: Method, uri: Uri, headers: HeaderMap, body: Bytes| {
+                let state = handler_state.clone();
+                async move {
+                    let mut state = state.lock();
+                    state.requests.push(RecordedRequest {
+                        method,
+                        uri: uri.to_string(),
+                        headers,
+                        body: String::from_utf8_lossy(&body).into_owned(),
+                    });
+                    let (status, body) = if state.responses.len() > 1 {
+                        state.responses.pop_front().unwrap()
+                    } else {
+                        state.responses[0].clone()
+                    };
+                    (
+                        StatusCode::from_u16(status).unwrap(),
+                        [("content-type", "application/json")],
+                        body,
+                    )
+                        .into_response()
+                }
+            },
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self { url, state, server }
+    }
+
+    /// Client for this server that retries up to `max_retries` times with
+    /// almost no delay
+    pub fn client(&self, max_retries: u32) -> SupabaseClient {
+        SupabaseClient::with_url(
+            self.url.clone(),
+            "service-role-key".to_string(),
+            RetryPolicy {
+                max_retries,
+                base_delay: Duration::from_millis(1),
+            },
+        )
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().requests.clone()
+    }
+}
+
+impl Drop for MockSupabase {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
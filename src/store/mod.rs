@@ -1,9 +1,19 @@
 //! Data store modules for Supabase integration
 
 pub mod inventory;
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod profiles;
+pub mod stats;
+pub mod stripe_events;
+pub mod subscriptions;
 pub mod supabase;
+pub mod wallet;
 
 pub use inventory::InventoryStore;
 pub use profiles::ProfileStore;
+pub use stats::StatsStore;
+pub use stripe_events::StripeEventStore;
+pub use subscriptions::SubscriptionStore;
 pub use supabase::SupabaseClient;
+pub use wallet::WalletStore;
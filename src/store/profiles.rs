@@ -10,6 +10,15 @@ use super::supabase::{SupabaseClient, SupabaseError};
 pub struct UserProfile {
     pub id: Uuid,
     pub display_name: Option<String>,
+    /// When the display name was last changed by the player
+    #[serde(default)]
+    pub display_name_changed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Matches left while still alive
+    #[serde(default)]
+    pub abandon_count: u32,
+    /// Matchmaking region used when a join doesn't name one
+    #[serde(default)]
+    pub region: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -21,10 +30,20 @@ pub struct NewProfile {
 }
 
 /// Profile update
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ProfileUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name_changed_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+/// Arguments to the `abandon_record` RPC
+#[derive(Debug, Serialize)]
+struct AbandonRecordArgs {
+    p_user_id: Uuid,
 }
 
 /// Profile store operations
@@ -67,6 +86,31 @@ impl ProfileStore {
         self.client.update("profiles", &query, &update).await
     }
 
+    /// Check whether another user already has this display name (case-insensitive)
+    pub async fn display_name_taken(
+        &self,
+        display_name: &str,
+        except_user: Uuid,
+    ) -> Result<bool, SupabaseError> {
+        // Validated names only contain [A-Za-z0-9 _-]; escape the LIKE wildcard `_`
+        let pattern = display_name.replace('_', "%5C_").replace(' ', "%20");
+        let query = format!(
+            "display_name=ilike.{}&id=neq.{}&select=id&limit=1",
+            pattern, except_user
+        );
+        let matches: Vec<serde_json::Value> = self.client.get("profiles", &query).await?;
+        Ok(!matches.is_empty())
+    }
+
+    /// Increment a user's lifetime abandon counter in the database, so
+    /// abandons recorded at the same time all count. Returns the new count
+    /// (none if the user has no profile)
+    pub async fn record_abandon(&self, user_id: Uuid) -> Result<Option<u32>, SupabaseError> {
+        let args = AbandonRecordArgs { p_user_id: user_id };
+        // Not retried: a repeat after a lost response would count twice
+        self.client.rpc("abandon_record", &args, false).await
+    }
+
     /// Get or create profile (ensures profile exists)
     pub async fn ensure_profile(
         &self,
@@ -79,3 +123,23 @@ impl ProfileStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+
+    #[tokio::test]
+    async fn abandons_are_counted_by_the_database() {
+        let mock = MockSupabase::start(&[(200, "3"), (200, "null")]).await;
+        let store = ProfileStore::new(mock.client(0));
+
+        assert_eq!(store.record_abandon(Uuid::from_u128(1)).await.unwrap(), Some(3));
+        // No profile to count against
+        assert_eq!(store.record_abandon(Uuid::from_u128(2)).await.unwrap(), None);
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].uri, "/rest/v1/rpc/abandon_record");
+        assert_eq!(requests[0].json(), serde_json::json!({"p_user_id": Uuid::from_u128(1)}));
+    }
+}
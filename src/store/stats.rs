@@ -0,0 +1,301 @@
+//! Match results and the career statistics aggregated from them
+//!
+//! Everything a finished match writes (results and progression) goes
+//! through one call to the `match_end_apply` Postgres function, so match
+//! cleanup costs a single round-trip and each part is applied atomically.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::supabase::{SupabaseClient, SupabaseError};
+use crate::game::MatchOutcome;
+use crate::ws::protocol::{PlayerMatchStats, ShipType};
+
+/// How long aggregated stats are served from memory
+const STATS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Expired entries are swept once the cache grows past this many users
+const STATS_CACHE_SWEEP_SIZE: usize = 10_000;
+
+/// Row in the player_career_stats view
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CareerStatsRow {
+    total_matches: u32,
+    wins: u32,
+    kills: u32,
+    deaths: u32,
+    best_placement: Option<u32>,
+    total_damage: f32,
+}
+
+/// Lifetime statistics for a player
+#[derive(Debug, Clone, Serialize)]
+pub struct CareerStats {
+    pub user_id: Uuid,
+    pub total_matches: u32,
+    pub wins: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    /// Kills per death (kills when deathless)
+    pub kd_ratio: f32,
+    /// Best finish (0 with no matches)
+    pub best_placement: u32,
+    pub total_damage: f32,
+    /// Fraction of matches won
+    pub win_rate: f32,
+}
+
+impl CareerStats {
+    fn from_row(user_id: Uuid, row: CareerStatsRow) -> Self {
+        let kd_ratio = if row.deaths > 0 {
+            row.kills as f32 / row.deaths as f32
+        } else {
+            row.kills as f32
+        };
+        let win_rate = if row.total_matches > 0 {
+            row.wins as f32 / row.total_matches as f32
+        } else {
+            0.0
+        };
+
+        Self {
+            user_id,
+            total_matches: row.total_matches,
+            wins: row.wins,
+            kills: row.kills,
+            deaths: row.deaths,
+            kd_ratio,
+            best_placement: row.best_placement.unwrap_or(0),
+            total_damage: row.total_damage,
+            win_rate,
+        }
+    }
+}
+
+/// Match-wide fields of a finished match (row in match_history)
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchSummary {
+    pub seed: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_secs: u32,
+    pub total_players: u32,
+    pub winner_user_id: Option<Uuid>,
+}
+
+impl From<&MatchOutcome> for MatchSummary {
+    fn from(outcome: &MatchOutcome) -> Self {
+        Self {
+            // BIGINT is signed; keep the seed's bits
+            seed: outcome.seed as i64,
+            started_at: millis_to_datetime(outcome.started_at),
+            ended_at: millis_to_datetime(outcome.ended_at),
+            duration_secs: outcome.stats.duration_secs,
+            total_players: outcome.stats.total_players,
+            winner_user_id: outcome.winner_user_id,
+        }
+    }
+}
+
+/// A human player's end-of-match result (row in player_match_stats, plus
+/// the XP to award)
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerEndState {
+    pub user_id: Uuid,
+    pub ship_type: ShipType,
+    pub kills: u32,
+    pub best_streak: u32,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub placement: u32,
+    pub alive_time_secs: u32,
+    pub xp_gained: u32,
+}
+
+impl From<&PlayerMatchStats> for PlayerEndState {
+    fn from(stats: &PlayerMatchStats) -> Self {
+        Self {
+            user_id: stats.user_id,
+            ship_type: stats.ship_type,
+            kills: stats.kills,
+            best_streak: stats.best_streak,
+            damage_dealt: stats.damage_dealt,
+            damage_taken: stats.damage_taken,
+            shots_fired: stats.shots_fired,
+            shots_hit: stats.shots_hit,
+            placement: stats.placement,
+            alive_time_secs: stats.alive_time_secs,
+            xp_gained: stats.xp_gained,
+        }
+    }
+}
+
+/// Which parts of an end-of-match write were applied. Each part commits or
+/// rolls back on its own, so a failed XP award doesn't lose the results
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MatchEndWrites {
+    /// match_history and player_match_stats rows were written
+    pub results: bool,
+    /// XP was awarded (or already had been for this match)
+    pub progression: bool,
+    /// Lifetime XP of each player after the award, if progression succeeded
+    #[serde(default)]
+    pub xp_totals: HashMap<Uuid, u64>,
+}
+
+/// Arguments to the `match_end_apply` RPC
+#[derive(Debug, Serialize)]
+struct MatchEndApply<'a> {
+    p_match_id: Uuid,
+    p_match: &'a MatchSummary,
+    p_players: &'a [PlayerEndState],
+}
+
+/// Match results and career stats store operations
+#[derive(Clone)]
+pub struct StatsStore {
+    client: SupabaseClient,
+    cache: Arc<DashMap<Uuid, (Instant, CareerStats)>>,
+}
+
+impl StatsStore {
+    pub fn new(client: SupabaseClient) -> Self {
+        Self {
+            client,
+            cache: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Get a player's career stats (all zeros if they haven't finished a match)
+    pub async fn get_career_stats(&self, user_id: Uuid) -> Result<CareerStats, SupabaseError> {
+        if let Some(entry) = self.cache.get(&user_id) {
+            let (cached_at, stats) = entry.value();
+            if cached_at.elapsed() < STATS_CACHE_TTL {
+                return Ok(stats.clone());
+            }
+        }
+
+        let query = format!("user_id=eq.{}", user_id);
+        let row: Option<CareerStatsRow> =
+            self.client.get_one("player_career_stats", &query).await?;
+        let stats = CareerStats::from_row(user_id, row.unwrap_or_default());
+
+        if self.cache.len() >= STATS_CACHE_SWEEP_SIZE {
+            self.cache
+                .retain(|_, (cached_at, _)| cached_at.elapsed() < STATS_CACHE_TTL);
+        }
+        self.cache.insert(user_id, (Instant::now(), stats.clone()));
+
+        Ok(stats)
+    }
+
+    /// Write a finished match: its match_history row, each player's
+    /// player_match_stats row and their XP. Safe to retry; rows already
+    /// written and XP already awarded for this match are left alone
+    pub async fn record_match_end(
+        &self,
+        match_id: Uuid,
+        summary: &MatchSummary,
+        players: Vec<PlayerEndState>,
+    ) -> Result<MatchEndWrites, SupabaseError> {
+        let args = MatchEndApply {
+            p_match_id: match_id,
+            p_match: summary,
+            p_players: &players,
+        };
+        let writes: MatchEndWrites = self.client.rpc("match_end_apply", &args, true).await?;
+
+        // Their cached career stats no longer include every match
+        if writes.results {
+            for player in &players {
+                self.cache.remove(&player.user_id);
+            }
+        }
+
+        Ok(writes)
+    }
+}
+
+fn millis_to_datetime(millis: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(millis as i64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+
+    fn summary() -> MatchSummary {
+        MatchSummary {
+            seed: -1,
+            started_at: millis_to_datetime(1_700_000_000_000),
+            ended_at: millis_to_datetime(1_700_000_300_000),
+            duration_secs: 300,
+            total_players: 2,
+            winner_user_id: Some(Uuid::from_u128(1)),
+        }
+    }
+
+    fn player(n: u128, xp_gained: u32) -> PlayerEndState {
+        PlayerEndState {
+            user_id: Uuid::from_u128(n),
+            ship_type: ShipType::Fighter,
+            kills: 1,
+            best_streak: 1,
+            damage_dealt: 120.0,
+            damage_taken: 40.0,
+            shots_fired: 10,
+            shots_hit: 4,
+            placement: n as u32,
+            alive_time_secs: 300,
+            xp_gained,
+        }
+    }
+
+    #[tokio::test]
+    async fn match_end_is_written_in_one_call() {
+        let id = Uuid::from_u128(1);
+        let body = format!(
+            r#"{{"results": true, "progression": true, "xp_totals": {{"{id}": 450}}}}"#
+        );
+        let mock = MockSupabase::start(&[(200, &body)]).await;
+        let store = StatsStore::new(mock.client(0));
+
+        let writes = store
+            .record_match_end(Uuid::from_u128(9), &summary(), vec![player(1, 150), player(2, 0)])
+            .await
+            .unwrap();
+
+        assert!(writes.results && writes.progression);
+        assert_eq!(writes.xp_totals.get(&id), Some(&450));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].uri, "/rest/v1/rpc/match_end_apply");
+        let args = requests[0].json();
+        assert_eq!(args["p_players"].as_array().unwrap().len(), 2);
+        assert_eq!(args["p_players"][0]["xp_gained"], 150);
+        assert_eq!(args["p_match"]["seed"], -1);
+    }
+
+    #[tokio::test]
+    async fn failed_parts_are_reported() {
+        let mock = MockSupabase::start(&[(200, r#"{"results": true, "progression": false}"#)]).await;
+        let store = StatsStore::new(mock.client(0));
+
+        let writes = store
+            .record_match_end(Uuid::from_u128(9), &summary(), vec![player(1, 150)])
+            .await
+            .unwrap();
+
+        assert!(writes.results);
+        assert!(!writes.progression);
+        assert!(writes.xp_totals.is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+//! Stripe webhook events already processed, so redeliveries are skipped
+//!
+//! Stripe delivers at least once and may send the same event ID again. Each
+//! event is recorded in `stripe_events` once its handler succeeds; the most
+//! recent IDs are also kept in memory so the usual quick redelivery doesn't
+//! cost a database round trip.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use super::supabase::{SupabaseClient, SupabaseError};
+
+/// Event IDs remembered in memory
+const RECENT_EVENTS: usize = 1024;
+
+#[derive(Serialize)]
+struct ProcessedEvent<'a> {
+    id: &'a str,
+    event_type: &'a str,
+}
+
+/// Recently processed IDs, oldest evicted first
+#[derive(Default)]
+struct RecentEvents {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl RecentEvents {
+    fn insert(&mut self, event_id: &str) {
+        if !self.ids.insert(event_id.to_string()) {
+            return;
+        }
+        self.order.push_back(event_id.to_string());
+        if self.order.len() > RECENT_EVENTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Processed Stripe event store operations
+#[derive(Clone)]
+pub struct StripeEventStore {
+    client: SupabaseClient,
+    recent: Arc<Mutex<RecentEvents>>,
+}
+
+impl StripeEventStore {
+    pub fn new(client: SupabaseClient) -> Self {
+        Self {
+            client,
+            recent: Arc::default(),
+        }
+    }
+
+    /// Whether an event with this ID has already been handled
+    pub async fn is_processed(&self, event_id: &str) -> Result<bool, SupabaseError> {
+        if self.recent.lock().ids.contains(event_id) {
+            return Ok(true);
+        }
+
+        let query = format!("id=eq.{}&select=id&limit=1", event_id);
+        let found: Vec<serde_json::Value> = self.client.get("stripe_events", &query).await?;
+        if found.is_empty() {
+            return Ok(false);
+        }
+        self.recent.lock().insert(event_id);
+        Ok(true)
+    }
+
+    /// Record an event as handled
+    pub async fn mark_processed(&self, event_id: &str, event_type: &str) -> Result<(), SupabaseError> {
+        let event = ProcessedEvent {
+            id: event_id,
+            event_type,
+        };
+        self.client.upsert("stripe_events", &event, "id").await?;
+        self.recent.lock().insert(event_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+
+    #[tokio::test]
+    async fn events_found_in_the_table_are_remembered() {
+        let mock = MockSupabase::start(&[(200, r#"[{"id": "evt_1"}]"#), (200, "[]")]).await;
+        let store = StripeEventStore::new(mock.client(0));
+
+        assert!(store.is_processed("evt_1").await.unwrap());
+        assert!(store.is_processed("evt_1").await.unwrap());
+        assert!(!store.is_processed("evt_2").await.unwrap());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].uri, "/rest/v1/stripe_events?id=eq.evt_1&select=id&limit=1");
+    }
+
+    #[test]
+    fn only_the_most_recent_ids_stay_in_memory() {
+        let mut recent = RecentEvents::default();
+        for n in 0..=RECENT_EVENTS {
+            recent.insert(&format!("evt_{n}"));
+        }
+        assert!(!recent.ids.contains("evt_0"));
+        assert!(recent.ids.contains("evt_1"));
+        assert_eq!(recent.ids.len(), RECENT_EVENTS);
+    }
+}
@@ -0,0 +1,71 @@
+//! Battle pass subscriptions, mirrored from Stripe webhooks
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::supabase::{SupabaseClient, SupabaseError};
+
+/// Subscription statuses that keep the battle pass active
+pub const ACTIVE_STATUSES: [&str; 2] = ["active", "trialing"];
+
+/// A user's Stripe subscription as last reported by a webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub stripe_subscription_id: String,
+    pub user_id: Uuid,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+struct SubscriptionApply<'a> {
+    p_subscription_id: &'a str,
+    p_user_id: Uuid,
+    p_status: &'a str,
+    p_event_at: i64,
+}
+
+/// Subscription store operations
+#[derive(Clone)]
+pub struct SubscriptionStore {
+    client: SupabaseClient,
+}
+
+impl SubscriptionStore {
+    pub fn new(client: SupabaseClient) -> Self {
+        Self { client }
+    }
+
+    /// Record a subscription's status from a webhook event created at
+    /// `event_at` (unix seconds). Events older than the last one applied are
+    /// ignored, so redelivered and out-of-order events are harmless. Returns
+    /// whether the event changed anything
+    pub async fn apply(
+        &self,
+        subscription_id: &str,
+        user_id: Uuid,
+        status: &str,
+        event_at: i64,
+    ) -> Result<bool, SupabaseError> {
+        let args = SubscriptionApply {
+            p_subscription_id: subscription_id,
+            p_user_id: user_id,
+            p_status: status,
+            p_event_at: event_at,
+        };
+
+        // Safe to retry: replaying the same event is a no-op
+        self.client.rpc("subscription_apply", &args, true).await
+    }
+
+    /// Whether the user has a subscription that keeps the battle pass active
+    pub async fn has_active_pass(&self, user_id: Uuid) -> Result<bool, SupabaseError> {
+        let query = format!(
+            "user_id=eq.{}&status=in.({})&select=stripe_subscription_id,user_id,status&limit=1",
+            user_id,
+            ACTIVE_STATUSES.join(",")
+        );
+        let subscription: Option<Subscription> =
+            self.client.get_one("subscriptions", &query).await?;
+        Ok(subscription.is_some())
+    }
+}
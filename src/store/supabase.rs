@@ -1,11 +1,52 @@
 //! Supabase REST API client using service_role key
 
-use reqwest::Client;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::config::Config;
 
+/// Retry policy for transient Supabase failures
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries allowed after the initial attempt
+    pub max_retries: u32,
+    /// Base delay for exponential backoff
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Backoff before the given retry (1-based), doubled each time with up to 50% jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16));
+        exp.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    /// Gateway errors are usually transient on Supabase's side
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Non-idempotent requests only retry when the connection was never established,
+    /// since any later failure may mean the request was already applied
+    pub fn is_retryable_error(err: &reqwest::Error, idempotent: bool) -> bool {
+        if idempotent {
+            err.is_connect() || err.is_timeout() || err.is_request()
+        } else {
+            err.is_connect()
+        }
+    }
+}
+
 /// Supabase client for server-side database operations
 /// Uses service_role key which bypasses RLS - handle with care!
 #[derive(Clone)]
@@ -13,14 +54,29 @@ pub struct SupabaseClient {
     client: Client,
     base_url: String,
     service_role_key: String,
+    retry: RetryPolicy,
 }
 
 impl SupabaseClient {
     pub fn new(config: &Config) -> Self {
+        Self::with_url(
+            config.supabase_url.clone(),
+            config.supabase_service_role_key.clone(),
+            RetryPolicy {
+                max_retries: config.supabase_max_retries,
+                base_delay: Duration::from_millis(config.supabase_retry_base_delay_ms),
+            },
+        )
+    }
+
+    /// Client for the Supabase project at `base_url`, which tests point at a
+    /// local mock server
+    pub(crate) fn with_url(base_url: String, service_role_key: String, retry: RetryPolicy) -> Self {
         Self {
             client: Client::new(),
-            base_url: config.supabase_url.clone(),
-            service_role_key: config.supabase_service_role_key.clone(),
+            base_url,
+            service_role_key,
+            retry,
         }
     }
 
@@ -29,6 +85,36 @@ impl SupabaseClient {
         format!("{}/rest/v1/{}", self.base_url, table)
     }
 
+    /// Send a request, retrying transient failures according to the retry policy.
+    /// `build` is called once per attempt since a `RequestBuilder` can't be reused.
+    async fn send_with_retry(
+        &self,
+        idempotent: bool,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, SupabaseError> {
+        let mut retry = 0;
+        loop {
+            let can_retry = retry < self.retry.max_retries;
+            match build().send().await {
+                Ok(response)
+                    if can_retry
+                        && idempotent
+                        && RetryPolicy::is_retryable_status(response.status()) =>
+                {
+                    warn!(status = %response.status(), retry = retry + 1, "Supabase request failed, retrying");
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if can_retry && RetryPolicy::is_retryable_error(&e, idempotent) => {
+                    warn!(error = %e, retry = retry + 1, "Supabase request failed, retrying");
+                }
+                Err(e) => return Err(SupabaseError::Request(e)),
+            }
+
+            retry += 1;
+            tokio::time::sleep(self.retry.backoff(retry)).await;
+        }
+    }
+
     /// Make an authenticated GET request
     pub async fn get<T: DeserializeOwned>(
         &self,
@@ -38,14 +124,14 @@ impl SupabaseClient {
         let url = format!("{}?{}", self.rest_url(table), query);
 
         let response = self
-            .client
-            .get(&url)
-            .header("apikey", &self.service_role_key)
-            .header("Authorization", format!("Bearer {}", self.service_role_key))
-            .header("Content-Type", "application/json")
-            .send()
-            .await
-            .map_err(SupabaseError::Request)?;
+            .send_with_retry(true, || {
+                self.client
+                    .get(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -65,15 +151,15 @@ impl SupabaseClient {
         let url = format!("{}?{}", self.rest_url(table), query);
 
         let response = self
-            .client
-            .get(&url)
-            .header("apikey", &self.service_role_key)
-            .header("Authorization", format!("Bearer {}", self.service_role_key))
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/vnd.pgrst.object+json")
-            .send()
-            .await
-            .map_err(SupabaseError::Request)?;
+            .send_with_retry(true, || {
+                self.client
+                    .get(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+                    .header("Accept", "application/vnd.pgrst.object+json")
+            })
+            .await?;
 
         if response.status() == reqwest::StatusCode::NOT_ACCEPTABLE {
             // No rows found
@@ -89,6 +175,13 @@ impl SupabaseClient {
         response.json().await.map(Some).map_err(SupabaseError::Parse)
     }
 
+    /// Cheap authenticated read to check the database is reachable
+    pub async fn ping(&self) -> Result<(), SupabaseError> {
+        self.get_one::<serde_json::Value>("items", "select=id&limit=1")
+            .await
+            .map(|_| ())
+    }
+
     /// Make an authenticated POST request (insert)
     pub async fn insert<T: Serialize, R: DeserializeOwned>(
         &self,
@@ -97,17 +190,18 @@ impl SupabaseClient {
     ) -> Result<R, SupabaseError> {
         let url = self.rest_url(table);
 
+        // Inserts are not idempotent, so only connection failures are retried
         let response = self
-            .client
-            .post(&url)
-            .header("apikey", &self.service_role_key)
-            .header("Authorization", format!("Bearer {}", self.service_role_key))
-            .header("Content-Type", "application/json")
-            .header("Prefer", "return=representation")
-            .json(data)
-            .send()
-            .await
-            .map_err(SupabaseError::Request)?;
+            .send_with_retry(false, || {
+                self.client
+                    .post(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+                    .header("Prefer", "return=representation")
+                    .json(data)
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -133,15 +227,15 @@ impl SupabaseClient {
         let url = format!("{}?{}", self.rest_url(table), query);
 
         let response = self
-            .client
-            .patch(&url)
-            .header("apikey", &self.service_role_key)
-            .header("Authorization", format!("Bearer {}", self.service_role_key))
-            .header("Content-Type", "application/json")
-            .json(data)
-            .send()
-            .await
-            .map_err(SupabaseError::Request)?;
+            .send_with_retry(true, || {
+                self.client
+                    .patch(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+                    .json(data)
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -162,17 +256,17 @@ impl SupabaseClient {
         let url = self.rest_url(table);
 
         let response = self
-            .client
-            .post(&url)
-            .header("apikey", &self.service_role_key)
-            .header("Authorization", format!("Bearer {}", self.service_role_key))
-            .header("Content-Type", "application/json")
-            .header("Prefer", format!("resolution=merge-duplicates,return=minimal"))
-            .header("On-Conflict", on_conflict)
-            .json(data)
-            .send()
-            .await
-            .map_err(SupabaseError::Request)?;
+            .send_with_retry(true, || {
+                self.client
+                    .post(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+                    .header("Prefer", format!("resolution=merge-duplicates,return=minimal"))
+                    .header("On-Conflict", on_conflict)
+                    .json(data)
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -182,6 +276,36 @@ impl SupabaseClient {
 
         Ok(())
     }
+
+    /// Call a Postgres function through PostgREST (`/rpc/<function>`).
+    /// Only retried on transient errors when the function is `idempotent`.
+    pub async fn rpc<T: Serialize, R: DeserializeOwned>(
+        &self,
+        function: &str,
+        args: &T,
+        idempotent: bool,
+    ) -> Result<R, SupabaseError> {
+        let url = self.rest_url(&format!("rpc/{}", function));
+
+        let response = self
+            .send_with_retry(idempotent, || {
+                self.client
+                    .post(&url)
+                    .header("apikey", &self.service_role_key)
+                    .header("Authorization", format!("Bearer {}", self.service_role_key))
+                    .header("Content-Type", "application/json")
+                    .json(args)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(SupabaseError::Api { status: status.as_u16(), body });
+        }
+
+        response.json().await.map_err(SupabaseError::Parse)
+    }
 }
 
 /// Store item as defined in items table
@@ -192,7 +316,13 @@ pub struct StoreItem {
     pub item_type: String,
     pub name: String,
     pub price_usd: i32,
+    /// Price in soft currency, if the item can be bought with coins
+    #[serde(default)]
+    pub price_coins: Option<i32>,
     pub stripe_price_id: Option<String>,
+    /// Only granted or equipped while the user's battle pass is active
+    #[serde(default)]
+    pub requires_pass: bool,
     pub active: bool,
 }
 
@@ -204,6 +334,7 @@ pub struct Purchase {
     pub stripe_session_id: Option<String>,
     pub stripe_payment_intent: Option<String>,
     pub item_id: Uuid,
+    pub quantity: u32,
     pub status: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
@@ -215,6 +346,7 @@ pub struct NewPurchase {
     pub user_id: Uuid,
     pub stripe_session_id: String,
     pub item_id: Uuid,
+    pub quantity: u32,
     pub status: String,
 }
 
@@ -233,3 +365,164 @@ pub enum SupabaseError {
     #[error("No row returned from insert")]
     NoRowReturned,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock::MockSupabase;
+
+    #[tokio::test]
+    async fn get_retries_gateway_errors() {
+        let mock = MockSupabase::start(&[(503, ""), (502, ""), (200, r#"[{"id": 1}]"#)]).await;
+
+        let rows: Vec<serde_json::Value> = mock.client(3).get("items", "select=id").await.unwrap();
+
+        assert_eq!(rows, vec![serde_json::json!({"id": 1})]);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|r| r.uri == "/rest/v1/items?select=id"));
+    }
+
+    #[tokio::test]
+    async fn get_gives_up_after_max_retries() {
+        let mock = MockSupabase::start(&[(504, "timeout")]).await;
+
+        let result = mock.client(2).get::<serde_json::Value>("items", "").await;
+
+        assert!(matches!(
+            result,
+            Err(SupabaseError::Api { status: 504, .. })
+        ));
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn ping_accepts_an_empty_table_but_not_a_rejected_key() {
+        let empty = MockSupabase::start(&[(406, "")]).await;
+        empty.client(0).ping().await.unwrap();
+
+        let rejected = MockSupabase::start(&[(401, "invalid key")]).await;
+        assert!(matches!(
+            rejected.client(0).ping().await,
+            Err(SupabaseError::Api { status: 401, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let mock = MockSupabase::start(&[(400, "bad filter"), (200, "[]")]).await;
+
+        let result = mock.client(3).get::<serde_json::Value>("items", "").await;
+
+        assert!(matches!(
+            result,
+            Err(SupabaseError::Api { status: 400, .. })
+        ));
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn idempotent_writes_retry_gateway_errors() {
+        let mock = MockSupabase::start(&[(503, ""), (204, "")]).await;
+        let client = mock.client(3);
+
+        client
+            .update("profiles", "id=eq.1", &serde_json::json!({"a": 1}))
+            .await
+            .unwrap();
+        client
+            .upsert("profiles", &serde_json::json!({"a": 1}), "id")
+            .await
+            .unwrap();
+
+        // One retried update, then an upsert that succeeds first time
+        let requests = mock.requests();
+        let methods: Vec<_> = requests.iter().map(|r| r.method.as_str()).collect();
+        assert_eq!(methods, ["PATCH", "PATCH", "POST"]);
+        assert_eq!(requests[2].header("On-Conflict"), Some("id"));
+        assert_eq!(requests[2].json(), serde_json::json!({"a": 1}));
+    }
+
+    #[tokio::test]
+    async fn insert_is_not_retried_after_a_response() {
+        let mock = MockSupabase::start(&[(503, ""), (201, r#"[{"id": 1}]"#)]).await;
+
+        let result = mock
+            .client(3)
+            .insert::<_, serde_json::Value>("purchases", &serde_json::json!({"id": 1}))
+            .await;
+
+        // The insert may have been applied before the gateway failed
+        assert!(matches!(
+            result,
+            Err(SupabaseError::Api { status: 503, .. })
+        ));
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn connection_errors_retry_then_fail() {
+        // Nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = SupabaseClient::with_url(
+            url,
+            "key".to_string(),
+            RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+            },
+        );
+
+        let result = client
+            .insert::<_, serde_json::Value>("purchases", &serde_json::json!({}))
+            .await;
+
+        match result {
+            Err(SupabaseError::Request(e)) => assert!(e.is_connect()),
+            other => panic!("expected a connection error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        };
+        for retry in 1..=3 {
+            let full = Duration::from_millis(100 << (retry - 1));
+            let delay = policy.backoff(retry);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "retry {retry}: {delay:?}"
+            );
+        }
+    }
+    #[tokio::test]
+    async fn non_idempotent_rpc_is_not_retried_after_a_response() {
+        let mock = MockSupabase::start(&[(502, ""), (200, "1")]).await;
+        let client = mock.client(3);
+
+        let result = client
+            .rpc::<_, i64>("wallet_debit", &serde_json::json!({}), false)
+            .await;
+        assert!(matches!(
+            result,
+            Err(SupabaseError::Api { status: 502, .. })
+        ));
+        assert_eq!(mock.requests().len(), 1);
+
+        // The same failure is retried for an idempotent function
+        let mock = MockSupabase::start(&[(502, ""), (200, "1")]).await;
+        let balance: i64 = mock
+            .client(3)
+            .rpc("wallet_balance", &serde_json::json!({}), true)
+            .await
+            .unwrap();
+        assert_eq!(balance, 1);
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+}
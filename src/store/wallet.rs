@@ -0,0 +1,106 @@
+//! Soft currency wallets
+//!
+//! Balance changes go through the `wallet_apply` Postgres function, which
+//! updates the balance and records the transaction atomically. Every change
+//! carries an idempotency key, so replaying a credit or debit is a no-op.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::supabase::{SupabaseClient, SupabaseError};
+
+/// Wallet row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wallet {
+    pub user_id: Uuid,
+    pub balance: i64,
+}
+
+/// Arguments to the `wallet_apply` RPC
+#[derive(Debug, Serialize)]
+struct WalletApply<'a> {
+    p_user_id: Uuid,
+    p_amount: i64,
+    p_reason: &'a str,
+    p_idempotency_key: &'a str,
+}
+
+/// Wallet operation errors
+#[derive(Debug, thiserror::Error)]
+pub enum WalletError {
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+
+    #[error("Database error: {0}")]
+    Database(#[from] SupabaseError),
+}
+
+/// Wallet store operations
+#[derive(Clone)]
+pub struct WalletStore {
+    client: SupabaseClient,
+}
+
+impl WalletStore {
+    pub fn new(client: SupabaseClient) -> Self {
+        Self { client }
+    }
+
+    /// Get a user's balance (0 if they have no wallet yet)
+    pub async fn get_balance(&self, user_id: Uuid) -> Result<i64, SupabaseError> {
+        let query = format!("user_id=eq.{}", user_id);
+        let wallet: Option<Wallet> = self.client.get_one("wallets", &query).await?;
+        Ok(wallet.map(|w| w.balance).unwrap_or(0))
+    }
+
+    /// Add currency, returning the new balance
+    pub async fn credit(
+        &self,
+        user_id: Uuid,
+        amount: u32,
+        reason: &str,
+        idempotency_key: &str,
+    ) -> Result<i64, WalletError> {
+        self.apply(user_id, amount as i64, reason, idempotency_key)
+            .await
+    }
+
+    /// Spend currency, returning the new balance. Fails without changing
+    /// anything if the balance would go negative
+    pub async fn debit(
+        &self,
+        user_id: Uuid,
+        amount: u32,
+        reason: &str,
+        idempotency_key: &str,
+    ) -> Result<i64, WalletError> {
+        self.apply(user_id, -(amount as i64), reason, idempotency_key)
+            .await
+    }
+
+    async fn apply(
+        &self,
+        user_id: Uuid,
+        amount: i64,
+        reason: &str,
+        idempotency_key: &str,
+    ) -> Result<i64, WalletError> {
+        let args = WalletApply {
+            p_user_id: user_id,
+            p_amount: amount,
+            p_reason: reason,
+            p_idempotency_key: idempotency_key,
+        };
+
+        // Safe to retry: the idempotency key makes repeated calls no-ops
+        self.client
+            .rpc("wallet_apply", &args, true)
+            .await
+            .map_err(|e| match e {
+                SupabaseError::Api { ref body, .. } if body.contains("insufficient_funds") => {
+                    WalletError::InsufficientFunds
+                }
+                e => WalletError::Database(e),
+            })
+    }
+}
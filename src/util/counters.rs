@@ -0,0 +1,82 @@
+//! Server-wide operational counters
+//!
+//! Plain atomics bumped where things happen and read by the `/metrics`
+//! endpoint, so counting never takes a lock. Every counter only goes up;
+//! gauges like open connections are derived from pairs of them.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Counters shared by the HTTP routes, WebSocket sessions and matchmaking
+#[derive(Debug, Default)]
+pub struct ServerCounters {
+    /// WebSocket connections upgraded
+    pub connections_opened: AtomicU64,
+    /// WebSocket connections that have ended
+    pub connections_closed: AtomicU64,
+    /// WebSocket upgrades refused for missing or bad credentials
+    pub auth_failures: AtomicU64,
+    /// Matches created by matchmaking
+    pub matches_created: AtomicU64,
+    /// Matches whose task has finished and been cleaned up
+    pub matches_ended: AtomicU64,
+    /// Players added to the matchmaking queue
+    pub queue_joins: AtomicU64,
+    /// Players taken out of the queue by `/matchmaking/leave` or disconnecting
+    pub queue_leaves: AtomicU64,
+    /// Stripe webhooks handled
+    pub webhooks_succeeded: AtomicU64,
+    /// Stripe webhooks rejected or failed
+    pub webhooks_failed: AtomicU64,
+}
+
+impl ServerCounters {
+    /// Count one
+    pub fn bump(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a new connection, and count it closed when the guard drops
+    pub fn open_connection(self: &Arc<Self>) -> ConnectionGuard {
+        Self::bump(&self.connections_opened);
+        ConnectionGuard(self.clone())
+    }
+
+    /// Connections open right now
+    pub fn open_connections(&self) -> u64 {
+        // Read closed first so a close racing the reads can't underflow
+        let closed = self.connections_closed.load(Ordering::Relaxed);
+        self.connections_opened
+            .load(Ordering::Relaxed)
+            .saturating_sub(closed)
+    }
+}
+
+/// Counts its connection closed however the session ends
+#[derive(Debug)]
+pub struct ConnectionGuard(Arc<ServerCounters>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        ServerCounters::bump(&self.0.connections_closed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_are_closed_when_the_guard_drops() {
+        let counters = Arc::new(ServerCounters::default());
+        let first = counters.open_connection();
+        let second = counters.open_connection();
+        assert_eq!(counters.open_connections(), 2);
+
+        drop(first);
+        assert_eq!(counters.open_connections(), 1);
+        drop(second);
+        assert_eq!(counters.open_connections(), 0);
+        assert_eq!(counters.connections_opened.load(Ordering::Relaxed), 2);
+    }
+}
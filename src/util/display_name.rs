@@ -0,0 +1,161 @@
+//! Display name validation
+
+/// Minimum display name length in characters
+pub const MIN_DISPLAY_NAME_LEN: usize = 3;
+
+/// Maximum display name length in characters
+pub const MAX_DISPLAY_NAME_LEN: usize = 20;
+
+/// Basic denylist, matched case-insensitively against each word of the name
+/// and the whole name run together, with leetspeak and trailing digits undone,
+/// so "Admin_42" and "4dm1n" are denied but "Badminton" is not
+const DENYLIST: &[&str] = &[
+    "admin", "moderator", "fuck", "shit", "cunt", "bitch", "nigga", "nigger", "fag", "faggot",
+    "rape", "rapist", "nazi",
+];
+
+/// Stems denied anywhere in the name, for words no innocent name contains
+const DENIED_STEMS: &[&str] = &["fuck", "nigger", "faggot"];
+
+/// Reasons a display name can be rejected
+#[derive(Debug, thiserror::Error)]
+pub enum DisplayNameError {
+    #[error("Display name must be between {MIN_DISPLAY_NAME_LEN} and {MAX_DISPLAY_NAME_LEN} characters")]
+    InvalidLength,
+
+    #[error("Display name may only contain letters, digits, spaces, '_' and '-'")]
+    InvalidCharacters,
+
+    #[error("Display name is not allowed")]
+    Denied,
+}
+
+/// In-memory name for a guest, who has no profile
+pub fn guest_display_name(user_id: uuid::Uuid) -> String {
+    format!("Guest_{}", &user_id.to_string()[..8])
+}
+
+/// Validate a requested display name, returning it with surrounding whitespace trimmed
+pub fn validate_display_name(name: &str) -> Result<String, DisplayNameError> {
+    let name = name.trim();
+
+    let len = name.chars().count();
+    if !(MIN_DISPLAY_NAME_LEN..=MAX_DISPLAY_NAME_LEN).contains(&len) {
+        return Err(DisplayNameError::InvalidLength);
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '-'))
+        || name.contains("  ")
+    {
+        return Err(DisplayNameError::InvalidCharacters);
+    }
+
+    let words = words(name);
+    let joined = words.concat();
+    if words.iter().chain([&joined]).any(|word| is_denied_word(word))
+        || DENIED_STEMS.iter().any(|stem| unleet(&joined).contains(stem))
+    {
+        return Err(DisplayNameError::Denied);
+    }
+
+    Ok(name.to_string())
+}
+
+/// Lowercased words of a name, split at separators and camelCase humps
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        let hump = prev_lower && c.is_ascii_uppercase();
+        if (matches!(c, ' ' | '_' | '-') || hump) && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        if c.is_ascii_alphanumeric() {
+            current.push(c.to_ascii_lowercase());
+        }
+        prev_lower = c.is_ascii_lowercase();
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Whether a word, read plainly or as leetspeak, is on the denylist
+fn is_denied_word(word: &str) -> bool {
+    [word.trim_end_matches(|c: char| c.is_ascii_digit()).to_string(), unleet(word)]
+        .iter()
+        .any(|w| {
+            let singular = w.strip_suffix('s').unwrap_or(w);
+            DENYLIST.contains(&w.as_str()) || DENYLIST.contains(&singular)
+        })
+}
+
+/// Read digits standing in for letters as those letters ("4dm1n" -> "admin")
+fn unleet(word: &str) -> String {
+    word.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            '9' => 'g',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denied(name: &str) -> bool {
+        matches!(validate_display_name(name), Err(DisplayNameError::Denied))
+    }
+
+    #[test]
+    fn ordinary_names_are_accepted_trimmed() {
+        for name in ["Pilot", "ace_42", "Red Baron", "x-wing-7", "Maverick99"] {
+            assert_eq!(validate_display_name(name).unwrap(), name);
+        }
+        assert_eq!(validate_display_name("  Pilot  ").unwrap(), "Pilot");
+    }
+
+    #[test]
+    fn malformed_names_are_rejected() {
+        assert!(matches!(validate_display_name("ab"), Err(DisplayNameError::InvalidLength)));
+        assert!(matches!(
+            validate_display_name(&"a".repeat(MAX_DISPLAY_NAME_LEN + 1)),
+            Err(DisplayNameError::InvalidLength)
+        ));
+        for name in ["pilot!", "ace  42", "héros"] {
+            assert!(
+                matches!(validate_display_name(name), Err(DisplayNameError::InvalidCharacters)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn denied_words_are_rejected_however_they_are_written() {
+        for name in [
+            "admin", "ADMIN", "Admin_42", "the admin", "AdminBob", "a-d-m-i-n", "4dm1n", "nazis",
+            "sh1t", "Moderator 7", "fuckface",
+        ] {
+            assert!(denied(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn names_merely_containing_a_denied_word_are_accepted() {
+        for name in ["Badminton", "Grapes", "Scunthorpe", "Shitake", "Cockpit", "Therapist"] {
+            assert!(validate_display_name(name).is_ok(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Per-IP request rate and connection limits
+//!
+//! Public routes are rate limited by client IP, and each IP may hold only so
+//! many WebSocket connections at once, so one host can't exhaust the
+//! per-connection tasks. Behind a reverse proxy the socket address is the
+//! proxy's, so the client IP is read from `X-Forwarded-For` instead, but only
+//! as many hops in as there are trusted proxies: entries further left were
+//! written by the client and can't be trusted.
+
+use axum::http::HeaderMap;
+use dashmap::DashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use governor::clock::{Clock, DefaultClock};
+
+use super::rate_limit::{create_keyed_limiter, KeyedLimiter};
+
+/// Per-IP limits and how far to trust `X-Forwarded-For`
+#[derive(Debug, Clone, Copy)]
+pub struct IpLimitPolicy {
+    /// Requests per second to public routes
+    pub requests_per_second: u32,
+    /// WebSocket connections held at once (0 = unlimited)
+    pub max_connections: usize,
+    /// Reverse proxies in front of the server that append to
+    /// `X-Forwarded-For` (0 = use the socket address)
+    pub trusted_proxy_hops: usize,
+}
+
+impl Default for IpLimitPolicy {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 20,
+            max_connections: 10,
+            trusted_proxy_hops: 0,
+        }
+    }
+}
+
+/// Client IP of a request, found by the IP rate limit middleware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Request rate and open connections per client IP
+pub struct IpLimiter {
+    policy: IpLimitPolicy,
+    requests: Arc<KeyedLimiter<IpAddr>>,
+    connections: DashMap<IpAddr, usize>,
+}
+
+impl IpLimiter {
+    pub fn new(policy: IpLimitPolicy) -> Self {
+        Self {
+            requests: create_keyed_limiter(policy.requests_per_second),
+            connections: DashMap::new(),
+            policy,
+        }
+    }
+
+    /// Client IP of a request arriving from `peer`: the address the
+    /// outermost trusted proxy saw, or the peer itself without proxies.
+    /// `None` if there's no peer address and no usable header
+    pub fn client_ip(&self, peer: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let hops = self.policy.trusted_proxy_hops;
+        if hops == 0 {
+            return peer.map(|addr| addr.ip());
+        }
+
+        // Proxies append, so the trusted entries are the last `hops`
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .collect();
+        forwarded
+            .len()
+            .checked_sub(hops)
+            .and_then(|i| forwarded[i].parse().ok())
+            .or_else(|| peer.map(|addr| addr.ip()))
+    }
+
+    /// Count a request from `ip`, returning how long to wait if it's over
+    /// the rate
+    pub fn check_request(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.requests
+            .check_key(&ip)
+            .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+
+    /// Take one of `ip`'s connection slots, held until the guard drops.
+    /// `None` if the IP already has its maximum open
+    pub fn acquire_connection(self: &Arc<Self>, ip: IpAddr) -> Option<IpConnectionGuard> {
+        let max = self.policy.max_connections;
+        let mut open = self.connections.entry(ip).or_insert(0);
+        if max > 0 && *open >= max {
+            return None;
+        }
+        *open += 1;
+        Some(IpConnectionGuard {
+            limiter: self.clone(),
+            ip,
+        })
+    }
+
+    /// Forget IPs whose request limit has fully replenished
+    pub fn cleanup(&self) {
+        self.requests.retain_recent();
+        self.requests.shrink_to_fit();
+    }
+}
+
+/// A connection slot of one IP, released on drop
+pub struct IpConnectionGuard {
+    limiter: Arc<IpLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for IpConnectionGuard {
+    fn drop(&mut self) {
+        self.limiter.connections.remove_if_mut(&self.ip, |_, open| {
+            *open = open.saturating_sub(1);
+            *open == 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: u32, max_connections: usize, hops: usize) -> Arc<IpLimiter> {
+        Arc::new(IpLimiter::new(IpLimitPolicy {
+            requests_per_second,
+            max_connections,
+            trusted_proxy_hops: hops,
+        }))
+    }
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([203, 0, 113, last])
+    }
+
+    #[test]
+    fn a_burst_from_one_ip_is_throttled_while_others_proceed() {
+        let limiter = limiter(3, 0, 0);
+        for _ in 0..3 {
+            assert!(limiter.check_request(ip(1)).is_ok());
+        }
+        assert!(limiter.check_request(ip(1)).is_err());
+        assert!(limiter.check_request(ip(2)).is_ok());
+    }
+
+    #[test]
+    fn connections_are_capped_per_ip_until_released() {
+        let limiter = limiter(10, 2, 0);
+        let first = limiter.acquire_connection(ip(1)).unwrap();
+        let _second = limiter.acquire_connection(ip(1)).unwrap();
+        assert!(limiter.acquire_connection(ip(1)).is_none());
+        assert!(limiter.acquire_connection(ip(2)).is_some());
+
+        drop(first);
+        assert!(limiter.acquire_connection(ip(1)).is_some());
+    }
+
+    #[test]
+    fn forwarded_addresses_are_trusted_only_as_far_as_the_proxies() {
+        let peer = Some(SocketAddr::from(([10, 0, 0, 1], 443)));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.7, 203.0.113.1".parse().unwrap(),
+        );
+
+        // Without proxies the header could be forged, so the peer counts
+        assert_eq!(
+            limiter(1, 0, 0).client_ip(peer, &headers),
+            Some(IpAddr::from([10, 0, 0, 1]))
+        );
+        // One proxy: the entry it appended, not the one the client sent
+        assert_eq!(limiter(1, 0, 1).client_ip(peer, &headers), Some(ip(1)));
+        // Fewer entries than proxies falls back to the peer
+        assert_eq!(
+            limiter(1, 0, 3).client_ip(peer, &headers),
+            Some(IpAddr::from([10, 0, 0, 1]))
+        );
+    }
+}
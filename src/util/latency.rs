@@ -0,0 +1,81 @@
+//! Round-trip time measurement for WebSocket connections
+//!
+//! RTT is sampled from WebSocket ping/pong frames: each ping carries a random
+//! nonce, and only a pong echoing the outstanding nonce counts. Client
+//! timestamps are never used, so a client can't skew its own estimate.
+
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Samples above this are treated as stalls rather than latency
+const MAX_RTT_SAMPLE: Duration = Duration::from_secs(5);
+
+/// Weight of a new sample in the smoothed estimate (as in TCP's SRTT)
+const SMOOTHING: f32 = 0.125;
+
+/// Ping awaiting its pong
+#[derive(Debug, Clone, Copy)]
+struct Probe {
+    nonce: u64,
+    sent_at: Instant,
+}
+
+/// Smoothed RTT for one connection, shared with the player's match
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    pending: Mutex<Option<Probe>>,
+    /// Smoothed RTT in milliseconds (0 until the first sample)
+    srtt_ms: AtomicU32,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a probe, returning the ping payload. Replaces any probe still
+    /// waiting for its pong
+    pub fn start_probe(&self) -> Vec<u8> {
+        let nonce = rand::random::<u64>();
+        *self.pending.lock() = Some(Probe {
+            nonce,
+            sent_at: Instant::now(),
+        });
+        nonce.to_be_bytes().to_vec()
+    }
+
+    /// Handle a pong payload, returning the new estimate if it answered the
+    /// outstanding probe
+    pub fn on_pong(&self, payload: &[u8]) -> Option<u32> {
+        let nonce = u64::from_be_bytes(payload.try_into().ok()?);
+
+        let sample = {
+            let mut pending = self.pending.lock();
+            let probe = pending.filter(|p| p.nonce == nonce)?;
+            *pending = None;
+            probe.sent_at.elapsed()
+        };
+        if sample > MAX_RTT_SAMPLE {
+            return None;
+        }
+
+        let sample_ms = sample.as_secs_f32() * 1000.0;
+        let srtt_ms = match self.rtt_ms() {
+            Some(prev) => prev as f32 + SMOOTHING * (sample_ms - prev as f32),
+            None => sample_ms,
+        };
+        // Keep 0 reserved for "no estimate yet"
+        let srtt_ms = (srtt_ms.round() as u32).max(1);
+        self.srtt_ms.store(srtt_ms, Ordering::Relaxed);
+        Some(srtt_ms)
+    }
+
+    /// Smoothed RTT in milliseconds, once measured
+    pub fn rtt_ms(&self) -> Option<u32> {
+        match self.srtt_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(ms),
+        }
+    }
+}
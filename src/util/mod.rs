@@ -1,4 +1,8 @@
 //! Utility modules
 
+pub mod counters;
+pub mod display_name;
+pub mod ip_limit;
+pub mod latency;
 pub mod rate_limit;
 pub mod time;
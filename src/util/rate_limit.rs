@@ -2,19 +2,35 @@
 
 use governor::{
     clock::DefaultClock,
-    state::{InMemoryState, NotKeyed},
+    state::{keyed::DefaultKeyedStateStore, InMemoryState, NotKeyed},
     Quota, RateLimiter,
 };
+use std::hash::Hash;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::ip_limit::{IpLimitPolicy, IpLimiter};
 
 /// Rate limiter type alias
 pub type Limiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
 
+/// Rate limiter with separate state per key (per user unless specified)
+pub type KeyedLimiter<K = Uuid> = RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>;
+
 /// Create a rate limiter with the specified requests per second
 pub fn create_limiter(requests_per_second: u32) -> Arc<Limiter> {
-    let quota = Quota::per_second(NonZeroU32::new(requests_per_second).unwrap_or(NonZeroU32::MIN));
-    Arc::new(RateLimiter::direct(quota))
+    Arc::new(RateLimiter::direct(per_second(requests_per_second)))
+}
+
+/// Create a keyed rate limiter with the specified requests per second
+pub fn create_keyed_limiter<K: Hash + Eq + Clone>(requests_per_second: u32) -> Arc<KeyedLimiter<K>> {
+    Arc::new(RateLimiter::keyed(per_second(requests_per_second)))
+}
+
+fn per_second(requests_per_second: u32) -> Quota {
+    Quota::per_second(NonZeroU32::new(requests_per_second).unwrap_or(NonZeroU32::MIN))
 }
 
 /// Input rate limiter for WebSocket messages (per player)
@@ -26,6 +42,58 @@ pub const MATCHMAKING_RATE_LIMIT: u32 = 5; // Max 5 join attempts per second
 /// Inventory API rate limit
 pub const INVENTORY_RATE_LIMIT: u32 = 10; // Max 10 requests per second
 
+/// Checkout rate limit
+pub const CHECKOUT_RATE_LIMIT: u32 = 2; // Max 2 checkouts per second
+
+/// How often idle per-user limiter state is dropped
+const KEYED_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Per-user limiters for HTTP endpoints, plus the per-IP limits on public
+/// routes and WebSocket connections, shared across requests
+#[derive(Clone)]
+pub struct ApiRateLimiters {
+    pub matchmaking: Arc<KeyedLimiter>,
+    pub inventory: Arc<KeyedLimiter>,
+    pub checkout: Arc<KeyedLimiter>,
+    pub ip: Arc<IpLimiter>,
+}
+
+impl ApiRateLimiters {
+    pub fn new() -> Self {
+        Self {
+            matchmaking: create_keyed_limiter(MATCHMAKING_RATE_LIMIT),
+            inventory: create_keyed_limiter(INVENTORY_RATE_LIMIT),
+            checkout: create_keyed_limiter(CHECKOUT_RATE_LIMIT),
+            ip: Arc::new(IpLimiter::new(IpLimitPolicy::default())),
+        }
+    }
+
+    /// Limit clients by IP with the given policy
+    pub fn with_ip_policy(mut self, policy: IpLimitPolicy) -> Self {
+        self.ip = Arc::new(IpLimiter::new(policy));
+        self
+    }
+
+    /// Periodically forget users whose limits have fully replenished
+    pub async fn run_cleanup(&self) {
+        let mut interval = tokio::time::interval(KEYED_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            for limiter in [&self.matchmaking, &self.inventory, &self.checkout] {
+                limiter.retain_recent();
+                limiter.shrink_to_fit();
+            }
+            self.ip.cleanup();
+        }
+    }
+}
+
+impl Default for ApiRateLimiters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Per-player rate limiter state
 #[derive(Clone)]
 pub struct PlayerRateLimiter {
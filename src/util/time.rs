@@ -34,15 +34,15 @@ pub fn uptime_secs() -> u64 {
         .unwrap_or(0)
 }
 
-/// Tick rate configuration
+/// Default tick rate configuration (matches can override via `MatchSettings`)
 pub const SIMULATION_TPS: u32 = 30; // 30 ticks per second
 pub const SNAPSHOT_TPS: u32 = 20; // 20 snapshots per second
 pub const TICK_DURATION_MICROS: u64 = 1_000_000 / SIMULATION_TPS as u64;
 pub const SNAPSHOT_INTERVAL_MICROS: u64 = 1_000_000 / SNAPSHOT_TPS as u64;
 
-/// Calculate delta time for physics (in seconds)
-pub fn tick_delta() -> f32 {
-    1.0 / SIMULATION_TPS as f32
+/// Calculate delta time for physics (in seconds) at the given tick rate
+pub fn tick_delta(tick_rate: u32) -> f32 {
+    1.0 / tick_rate.max(1) as f32
 }
 
 /// A simple timer for measuring durations
@@ -76,3 +76,82 @@ impl Default for Timer {
         Self::new()
     }
 }
+
+/// Steps a `FixedTimestep` hands out for one pass of a loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestepSteps {
+    /// Steps to run now
+    pub run: u32,
+    /// Steps owed past the cap, dropped rather than run
+    pub dropped: u32,
+}
+
+/// Fixed-timestep accumulator for a loop woken once per step
+///
+/// Each pass adds the time since the previous one and runs as many whole
+/// steps as fit, so a loop that stalls catches up afterwards instead of its
+/// simulation falling behind the wall clock. At most `max_steps` run per
+/// pass; time owed beyond that is dropped, since an overloaded loop that
+/// tried to repay it would only fall further behind.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps: u32,
+    /// Time accumulated but not yet simulated (always less than one step
+    /// after a pass)
+    lag: Duration,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration, max_steps: u32) -> Self {
+        Self {
+            step: step.max(Duration::from_micros(1)),
+            max_steps: max_steps.max(1),
+            lag: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    /// Steps owed for a pass at `now`. The first pass runs one step
+    pub fn advance(&mut self, now: Instant) -> TimestepSteps {
+        let elapsed = self
+            .last
+            .map_or(self.step, |last| now.saturating_duration_since(last));
+        self.last = Some(now);
+        self.lag += elapsed;
+
+        let owed = (self.lag.as_nanos() / self.step.as_nanos()).min(u32::MAX as u128) as u32;
+        self.lag = self.lag.saturating_sub(self.step * owed);
+        let run = owed.min(self.max_steps);
+        TimestepSteps {
+            run,
+            dropped: owed - run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_stall_is_caught_up_within_the_cap() {
+        let step = Duration::from_millis(10);
+        let mut timestep = FixedTimestep::new(step, 3);
+        let start = Instant::now();
+
+        assert_eq!(timestep.advance(start).run, 1);
+        assert_eq!(timestep.advance(start + step).run, 1);
+        // A pass that comes early runs nothing, and the next makes up for it
+        assert_eq!(timestep.advance(start + step * 2 - step / 2).run, 0);
+        assert_eq!(timestep.advance(start + step * 3).run, 2);
+
+        // Stalled for five steps: three run, two are dropped
+        assert_eq!(
+            timestep.advance(start + step * 8),
+            TimestepSteps { run: 3, dropped: 2 }
+        );
+        assert_eq!(timestep.advance(start + step * 9).run, 1);
+    }
+}
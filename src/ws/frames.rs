@@ -0,0 +1,110 @@
+//! Limits on what clients may send over the WebSocket
+//!
+//! Client messages are small JSON objects, so anything much bigger comes from
+//! a broken or hostile client. Frames over `max_frame_bytes` are refused by
+//! the WebSocket layer before they're buffered, which ends the connection.
+//! Messages over `max_message_bytes` are dropped without being parsed. Each
+//! dropped message, like each one that doesn't parse, is a strike, and a
+//! connection that collects `max_strikes` is closed.
+
+use crate::ws::protocol::ClientMsg;
+
+/// Size limits and how many bad messages a connection may send
+#[derive(Debug, Clone, Copy)]
+pub struct FramePolicy {
+    /// Largest frame or message the WebSocket layer accepts
+    pub max_frame_bytes: usize,
+    /// Largest text message that gets parsed
+    pub max_message_bytes: usize,
+    /// Bad messages that close the connection (0 = never)
+    pub max_strikes: usize,
+}
+
+impl Default for FramePolicy {
+    fn default() -> Self {
+        Self {
+            max_frame_bytes: 64 * 1024,
+            max_message_bytes: 4 * 1024,
+            max_strikes: 5,
+        }
+    }
+}
+
+/// Why a client message was dropped
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    #[error("message of {0} bytes is over the size limit")]
+    TooLarge(usize),
+
+    #[error("malformed message: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Bad messages received on one connection
+#[derive(Debug)]
+pub struct FrameGuard {
+    policy: FramePolicy,
+    strikes: usize,
+}
+
+impl FrameGuard {
+    pub fn new(policy: FramePolicy) -> Self {
+        Self { policy, strikes: 0 }
+    }
+
+    /// Parse a text message, counting a strike if it's too large or malformed
+    pub fn parse(&mut self, text: &str) -> Result<ClientMsg, FrameError> {
+        let result = if text.len() > self.policy.max_message_bytes {
+            Err(FrameError::TooLarge(text.len()))
+        } else {
+            serde_json::from_str(text).map_err(FrameError::from)
+        };
+        if result.is_err() {
+            self.strikes += 1;
+        }
+        result
+    }
+
+    /// Whether the connection has sent enough bad messages to be closed
+    pub fn exhausted(&self) -> bool {
+        self.policy.max_strikes > 0 && self.strikes >= self.policy.max_strikes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_message_bytes: usize, max_strikes: usize) -> FramePolicy {
+        FramePolicy {
+            max_frame_bytes: 1024,
+            max_message_bytes,
+            max_strikes,
+        }
+    }
+
+    #[test]
+    fn oversized_and_malformed_messages_are_strikes() {
+        let mut guard = FrameGuard::new(policy(64, 3));
+        let ping = r#"{"type":"ping","t":1}"#;
+        assert!(guard.parse(ping).is_ok());
+
+        let padded = format!(r#"{{"type":"ping","t":1,"pad":"{}"}}"#, "x".repeat(64));
+        assert!(matches!(guard.parse(&padded), Err(FrameError::TooLarge(_))));
+        assert!(matches!(guard.parse("{"), Err(FrameError::Malformed(_))));
+        assert!(!guard.exhausted());
+
+        assert!(guard.parse(ping).is_ok());
+        assert!(guard.parse("[]").is_err());
+        assert!(guard.exhausted());
+    }
+
+    #[test]
+    fn zero_strikes_never_closes() {
+        let mut guard = FrameGuard::new(policy(64, 0));
+        for _ in 0..100 {
+            assert!(guard.parse("not json").is_err());
+        }
+        assert!(!guard.exhausted());
+    }
+}
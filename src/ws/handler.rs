@@ -2,45 +2,144 @@
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        Query, State,
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Extension, Query, State,
     },
+    http::{header, HeaderMap},
     response::Response,
 };
+use flate2::{write::DeflateEncoder, Compression};
 use futures::{SinkExt, StreamExt};
 use serde::Deserialize;
-use tokio::sync::{broadcast, mpsc};
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Notify};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::app::AppState;
-use crate::game::PlayerInput;
-use crate::http::middleware::verify_jwt;
+use crate::game::{Cosmetics, PlayerInput};
+use crate::http::middleware::{too_many_requests, verify_jwt, JwtValidation};
+use crate::matchmaking::rewards::{daily_login_key, DAILY_LOGIN_REWARD};
+use crate::util::counters::ServerCounters;
+use crate::util::display_name::guest_display_name;
+use crate::util::ip_limit::ClientIp;
+use crate::util::latency::LatencyTracker;
 use crate::util::rate_limit::PlayerRateLimiter;
 use crate::util::time::unix_millis;
-use crate::ws::protocol::{ClientMsg, ServerMsg};
+use crate::ws::frames::{FrameError, FrameGuard, FramePolicy};
+use crate::ws::lag::{LagMetrics, LagPolicy, LagTracker};
+use crate::ws::protocol::{
+    is_compatible_version, ClientMsg, ErrorCode, ServerMsg, PROTOCOL_VERSION,
+};
+use crate::ws::reauth::{ExpiryStep, Reauth, ReauthPolicy, TokenExpiry};
+use crate::ws::ticket::Credentials;
+
+/// Subprotocol clients use to carry a JWT in `Sec-WebSocket-Protocol`
+/// (e.g. `new WebSocket(url, ["bearer", token])`)
+const BEARER_PROTOCOL: &str = "bearer";
+
+/// `compression` query value that opts into deflated messages
+const DEFLATE_COMPRESSION: &str = "deflate";
+
+/// Messages shorter than this are sent as plain text even when compressing
+const COMPRESSION_MIN_BYTES: usize = 512;
+
+/// Longest a member's profile lookup may take before they keep their
+/// placeholder name
+const PROFILE_FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `Retry-After` for an upgrade refused because its IP has too many
+/// connections open
+const IP_CONNECTION_RETRY: Duration = Duration::from_secs(5);
+
+/// Longest the writer gets to send a close frame the reader asked for
+const CLOSE_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Protocol-level keepalive settings
+#[derive(Debug, Clone, Copy)]
+struct Heartbeat {
+    /// How often to send a WebSocket ping frame
+    ping_interval: Duration,
+    /// Close the connection if nothing is received for this long
+    idle_timeout: Duration,
+}
+
+/// Per-connection session settings
+#[derive(Debug, Clone)]
+struct SessionOptions {
+    heartbeat: Heartbeat,
+    /// Send large messages as deflated binary frames
+    compress: bool,
+    /// When to give up on a client that can't keep up
+    lag: LagPolicy,
+    /// Where lag events are counted
+    lag_metrics: Arc<LagMetrics>,
+    /// Limits on client messages
+    frames: FramePolicy,
+    /// Keeping the connection's token fresh (`None` = never expires)
+    reauth: Option<Reauth>,
+    /// Notified when a newer connection of the same user replaces this one
+    evicted: Arc<Notify>,
+}
 
 /// Query parameters for WebSocket connection
 #[derive(Debug, Deserialize)]
 pub struct WsQuery {
-    /// JWT token for authentication
-    pub token: String,
+    /// Single-use ticket from `POST /ws-ticket`
+    pub ticket: Option<Uuid>,
+    /// JWT token for authentication (deprecated, leaks into access logs)
+    pub token: Option<String>,
+    /// `deflate` to receive large messages as raw-deflate binary frames
+    pub compression: Option<String>,
+    /// Protocol version the client was built against (e.g. `2` or `2.1`)
+    pub protocol: Option<String>,
 }
 
 /// WebSocket upgrade handler
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     Query(query): Query<WsQuery>,
+    headers: HeaderMap,
+    client_ip: Option<Extension<ClientIp>>,
     State(state): State<AppState>,
 ) -> Response {
-    // Verify JWT token before upgrading
-    match verify_jwt(&query.token, &state.config.supabase_jwt_secret) {
-        Ok(claims) => {
-            info!(user_id = %claims.sub, "WebSocket upgrade for authenticated user");
-            ws.on_upgrade(move |socket| handle_socket(socket, claims.sub, state))
+    // Each IP holds a limited number of connections; the slot is released
+    // when the session ends
+    let slot = match client_ip {
+        Some(Extension(ClientIp(ip))) => match state.rate_limiters.ip.acquire_connection(ip) {
+            Some(slot) => Some(slot),
+            None => {
+                warn!(ip = %ip, "WebSocket refused: too many connections from this IP");
+                return too_many_requests(IP_CONNECTION_RETRY);
+            }
+        },
+        None => None,
+    };
+
+    // Compression is negotiated here, at upgrade time, and fixed for the connection
+    let compress =
+        state.config.ws_compression && query.compression.as_deref() == Some(DEFLATE_COMPRESSION);
+
+    // Authenticate before upgrading
+    match authenticate(&state, &query, &headers) {
+        Ok(credentials) => {
+            let (user_id, guest) = (credentials.user_id, credentials.guest);
+            info!(user_id = %user_id, guest, compress, "WebSocket upgrade for authenticated user");
+            let client_protocol = query.protocol;
+            let frames = frame_policy(&state);
+            limit_frames(ws, &frames)
+                .protocols([BEARER_PROTOCOL])
+                .on_upgrade(move |socket| async move {
+                    let _slot = slot;
+                    handle_socket(socket, credentials, compress, client_protocol, state).await
+                })
         }
         Err(e) => {
             error!(error = %e, "WebSocket auth failed");
+            ServerCounters::bump(&state.counters.auth_failures);
             Response::builder()
                 .status(401)
                 .body("Unauthorized".into())
@@ -49,89 +148,392 @@ pub async fn ws_handler(
     }
 }
 
+fn frame_policy(state: &AppState) -> FramePolicy {
+    FramePolicy {
+        max_frame_bytes: state.config.ws_max_frame_bytes,
+        max_message_bytes: state.config.ws_max_message_bytes,
+        max_strikes: state.config.ws_max_bad_messages,
+    }
+}
+
+/// Have the WebSocket layer refuse frames and messages over the hard limit
+/// instead of buffering them
+fn limit_frames(ws: WebSocketUpgrade, policy: &FramePolicy) -> WebSocketUpgrade {
+    ws.max_frame_size(policy.max_frame_bytes)
+        .max_message_size(policy.max_frame_bytes)
+}
+
+/// Resolve the connecting user from a ticket, subprotocol token, or query
+/// token
+fn authenticate(
+    state: &AppState,
+    query: &WsQuery,
+    headers: &HeaderMap,
+) -> Result<Credentials, String> {
+    if let Some(ticket) = query.ticket {
+        return state
+            .ws_tickets
+            .consume(ticket)
+            .ok_or_else(|| "Invalid or expired ticket".to_string());
+    }
+
+    let validation = JwtValidation::from_config(&state.config);
+
+    if let Some(token) = protocol_token(headers) {
+        return verify_jwt(token, &validation)
+            .map(|claims| Credentials::from_claims(&claims))
+            .map_err(|e| e.to_string());
+    }
+
+    match &query.token {
+        Some(token) if state.config.ws_allow_query_token => {
+            warn!("WebSocket token passed in query string (deprecated)");
+            verify_jwt(token, &validation)
+                .map(|claims| Credentials::from_claims(&claims))
+                .map_err(|e| e.to_string())
+        }
+        Some(_) => Err("Query string tokens are disabled".to_string()),
+        None => Err("Missing credentials".to_string()),
+    }
+}
+
+/// Extract the token following the bearer entry in `Sec-WebSocket-Protocol`
+fn protocol_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+    let mut protocols = value.split(',').map(str::trim);
+    protocols.find(|p| *p == BEARER_PROTOCOL)?;
+    protocols.next().filter(|t| !t.is_empty())
+}
+
 /// Handle the upgraded WebSocket connection
-async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
+async fn handle_socket(
+    socket: WebSocket,
+    credentials: Credentials,
+    compress: bool,
+    client_protocol: Option<String>,
+    state: AppState,
+) {
+    let Credentials { user_id, guest, .. } = credentials;
     info!(user_id = %user_id, "New WebSocket connection");
+    let _connection = state.counters.open_connection();
 
     let (mut ws_sink, ws_stream) = socket.split();
 
-    // Get user profile for display name
-    let display_name = match state.profile_store.get_profile(user_id).await {
-        Ok(Some(profile)) => profile.display_name.unwrap_or_else(|| "Unknown".to_string()),
-        Ok(None) => {
-            let name = format!("Player_{}", &user_id.to_string()[..8]);
-            let _ = state.profile_store.create_profile(user_id, &name).await;
-            name
-        }
-        Err(e) => {
-            error!(user_id = %user_id, error = %e, "Failed to fetch profile");
-            format!("Player_{}", &user_id.to_string()[..8])
-        }
+    // Turn away outdated clients before they join anything; clients that
+    // don't send a version are let through
+    if let Some(version) = client_protocol.filter(|v| !is_compatible_version(v)) {
+        warn!(user_id = %user_id, client_protocol = %version, "Incompatible client protocol");
+        let error = ServerMsg::Error {
+            code: ErrorCode::VersionMismatch,
+            message: format!(
+                "Client protocol {} is not supported, this server speaks version {}",
+                version, PROTOCOL_VERSION
+            ),
+        };
+        let _ = send_msg(&mut ws_sink, &error, compress).await;
+        let close = CloseFrame {
+            code: close_code::POLICY,
+            reason: "protocol version mismatch".into(),
+        };
+        let _ = ws_sink.send(Message::Close(Some(close))).await;
+        return;
+    }
+
+    // Members start with a placeholder name while their profile loads in the
+    // background, so a slow database doesn't hold up the connection (guests
+    // have no profile and get no rewards)
+    let (display_name, profile_fetch) = if guest {
+        (guest_display_name(user_id), None)
+    } else {
+        let fetch_state = state.clone();
+        let fetch = tokio::spawn(async move {
+            tokio::time::timeout(
+                PROFILE_FETCH_TIMEOUT,
+                member_display_name(&fetch_state, user_id),
+            )
+            .await
+        });
+        (placeholder_display_name(user_id), Some(fetch))
     };
 
+    // First connection of the day earns the login reward (repeats are no-ops)
+    if !guest {
+        let wallet_store = state.wallet_store.clone();
+        tokio::spawn(async move {
+            let key = daily_login_key(user_id, chrono::Utc::now().date_naive());
+            if let Err(e) = wallet_store
+                .credit(user_id, DAILY_LOGIN_REWARD, "daily_login", &key)
+                .await
+            {
+                error!(user_id = %user_id, error = %e, "Failed to credit daily login reward");
+            }
+        });
+    }
+
     // Send welcome message
     let welcome = ServerMsg::Welcome {
         user_id,
         server_time: unix_millis(),
+        protocol_version: PROTOCOL_VERSION,
     };
 
-    if let Err(e) = send_msg(&mut ws_sink, &welcome).await {
+    if let Err(e) = send_msg(&mut ws_sink, &welcome, compress).await {
         error!(user_id = %user_id, error = %e, "Failed to send welcome");
         return;
     }
 
     // Register with matchmaking to get channels
-    let (input_tx, snapshot_rx) = state.matchmaking.register_player(user_id).await;
+    let latency = Arc::new(LatencyTracker::new());
+    let registration = match state
+        .matchmaking
+        .register_player(user_id, display_name.clone(), guest, latency.clone())
+        .await
+    {
+        Ok(registration) => registration,
+        Err(e) => {
+            let error = ServerMsg::Error {
+                code: ErrorCode::AlreadyConnected,
+                message: e.to_string(),
+            };
+            let _ = send_msg(&mut ws_sink, &error, compress).await;
+            let close = CloseFrame {
+                code: close_code::POLICY,
+                reason: "already connected".into(),
+            };
+            let _ = ws_sink.send(Message::Close(Some(close))).await;
+            return;
+        }
+    };
+
+    // Patch in the real name once the profile resolves
+    if let Some(fetch) = profile_fetch {
+        let matchmaking = state.matchmaking.clone();
+        tokio::spawn(async move {
+            match fetch.await {
+                Ok(Ok(Some(name))) if name != display_name => {
+                    matchmaking.set_display_name(user_id, &name);
+                }
+                Ok(Err(_)) => {
+                    warn!(user_id = %user_id, "Profile fetch timed out, keeping placeholder name");
+                }
+                _ => {}
+            }
+        });
+    }
+
+    let options = SessionOptions {
+        heartbeat: Heartbeat {
+            ping_interval: Duration::from_secs(state.config.ws_ping_interval_secs.max(1)),
+            idle_timeout: Duration::from_secs(state.config.ws_idle_timeout_secs.max(1)),
+        },
+        compress,
+        lag: LagPolicy {
+            threshold: state.config.ws_lag_disconnect_threshold,
+            window: Duration::from_secs(state.config.ws_lag_window_secs),
+        },
+        lag_metrics: state.lag_metrics.clone(),
+        frames: frame_policy(&state),
+        reauth: Some(reauth(&state, credentials)),
+        evicted: registration.evicted,
+    };
 
     // Run the session with split read/write
-    run_session(user_id, display_name, ws_sink, ws_stream, input_tx, snapshot_rx).await;
+    run_session(
+        user_id,
+        ws_sink,
+        ws_stream,
+        registration.input_tx,
+        registration.snapshot_rx,
+        options,
+        latency,
+    )
+    .await;
 
     // Cleanup on disconnect
-    state.matchmaking.unregister_player(user_id).await;
+    state
+        .matchmaking
+        .unregister_player(user_id, registration.connection_id)
+        .await;
 
     info!(user_id = %user_id, "WebSocket connection closed");
 }
 
+/// Re-authentication for a connection opened with `credentials`, checking
+/// refreshed tokens like the one it was opened with
+fn reauth(state: &AppState, credentials: Credentials) -> Reauth {
+    let config = state.config.clone();
+    Reauth {
+        user_id: credentials.user_id,
+        expires_at: credentials.token_expires_at,
+        policy: ReauthPolicy {
+            warning: Duration::from_secs(state.config.ws_token_refresh_secs),
+            leeway: Duration::from_secs(state.config.jwt_leeway_secs),
+        },
+        verify: Arc::new(move |token| verify_jwt(token, &JwtValidation::from_config(&config))),
+    }
+}
+
+/// Name a member goes by until their profile loads, and the name new
+/// profiles start with
+fn placeholder_display_name(user_id: Uuid) -> String {
+    format!("Player_{}", &user_id.to_string()[..8])
+}
+
+/// Display name from a member's profile, creating the profile on first
+/// connect. None if the profile couldn't be fetched
+async fn member_display_name(state: &AppState, user_id: Uuid) -> Option<String> {
+    match state.profile_store.get_profile(user_id).await {
+        Ok(Some(profile)) => Some(profile.display_name.unwrap_or_else(|| "Unknown".to_string())),
+        Ok(None) => {
+            let name = placeholder_display_name(user_id);
+            let _ = state.profile_store.create_profile(user_id, &name).await;
+            Some(name)
+        }
+        Err(e) => {
+            error!(user_id = %user_id, error = %e, "Failed to fetch profile");
+            None
+        }
+    }
+}
+
 /// Run the WebSocket session with read/write split
 async fn run_session(
     user_id: Uuid,
-    display_name: String,
     mut ws_sink: futures::stream::SplitSink<WebSocket, Message>,
     mut ws_stream: futures::stream::SplitStream<WebSocket>,
     input_tx: mpsc::Sender<PlayerInput>,
     mut snapshot_rx: broadcast::Receiver<ServerMsg>,
+    options: SessionOptions,
+    latency: Arc<LatencyTracker>,
 ) {
+    let heartbeat = options.heartbeat;
     let rate_limiter = PlayerRateLimiter::new();
+    let mut frame_guard = FrameGuard::new(options.frames);
+
+    // Closes the reader decides on are sent by the writer, which owns the sink
+    let (close_tx, mut close_rx) = mpsc::channel::<CloseFrame<'static>>(1);
+
+    // The reader checks refreshed tokens and hands their expiry to the
+    // writer, which prompts for them and closes once the token runs out
+    let reauth = options.reauth.clone();
+    let (refresh_tx, mut refresh_rx) = mpsc::unbounded_channel::<u64>();
+    let evicted = options.evicted.clone();
 
-    // Spawn writer task: broadcast snapshots -> WebSocket
+    // Spawn writer task: broadcast snapshots -> WebSocket, plus keepalive pings
+    // (which double as RTT probes)
     let writer_user_id = user_id;
-    let writer_handle = tokio::spawn(async move {
+    let writer_latency = latency.clone();
+    let mut writer_handle = tokio::spawn(async move {
+        let mut ping_interval = tokio::time::interval(heartbeat.ping_interval);
+        ping_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        ping_interval.tick().await;
+        let mut lag_tracker = LagTracker::new(options.lag);
+        let mut expiry = options
+            .reauth
+            .as_ref()
+            .map(|r| TokenExpiry::new(r.expires_at, r.policy));
+
         loop {
-            match snapshot_rx.recv().await {
-                Ok(msg) => {
-                    if let Err(e) = send_msg(&mut ws_sink, &msg).await {
-                        debug!(user_id = %writer_user_id, error = %e, "WebSocket send failed");
+            tokio::select! {
+                result = snapshot_rx.recv() => match result {
+                    Ok(msg) => {
+                        if let Err(e) = send_msg(&mut ws_sink, &msg, options.compress).await {
+                            debug!(user_id = %writer_user_id, error = %e, "WebSocket send failed");
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(
+                            user_id = %writer_user_id,
+                            lagged_count = n,
+                            "Client lagged, skipping {} snapshots", n
+                        );
+                        options.lag_metrics.lagged_messages.fetch_add(n, Ordering::Relaxed);
+
+                        // An occasional lag resyncs with the next full snapshot; a
+                        // client that keeps lagging is missing events and is dropped
+                        if lag_tracker.record() {
+                            warn!(user_id = %writer_user_id, "Client keeps lagging, disconnecting");
+                            options.lag_metrics.disconnects.fetch_add(1, Ordering::Relaxed);
+                            let close = CloseFrame {
+                                code: close_code::AGAIN,
+                                reason: "lagging".into(),
+                            };
+                            let _ = ws_sink.send(Message::Close(Some(close))).await;
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        debug!(user_id = %writer_user_id, "Snapshot channel closed");
                         break;
                     }
+                },
+                Some(close) = close_rx.recv() => {
+                    let _ = ws_sink.send(Message::Close(Some(close))).await;
+                    break;
                 }
-                Err(broadcast::error::RecvError::Lagged(n)) => {
-                    warn!(
-                        user_id = %writer_user_id,
-                        lagged_count = n,
-                        "Client lagged, skipping {} snapshots", n
-                    );
-                    // Continue - don't disconnect for lag
+                Some(expires_at) = refresh_rx.recv() => {
+                    if let Some(expiry) = expiry.as_mut() {
+                        expiry.extend(expires_at);
+                    }
                 }
-                Err(broadcast::error::RecvError::Closed) => {
-                    debug!(user_id = %writer_user_id, "Snapshot channel closed");
-                    break;
+                step = next_expiry_step(expiry.as_mut()) => match step {
+                    ExpiryStep::Warn { expires_in_secs } => {
+                        debug!(user_id = %writer_user_id, expires_in_secs, "Token expiring, asking for a refresh");
+                        let msg = ServerMsg::TokenExpiring { expires_in_secs };
+                        if let Err(e) = send_msg(&mut ws_sink, &msg, options.compress).await {
+                            debug!(user_id = %writer_user_id, error = %e, "WebSocket send failed");
+                            break;
+                        }
+                    }
+                    ExpiryStep::Close => {
+                        info!(user_id = %writer_user_id, "Token expired without a refresh, closing connection");
+                        let close = CloseFrame {
+                            code: close_code::POLICY,
+                            reason: "token expired".into(),
+                        };
+                        let _ = ws_sink.send(Message::Close(Some(close))).await;
+                        break;
+                    }
+                },
+                _ = ping_interval.tick() => {
+                    let probe = writer_latency.start_probe();
+                    if let Err(e) = ws_sink.send(Message::Ping(probe)).await {
+                        debug!(user_id = %writer_user_id, error = %e, "WebSocket ping failed");
+                        break;
+                    }
                 }
             }
         }
     });
 
     // Reader loop: WebSocket -> match loop
-    while let Some(result) = ws_stream.next().await {
+    // Any frame (including pong replies to our pings) resets the idle timeout
+    let mut closing = false;
+    loop {
+        let next = tokio::select! {
+            next = tokio::time::timeout(heartbeat.idle_timeout, ws_stream.next()) => next,
+            _ = evicted.notified() => {
+                info!(user_id = %user_id, "Replaced by a newer connection, closing");
+                closing = close_tx
+                    .try_send(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: "connected elsewhere".into(),
+                    })
+                    .is_ok();
+                break;
+            }
+        };
+        let result = match next {
+            Ok(Some(result)) => result,
+            Ok(None) => break,
+            Err(_) => {
+                info!(user_id = %user_id, "WebSocket idle timeout, closing connection");
+                break;
+            }
+        };
+
         match result {
             Ok(Message::Text(text)) => {
                 if !rate_limiter.check_input() {
@@ -139,12 +541,37 @@ async fn run_session(
                     continue;
                 }
 
-                match serde_json::from_str::<ClientMsg>(&text) {
+                match frame_guard.parse(&text) {
+                    Ok(ClientMsg::Refresh { token }) => {
+                        match reauth.as_ref().map(|r| r.check(&token)) {
+                            Some(Ok(expires_at)) => {
+                                debug!(user_id = %user_id, expires_at, "Token refreshed");
+                                let _ = refresh_tx.send(expires_at);
+                            }
+                            Some(Err(e)) => {
+                                warn!(user_id = %user_id, error = %e, "Token refresh refused, closing connection");
+                                closing = close_tx
+                                    .try_send(CloseFrame {
+                                        code: close_code::POLICY,
+                                        reason: "invalid token".into(),
+                                    })
+                                    .is_ok();
+                                break;
+                            }
+                            None => {
+                                debug!(user_id = %user_id, "Refresh on a connection that doesn't expire");
+                            }
+                        }
+                    }
                     Ok(client_msg) => {
                         let input = PlayerInput {
                             user_id,
                             msg: client_msg,
                             received_at: unix_millis(),
+                            display_name: None,
+                            latency: None,
+                            guest: false,
+                            cosmetics: Cosmetics::default(),
                         };
 
                         if input_tx.send(input).await.is_err() {
@@ -153,7 +580,21 @@ async fn run_session(
                         }
                     }
                     Err(e) => {
-                        warn!(user_id = %user_id, error = %e, "Failed to parse client message");
+                        warn!(user_id = %user_id, error = %e, "Dropped client message");
+                        if frame_guard.exhausted() {
+                            warn!(user_id = %user_id, "Too many bad messages, closing connection");
+                            let code = match e {
+                                FrameError::TooLarge(_) => close_code::SIZE,
+                                FrameError::Malformed(_) => close_code::POLICY,
+                            };
+                            closing = close_tx
+                                .try_send(CloseFrame {
+                                    code,
+                                    reason: "too many bad messages".into(),
+                                })
+                                .is_ok();
+                            break;
+                        }
                     }
                 }
             }
@@ -163,15 +604,24 @@ async fn run_session(
             Ok(Message::Ping(_)) => {
                 debug!(user_id = %user_id, "Received ping");
             }
-            Ok(Message::Pong(_)) => {
-                debug!(user_id = %user_id, "Received pong");
+            Ok(Message::Pong(payload)) => {
+                let rtt_ms = latency.on_pong(&payload);
+                debug!(user_id = %user_id, rtt_ms = ?rtt_ms, "Received pong");
             }
             Ok(Message::Close(_)) => {
                 info!(user_id = %user_id, "Client initiated close");
                 break;
             }
             Err(e) => {
-                error!(user_id = %user_id, error = %e, "WebSocket error");
+                // Frames over the size limit end up here too. Reads can't
+                // continue past an error, but a close frame may still get out
+                warn!(user_id = %user_id, error = %e, "WebSocket read failed");
+                closing = close_tx
+                    .try_send(CloseFrame {
+                        code: close_code::PROTOCOL,
+                        reason: "invalid frame".into(),
+                    })
+                    .is_ok();
                 break;
             }
         }
@@ -183,22 +633,280 @@ async fn run_session(
             user_id,
             msg: ClientMsg::LeaveMatch,
             received_at: unix_millis(),
+            display_name: None,
+            latency: None,
+            guest: false,
+            cosmetics: Cosmetics::default(),
         })
         .await;
 
-    // Abort writer task
+    // Let the writer send any close frame, then stop it
+    if closing {
+        let _ = tokio::time::timeout(CLOSE_FLUSH_TIMEOUT, &mut writer_handle).await;
+    }
     writer_handle.abort();
+}
 
-    let _ = display_name; // Used for logging context
+/// Wait until the token's next expiry step is due. Without a token to
+/// watch, never completes
+async fn next_expiry_step(expiry: Option<&mut TokenExpiry>) -> ExpiryStep {
+    let Some(expiry) = expiry else {
+        return std::future::pending().await;
+    };
+    loop {
+        let wait = expiry.next_step_at().saturating_sub(unix_millis());
+        tokio::time::sleep(Duration::from_millis(wait)).await;
+        if let Some(step) = expiry.poll(unix_millis()) {
+            return step;
+        }
+    }
 }
 
 /// Send a message over WebSocket
 async fn send_msg(
     sink: &mut futures::stream::SplitSink<WebSocket, Message>,
     msg: &ServerMsg,
+    compress: bool,
 ) -> Result<(), String> {
     let json = serde_json::to_string(msg).map_err(|e| e.to_string())?;
-    sink.send(Message::Text(json))
-        .await
-        .map_err(|e| e.to_string())
+    let frame = if compress && json.len() >= COMPRESSION_MIN_BYTES {
+        Message::Binary(deflate(json.as_bytes()).map_err(|e| e.to_string())?)
+    } else {
+        Message::Text(json)
+    };
+
+    sink.send(frame).await.map_err(|e| e.to_string())
+}
+
+/// Raw-deflate (RFC 1951) a message at the fastest level, keeping the
+/// per-snapshot CPU cost low (see `tests::deflate_cost`)
+fn deflate(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    deflate_at(data, Compression::fast())
+}
+
+fn deflate_at(data: &[u8], level: Compression) -> std::io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len() / 4), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::middleware::AuthError;
+    use crate::ws::reauth::TokenVerifier;
+    use axum::{routing::get, Router};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as ClientFrame;
+
+    const FRAMES: FramePolicy = FramePolicy {
+        max_frame_bytes: 1024,
+        max_message_bytes: 256,
+        max_strikes: 3,
+    };
+
+    fn options() -> SessionOptions {
+        SessionOptions {
+            heartbeat: Heartbeat {
+                ping_interval: Duration::from_secs(60),
+                idle_timeout: Duration::from_secs(60),
+            },
+            compress: false,
+            lag: LagPolicy::default(),
+            lag_metrics: Arc::new(LagMetrics::default()),
+            frames: FRAMES,
+            reauth: None,
+            evicted: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Options for a session whose token expires `expires_in` seconds from
+    /// now, with no leeway, that accepts `"fresh"` as a refreshed token
+    fn expiring_options(expires_in: i64) -> SessionOptions {
+        let now = unix_millis() / 1000;
+        let verify: TokenVerifier = Arc::new(move |token| match token {
+            "fresh" => Ok(serde_json::from_value(serde_json::json!({
+                "sub": Uuid::from_u128(1),
+                "exp": now + 3600,
+            }))
+            .unwrap()),
+            _ => Err(AuthError::InvalidToken),
+        });
+        SessionOptions {
+            reauth: Some(Reauth {
+                user_id: Uuid::from_u128(1),
+                expires_at: now.saturating_add_signed(expires_in),
+                policy: ReauthPolicy {
+                    warning: Duration::from_secs(60),
+                    leeway: Duration::ZERO,
+                },
+                verify,
+            }),
+            ..options()
+        }
+    }
+
+    /// Serve a bare session on a local port, returning its URL and the
+    /// inputs it forwards
+    async fn serve(options: SessionOptions) -> (String, mpsc::Receiver<PlayerInput>) {
+        let (input_tx, input_rx) = mpsc::channel(64);
+        let app = Router::new().route(
+            "/",
+            get(move |ws: WebSocketUpgrade| async move {
+                limit_frames(ws, &FRAMES).on_upgrade(move |socket| async move {
+                    let (ws_sink, ws_stream) = socket.split();
+                    let (snapshot_tx, snapshot_rx) = broadcast::channel(8);
+                    let latency = Arc::new(LatencyTracker::new());
+                    run_session(
+                        Uuid::from_u128(1),
+                        ws_sink,
+                        ws_stream,
+                        input_tx,
+                        snapshot_rx,
+                        options,
+                        latency,
+                    )
+                    .await;
+                    drop(snapshot_tx);
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("ws://{addr}/"), input_rx)
+    }
+
+    /// The close code the server ends the connection with
+    async fn close_code<S>(client: &mut S) -> Option<CloseCode>
+    where
+        S: futures::Stream<Item = Result<ClientFrame, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        let next = tokio::time::timeout(Duration::from_secs(5), client.next()).await;
+        match next.expect("server didn't close the connection") {
+            Some(Ok(ClientFrame::Close(frame))) => frame.map(|f| f.code),
+            other => panic!("expected a close frame, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_oversized_messages_close_the_connection() {
+        let (url, mut inputs) = serve(options()).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let oversized = format!(r#"{{"type":"ping","t":1,"pad":"{}"}}"#, "x".repeat(300));
+        for _ in 0..2 {
+            client.send(ClientFrame::text(oversized.clone())).await.unwrap();
+        }
+        // Still open: a good message gets through between strikes
+        client.send(ClientFrame::text(r#"{"type":"ping","t":2}"#)).await.unwrap();
+        let input = inputs.recv().await.unwrap();
+        assert!(matches!(input.msg, ClientMsg::Ping { t: 2 }));
+
+        client.send(ClientFrame::text(oversized)).await.unwrap();
+        assert_eq!(close_code(&mut client).await, Some(CloseCode::Size));
+        let leave = inputs.recv().await.unwrap();
+        assert!(matches!(leave.msg, ClientMsg::LeaveMatch));
+    }
+
+    #[tokio::test]
+    async fn frames_over_the_hard_limit_close_the_connection() {
+        let (url, mut inputs) = serve(options()).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        client.send(ClientFrame::text("x".repeat(4096))).await.unwrap();
+        assert_eq!(close_code(&mut client).await, Some(CloseCode::Protocol));
+        let leave = inputs.recv().await.unwrap();
+        assert!(matches!(leave.msg, ClientMsg::LeaveMatch));
+    }
+
+    /// The next text message from the server, parsed
+    async fn next_json<S>(client: &mut S) -> serde_json::Value
+    where
+        S: futures::Stream<Item = Result<ClientFrame, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        let next = tokio::time::timeout(Duration::from_secs(5), client.next()).await;
+        match next.expect("server sent nothing") {
+            Some(Ok(ClientFrame::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn expiring_tokens_are_refreshed_or_the_connection_closes() {
+        let (url, _inputs) = serve(expiring_options(30)).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        // Already inside the warning window, so the prompt comes at once
+        let prompt = next_json(&mut client).await;
+        assert_eq!(prompt["type"], "token_expiring");
+        assert!(prompt["expires_in_secs"].as_u64().unwrap() <= 30);
+
+        client.send(ClientFrame::text(r#"{"type":"refresh","token":"fresh"}"#)).await.unwrap();
+        client.send(ClientFrame::text(r#"{"type":"refresh","token":"forged"}"#)).await.unwrap();
+        assert_eq!(close_code(&mut client).await, Some(CloseCode::Policy));
+    }
+
+    #[tokio::test]
+    async fn connections_close_once_the_token_runs_out() {
+        let (url, mut inputs) = serve(expiring_options(-1)).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        assert_eq!(next_json(&mut client).await["type"], "token_expiring");
+        assert_eq!(close_code(&mut client).await, Some(CloseCode::Policy));
+        // Reading on echoes the close, which ends the session
+        let _ = client.next().await;
+        let leave = inputs.recv().await.unwrap();
+        assert!(matches!(leave.msg, ClientMsg::LeaveMatch));
+    }
+
+    #[tokio::test]
+    async fn an_evicted_session_closes() {
+        let evicted = Arc::new(Notify::new());
+        let (url, mut inputs) = serve(SessionOptions {
+            evicted: evicted.clone(),
+            ..options()
+        })
+        .await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        evicted.notify_one();
+        assert_eq!(close_code(&mut client).await, Some(CloseCode::Policy));
+        let leave = inputs.recv().await.unwrap();
+        assert!(matches!(leave.msg, ClientMsg::LeaveMatch));
+    }
+
+    /// CPU against bandwidth for compressing full-lobby snapshots, behind the
+    /// numbers in the README. Run with
+    /// `cargo test --release deflate_cost -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn deflate_cost() {
+        const RUNS: u32 = 5000;
+        let msg = crate::game::snapshot::full_lobby_snapshot();
+        let json = serde_json::to_string(&msg).unwrap();
+
+        let start = std::time::Instant::now();
+        for _ in 0..RUNS {
+            std::hint::black_box(serde_json::to_string(std::hint::black_box(&msg)).unwrap());
+        }
+        let serialize = start.elapsed() / RUNS;
+        println!("snapshot: {} bytes, serialized in {:?}", json.len(), serialize);
+
+        for (name, level) in [("fast", Compression::fast()), ("default", Compression::default())] {
+            let start = std::time::Instant::now();
+            let mut size = 0;
+            for _ in 0..RUNS {
+                size = deflate_at(std::hint::black_box(json.as_bytes()), level).unwrap().len();
+            }
+            let per_message = start.elapsed() / RUNS;
+            println!(
+                "{name}: {size} bytes ({:.0}%), {per_message:?} per message, {:?} per second for 20 clients at 20 snapshots/s",
+                100.0 * size as f64 / json.len() as f64,
+                per_message * 20 * 20,
+            );
+        }
+    }
 }
@@ -0,0 +1,101 @@
+//! Handling of WebSocket clients that fall behind the broadcast channel
+//!
+//! Snapshots are full state, so a client that misses some recovers with the
+//! next one it receives. Dropped one-off messages (joins, kills, match end)
+//! are gone for good, so clients that keep lagging are disconnected rather
+//! than left playing on a stale view.
+
+use std::sync::atomic::AtomicU64;
+use std::time::{Duration, Instant};
+
+/// When a lagging client is disconnected
+#[derive(Debug, Clone, Copy)]
+pub struct LagPolicy {
+    /// Lag events within `window` that close the connection (0 = never)
+    pub threshold: usize,
+    /// How far back lag events count towards the threshold
+    pub window: Duration,
+}
+
+impl Default for LagPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            window: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Recent lag events for one connection
+#[derive(Debug)]
+pub struct LagTracker {
+    policy: LagPolicy,
+    recent: Vec<Instant>,
+}
+
+impl LagTracker {
+    pub fn new(policy: LagPolicy) -> Self {
+        Self {
+            policy,
+            recent: Vec::new(),
+        }
+    }
+
+    /// Record a lag event, returning whether the client should be disconnected
+    pub fn record(&mut self) -> bool {
+        self.record_at(Instant::now())
+    }
+
+    fn record_at(&mut self, now: Instant) -> bool {
+        self.recent
+            .retain(|t| now.duration_since(*t) < self.policy.window);
+        self.recent.push(now);
+
+        self.policy.threshold > 0 && self.recent.len() >= self.policy.threshold
+    }
+}
+
+/// Server-wide lag counters, read by the `/metrics` endpoint
+#[derive(Debug, Default)]
+pub struct LagMetrics {
+    /// Messages dropped because a client's writer fell behind
+    pub lagged_messages: AtomicU64,
+    /// Connections closed for lagging too often
+    pub disconnects: AtomicU64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(threshold: usize, window: Duration) -> LagPolicy {
+        LagPolicy { threshold, window }
+    }
+
+    #[test]
+    fn repeated_lag_within_window_disconnects() {
+        let mut tracker = LagTracker::new(policy(3, Duration::from_secs(30)));
+        assert!(!tracker.record());
+        assert!(!tracker.record());
+        assert!(tracker.record());
+    }
+
+    #[test]
+    fn lag_outside_window_is_forgotten() {
+        let mut tracker = LagTracker::new(policy(2, Duration::from_secs(30)));
+        let start = Instant::now();
+        assert!(!tracker.record_at(start));
+        // The first lag has aged out, so this one starts a new count
+        let later = start + Duration::from_secs(31);
+        assert!(!tracker.record_at(later));
+        assert!(tracker.record_at(later + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn zero_threshold_never_disconnects() {
+        let mut tracker = LagTracker::new(policy(0, Duration::from_secs(30)));
+        for _ in 0..100 {
+            assert!(!tracker.record());
+        }
+    }
+}
@@ -1,4 +1,8 @@
 //! WebSocket handling modules
 
+pub mod frames;
 pub mod handler;
+pub mod lag;
 pub mod protocol;
+pub mod reauth;
+pub mod ticket;
@@ -2,10 +2,32 @@
 //! These are the wire types for client-server communication
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
+/// Wire format version, sent in `welcome`. Bumped only on breaking message
+/// changes: removing or renaming a message, field or enum value, changing a
+/// field's type, or making a client field required.
+///
+/// New server messages, error codes and optional fields are additions, not
+/// breaks, and keep the same version (as `queue_status` and the `kicked` error
+/// code did), so clients must skip a `type`, `code` or field they don't know.
+/// A new client message is also an addition; a server that predates it drops
+/// it as malformed
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Whether a client's protocol version (`"2"` or `"2.x"`) can talk to this
+/// server. Only the major version has to match exactly
+pub fn is_compatible_version(client_version: &str) -> bool {
+    let major = client_version.split('.').next().unwrap_or_default();
+    major.trim().parse::<u32>() == Ok(PROTOCOL_VERSION)
+}
+
 /// Ship types available in the game
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ShipType {
     /// Fast but fragile
@@ -16,6 +38,8 @@ pub enum ShipType {
     Cruiser,
     /// High damage, low mobility
     Destroyer,
+    /// Light hull with side thrusters, the only one that can strafe
+    Corvette,
 }
 
 impl Default for ShipType {
@@ -24,6 +48,160 @@ impl Default for ShipType {
     }
 }
 
+impl ShipType {
+    pub const ALL: [Self; 5] = [
+        Self::Scout,
+        Self::Fighter,
+        Self::Cruiser,
+        Self::Destroyer,
+        Self::Corvette,
+    ];
+
+    /// Name as sent over the wire
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Scout => "scout",
+            Self::Fighter => "fighter",
+            Self::Cruiser => "cruiser",
+            Self::Destroyer => "destroyer",
+            Self::Corvette => "corvette",
+        }
+    }
+}
+
+impl fmt::Display for ShipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// A ship type name that isn't one of `ShipType::ALL`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown ship type {0:?}")]
+pub struct UnknownShipType(pub String);
+
+impl FromStr for ShipType {
+    type Err = UnknownShipType;
+
+    /// Parse the name sent over the wire
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|ship_type| ship_type.key() == s)
+            .ok_or_else(|| UnknownShipType(s.to_string()))
+    }
+}
+
+/// Weapon loadouts, chosen independently of hull
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponLoadout {
+    /// Scout default: light, fast-firing
+    Autocannon,
+    /// Fighter and Corvette default: all-rounder
+    #[default]
+    Blaster,
+    /// Cruiser default: slower, heavier shots
+    Cannon,
+    /// Destroyer default: slow, hard-hitting shells
+    Artillery,
+    /// Unlockable: very fast, heavy single shots on a long cooldown
+    Railgun,
+    /// Unlockable: rapid low-damage bursts
+    Pulse,
+    /// Unlockable: a fan of short-range pellets per shot
+    Scattergun,
+    /// Unlockable: short-range bursts that set targets burning
+    Flamer,
+}
+
+impl WeaponLoadout {
+    pub const ALL: [Self; 8] = [
+        Self::Autocannon,
+        Self::Blaster,
+        Self::Cannon,
+        Self::Artillery,
+        Self::Railgun,
+        Self::Pulse,
+        Self::Scattergun,
+        Self::Flamer,
+    ];
+
+    /// The weapon a hull uses when none is chosen (always available)
+    pub fn default_for(ship_type: ShipType) -> Self {
+        match ship_type {
+            ShipType::Scout => Self::Autocannon,
+            ShipType::Fighter | ShipType::Corvette => Self::Blaster,
+            ShipType::Cruiser => Self::Cannon,
+            ShipType::Destroyer => Self::Artillery,
+        }
+    }
+
+    /// Name of the `weapon_loadout` inventory item that unlocks this loadout
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Autocannon => "autocannon",
+            Self::Blaster => "blaster",
+            Self::Cannon => "cannon",
+            Self::Artillery => "artillery",
+            Self::Railgun => "railgun",
+            Self::Pulse => "pulse",
+            Self::Scattergun => "scattergun",
+            Self::Flamer => "flamer",
+        }
+    }
+}
+
+/// Cosmetic slots a player can fill, each holding one equipped item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CosmeticSlot {
+    /// Flag flown on the ship
+    Flag,
+    /// Paint on the ship's hull
+    HullSkin,
+    /// Trail left behind the ship
+    Trail,
+    /// Effect played on kills
+    KillEffect,
+}
+
+impl CosmeticSlot {
+    pub const ALL: [Self; 4] = [Self::Flag, Self::HullSkin, Self::Trail, Self::KillEffect];
+
+    /// Inventory item type that fills this slot
+    pub fn item_type(&self) -> &'static str {
+        match self {
+            Self::Flag => "flag_skin",
+            Self::HullSkin => "ship_skin",
+            Self::Trail => "trail_effect",
+            Self::KillEffect => "kill_effect",
+        }
+    }
+
+    /// Slot an inventory item type fills, if it's a cosmetic
+    pub fn for_item_type(item_type: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|slot| slot.item_type() == item_type)
+    }
+}
+
+/// Status effects a ship can be under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEffectKind {
+    /// Loses health every tick
+    Burning,
+}
+
+impl StatusEffectKind {
+    /// Bit set in `PlayerSnapshot::effects` while a ship is under this effect
+    pub fn bit(self) -> u8 {
+        match self {
+            Self::Burning => 1 << 0,
+        }
+    }
+}
+
 /// Messages sent from client to server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -34,6 +212,13 @@ pub enum ClientMsg {
         match_id: Option<Uuid>,
         /// Ship type selection
         ship_type: ShipType,
+        /// Weapon loadout (defaults to the ship type's weapon)
+        #[serde(default)]
+        weapon: Option<WeaponLoadout>,
+        /// Accessibility aim assist: shots bend slightly toward an enemy
+        /// just off the aim (see `AIM_ASSIST_CONE_DEG`)
+        #[serde(default)]
+        aim_assist: bool,
     },
 
     /// Player input for current tick
@@ -44,10 +229,20 @@ pub enum ClientMsg {
         throttle: f32,
         /// Steering input (-1.0 = full left, 1.0 = full right)
         steer: f32,
+        /// Sideways thrust (-1.0 = full left, 1.0 = full right), ignored by
+        /// hulls that can't strafe
+        #[serde(default)]
+        strafe: f32,
         /// Fire weapon this tick
         shoot: bool,
         /// Aim direction in radians
         aim_yaw: f32,
+        /// Engage boost this tick
+        #[serde(default)]
+        boost: bool,
+        /// Drop a mine at the ship's position this tick
+        #[serde(default)]
+        deploy_mine: bool,
     },
 
     /// Ping for latency measurement
@@ -58,6 +253,51 @@ pub enum ClientMsg {
 
     /// Leave current match
     LeaveMatch,
+
+    /// Pause or resume the match (host only)
+    SetPause {
+        paused: bool,
+    },
+
+    /// Fresh token for the connection, sent in answer to `token_expiring`.
+    /// Handled by the connection, never forwarded to the match
+    Refresh {
+        token: String,
+    },
+}
+
+/// Machine-readable reason carried by `ServerMsg::Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Direct join while already in a match
+    AlreadyInMatch,
+    /// Direct join to a match that doesn't exist
+    MatchNotFound,
+    /// No free slot, even after replacing bots
+    MatchFull,
+    /// Direct join after the countdown ended
+    MatchInProgress,
+    /// Joining too soon after abandoning matches
+    MatchmakingCooldown,
+    /// Weapon loadout not owned
+    LoadoutLocked,
+    /// Inventory lookup for the loadout failed
+    LoadoutCheckFailed,
+    /// Pause requested by someone other than the host
+    NotHost,
+    /// Pause requested outside the countdown or match
+    CannotPause,
+    /// No pause time left this match
+    PauseLimit,
+    /// Client speaks an incompatible protocol version
+    VersionMismatch,
+    /// Removed from the match for sending implausible input
+    Kicked,
+    /// The match stopped without finishing; queue again
+    MatchEnded,
+    /// Connection refused: the user is already connected elsewhere
+    AlreadyConnected,
 }
 
 /// Messages sent from server to client
@@ -68,6 +308,29 @@ pub enum ServerMsg {
     Welcome {
         user_id: Uuid,
         server_time: u64,
+        protocol_version: u32,
+    },
+
+    /// The token the connection was authenticated with is about to expire;
+    /// answer with `refresh` before then or be disconnected
+    TokenExpiring {
+        expires_in_secs: u64,
+    },
+
+    /// Your display name changed, either because your profile loaded after
+    /// connecting or because you renamed yourself. Applies to matches joined
+    /// from now on
+    DisplayNameUpdated {
+        display_name: String,
+    },
+
+    /// Matchmaking progress, sent about once a second while queued
+    QueueStatus {
+        /// 1-based place in the queue for your region
+        position: u32,
+        /// Players queued in your region
+        queue_size: u32,
+        estimated_wait_secs: u32,
     },
 
     /// Confirmation of match join
@@ -77,6 +340,15 @@ pub enum ServerMsg {
         seed: u64,
         /// All players in the match at join time
         players: Vec<PlayerInfo>,
+        /// Hard world boundary ships can't leave
+        bounds: WorldBounds,
+        /// Arena the match is played on, e.g. "open" or "cluttered"
+        arena_id: String,
+        /// Player allowed to pause the match
+        host_id: Option<Uuid>,
+        /// The arena's repair pads (omitted when it has none)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        repair_pads: Vec<RepairPad>,
     },
 
     /// Player joined the match
@@ -97,9 +369,38 @@ pub enum ServerMsg {
         /// Current zone state
         zone: ZoneState,
         /// All player states
-        players: Vec<PlayerSnapshot>,
+        players: SnapshotPlayers,
         /// Events that occurred since last snapshot
         events: Vec<GameEvent>,
+        /// Encoding of `players` (omitted for full precision)
+        #[serde(default, skip_serializing_if = "SnapshotPrecision::is_full")]
+        precision: SnapshotPrecision,
+        /// Live projectiles, if the match sends them (omitted when empty)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        projectiles: Vec<ProjectileSnapshot>,
+        /// State of each of the arena's repair pads (omitted when it has none)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        repair_pads: Vec<RepairPadStatus>,
+    },
+
+    /// Result of simulating the receiving player's latest input. Sent only
+    /// to that player
+    InputAck {
+        /// Highest input `seq` applied
+        seq: u32,
+        /// Tick the state is from
+        tick: u64,
+        /// The client's own prediction is within the server's tolerance, so
+        /// no correction is sent
+        prediction_ok: bool,
+        /// Authoritative state to reset to (omitted when `prediction_ok`)
+        #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+        correction: Option<ShipMotion>,
+    },
+
+    /// Warmup starting: ships can move but not fight until the countdown
+    MatchWarmup {
+        seconds_remaining: u32,
     },
 
     /// Match countdown starting
@@ -107,11 +408,60 @@ pub enum ServerMsg {
         seconds_remaining: u32,
     },
 
+    /// Players left during the warmup or countdown and too few remain; the
+    /// match is waiting for players again
+    CountdownCancelled {
+        players: usize,
+        min_players: usize,
+    },
+
     /// Match has started
     MatchStarted {
         tick: u64,
     },
 
+    /// Measured round-trip times of the players in the match (sent periodically)
+    LatencyReport {
+        players: Vec<PlayerLatency>,
+    },
+
+    /// The server clock and match tick, sent periodically so clients can
+    /// keep their tick clock aligned over a long session
+    TimeSync {
+        /// Server time (Unix ms)
+        server_time: u64,
+        /// Tick the match is on
+        tick: u64,
+    },
+
+    /// Match paused by the host; the simulation is frozen until resumed
+    MatchPaused {
+        by: Uuid,
+        /// Pause time left for the rest of the match (seconds)
+        remaining_secs: f32,
+    },
+
+    /// Match resumed after a pause
+    MatchResumed {
+        tick: u64,
+    },
+
+    /// The zone starts shrinking soon. Sent once per phase, ahead of the
+    /// `zone_shrink` event that starts it
+    ZoneWarning {
+        phase: u32,
+        seconds_until_shrink: f32,
+        next_radius: f32,
+        next_center_x: f32,
+        next_center_y: f32,
+    },
+
+    /// The match hit its time limit: the zone closes to nothing over
+    /// `seconds_remaining`, then the survivors are ranked and the match ends
+    SuddenDeath {
+        seconds_remaining: f32,
+    },
+
     /// Match has ended
     MatchEnd {
         winner_user_id: Option<Uuid>,
@@ -121,7 +471,7 @@ pub enum ServerMsg {
 
     /// Error message
     Error {
-        code: String,
+        code: ErrorCode,
         message: String,
     },
 
@@ -129,22 +479,114 @@ pub enum ServerMsg {
     Pong {
         /// Echo back client timestamp
         t: u64,
+        /// Server time (Unix ms) when the ping was answered
+        server_time: u64,
     },
 }
 
+/// Position and velocity of a ship
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShipMotion {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+}
+
+/// One player's entry in a latency report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerLatency {
+    pub user_id: Uuid,
+    /// Smoothed round-trip time in milliseconds
+    pub rtt_ms: u32,
+}
+
 /// Player info for lobby/join
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerInfo {
     pub user_id: Uuid,
     pub display_name: String,
     pub ship_type: ShipType,
-    /// Equipped flag skin ID (if any)
+    /// Equipped flag skin ID (if any), also under `cosmetics`
     pub flag_skin_id: Option<Uuid>,
+    /// Equipped item ID per cosmetic slot, leaving out empty slots
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cosmetics: BTreeMap<CosmeticSlot, Uuid>,
+    /// AI-controlled ship
+    #[serde(default)]
+    pub is_bot: bool,
+    /// Equipped weapon loadout
+    #[serde(default)]
+    pub weapon: WeaponLoadout,
+}
+
+/// Shape of the safe zone
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ZoneShape {
+    /// Circle of `radius`
+    #[default]
+    Circle,
+    /// Axis-aligned rectangle with half-width `radius` and half-height `radius * aspect`
+    Rect { aspect: f32 },
+}
+
+impl ZoneShape {
+    /// Ratio of half-height to half-width (1.0 for circles)
+    pub fn aspect(&self) -> f32 {
+        match self {
+            ZoneShape::Circle => 1.0,
+            ZoneShape::Rect { aspect } => *aspect,
+        }
+    }
+}
+
+/// Axis-aligned world boundary centered on the origin
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldBounds {
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+/// Circle that repairs damaged ships parked on it, part of an arena
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepairPad {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// Health restored per second to each damaged ship on the pad
+    pub heal_per_sec: f32,
+    /// Seconds the pad goes dark once it stops repairing, so nobody can camp
+    /// on it (0 = never)
+    #[serde(default)]
+    pub cooldown: f32,
+}
+
+impl RepairPad {
+    /// Whether a ship centered at (`x`, `y`) is on the pad
+    pub fn covers(&self, x: f32, y: f32) -> bool {
+        (x - self.x).hypot(y - self.y) <= self.radius
+    }
+}
+
+/// Where a repair pad is in its cycle, listed in a snapshot in the same
+/// order as the pads in `match_joined`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RepairPadStatus {
+    /// Repairing ships that park on it
+    pub active: bool,
+    /// Seconds until it comes back (0 while active)
+    pub cooldown_remaining: f32,
 }
 
 /// Zone (shrinking play area) state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoneState {
+    /// Zone shape; `radius` is the half-width for rectangles
+    #[serde(default)]
+    pub shape: ZoneShape,
     /// Current zone center X
     pub center_x: f32,
     /// Current zone center Y  
@@ -168,6 +610,7 @@ pub struct ZoneState {
 impl Default for ZoneState {
     fn default() -> Self {
         Self {
+            shape: ZoneShape::Circle,
             center_x: 0.0,
             center_y: 0.0,
             radius: 1000.0,
@@ -181,28 +624,149 @@ impl Default for ZoneState {
     }
 }
 
+/// Encoding of positions, velocities and rotation in snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SnapshotPrecision {
+    /// Plain f32 values
+    #[default]
+    Full,
+    /// Positions and velocities as integers in units of `1 / position_scale`
+    /// (error at most `0.5 / position_scale`), rotation as a u16 fraction of a
+    /// full turn (error at most `PI / 65536` radians)
+    Quantized { position_scale: f32 },
+}
+
+impl SnapshotPrecision {
+    pub fn is_full(&self) -> bool {
+        matches!(self, SnapshotPrecision::Full)
+    }
+}
+
+/// Quantize a position or velocity component
+pub fn quantize_position(value: f32, scale: f32) -> i32 {
+    (value * scale).round() as i32
+}
+
+/// Decode a quantized position or velocity component
+#[allow(dead_code)] // Reference decoder for clients
+pub fn dequantize_position(value: i32, scale: f32) -> f32 {
+    value as f32 / scale
+}
+
+/// Quantize a rotation in radians to a fraction of a full turn
+pub fn quantize_rotation(rotation: f32) -> u16 {
+    let turns = rotation.rem_euclid(TAU) / TAU;
+    // Rounding up to a full turn wraps back to 0
+    ((turns * 65536.0).round() as u32 & 0xFFFF) as u16
+}
+
+/// Decode a quantized rotation to radians in 0..2π
+#[allow(dead_code)] // Reference decoder for clients
+pub fn dequantize_rotation(value: u16) -> f32 {
+    value as f32 / 65536.0 * TAU
+}
+
+/// Player states in a snapshot, in the encoding given by `SnapshotPrecision`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SnapshotPlayers {
+    Quantized(Vec<QuantizedPlayerSnapshot>),
+    Full(Vec<PlayerSnapshot>),
+}
+
+/// Player snapshot with fixed-point position/velocity and u16 rotation
+pub type QuantizedPlayerSnapshot = PlayerSnapshot<i32, u16>;
+
 /// Player state in a snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlayerSnapshot {
+pub struct PlayerSnapshot<P = f32, R = f32> {
     pub user_id: Uuid,
     /// Position X
-    pub x: f32,
+    pub x: P,
     /// Position Y
-    pub y: f32,
+    pub y: P,
     /// Rotation in radians
-    pub rotation: f32,
+    pub rotation: R,
     /// Current velocity X
-    pub vel_x: f32,
+    pub vel_x: P,
     /// Current velocity Y
-    pub vel_y: f32,
+    pub vel_y: P,
     /// Health (0-100)
     pub health: f32,
+    /// Shield, absorbed before health
+    pub shield: f32,
     /// Is player alive
     pub alive: bool,
     /// Last processed input sequence
     pub last_input_seq: u32,
     /// Weapon cooldown remaining (0 = can fire)
     pub weapon_cooldown: f32,
+    /// Boost meter remaining
+    pub boost_energy: f32,
+    /// Status effects the ship is under, one `StatusEffectKind::bit` each
+    #[serde(default)]
+    pub effects: u8,
+    /// Spawn protection is up: the ship neither takes nor deals damage
+    #[serde(default)]
+    pub spawn_protected: bool,
+}
+
+impl PlayerSnapshot {
+    /// Convert to the fixed-point encoding
+    pub fn quantize(&self, position_scale: f32) -> QuantizedPlayerSnapshot {
+        PlayerSnapshot {
+            user_id: self.user_id,
+            x: quantize_position(self.x, position_scale),
+            y: quantize_position(self.y, position_scale),
+            rotation: quantize_rotation(self.rotation),
+            vel_x: quantize_position(self.vel_x, position_scale),
+            vel_y: quantize_position(self.vel_y, position_scale),
+            health: self.health,
+            shield: self.shield,
+            alive: self.alive,
+            last_input_seq: self.last_input_seq,
+            weapon_cooldown: self.weapon_cooldown,
+            boost_energy: self.boost_energy,
+            effects: self.effects,
+            spawn_protected: self.spawn_protected,
+        }
+    }
+}
+
+impl QuantizedPlayerSnapshot {
+    /// Decode back to f32 values
+    #[allow(dead_code)] // Reference decoder for clients
+    pub fn dequantize(&self, position_scale: f32) -> PlayerSnapshot {
+        PlayerSnapshot {
+            user_id: self.user_id,
+            x: dequantize_position(self.x, position_scale),
+            y: dequantize_position(self.y, position_scale),
+            rotation: dequantize_rotation(self.rotation),
+            vel_x: dequantize_position(self.vel_x, position_scale),
+            vel_y: dequantize_position(self.vel_y, position_scale),
+            health: self.health,
+            shield: self.shield,
+            alive: self.alive,
+            last_input_seq: self.last_input_seq,
+            weapon_cooldown: self.weapon_cooldown,
+            boost_energy: self.boost_energy,
+            effects: self.effects,
+            spawn_protected: self.spawn_protected,
+        }
+    }
+}
+
+/// A live projectile in a snapshot, always in world units. `id` matches the
+/// `projectile_id` of the `Shot` event that fired it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectileSnapshot {
+    pub id: Uuid,
+    pub x: f32,
+    pub y: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+    pub radius: f32,
 }
 
 /// Game events (damage, kills, etc.)
@@ -217,6 +781,10 @@ pub enum GameEvent {
         y: f32,
         direction: f32,
         speed: f32,
+        /// Aim the shot was fired along when aim assist moved it off the
+        /// player's `aim_yaw` (radians, omitted otherwise)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        assisted_yaw: Option<f32>,
     },
     
     /// Hit registered
@@ -224,22 +792,83 @@ pub enum GameEvent {
         shooter_id: Uuid,
         target_id: Uuid,
         damage: f32,
+        /// Critical hit (damage already includes the multiplier)
+        crit: bool,
         x: f32,
         y: f32,
+        /// Target's health and shield after the hit, for immediate health bars
+        target_remaining_health: f32,
+        target_remaining_shield: f32,
     },
 
     /// Player killed
     Kill {
         killer_id: Option<Uuid>,
         victim_id: Uuid,
-        /// "shot", "zone", "collision"
+        /// "shot", "mine", "burn", "zone", "collision", or "self" for a ship
+        /// caught in its own splash (`killer_id` is the victim)
         cause: String,
     },
 
+    /// Kill chained within the multi-kill window of the killer's previous one
+    MultiKill {
+        killer_id: Uuid,
+        /// Kills in the chain so far (2 = double, 3 = triple, ...)
+        count: u32,
+    },
+
+    /// Mine dropped
+    MineDeployed {
+        owner_id: Uuid,
+        mine_id: Uuid,
+        x: f32,
+        y: f32,
+        trigger_radius: f32,
+        blast_radius: f32,
+        /// Seconds before it detonates on its own
+        lifetime: f32,
+    },
+
+    /// Shots from two players met and cancelled each other out
+    ProjectileClash {
+        projectile_ids: [Uuid; 2],
+        owner_ids: [Uuid; 2],
+        x: f32,
+        y: f32,
+    },
+
+    /// Mine exploded (individual damage follows as `hit` events)
+    MineDetonated {
+        owner_id: Uuid,
+        mine_id: Uuid,
+        x: f32,
+        y: f32,
+        blast_radius: f32,
+        /// Ship that set it off (none when it expired)
+        triggered_by: Option<Uuid>,
+    },
+
+    /// Damage dealt this tick by a status effect, credited to `source_id`
+    EffectDamage {
+        user_id: Uuid,
+        source_id: Uuid,
+        effect: StatusEffectKind,
+        damage: f32,
+    },
+
+    /// Health restored this tick by a repair pad
+    Heal {
+        user_id: Uuid,
+        amount: f32,
+    },
+
     /// Zone damage tick
     ZoneDamage {
         user_id: Uuid,
         damage: f32,
+        /// Health and shield after the damage
+        remaining_health: f32,
+        remaining_shield: f32,
     },
 
     /// Zone phase change
@@ -251,22 +880,205 @@ pub enum GameEvent {
     },
 }
 
+impl GameEvent {
+    /// The `event_type` tag this event is sent with
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::Shot { .. } => "shot",
+            GameEvent::Hit { .. } => "hit",
+            GameEvent::Kill { .. } => "kill",
+            GameEvent::MultiKill { .. } => "multi_kill",
+            GameEvent::MineDeployed { .. } => "mine_deployed",
+            GameEvent::ProjectileClash { .. } => "projectile_clash",
+            GameEvent::MineDetonated { .. } => "mine_detonated",
+            GameEvent::EffectDamage { .. } => "effect_damage",
+            GameEvent::Heal { .. } => "heal",
+            GameEvent::ZoneDamage { .. } => "zone_damage",
+            GameEvent::ZoneShrink { .. } => "zone_shrink",
+        }
+    }
+
+    /// Whether the player caused or was affected by this event
+    pub fn involves(&self, user_id: Uuid) -> bool {
+        match self {
+            GameEvent::Shot { shooter_id, .. } => *shooter_id == user_id,
+            GameEvent::Hit {
+                shooter_id,
+                target_id,
+                ..
+            } => *shooter_id == user_id || *target_id == user_id,
+            GameEvent::Kill {
+                killer_id,
+                victim_id,
+                ..
+            } => *killer_id == Some(user_id) || *victim_id == user_id,
+            GameEvent::MultiKill { killer_id, .. } => *killer_id == user_id,
+            GameEvent::MineDeployed { owner_id, .. } => *owner_id == user_id,
+            GameEvent::ProjectileClash { owner_ids, .. } => owner_ids.contains(&user_id),
+            GameEvent::MineDetonated {
+                owner_id,
+                triggered_by,
+                ..
+            } => *owner_id == user_id || *triggered_by == Some(user_id),
+            GameEvent::EffectDamage {
+                user_id: id,
+                source_id,
+                ..
+            } => *id == user_id || *source_id == user_id,
+            GameEvent::Heal { user_id: id, .. } | GameEvent::ZoneDamage { user_id: id, .. } => {
+                *id == user_id
+            }
+            GameEvent::ZoneShrink { .. } => false,
+        }
+    }
+}
+
 /// Match statistics at end
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MatchStats {
     pub duration_secs: u32,
     pub total_players: u32,
     pub player_stats: Vec<PlayerMatchStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayerMatchStats {
     pub user_id: Uuid,
+    /// Bots are excluded from persistent stats and leaderboards
+    #[serde(default)]
+    pub is_bot: bool,
+    /// Guests are shown in match results but never persisted
+    #[serde(default)]
+    pub is_guest: bool,
+    pub ship_type: ShipType,
     pub kills: u32,
+    /// Most kills without dying
+    #[serde(default)]
+    pub best_streak: u32,
     pub damage_dealt: f32,
     pub damage_taken: f32,
     pub shots_fired: u32,
     pub shots_hit: u32,
     pub placement: u32,
     pub alive_time_secs: u32,
+    /// Left the match while still alive
+    #[serde(default)]
+    pub abandoned: bool,
+    /// XP awarded for this match
+    #[serde(default)]
+    pub xp_gained: u32,
+    /// Level after the award, if the new total could be read back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_level: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn ship_type_names_round_trip_like_the_wire_format() {
+        for ship_type in ShipType::ALL {
+            let name = ship_type.to_string();
+            assert_eq!(serde_json::to_value(ship_type).unwrap(), name.as_str());
+            assert_eq!(name.parse::<ShipType>(), Ok(ship_type));
+        }
+        assert_eq!(
+            "Scout".parse::<ShipType>(),
+            Err(UnknownShipType("Scout".to_string()))
+        );
+    }
+
+    /// Shortest distance between two angles
+    fn angle_between(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(TAU);
+        d.min(TAU - d)
+    }
+
+    fn player(x: f32, y: f32, rotation: f32) -> PlayerSnapshot {
+        PlayerSnapshot {
+            user_id: Uuid::from_u128(1),
+            x,
+            y,
+            rotation,
+            vel_x: -x / 7.0,
+            vel_y: y / 3.0,
+            health: 75.0,
+            shield: 20.0,
+            alive: true,
+            last_input_seq: 42,
+            weapon_cooldown: 0.25,
+            boost_energy: 1.0,
+            effects: 0,
+            spawn_protected: false,
+        }
+    }
+
+    #[test]
+    fn position_round_trip_error_is_bounded() {
+        for scale in [1.0, 10.0, 100.0] {
+            let bound = 0.5 / scale + 1e-3;
+            for i in -2000..=2000 {
+                let value = i as f32 * 2.713;
+                let decoded = dequantize_position(quantize_position(value, scale), scale);
+                assert!(
+                    (decoded - value).abs() <= bound,
+                    "{value} at scale {scale} decoded as {decoded}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_round_trip_error_is_bounded() {
+        let bound = PI / 65536.0 + 1e-5;
+        for i in -1000..=1000 {
+            let rotation = i as f32 * 0.0271;
+            let decoded = dequantize_rotation(quantize_rotation(rotation));
+            assert!((0.0..TAU).contains(&decoded));
+            assert!(
+                angle_between(decoded, rotation) <= bound,
+                "{rotation} decoded as {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn rotation_just_below_full_turn_wraps_to_zero() {
+        assert_eq!(quantize_rotation(TAU - 1e-6), 0);
+        assert_eq!(quantize_rotation(-TAU), 0);
+    }
+
+    #[test]
+    fn quantized_snapshot_round_trips_through_json() {
+        let scale = 10.0;
+        let full = player(1234.56, -789.01, 5.5);
+        let json =
+            serde_json::to_string(&SnapshotPlayers::Quantized(vec![full.quantize(scale)])).unwrap();
+
+        let SnapshotPlayers::Quantized(players) = serde_json::from_str(&json).unwrap() else {
+            panic!("quantized players decoded as full precision: {json}");
+        };
+        let decoded = players[0].dequantize(scale);
+        assert!((decoded.x - full.x).abs() <= 0.05 + 1e-3);
+        assert!((decoded.y - full.y).abs() <= 0.05 + 1e-3);
+        assert!((decoded.vel_x - full.vel_x).abs() <= 0.05 + 1e-3);
+        assert!((decoded.vel_y - full.vel_y).abs() <= 0.05 + 1e-3);
+        assert!(angle_between(decoded.rotation, full.rotation) <= PI / 65536.0 + 1e-5);
+        assert_eq!(decoded.health, full.health);
+        assert_eq!(decoded.last_input_seq, full.last_input_seq);
+    }
+
+    #[test]
+    fn full_snapshot_stays_full_precision() {
+        let full = player(12.5, 3.25, 1.0);
+        let json = serde_json::to_string(&SnapshotPlayers::Full(vec![full.clone()])).unwrap();
+
+        let SnapshotPlayers::Full(players) = serde_json::from_str(&json).unwrap() else {
+            panic!("full precision players decoded as quantized: {json}");
+        };
+        assert_eq!(players[0].x, full.x);
+        assert_eq!(players[0].rotation, full.rotation);
+    }
 }
@@ -0,0 +1,170 @@
+//! Re-authentication of long-lived WebSocket connections
+//!
+//! A connection is authenticated once, at upgrade, but can easily outlive the
+//! token it was opened with. The session keeps the token's `exp`, and
+//! `warning` before it sends `token_expiring`; the client answers with a
+//! `refresh` carrying a fresh token for the same user, which moves the expiry
+//! forward. A refresh that fails verification closes the connection, and so
+//! does reaching `exp` (plus the JWT leeway) without one.
+
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::http::middleware::{AuthError, JwtClaims};
+
+/// When clients are asked to refresh, and how long past expiry they may run
+#[derive(Debug, Clone, Copy)]
+pub struct ReauthPolicy {
+    /// Send `token_expiring` this long before the token expires
+    pub warning: Duration,
+    /// Clock skew tolerated past `exp`, as when verifying tokens
+    pub leeway: Duration,
+}
+
+impl Default for ReauthPolicy {
+    fn default() -> Self {
+        Self {
+            warning: Duration::from_secs(60),
+            leeway: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Verifies a token sent in a `refresh`
+pub type TokenVerifier = Arc<dyn Fn(&str) -> Result<JwtClaims, AuthError> + Send + Sync>;
+
+/// Why a refreshed token was refused
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshError {
+    #[error(transparent)]
+    Invalid(#[from] AuthError),
+
+    #[error("token belongs to another user")]
+    WrongUser,
+}
+
+/// Re-authentication settings for one connection
+#[derive(Clone)]
+pub struct Reauth {
+    pub user_id: Uuid,
+    /// `exp` of the token the connection was opened with (Unix timestamp)
+    pub expires_at: u64,
+    pub policy: ReauthPolicy,
+    pub verify: TokenVerifier,
+}
+
+impl std::fmt::Debug for Reauth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reauth")
+            .field("user_id", &self.user_id)
+            .field("expires_at", &self.expires_at)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reauth {
+    /// Verify a refreshed token, returning its `exp`
+    pub fn check(&self, token: &str) -> Result<u64, RefreshError> {
+        let claims = (self.verify)(token)?;
+        if claims.sub != self.user_id {
+            return Err(RefreshError::WrongUser);
+        }
+        Ok(claims.exp)
+    }
+}
+
+/// What a connection has to do about its token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryStep {
+    /// Ask the client for a fresh token
+    Warn { expires_in_secs: u64 },
+    /// The token ran out without a refresh
+    Close,
+}
+
+/// Expiry of the token a connection is running on
+#[derive(Debug)]
+pub struct TokenExpiry {
+    policy: ReauthPolicy,
+    /// Unix timestamp
+    expires_at: u64,
+    warned: bool,
+}
+
+impl TokenExpiry {
+    pub fn new(expires_at: u64, policy: ReauthPolicy) -> Self {
+        Self {
+            policy,
+            expires_at,
+            warned: false,
+        }
+    }
+
+    /// Unix time in milliseconds the next step is due
+    pub fn next_step_at(&self) -> u64 {
+        let expires_at = self.expires_at.saturating_mul(1000);
+        if self.warned {
+            expires_at.saturating_add(self.policy.leeway.as_millis() as u64)
+        } else {
+            expires_at.saturating_sub(self.policy.warning.as_millis() as u64)
+        }
+    }
+
+    /// The step due at `now` (Unix milliseconds), if any
+    pub fn poll(&mut self, now: u64) -> Option<ExpiryStep> {
+        if now < self.next_step_at() {
+            return None;
+        }
+        if self.warned {
+            return Some(ExpiryStep::Close);
+        }
+        self.warned = true;
+        Some(ExpiryStep::Warn {
+            expires_in_secs: self.expires_at.saturating_sub(now / 1000),
+        })
+    }
+
+    /// Run on a refreshed token until its `exp`. A token expiring no later
+    /// than the current one changes nothing
+    pub fn extend(&mut self, expires_at: u64) {
+        if expires_at > self.expires_at {
+            self.expires_at = expires_at;
+            self.warned = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: ReauthPolicy = ReauthPolicy {
+        warning: Duration::from_secs(60),
+        leeway: Duration::from_secs(10),
+    };
+
+    #[test]
+    fn warns_before_expiry_then_closes_past_the_leeway() {
+        let mut expiry = TokenExpiry::new(1_000, POLICY);
+        assert_eq!(expiry.poll(939_000), None);
+        assert_eq!(expiry.poll(940_000), Some(ExpiryStep::Warn { expires_in_secs: 60 }));
+        assert_eq!(expiry.poll(1_009_999), None);
+        assert_eq!(expiry.poll(1_010_000), Some(ExpiryStep::Close));
+    }
+
+    #[test]
+    fn a_refresh_starts_over_on_the_new_expiry() {
+        let mut expiry = TokenExpiry::new(1_000, POLICY);
+        assert!(matches!(expiry.poll(950_000), Some(ExpiryStep::Warn { .. })));
+
+        // An older token doesn't rewind anything
+        expiry.extend(900);
+        assert_eq!(expiry.next_step_at(), 1_010_000);
+
+        expiry.extend(4_600);
+        assert_eq!(expiry.poll(1_010_000), None);
+        assert_eq!(expiry.next_step_at(), 4_540_000);
+    }
+}
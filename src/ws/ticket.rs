@@ -0,0 +1,87 @@
+//! Single-use WebSocket connection tickets
+//!
+//! Browsers can't set an Authorization header on a WebSocket upgrade, so clients
+//! exchange their bearer token for a short-lived ticket and pass that instead.
+
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::http::middleware::JwtClaims;
+
+/// How long an issued ticket remains valid
+pub const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// Who a connection authenticates as, from a ticket or a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    pub user_id: Uuid,
+    pub guest: bool,
+    /// `exp` of the token presented (Unix timestamp); the connection must
+    /// refresh it before then
+    pub token_expires_at: u64,
+}
+
+impl Credentials {
+    pub fn from_claims(claims: &JwtClaims) -> Self {
+        Self {
+            user_id: claims.sub,
+            guest: claims.is_guest(),
+            token_expires_at: claims.exp,
+        }
+    }
+}
+
+/// An issued ticket awaiting use
+struct Ticket {
+    credentials: Credentials,
+    expires_at: Instant,
+}
+
+/// In-memory store of outstanding tickets
+pub struct TicketStore {
+    tickets: DashMap<Uuid, Ticket>,
+}
+
+impl TicketStore {
+    pub fn new() -> Self {
+        Self {
+            tickets: DashMap::new(),
+        }
+    }
+
+    /// Issue a new ticket for the holder of a token
+    pub fn issue(&self, credentials: Credentials) -> Uuid {
+        self.evict_expired();
+
+        let ticket = Uuid::new_v4();
+        self.tickets.insert(
+            ticket,
+            Ticket {
+                credentials,
+                expires_at: Instant::now() + TICKET_TTL,
+            },
+        );
+        ticket
+    }
+
+    /// Consume a ticket, returning who it was issued to if still valid
+    pub fn consume(&self, ticket: Uuid) -> Option<Credentials> {
+        self.tickets
+            .remove(&ticket)
+            .filter(|(_, t)| t.expires_at > Instant::now())
+            .map(|(_, t)| t.credentials)
+    }
+
+    /// Drop tickets past their TTL
+    pub fn evict_expired(&self) {
+        let now = Instant::now();
+        self.tickets.retain(|_, t| t.expires_at > now);
+    }
+}
+
+impl Default for TicketStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}